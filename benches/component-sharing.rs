@@ -4,7 +4,7 @@ use criterion::{
 
 use calyx::{frontend, ir, passes};
 use ir::traversal::Visitor;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 fn cell_share_bench(c: &mut Criterion) {
     let mut gemm_group = c.benchmark_group("gemm");
@@ -18,11 +18,11 @@ fn cell_share_bench(c: &mut Criterion) {
                         let name =
                             format!("benches/component-sharing/{}.futil", name);
                         let bench = Path::new(&name);
-                        let lib = Path::new(".");
+                        let lib = [PathBuf::from(".")];
 
                         let ws = frontend::Workspace::construct(
                             &Some(bench.into()),
-                            lib,
+                            &lib,
                         )
                         .unwrap();
 
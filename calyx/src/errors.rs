@@ -1,5 +1,9 @@
 //! Errors generated by the compiler.
 use crate::utils::{GPosIdx, WithPos};
+use itertools::Itertools;
+use pest::error::{InputLocation, LineColLocation};
+use serde_json::json;
+use std::{iter, mem};
 
 use crate::frontend::parser;
 use crate::ir;
@@ -11,23 +15,118 @@ pub struct Error {
     kind: Box<ErrorKind>,
     pos: GPosIdx,
     post_msg: Option<String>,
+    /// Most errors have no details so they are only allocated when needed.
+    /// This keeps [CalyxResult] small.
+    details: Option<Box<Details>>,
+}
+
+/// The parts of an [Error] that are rarely used.
+#[derive(Default)]
+struct Details {
+    /// Other locations related to this error along with a label for each.
+    secondary: Vec<(GPosIdx, String)>,
+    /// Notes shown after the locations of the error.
+    notes: Vec<String>,
+    /// Other errors reported along with this one.
+    others: Vec<Error>,
+}
+
+/// Whether errors should be rendered with colors.
+fn use_color() -> bool {
+    std::env::var_os("NO_COLOR").is_none() && atty::is(atty::Stream::Stderr)
+}
+
+/// Wrap `text` in the ANSI escape `code` if `color` is enabled.
+fn paint(color: bool, code: &str, text: &str) -> String {
+    if color {
+        format!("\x1b[{code}m{text}\x1b[0m")
+    } else {
+        text.to_string()
+    }
 }
 
 impl std::fmt::Debug for Error {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let color = use_color();
+        let kind = self.kind.to_string();
+        // The primary location is underlined with `^` and the related
+        // locations with `-`.
+        let spans = iter::once((self.pos, '^', "1;31", kind.as_str()))
+            .chain(
+                self.secondary_spans()
+                    .iter()
+                    .map(|(pos, label)| (*pos, '-', "1;34", label.as_str())),
+            )
+            .filter(|(pos, ..)| *pos != GPosIdx::UNKNOWN)
+            .collect_vec();
+        let width = spans
+            .iter()
+            .map(|(pos, ..)| pos.get_first_line().0.to_string().len())
+            .max()
+            .unwrap_or(0);
+        let gutter = " ".repeat(width + 1);
+
+        let mut file = None;
         if self.pos == GPosIdx::UNKNOWN {
-            write!(f, "{}", self.kind)?
-        } else {
-            write!(f, "{}", self.pos.format(self.kind.to_string()))?
+            write!(f, "{}", kind)?;
+            file = Some("");
+        }
+        for (pos, mark, code, label) in spans {
+            let (name, _, _) = pos.get_location();
+            if file != Some(name) {
+                if file.is_some() {
+                    writeln!(f)?;
+                }
+                write!(f, "{}", name)?;
+                file = Some(name);
+            }
+            let (linum, line, start, end) = pos.get_first_line();
+            let marks = mark.to_string().repeat(end - start);
+            write!(
+                f,
+                "\n{}{}\n{}{}{}",
+                paint(color, "1;34", &format!("{:<width$} |", linum)),
+                line,
+                paint(color, "1;34", &format!("{gutter}|")),
+                " ".repeat(start),
+                paint(color, code, &format!("{marks} {label}")),
+            )?;
         }
         if let Some(post) = &self.post_msg {
             write!(f, "\n{}", post)?;
         }
+        for note in self.notes() {
+            write!(f, "\n{}= {} {}", gutter, paint(color, "1", "note:"), note)?;
+        }
+        for other in self.others() {
+            write!(f, "\nError: {:?}", other)?;
+        }
         Ok(())
     }
 }
 
 impl Error {
+    fn new(kind: ErrorKind) -> Self {
+        Self {
+            kind: Box::new(kind),
+            pos: GPosIdx::UNKNOWN,
+            post_msg: None,
+            details: None,
+        }
+    }
+
+    fn details_mut(&mut self) -> &mut Details {
+        self.details.get_or_insert_with(Box::default)
+    }
+
+    fn secondary_spans(&self) -> &[(GPosIdx, String)] {
+        self.details.as_ref().map_or(&[], |d| &d.secondary)
+    }
+
+    fn others(&self) -> &[Error] {
+        self.details.as_ref().map_or(&[], |d| &d.others)
+    }
+
     pub fn with_pos<T: WithPos>(mut self, pos: &T) -> Self {
         self.pos = pos.copy_span();
         self
@@ -38,93 +137,207 @@ impl Error {
         self
     }
 
-    pub fn parse_error(err: pest_consume::Error<parser::Rule>) -> Self {
-        Self {
-            kind: Box::new(ErrorKind::ParseError(err)),
-            pos: GPosIdx::UNKNOWN,
-            post_msg: None,
+    /// Add another location related to this error. The location is shown
+    /// along with `label` after the location of the error.
+    pub fn with_secondary<T: WithPos, S: ToString>(
+        mut self,
+        pos: &T,
+        label: S,
+    ) -> Self {
+        self.details_mut()
+            .secondary
+            .push((pos.copy_span(), label.to_string()));
+        self
+    }
+
+    /// Add a note that is shown after the locations of this error.
+    pub fn with_note<S: ToString>(mut self, note: S) -> Self {
+        self.details_mut().notes.push(note.to_string());
+        self
+    }
+
+    /// The notes attached to this error.
+    pub fn notes(&self) -> &[String] {
+        self.details.as_ref().map_or(&[], |d| &d.notes)
+    }
+
+    /// The locations related to this error with known positions along with
+    /// their labels. Each location is the name of the file and the byte
+    /// offsets of the start and end of the source.
+    pub fn secondary(
+        &self,
+    ) -> impl Iterator<Item = ((&'static str, usize, usize), &str)> {
+        self.secondary_spans().iter().filter_map(|(pos, label)| {
+            pos.into_option()
+                .map(|pos| (pos.get_location(), label.as_str()))
+        })
+    }
+
+    /// The message associated with this error without any position
+    /// information.
+    pub fn message(&self) -> String {
+        match &*self.kind {
+            ErrorKind::ParseError(err) => {
+                format!("Calyx Parser: {}", err.variant.message())
+            }
+            kind => kind.to_string(),
         }
     }
 
-    pub fn reserved_name(name: ir::Id) -> Self {
-        Self {
-            kind: Box::new(ErrorKind::ReservedName(name)),
-            pos: GPosIdx::UNKNOWN,
-            post_msg: None,
+    /// The name of the file and the byte offsets of the start and end of the
+    /// source that caused this error, if they are known.
+    pub fn location(&self) -> Option<(&str, usize, usize)> {
+        if let ErrorKind::ParseError(err) = &*self.kind {
+            let (start, end) = match err.location {
+                InputLocation::Pos(pos) => (pos, pos),
+                InputLocation::Span(span) => span,
+            };
+            return err.path().map(|path| (path, start, end));
         }
+        self.pos.into_option().map(|pos| pos.get_location())
     }
-    pub fn malformed_control(msg: String) -> Self {
-        Self {
-            kind: Box::new(ErrorKind::MalformedControl(msg)),
-            pos: GPosIdx::UNKNOWN,
-            post_msg: None,
+
+    /// Combine `errors` into one error that reports all of them. Returns `Ok`
+    /// if there are no errors.
+    pub fn collect<I: IntoIterator<Item = Error>>(
+        errors: I,
+    ) -> CalyxResult<()> {
+        let mut errors = errors.into_iter().flat_map(Error::into_errors);
+        match errors.next() {
+            None => Ok(()),
+            Some(mut first) => {
+                let others = errors.collect_vec();
+                if !others.is_empty() {
+                    first.details_mut().others = others;
+                }
+                Err(first)
+            }
         }
     }
-    pub fn malformed_structure<S: ToString>(msg: S) -> Self {
-        Self {
-            kind: Box::new(ErrorKind::MalformedStructure(msg.to_string())),
-            pos: GPosIdx::UNKNOWN,
-            post_msg: None,
+
+    /// Split this error into all the errors it reports.
+    pub fn into_errors(mut self) -> Vec<Error> {
+        let others = self
+            .details
+            .as_mut()
+            .map(|d| mem::take(&mut d.others))
+            .unwrap_or_default();
+        iter::once(self).chain(others).collect()
+    }
+
+    /// Iterate over all the errors reported by this error. Unlike
+    /// [Error::into_errors], the returned errors still contain the errors
+    /// reported along with them.
+    pub fn errors(&self) -> impl Iterator<Item = &Error> {
+        iter::once(self).chain(self.others())
+    }
+
+    /// A short name for the kind of this error.
+    pub fn code(&self) -> &'static str {
+        use ErrorKind::*;
+        match &*self.kind {
+            ParseError(_) => "parse-error",
+            ReservedName(_) => "reserved-name",
+            MalformedControl(_) => "malformed-control",
+            MalformedStructure(_) => "malformed-structure",
+            PassAssumption(..) => "pass-assumption",
+            Undefined(..) => "undefined",
+            AlreadyBound(..) => "already-bound",
+            Unused(..) => "unused",
+            Papercut(_) => "papercut",
+            ImpossibleLatencyAnnotation(..) => "impossible-latency",
+            Misc(_) => "misc",
+            InvalidFile(_) => "invalid-file",
+            WriteError(_) => "write-error",
         }
     }
-    pub fn pass_assumption<S: ToString, M: ToString>(pass: S, msg: M) -> Self {
-        Self {
-            kind: Box::new(ErrorKind::PassAssumption(
-                pass.to_string(),
-                msg.to_string(),
-            )),
-            pos: GPosIdx::UNKNOWN,
-            post_msg: None,
+
+    /// A JSON representation of this error (but not the errors reported
+    /// along with it) with the 1-indexed line and column of the start and end
+    /// of the source that caused it.
+    pub fn to_json(&self) -> serde_json::Value {
+        let location = if let ErrorKind::ParseError(err) = &*self.kind {
+            let (start, end) = match err.line_col {
+                LineColLocation::Pos(pos) => (pos, pos),
+                LineColLocation::Span(start, end) => (start, end),
+            };
+            err.path().map(|file| (file, start, end))
+        } else {
+            self.pos.into_option().map(|pos| {
+                let (start, end) = pos.get_line_col();
+                (pos.get_location().0, start, end)
+            })
+        };
+        let mut message = self.message();
+        if let Some(post) = &self.post_msg {
+            message = format!("{message}\n{post}");
         }
+        json!({
+            "file": location.map(|(file, _, _)| file),
+            "line": location.map(|(_, (line, _), _)| line),
+            "column": location.map(|(_, (_, col), _)| col),
+            "end_line": location.map(|(_, _, (line, _))| line),
+            "end_column": location.map(|(_, _, (_, col))| col),
+            "severity": "error",
+            "code": self.code(),
+            "message": message,
+            "related": self
+                .secondary_spans()
+                .iter()
+                .filter_map(|(pos, label)| {
+                    let pos = pos.into_option()?;
+                    let ((line, column), (end_line, end_column)) =
+                        pos.get_line_col();
+                    Some(json!({
+                        "file": pos.get_location().0,
+                        "line": line,
+                        "column": column,
+                        "end_line": end_line,
+                        "end_column": end_column,
+                        "label": label,
+                    }))
+                })
+                .collect::<Vec<_>>(),
+            "notes": self.notes(),
+        })
+    }
+
+    pub fn parse_error(err: pest_consume::Error<parser::Rule>) -> Self {
+        Self::new(ErrorKind::ParseError(err))
+    }
+
+    pub fn reserved_name(name: ir::Id) -> Self {
+        Self::new(ErrorKind::ReservedName(name))
+    }
+    pub fn malformed_control(msg: String) -> Self {
+        Self::new(ErrorKind::MalformedControl(msg))
+    }
+    pub fn malformed_structure<S: ToString>(msg: S) -> Self {
+        Self::new(ErrorKind::MalformedStructure(msg.to_string()))
+    }
+    pub fn pass_assumption<S: ToString, M: ToString>(pass: S, msg: M) -> Self {
+        Self::new(ErrorKind::PassAssumption(pass.to_string(), msg.to_string()))
     }
     pub fn undefined(name: ir::Id, typ: String) -> Self {
-        Self {
-            kind: Box::new(ErrorKind::Undefined(name, typ)),
-            pos: GPosIdx::UNKNOWN,
-            post_msg: None,
-        }
+        Self::new(ErrorKind::Undefined(name, typ))
     }
     pub fn already_bound(name: ir::Id, typ: String) -> Self {
-        Self {
-            kind: Box::new(ErrorKind::AlreadyBound(name, typ)),
-            pos: GPosIdx::UNKNOWN,
-            post_msg: None,
-        }
+        Self::new(ErrorKind::AlreadyBound(name, typ))
     }
     pub fn unused<S: ToString>(group: ir::Id, typ: S) -> Self {
-        Self {
-            kind: Box::new(ErrorKind::Unused(group, typ.to_string())),
-            pos: GPosIdx::UNKNOWN,
-            post_msg: None,
-        }
+        Self::new(ErrorKind::Unused(group, typ.to_string()))
     }
     pub fn papercut(msg: String) -> Self {
-        Self {
-            kind: Box::new(ErrorKind::Papercut(msg)),
-            pos: GPosIdx::UNKNOWN,
-            post_msg: None,
-        }
+        Self::new(ErrorKind::Papercut(msg))
     }
     pub fn misc(msg: String) -> Self {
-        Self {
-            kind: Box::new(ErrorKind::Misc(msg)),
-            pos: GPosIdx::UNKNOWN,
-            post_msg: None,
-        }
+        Self::new(ErrorKind::Misc(msg))
     }
     pub fn invalid_file(msg: String) -> Self {
-        Self {
-            kind: Box::new(ErrorKind::InvalidFile(msg)),
-            pos: GPosIdx::UNKNOWN,
-            post_msg: None,
-        }
+        Self::new(ErrorKind::InvalidFile(msg))
     }
     pub fn write_error(msg: String) -> Self {
-        Self {
-            kind: Box::new(ErrorKind::WriteError(msg)),
-            pos: GPosIdx::UNKNOWN,
-            post_msg: None,
-        }
+        Self::new(ErrorKind::WriteError(msg))
     }
 }
 
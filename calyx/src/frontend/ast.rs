@@ -2,6 +2,7 @@
 use std::path::PathBuf;
 
 use atty::Stream;
+use serde::{Deserialize, Serialize};
 
 use crate::errors::{CalyxResult, Error};
 use crate::ir;
@@ -10,10 +11,10 @@ use crate::utils::GPosIdx;
 use super::parser;
 
 /// Corresponds to an individual Calyx file.
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct NamespaceDef {
-    /// Path to extern files.
-    pub imports: Vec<String>,
+    /// Imported files.
+    pub imports: Vec<Import>,
     /// List of component definitions.
     pub components: Vec<ComponentDef>,
     /// Extern statements and any primitive declarations in them.
@@ -39,8 +40,31 @@ impl NamespaceDef {
     }
 }
 
+/// An `import` statement.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Import {
+    /// The imported path as written in the program.
+    pub path: String,
+    /// The namespace for the definitions in the imported file, if the file
+    /// is imported using `import "lib.futil" as lib;`. Definitions in the
+    /// file are then referred to as `lib::name`.
+    pub alias: Option<ir::Id>,
+    /// Position of the path in the program.
+    pub span: GPosIdx,
+}
+
+impl std::fmt::Display for Import {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "import \"{}\"", self.path)?;
+        if let Some(alias) = self.alias {
+            write!(f, " as {}", alias)?;
+        }
+        write!(f, ";")
+    }
+}
+
 /// AST statement for defining components.
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct ComponentDef {
     /// Name of the component.
     pub name: ir::Id,
@@ -84,7 +108,7 @@ impl ComponentDef {
 
 /// Statement that refers to a port on a subcomponent.
 /// This is distinct from a `Portdef` which defines a port.
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize)]
 pub enum Port {
     /// Refers to the port named `port` on the subcomponent
     /// `component`.
@@ -103,7 +127,7 @@ pub enum Port {
 // AST for wire guard expressions
 // ===================================
 
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize)]
 pub enum NumType {
     Decimal,
     Binary,
@@ -112,7 +136,7 @@ pub enum NumType {
 }
 
 /// Custom bitwidth numbers
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct BitNum {
     pub width: u64,
     pub num_type: NumType,
@@ -122,16 +146,116 @@ pub struct BitNum {
 
 /// Atomic operations used in guard conditions and RHS of the
 /// guarded assignments.
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize)]
 pub enum Atom {
     /// Accessing a particular port on a component.
     Port(Port),
     /// A constant.
     Num(BitNum),
+    /// An inline binary operation like `a.out + 32'd1`.
+    BinOp {
+        op: BinOp,
+        left: Box<Atom>,
+        right: Box<Atom>,
+        span: GPosIdx,
+    },
+    /// An inline slice `atom[hi:lo]` of the bits `hi` down to `lo`.
+    Slice {
+        atom: Box<Atom>,
+        hi: u64,
+        lo: u64,
+        span: GPosIdx,
+    },
+}
+
+/// Operators in inline expressions. Each one is desugared into a cell of the
+/// corresponding `core.futil` primitive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BinOp {
+    Add,
+    Sub,
+    Lsh,
+    Rsh,
+    And,
+    Or,
+    Xor,
+    Eq,
+    Neq,
+    Gt,
+    Lt,
+    Geq,
+    Leq,
+}
+
+impl BinOp {
+    /// The primitive implementing this operator.
+    pub fn primitive(&self) -> &'static str {
+        match self {
+            BinOp::Add => "std_add",
+            BinOp::Sub => "std_sub",
+            BinOp::Lsh => "std_lsh",
+            BinOp::Rsh => "std_rsh",
+            BinOp::And => "std_and",
+            BinOp::Or => "std_or",
+            BinOp::Xor => "std_xor",
+            BinOp::Eq => "std_eq",
+            BinOp::Neq => "std_neq",
+            BinOp::Gt => "std_gt",
+            BinOp::Lt => "std_lt",
+            BinOp::Geq => "std_ge",
+            BinOp::Leq => "std_le",
+        }
+    }
+
+    /// The symbol used for this operator in programs.
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            BinOp::Add => "+",
+            BinOp::Sub => "-",
+            BinOp::Lsh => "<<",
+            BinOp::Rsh => ">>",
+            BinOp::And => "&",
+            BinOp::Or => "|",
+            BinOp::Xor => "^",
+            BinOp::Eq => "==",
+            BinOp::Neq => "!=",
+            BinOp::Gt => ">",
+            BinOp::Lt => "<",
+            BinOp::Geq => ">=",
+            BinOp::Leq => "<=",
+        }
+    }
+
+    /// Operators with a higher precedence bind tighter. Follows the
+    /// precedence of Verilog.
+    pub fn precedence(&self) -> u8 {
+        match self {
+            BinOp::Or => 1,
+            BinOp::Xor => 2,
+            BinOp::And => 3,
+            BinOp::Eq | BinOp::Neq => 4,
+            BinOp::Gt | BinOp::Lt | BinOp::Geq | BinOp::Leq => 5,
+            BinOp::Lsh | BinOp::Rsh => 6,
+            BinOp::Add | BinOp::Sub => 7,
+        }
+    }
+
+    /// Returns true if the operator produces a single bit.
+    pub fn is_comparison(&self) -> bool {
+        matches!(
+            self,
+            BinOp::Eq
+                | BinOp::Neq
+                | BinOp::Gt
+                | BinOp::Lt
+                | BinOp::Geq
+                | BinOp::Leq
+        )
+    }
 }
 
 /// The AST for GuardExprs
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize)]
 pub enum GuardExpr {
     // Logical operations
     And(Box<GuardExpr>, Box<GuardExpr>),
@@ -142,7 +266,7 @@ pub enum GuardExpr {
 }
 
 /// Possible comparison operators for guards.
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize)]
 pub enum GuardComp {
     Eq,
     Neq,
@@ -153,7 +277,7 @@ pub enum GuardComp {
 }
 
 /// Guards `expr` using the optional guard condition `guard`.
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct Guard {
     pub guard: Option<GuardExpr>,
     pub expr: Atom,
@@ -164,7 +288,7 @@ pub struct Guard {
 // ===================================
 
 /// Prototype of the cell definition
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct Proto {
     /// Name of the primitive.
     pub name: ir::Id,
@@ -173,7 +297,7 @@ pub struct Proto {
 }
 
 /// The Cell AST nodes.
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct Cell {
     /// Name of the cell.
     pub name: ir::Id,
@@ -207,7 +331,7 @@ impl Cell {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct Group {
     pub name: ir::Id,
     pub wires: Vec<Wire>,
@@ -216,7 +340,7 @@ pub struct Group {
 }
 
 /// Data for the `->` structure statement.
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct Wire {
     /// Source of the wire.
     pub src: Guard,
@@ -229,7 +353,7 @@ pub struct Wire {
 }
 
 /// Control AST nodes.
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize)]
 #[allow(clippy::large_enum_variant)]
 pub enum Control {
     /// Represents sequential composition of control statements.
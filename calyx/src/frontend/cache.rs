@@ -0,0 +1,374 @@
+//! On-disk cache of parsed Calyx files and workspaces.
+//!
+//! Parsing large libraries dominates the time spent by the compiler on small
+//! programs that import them. When the `CALYX_CACHE_DIR` environment variable
+//! is set, the [Workspace] constructed for a file is stored in that directory
+//! and loaded by later compilations of the file instead of parsing it and the
+//! files it imports again. The AST of every imported file is cached as well
+//! and used when the workspace cannot be, for example because the top-level
+//! file changed.
+//!
+//! Entries are keyed by a SHA-256 hash of the contents of the file, the
+//! version of the compiler and the sources of the parser and the AST, so
+//! stale entries are never used, even by builds of the compiler with the same
+//! version. Workspace entries also record every file read and every path
+//! looked up to resolve imports and externs, and are only used if those are
+//! unchanged. Entries that cannot be read are ignored and the files are parsed
+//! instead.
+//!
+//! Checking that a program is well-formed is not cached: it is still done on
+//! every compilation.
+use super::{ast::NamespaceDef, parser, Workspace};
+use crate::{
+    errors::{CalyxResult, Error},
+    utils::{GPosIdx, GlobalPositionTable},
+};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+/// Environment variable with the directory used to cache parsed files.
+pub const CACHE_DIR_VAR: &str = "CALYX_CACHE_DIR";
+
+/// Version of the compiler that produced a cache entry.
+const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// The sources that determine the AST of a file and how it is serialized.
+/// The AST can change without a new version of the compiler, so they are part
+/// of the key of every entry.
+const SCHEMA: &[&str] = &[
+    include_str!("syntax.pest"),
+    include_str!("parser.rs"),
+    include_str!("ast.rs"),
+    include_str!("workspace.rs"),
+    include_str!("cache.rs"),
+    include_str!("../ir/attribute.rs"),
+    include_str!("../ir/id.rs"),
+    include_str!("../ir/primitives.rs"),
+    include_str!("../utils/position.rs"),
+];
+
+/// The hex encoded SHA-256 hash of `source`.
+fn digest(source: &str) -> String {
+    format!("{:x}", Sha256::digest(source.as_bytes()))
+}
+
+/// A file read to produce a cache entry.
+#[derive(Serialize, Deserialize)]
+struct Source {
+    path: PathBuf,
+    sha256: String,
+}
+
+/// A path looked up while resolving an import or an extern.
+#[derive(PartialEq, Eq, Serialize, Deserialize)]
+struct Probe {
+    path: PathBuf,
+    /// The canonical path and whether it is a file, if the path exists.
+    found: Option<(PathBuf, bool)>,
+}
+
+impl Probe {
+    fn new(path: PathBuf) -> Self {
+        let found = path.canonicalize().ok().map(|canonical| {
+            let is_file = canonical.is_file();
+            (canonical, is_file)
+        });
+        Self { path, found }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct Entry {
+    version: String,
+    /// The files read to produce `data`. The first one is the file the entry
+    /// is for.
+    sources: Vec<Source>,
+    /// The paths looked up to produce `data`.
+    probes: Vec<Probe>,
+    /// The files of the positions in `data` as indices into `sources`.
+    files: Vec<usize>,
+    /// The cached data serialized as JSON. It is only deserialized once the
+    /// entry is known to be up to date.
+    data: String,
+}
+
+/// The files read and the paths looked up while constructing a [Workspace].
+/// A cached workspace is only used if none of them changed.
+#[derive(Default)]
+pub struct Inputs {
+    sources: Vec<Source>,
+    probes: Vec<Probe>,
+}
+
+impl Inputs {
+    /// Read the file at `path`.
+    pub fn read(&mut self, path: &Path) -> CalyxResult<String> {
+        let source = fs::read_to_string(path).map_err(|err| {
+            Error::invalid_file(format!(
+                "Failed to read {}: {err}",
+                path.to_string_lossy(),
+            ))
+        })?;
+        self.sources.push(Source {
+            path: path.to_path_buf(),
+            sha256: digest(&source),
+        });
+        Ok(source)
+    }
+
+    /// Look up `path`. Returns its canonical path and whether it is a file if
+    /// it exists.
+    pub fn probe(&mut self, path: PathBuf) -> Option<(PathBuf, bool)> {
+        let probe = Probe::new(path);
+        let found = probe.found.clone();
+        self.probes.push(probe);
+        found
+    }
+}
+
+/// A directory of cached ASTs and workspaces.
+pub struct Cache {
+    dir: PathBuf,
+}
+
+impl Cache {
+    /// The cache in the directory named by `CALYX_CACHE_DIR`, if it is set.
+    pub fn from_env() -> Option<Self> {
+        std::env::var_os(CACHE_DIR_VAR).map(|dir| Self { dir: dir.into() })
+    }
+
+    /// The location of the entry with the key made of `fields`.
+    fn entry_path(&self, fields: &[&[u8]]) -> PathBuf {
+        let mut hasher = Sha256::new();
+        let schema = SCHEMA.iter().map(|s| s.as_bytes());
+        for field in std::iter::once(VERSION.as_bytes())
+            .chain(schema)
+            .chain(fields.iter().copied())
+        {
+            // Prefix every field with its length so that the fields cannot
+            // run into each other.
+            hasher.update((field.len() as u64).to_le_bytes());
+            hasher.update(field);
+        }
+        self.dir.join(format!("{:x}.json", hasher.finalize()))
+    }
+
+    /// Parse `source` as the contents of the file at `path`, using the cached
+    /// AST if a file with the same contents was parsed before.
+    pub fn parse_source(
+        &self,
+        path: &Path,
+        source: String,
+    ) -> CalyxResult<NamespaceDef> {
+        let time = std::time::Instant::now();
+        let entry_path = self.entry_path(&[b"file", source.as_bytes()]);
+        if let Some(ns) = Self::load(&entry_path, path, &source) {
+            log::info!(
+                "Loaded `{}` from the cache in {}ms",
+                path.to_string_lossy(),
+                time.elapsed().as_millis()
+            );
+            return Ok(ns);
+        }
+
+        let sources = vec![Source {
+            path: path.to_path_buf(),
+            sha256: digest(&source),
+        }];
+        let ns = parser::CalyxParser::parse_source(path, source)?;
+        log::info!(
+            "Parsed `{}` in {}ms",
+            path.to_string_lossy(),
+            time.elapsed().as_millis()
+        );
+        self.store(&entry_path, path, sources, vec![], &ns);
+        Ok(ns)
+    }
+
+    /// Construct the workspace for the file at `file` with the contents
+    /// `source`, using the cached workspace if none of the files it was
+    /// constructed from changed. Otherwise, `construct` is called with the
+    /// source to construct it and record its [Inputs].
+    pub fn construct_workspace(
+        &self,
+        file: &Path,
+        source: String,
+        parent: &Path,
+        lib_paths: &[PathBuf],
+        shallow: bool,
+        construct: impl FnOnce(String, &mut Inputs) -> CalyxResult<Workspace>,
+    ) -> CalyxResult<Workspace> {
+        let time = std::time::Instant::now();
+        let file_name = file.to_string_lossy();
+        let parent_name = parent.to_string_lossy();
+        let lib_names = lib_paths
+            .iter()
+            .map(|p| p.to_string_lossy())
+            .collect::<Vec<_>>();
+        let mut fields: Vec<&[u8]> = vec![
+            b"workspace",
+            file_name.as_bytes(),
+            parent_name.as_bytes(),
+            if shallow { &b"shallow"[..] } else { b"deep" },
+            source.as_bytes(),
+        ];
+        fields.extend(lib_names.iter().map(|p| p.as_bytes()));
+        let entry_path = self.entry_path(&fields);
+        if let Some(ws) = Self::load(&entry_path, file, &source) {
+            log::info!(
+                "Loaded the workspace of `{}` from the cache in {}ms",
+                file_name,
+                time.elapsed().as_millis()
+            );
+            return Ok(ws);
+        }
+
+        let mut inputs = Inputs::default();
+        inputs.sources.push(Source {
+            path: file.to_path_buf(),
+            sha256: digest(&source),
+        });
+        let ws = construct(source, &mut inputs)?;
+        self.store(&entry_path, file, inputs.sources, inputs.probes, &ws);
+        Ok(ws)
+    }
+
+    /// Load the data in the entry at `entry_path` for the file `path` with the
+    /// contents `source`.
+    fn load<T: DeserializeOwned>(
+        entry_path: &Path,
+        path: &Path,
+        source: &str,
+    ) -> Option<T> {
+        let contents = fs::read(entry_path).ok()?;
+        let entry: Entry = match serde_json::from_slice(&contents) {
+            Ok(entry) => entry,
+            Err(err) => {
+                log::info!(
+                    "Ignoring cache entry `{}`: {err}",
+                    entry_path.to_string_lossy()
+                );
+                return None;
+            }
+        };
+        if entry.version != VERSION
+            || entry.files.iter().any(|idx| *idx >= entry.sources.len())
+        {
+            return None;
+        }
+        // The first source is the file itself. The others must be unchanged
+        // on disk.
+        let (first, rest) = entry.sources.split_first()?;
+        if first.sha256 != digest(source) {
+            return None;
+        }
+        let mut files = vec![(path.to_path_buf(), source.to_string())];
+        for src in rest {
+            let contents = fs::read_to_string(&src.path).ok()?;
+            if digest(&contents) != src.sha256 {
+                return None;
+            }
+            files.push((src.path.clone(), contents));
+        }
+        if entry
+            .probes
+            .into_iter()
+            .any(|probe| Probe::new(probe.path.clone()) != probe)
+        {
+            return None;
+        }
+
+        // Only add the files to the position table once the entry is known to
+        // be up to date.
+        let checkpoint = GlobalPositionTable::as_ref().checkpoint();
+        let file_idxs = files
+            .into_iter()
+            .map(|(path, source)| {
+                GlobalPositionTable::as_mut()
+                    .add_file(path.to_string_lossy().to_string(), source)
+            })
+            .collect::<Vec<_>>();
+        let pos_files = entry
+            .files
+            .iter()
+            .map(|idx| file_idxs[*idx])
+            .collect::<Vec<_>>();
+        match GPosIdx::deserialize_in(&pos_files, || {
+            serde_json::from_str::<T>(&entry.data)
+        }) {
+            Ok(data) => Some(data),
+            Err(err) => {
+                GlobalPositionTable::as_mut().truncate(checkpoint);
+                log::info!(
+                    "Ignoring cache entry `{}`: {err}",
+                    entry_path.to_string_lossy()
+                );
+                None
+            }
+        }
+    }
+
+    /// Store `data` for the file at `path`, read from `sources`, in the entry
+    /// at `entry_path`. Failures are only reported as warnings.
+    fn store<T: Serialize>(
+        &self,
+        entry_path: &Path,
+        path: &Path,
+        sources: Vec<Source>,
+        probes: Vec<Probe>,
+        data: &T,
+    ) {
+        if let Err(err) = Self::write(entry_path, sources, probes, data) {
+            log::warn!(
+                "Failed to cache `{}` in `{}`: {err}",
+                path.to_string_lossy(),
+                self.dir.to_string_lossy()
+            );
+        }
+    }
+
+    fn write<T: Serialize>(
+        entry_path: &Path,
+        sources: Vec<Source>,
+        probes: Vec<Probe>,
+        data: &T,
+    ) -> io::Result<()> {
+        let (data, pos_files) =
+            GPosIdx::serialize_files(|| serde_json::to_string(data));
+        let table = GlobalPositionTable::as_ref();
+        let files = pos_files
+            .into_iter()
+            .map(|file| {
+                let name = table.get_name(file);
+                sources
+                    .iter()
+                    .position(|src| src.path.to_string_lossy() == name)
+                    .ok_or_else(|| {
+                        io::Error::new(
+                            io::ErrorKind::Other,
+                            format!("`{name}` was not read from disk"),
+                        )
+                    })
+            })
+            .collect::<io::Result<Vec<_>>>()?;
+        let entry = Entry {
+            version: VERSION.to_string(),
+            sources,
+            probes,
+            files,
+            data: data?,
+        };
+
+        fs::create_dir_all(entry_path.parent().unwrap())?;
+        // Write to a temporary file first so that concurrent compilations
+        // never observe partially written entries.
+        let tmp =
+            entry_path.with_extension(format!("{}.tmp", std::process::id()));
+        fs::write(&tmp, serde_json::to_vec(&entry)?)?;
+        fs::rename(tmp, entry_path)
+    }
+}
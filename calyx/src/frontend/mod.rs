@@ -5,8 +5,10 @@
 //! in the `ir` module.
 
 pub mod ast;
+mod cache;
 pub mod parser;
 mod workspace;
 
 pub use ast::NamespaceDef;
+pub use cache::CACHE_DIR_VAR;
 pub use workspace::Workspace;
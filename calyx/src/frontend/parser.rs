@@ -5,6 +5,7 @@ use super::ast::{self, BitNum, Control, GuardComp as GC, GuardExpr, NumType};
 use crate::errors::{self, CalyxResult};
 use crate::ir::{self, Primitive};
 use crate::utils::{FileIdx, GPosIdx, GlobalPositionTable};
+use pest::error::InputLocation;
 use pest::pratt_parser::{Assoc, Op, PrattParser};
 use pest_consume::{match_nodes, Error, Parser};
 use std::convert::TryInto;
@@ -30,6 +31,9 @@ type Node<'i> = pest_consume::Node<'i, Rule, UserData>;
 // include the grammar file so that Cargo knows to rebuild this file on grammar changes
 const _GRAMMAR: &str = include_str!("syntax.pest");
 
+/// Maximum number of syntax errors reported for a single file.
+const MAX_PARSE_ERRORS: usize = 10;
+
 // Define the precedence of binary operations. We use `lazy_static` so that
 // this is only ever constructed once.
 lazy_static::lazy_static! {
@@ -37,6 +41,24 @@ lazy_static::lazy_static! {
     PrattParser::new()
         .op(Op::infix(Rule::guard_or, Assoc::Left))
         .op(Op::infix(Rule::guard_and, Assoc::Left));
+
+    // Precedence of the operators in inline expressions, which follows
+    // Verilog.
+    static ref ARITH_PRATT: PrattParser<Rule> =
+    PrattParser::new()
+        .op(Op::infix(Rule::arith_or, Assoc::Left))
+        .op(Op::infix(Rule::arith_xor, Assoc::Left))
+        .op(Op::infix(Rule::arith_and, Assoc::Left))
+        .op(Op::infix(Rule::arith_eq, Assoc::Left)
+            | Op::infix(Rule::arith_neq, Assoc::Left))
+        .op(Op::infix(Rule::arith_lt, Assoc::Left)
+            | Op::infix(Rule::arith_gt, Assoc::Left)
+            | Op::infix(Rule::arith_leq, Assoc::Left)
+            | Op::infix(Rule::arith_geq, Assoc::Left))
+        .op(Op::infix(Rule::arith_lsh, Assoc::Left)
+            | Op::infix(Rule::arith_rsh, Assoc::Left))
+        .op(Op::infix(Rule::arith_add, Assoc::Left)
+            | Op::infix(Rule::arith_sub, Assoc::Left));
 }
 
 #[derive(Parser)]
@@ -53,18 +75,8 @@ impl CalyxParser {
                 path.to_string_lossy(),
             ))
         })?;
-        // Add a new file to the position table
         let string_content = std::str::from_utf8(content)?.to_string();
-        let file = GlobalPositionTable::as_mut()
-            .add_file(path.to_string_lossy().to_string(), string_content);
-        let user_data = UserData { file };
-        let content = GlobalPositionTable::as_ref().get_source(file);
-        // Parse the file
-        let inputs =
-            CalyxParser::parse_with_userdata(Rule::file, content, user_data)
-                .map_err(|e| e.with_path(&path.to_string_lossy()))?;
-        let input = inputs.single()?;
-        let out = CalyxParser::file(input)?;
+        let out = CalyxParser::parse_source(path, string_content)?;
         log::info!(
             "Parsed `{}` in {}ms",
             path.to_string_lossy(),
@@ -73,6 +85,79 @@ impl CalyxParser {
         Ok(out)
     }
 
+    /// Parse `source` as the contents of the file at `path`. Used when the
+    /// contents of the file on disk may be out of date.
+    pub fn parse_source(
+        path: &Path,
+        source: String,
+    ) -> CalyxResult<ast::NamespaceDef> {
+        let path_name = path.to_string_lossy();
+        // Add a new file to the position table
+        let file = GlobalPositionTable::as_mut()
+            .add_file(path_name.to_string(), source);
+        let user_data = UserData { file };
+        let content = GlobalPositionTable::as_ref().get_source(file);
+        // Parse the file
+        let inputs = CalyxParser::parse_with_userdata(
+            Rule::file,
+            content,
+            user_data.clone(),
+        )
+        .map_err(|e| Self::recover(content, e, &path_name, user_data))?;
+        let input = inputs.single().map_err(|e| e.with_path(&path_name))?;
+        let out =
+            CalyxParser::file(input).map_err(|e| e.with_path(&path_name))?;
+        Ok(out)
+    }
+
+    /// Report the syntax error `err` in `content` along with the errors that
+    /// follow it. To find more errors, the statement containing each error
+    /// is blanked out and the source is parsed again.
+    fn recover(
+        content: &str,
+        mut err: Error<Rule>,
+        path: &str,
+        user_data: UserData,
+    ) -> errors::Error {
+        let mut errs = vec![];
+        let mut text = content.to_string();
+        loop {
+            let (start, end) = match err.location {
+                InputLocation::Pos(pos) => (pos, pos),
+                InputLocation::Span(span) => span,
+            };
+            // Blanking preserves offsets so the error can be reported against
+            // the original source.
+            let orig = match err.location {
+                InputLocation::Pos(pos) => Error::new_from_pos(
+                    err.variant,
+                    pest::Position::new(content, pos).unwrap(),
+                ),
+                InputLocation::Span(_) => Error::new_from_span(
+                    err.variant,
+                    pest::Span::new(content, start, end).unwrap(),
+                ),
+            };
+            errs.push(errors::Error::from(orig.with_path(path)));
+            if errs.len() >= MAX_PARSE_ERRORS {
+                break;
+            }
+            let Some((from, to)) = statement_bounds(&text, start) else {
+                break;
+            };
+            text = blank(&text, from, to);
+            match CalyxParser::parse_with_userdata(
+                Rule::file,
+                &text,
+                user_data.clone(),
+            ) {
+                Ok(_) => break,
+                Err(e) => err = e,
+            }
+        }
+        errors::Error::collect(errs).unwrap_err()
+    }
+
     pub fn parse<R: Read>(mut r: R) -> CalyxResult<ast::NamespaceDef> {
         let mut buf = String::new();
         r.read_to_string(&mut buf).map_err(|err| {
@@ -125,6 +210,68 @@ impl CalyxParser {
             })
             .parse(pairs)
     }
+
+    /// Parses the operands and operators of an inline expression. Returns the
+    /// expression along with the byte offsets of its start and end.
+    #[allow(clippy::result_large_err)]
+    fn arith_expr_helper(
+        ud: UserData,
+        pairs: pest::iterators::Pairs<Rule>,
+    ) -> ParseResult<(ast::Atom, usize, usize)> {
+        let file = ud.file;
+        // Operators are wrapped in a single rule so that syntax errors
+        // mention them together.
+        let pairs = pairs.map(|pair| match pair.as_rule() {
+            Rule::arith_op | Rule::guard_arith_op => {
+                pair.into_inner().next().unwrap()
+            }
+            _ => pair,
+        });
+        ARITH_PRATT
+            .map_primary(|primary| {
+                let sp = primary.as_span();
+                let (start, end) = (sp.start(), sp.end());
+                Self::arith_primary(Node::new_with_user_data(
+                    primary,
+                    ud.clone(),
+                ))
+                .map(|atom| (atom, start, end))
+            })
+            .map_infix(move |lhs, op, rhs| {
+                let (left, start, _) = lhs?;
+                let (right, _, end) = rhs?;
+                let op = match op.as_rule() {
+                    Rule::arith_add => ast::BinOp::Add,
+                    Rule::arith_sub => ast::BinOp::Sub,
+                    Rule::arith_lsh => ast::BinOp::Lsh,
+                    Rule::arith_rsh => ast::BinOp::Rsh,
+                    Rule::arith_and => ast::BinOp::And,
+                    Rule::arith_or => ast::BinOp::Or,
+                    Rule::arith_xor => ast::BinOp::Xor,
+                    Rule::arith_eq => ast::BinOp::Eq,
+                    Rule::arith_neq => ast::BinOp::Neq,
+                    Rule::arith_gt => ast::BinOp::Gt,
+                    Rule::arith_lt => ast::BinOp::Lt,
+                    Rule::arith_geq => ast::BinOp::Geq,
+                    Rule::arith_leq => ast::BinOp::Leq,
+                    x => unreachable!("Unexpected rule {:?} for arith_expr", x),
+                };
+                let span = GPosIdx(
+                    GlobalPositionTable::as_mut().add_pos(file, start, end),
+                );
+                Ok((
+                    ast::Atom::BinOp {
+                        op,
+                        left: Box::new(left),
+                        right: Box::new(right),
+                        span,
+                    },
+                    start,
+                    end,
+                ))
+            })
+            .parse(pairs)
+    }
 }
 
 #[allow(clippy::large_enum_variant)]
@@ -134,6 +281,56 @@ enum ExtOrComp {
     PrimInline(Primitive),
 }
 
+/// The bounds of the statement containing the offset `pos`. The statement
+/// starts after the last `;`, `{`, or `}` before `pos` and ends with the next
+/// `;`, before the next `}`, or with the block opened by the next `{`.
+/// Returns `None` if the statement only contains whitespace.
+fn statement_bounds(text: &str, pos: usize) -> Option<(usize, usize)> {
+    let start = text[..pos].rfind([';', '{', '}']).map_or(0, |idx| idx + 1);
+    let (idx, c) = text[pos..]
+        .char_indices()
+        .find(|(_, c)| matches!(c, ';' | '{' | '}'))?;
+    let end = match c {
+        ';' => pos + idx + 1,
+        '}' => pos + idx,
+        _ => {
+            let mut depth = 0;
+            let (close, _) =
+                text[pos + idx..].char_indices().find(|&(_, c)| {
+                    match c {
+                        '{' => depth += 1,
+                        '}' => depth -= 1,
+                        _ => (),
+                    }
+                    depth == 0
+                })?;
+            pos + idx + close + 1
+        }
+    };
+    if text[start..end].trim().is_empty() {
+        None
+    } else {
+        Some((start, end))
+    }
+}
+
+/// Replace the bytes between `start` and `end` with spaces while preserving
+/// newlines.
+fn blank(text: &str, start: usize, end: usize) -> String {
+    let blanked = text.as_bytes()[start..end].iter().map(|&b| {
+        if b == b'\n' {
+            '\n'
+        } else {
+            ' '
+        }
+    });
+    text[..start]
+        .chars()
+        .chain(blanked)
+        .chain(text[end..].chars())
+        .collect()
+}
+
 #[pest_consume::parser]
 impl CalyxParser {
     fn EOI(_input: Node) -> ParseResult<()> {
@@ -157,6 +354,10 @@ impl CalyxParser {
         Ok(ir::Id::new(input.as_str()))
     }
 
+    fn qualified_name(input: Node) -> ParseResult<ir::Id> {
+        Ok(ir::Id::new(input.as_str()))
+    }
+
     fn bitwidth(input: Node) -> ParseResult<u64> {
         input
             .as_str()
@@ -272,6 +473,14 @@ impl CalyxParser {
         ))
     }
 
+    fn def_name(input: Node) -> ParseResult<(ir::Id, ir::Attributes)> {
+        Ok(match_nodes!(
+            input.into_children();
+            [qualified_name(name), attributes(attrs)] => (name, attrs),
+            [qualified_name(name)] => (name, ir::Attributes::default()),
+        ))
+    }
+
     fn block_char(input: Node) -> ParseResult<&str> {
         Ok(input.as_str())
     }
@@ -383,7 +592,7 @@ impl CalyxParser {
         let span = Self::get_span(&input);
         Ok(match_nodes!(
             input.into_children();
-            [name_with_attribute((name, attrs)), sig_with_params((p, s))] => ir::Primitive {
+            [def_name((name, attrs)), sig_with_params((p, s))] => ir::Primitive {
                 name,
                 params: p,
                 signature: s,
@@ -391,7 +600,7 @@ impl CalyxParser {
                 is_comb: false,
                 body: None,
             },
-            [comb(_), name_with_attribute((name, attrs)), sig_with_params((p, s))] => ir::Primitive {
+            [comb(_), def_name((name, attrs)), sig_with_params((p, s))] => ir::Primitive {
                 name,
                 params: p,
                 signature: s,
@@ -407,9 +616,9 @@ impl CalyxParser {
         let span = Self::get_span(&input);
         Ok(match_nodes!(
             input.into_children();
-            [at_attributes(attrs), reference(_), identifier(id), identifier(prim), args(args)] =>
+            [at_attributes(attrs), reference(_), identifier(id), qualified_name(prim), args(args)] =>
             ast::Cell::from(id, prim, args, attrs.add_span(span),true),
-            [at_attributes(attrs), identifier(id), identifier(prim), args(args)] =>
+            [at_attributes(attrs), identifier(id), qualified_name(prim), args(args)] =>
             ast::Cell::from(id, prim, args, attrs.add_span(span),false)
         ))
     }
@@ -465,6 +674,42 @@ impl CalyxParser {
         )
     }
 
+    fn slice(input: Node) -> ParseResult<(u64, u64)> {
+        Ok(match_nodes!(
+            input.into_children();
+            [bitwidth(hi), bitwidth(lo)] => (hi, lo)
+        ))
+    }
+
+    fn arith_primary(input: Node) -> ParseResult<ast::Atom> {
+        let span = Self::get_span(&input);
+        let slice = |atom, (hi, lo)| ast::Atom::Slice {
+            atom: Box::new(atom),
+            hi,
+            lo,
+            span,
+        };
+        Ok(match_nodes!(
+            input.into_children();
+            [arith_expr(e)] => e,
+            [expr(e)] => e,
+            [arith_expr(e), slice(range)] => slice(e, range),
+            [expr(e), slice(range)] => slice(e, range),
+        ))
+    }
+
+    fn arith_expr(input: Node) -> ParseResult<ast::Atom> {
+        let ud = input.user_data().clone();
+        Self::arith_expr_helper(ud, input.into_pair().into_inner())
+            .map(|(atom, _, _)| atom)
+    }
+
+    fn guard_operand(input: Node) -> ParseResult<ast::Atom> {
+        let ud = input.user_data().clone();
+        Self::arith_expr_helper(ud, input.into_pair().into_inner())
+            .map(|(atom, _, _)| atom)
+    }
+
     fn guard_eq(_input: Node) -> ParseResult<()> {
         Ok(())
     }
@@ -487,12 +732,12 @@ impl CalyxParser {
     fn cmp_expr(input: Node) -> ParseResult<ast::GuardExpr> {
         Ok(match_nodes!(
             input.into_children();
-            [expr(l), guard_eq(_), expr(r)] => GuardExpr::CompOp(GC::Eq, l, r),
-            [expr(l), guard_neq(_), expr(r)] => GuardExpr::CompOp(GC::Neq, l, r),
-            [expr(l), guard_geq(_), expr(r)] => GuardExpr::CompOp(GC::Geq, l, r),
-            [expr(l), guard_leq(_), expr(r)] => GuardExpr::CompOp(GC::Leq, l, r),
-            [expr(l), guard_gt(_), expr(r)] =>  GuardExpr::CompOp(GC::Gt, l, r),
-            [expr(l), guard_lt(_), expr(r)] =>  GuardExpr::CompOp(GC::Lt, l, r),
+            [guard_operand(l), guard_eq(_), guard_operand(r)] => GuardExpr::CompOp(GC::Eq, l, r),
+            [guard_operand(l), guard_neq(_), guard_operand(r)] => GuardExpr::CompOp(GC::Neq, l, r),
+            [guard_operand(l), guard_geq(_), guard_operand(r)] => GuardExpr::CompOp(GC::Geq, l, r),
+            [guard_operand(l), guard_leq(_), guard_operand(r)] => GuardExpr::CompOp(GC::Leq, l, r),
+            [guard_operand(l), guard_gt(_), guard_operand(r)] =>  GuardExpr::CompOp(GC::Gt, l, r),
+            [guard_operand(l), guard_lt(_), guard_operand(r)] =>  GuardExpr::CompOp(GC::Lt, l, r),
         ))
     }
 
@@ -528,7 +773,7 @@ impl CalyxParser {
     fn switch_stmt(input: Node) -> ParseResult<ast::Guard> {
         Ok(match_nodes!(
             input.into_children();
-            [guard_expr(guard), expr(expr)] => ast::Guard { guard: Some(*guard), expr },
+            [guard_expr(guard), arith_expr(expr)] => ast::Guard { guard: Some(*guard), expr },
         ))
     }
 
@@ -536,7 +781,7 @@ impl CalyxParser {
         let span = Self::get_span(&input);
         Ok(match_nodes!(
             input.into_children();
-            [at_attributes(attrs), LHS(dest), expr(expr)] => ast::Wire {
+            [at_attributes(attrs), LHS(dest), arith_expr(expr)] => ast::Wire {
                 src: ast::Guard { guard: None, expr },
                 dest,
                 attributes: attrs.add_span(span),
@@ -781,7 +1026,7 @@ impl CalyxParser {
         input.clone().into_children();
         [
             comb(_),
-            name_with_attribute((name, attributes)),
+            def_name((name, attributes)),
             signature(sig),
             cells(cells),
             connections(connections)
@@ -789,16 +1034,16 @@ impl CalyxParser {
             let (continuous_assignments, groups) = connections;
             let sig = sig.into_iter().map(|ir::PortDef { name, width, direction, attributes }| {
                 if let ir::Width::Const { value } = width {
-                    Ok(ir::PortDef {
+                    Some(ir::PortDef {
                         name,
                         width: value,
                         direction,
                         attributes
                     })
                 } else {
-                    Err(input.error("Components cannot use parameters"))
+                    None
                 }
-            }).collect::<Result<_, _>>()?;
+            }).collect::<Option<_>>().ok_or_else(|| input.error("Components cannot use parameters"))?;
             Ok(ComponentDef {
                 name,
                 signature: sig,
@@ -811,7 +1056,7 @@ impl CalyxParser {
             })
         },
         [
-            name_with_attribute((name, attributes)),
+            def_name((name, attributes)),
             signature(sig),
             cells(cells),
             connections(connections),
@@ -820,16 +1065,16 @@ impl CalyxParser {
             let (continuous_assignments, groups) = connections;
             let sig = sig.into_iter().map(|ir::PortDef { name, width, direction, attributes }| {
                 if let ir::Width::Const { value } = width {
-                    Ok(ir::PortDef {
+                    Some(ir::PortDef {
                         name,
                         width: value,
                         direction,
                         attributes
                     })
                 } else {
-                    Err(input.error("Components cannot use parameters"))
+                    None
                 }
-            }).collect::<Result<_, _>>()?;
+            }).collect::<Option<_>>().ok_or_else(|| input.error("Components cannot use parameters"))?;
             Ok(ComponentDef {
                 name,
                 signature: sig,
@@ -843,10 +1088,21 @@ impl CalyxParser {
         })
     }
 
-    fn imports(input: Node) -> ParseResult<Vec<String>> {
+    fn import(input: Node) -> ParseResult<ast::Import> {
+        let mut children = input.into_children();
+        let path = children.next().unwrap();
+        let span = Self::get_span(&path);
+        Ok(ast::Import {
+            path: Self::string_lit(path)?,
+            alias: children.next().map(Self::identifier).transpose()?,
+            span,
+        })
+    }
+
+    fn imports(input: Node) -> ParseResult<Vec<ast::Import>> {
         Ok(match_nodes!(
             input.into_children();
-            [string_lit(path)..] => path.collect()
+            [import(imports)..] => imports.collect()
         ))
     }
 
@@ -861,7 +1117,7 @@ impl CalyxParser {
         let span = Self::get_span(&input);
         Ok(match_nodes!(
             input.into_children();
-            [name_with_attribute((name, attrs)), sig_with_params((p, s)), block_string(b)] => {
+            [def_name((name, attrs)), sig_with_params((p, s)), block_string(b)] => {
                 ir::Primitive {
                 name,
                 params: p,
@@ -870,7 +1126,7 @@ impl CalyxParser {
                 is_comb: false,
                 body: Some(b),
             }},
-            [comb(_), name_with_attribute((name, attrs)), sig_with_params((p, s)), block_string(b)] => ir::Primitive {
+            [comb(_), def_name((name, attrs)), sig_with_params((p, s)), block_string(b)] => ir::Primitive {
                 name,
                 params: p,
                 signature: s,
@@ -1,17 +1,26 @@
 use super::{
-    ast::{ComponentDef, NamespaceDef},
+    ast::{ComponentDef, Import, NamespaceDef},
+    cache::{Cache, Inputs},
     parser,
 };
 use crate::{
     errors::{CalyxResult, Error},
     ir,
+    utils::GPosIdx,
 };
+use itertools::Itertools;
 use linked_hash_map::LinkedHashMap;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::{
-    collections::HashSet,
+    collections::{HashMap, HashSet},
+    env,
     path::{Path, PathBuf},
 };
 
+/// Environment variable with library paths that are searched after the paths
+/// passed to [Workspace::construct]. Paths are separated like in `PATH`.
+pub const LIB_PATH_VAR: &str = "CALYX_LIB_PATH";
+
 /// A Workspace represents all Calyx files transitively discovered while trying to compile a
 /// top-level file.
 ///
@@ -24,7 +33,10 @@ use std::{
 /// ```
 ///
 /// The workspace gets the absolute path for `core.futil` and adds `main` to the set of defined
-/// components. `core.futil` is searched *both* relative to the current file and the library path.
+/// components. `core.futil` is searched relative to the current file and then in each library
+/// path in order. The library paths are the paths passed to the workspace followed by the paths
+/// in the `CALYX_LIB_PATH` environment variable. The first file found is used and a warning is
+/// emitted if the file is also found in other locations.
 /// Next `core.futil` is parsed:
 /// ```
 /// extern "core.sv" {
@@ -35,7 +47,19 @@ use std::{
 /// relative path to this file. It *does not* look for `core.sv` on the library path.
 ///
 /// Finally, since `core.futil` does not `import` any file, the parsing process is completed.
-#[derive(Default)]
+///
+/// # Namespaces
+/// Files imported with an alias, like `import "lib.futil" as lib;`, do not add their definitions
+/// to the global namespace. Instead, a component `pe` defined in `lib.futil` is added as
+/// `lib::pe` and uses of `pe` within `lib.futil` are rewritten to refer to `lib::pe`. Files
+/// imported without an alias are always added to the global namespace, even when they are
+/// imported by a namespaced file.
+///
+/// # Caching
+/// If the `CALYX_CACHE_DIR` environment variable is set, the workspace and the parsed ASTs of
+/// imported files are cached in that directory and reused by later compilations as long as the
+/// files and the resolution of imports do not change.
+#[derive(Default, Serialize, Deserialize)]
 pub struct Workspace {
     /// List of component definitions that need to be compiled.
     pub components: Vec<ComponentDef>,
@@ -44,40 +68,185 @@ pub struct Workspace {
     /// compilation mode.
     pub declarations: Vec<ComponentDef>,
     /// Absolute path to extern definitions and primitives defined by them.
+    #[serde(
+        serialize_with = "serialize_externs",
+        deserialize_with = "deserialize_externs"
+    )]
     pub externs: LinkedHashMap<Option<PathBuf>, Vec<ir::Primitive>>,
     /// Original import statements present in the top-level file.
-    pub original_imports: Vec<String>,
+    pub original_imports: Vec<Import>,
     /// Optional opaque metadata attached to the top-level file
     pub metadata: Option<String>,
 }
 
+/// Serializes [Workspace::externs] as a list of pairs because JSON only allows
+/// strings as keys.
+fn serialize_externs<S: Serializer>(
+    externs: &LinkedHashMap<Option<PathBuf>, Vec<ir::Primitive>>,
+    ser: S,
+) -> Result<S::Ok, S::Error> {
+    ser.collect_seq(externs.iter())
+}
+
+fn deserialize_externs<'de, D: Deserializer<'de>>(
+    de: D,
+) -> Result<LinkedHashMap<Option<PathBuf>, Vec<ir::Primitive>>, D::Error> {
+    let externs =
+        Vec::<(Option<PathBuf>, Vec<ir::Primitive>)>::deserialize(de)?;
+    Ok(externs.into_iter().collect())
+}
+
 impl Workspace {
     /// Returns the absolute location to an imported file.
-    /// Imports can refer to files either in the library path or in the parent
-    /// folder.
-    fn canonicalize_import<S>(
-        import: S,
+    /// Imports can refer to files either in the parent folder or in one of
+    /// the library paths. The locations are searched in order.
+    fn canonicalize_import(
+        import: &Import,
         parent: &Path,
-        lib_path: &Path,
-    ) -> CalyxResult<PathBuf>
-    where
-        S: AsRef<Path> + Clone,
-    {
-        let parent_path = parent.join(import.clone());
-        if parent_path.exists() {
-            return Ok(parent_path);
+        lib_paths: &[PathBuf],
+        inputs: &mut Inputs,
+    ) -> CalyxResult<PathBuf> {
+        let search = std::iter::once(parent)
+            .chain(lib_paths.iter().map(|p| p.as_path()))
+            .unique();
+        let found = search
+            .clone()
+            .filter_map(|dir| inputs.probe(dir.join(&import.path)))
+            .filter_map(|(path, is_file)| is_file.then_some(path))
+            .unique()
+            .collect_vec();
+
+        match found.as_slice() {
+            [] => Err(Error::invalid_file(format!(
+                "Import path `{}` not found. Searched in:\n{}",
+                import.path,
+                search
+                    .map(|dir| format!("  {}", dir.to_string_lossy()))
+                    .join("\n")
+            ))
+            .with_pos(&import.span)),
+            [path] => Ok(path.clone()),
+            [path, others @ ..] => {
+                log::warn!(
+                    "Import path `{}` is ambiguous. Using `{}` instead of {}",
+                    import.path,
+                    path.to_string_lossy(),
+                    others
+                        .iter()
+                        .map(|p| format!("`{}`", p.to_string_lossy()))
+                        .join(", ")
+                );
+                Ok(path.clone())
+            }
         }
-        let lib = lib_path.join(import.clone());
-        if lib.exists() {
-            return Ok(lib);
+    }
+
+    /// Adds notes to errors in the imported `file` showing the chain of
+    /// imports that led to the file.
+    fn import_chain<'a>(
+        importer: &'a HashMap<PathBuf, PathBuf>,
+        mut file: &'a Path,
+        mut err: Error,
+    ) -> Error {
+        while let Some(parent) = importer.get(file) {
+            err = err.with_note(format!(
+                "`{}` is imported by `{}`",
+                file.to_string_lossy(),
+                parent.to_string_lossy()
+            ));
+            file = parent;
         }
+        err
+    }
 
-        Err(Error::invalid_file(
-            format!("Import path `{}` found neither in the parent ({}) nor library path ({})",
-            import.as_ref().to_string_lossy(),
-            parent.to_string_lossy(),
-            lib_path.to_string_lossy()
-        )))
+    /// Checks that each import alias in a file is only used once.
+    fn check_aliases(imports: &[Import]) -> CalyxResult<()> {
+        let mut aliases: HashMap<ir::Id, GPosIdx> = HashMap::new();
+        for import in imports {
+            let Some(alias) = import.alias else {
+                continue;
+            };
+            if let Some(prev) = aliases.insert(alias, import.span) {
+                return Err(Error::already_bound(
+                    alias,
+                    "import alias".to_string(),
+                )
+                .with_pos(&import.span)
+                .with_secondary(&prev, "previous definition"));
+            }
+        }
+        Ok(())
+    }
+
+    /// Moves the definitions in `ns` into the namespace `prefix`. Uses of
+    /// definitions from the same file and from the files it imports with an
+    /// alias are qualified with `prefix` as well.
+    fn qualify(ns: &mut NamespaceDef, prefix: &str) {
+        let aliases: HashSet<&str> = ns
+            .imports
+            .iter()
+            .filter_map(|i| i.alias.map(|alias| alias.id.as_str()))
+            .collect();
+        let local: HashSet<ir::Id> = ns
+            .components
+            .iter()
+            .map(|comp| comp.name)
+            .chain(
+                ns.externs
+                    .iter()
+                    .flat_map(|(_, prims)| prims.iter().map(|p| p.name)),
+            )
+            .collect();
+        let qualify = |name: ir::Id| ir::Id::new(format!("{prefix}::{name}"));
+
+        for comp in &mut ns.components {
+            comp.name = qualify(comp.name);
+            for cell in &mut comp.cells {
+                let proto = cell.prototype.name;
+                let namespace = proto.id.as_str().split("::").next().unwrap();
+                if local.contains(&proto)
+                    || (proto.id.as_str().contains("::")
+                        && aliases.contains(namespace))
+                {
+                    cell.prototype.name = qualify(proto);
+                }
+            }
+        }
+        for (_, prims) in &mut ns.externs {
+            for prim in prims {
+                prim.name = qualify(prim.name);
+            }
+        }
+    }
+
+    /// The canonical library paths: `lib_paths` followed by the paths in
+    /// [LIB_PATH_VAR]. Paths in the environment variable that do not exist
+    /// are ignored.
+    fn library_paths(lib_paths: &[PathBuf]) -> CalyxResult<Vec<PathBuf>> {
+        let mut paths = lib_paths
+            .iter()
+            .map(|lib_path| {
+                lib_path.canonicalize().map_err(|err| {
+                    Error::invalid_file(format!(
+                        "Failed to canonicalize library path `{}`: {}",
+                        lib_path.to_string_lossy(),
+                        err
+                    ))
+                })
+            })
+            .collect::<CalyxResult<Vec<_>>>()?;
+        if let Some(var) = env::var_os(LIB_PATH_VAR) {
+            for path in env::split_paths(&var) {
+                match path.canonicalize() {
+                    Ok(path) => paths.push(path),
+                    Err(err) => log::warn!(
+                        "Ignoring library path `{}` in {LIB_PATH_VAR}: {err}",
+                        path.to_string_lossy(),
+                    ),
+                }
+            }
+        }
+        Ok(paths.into_iter().unique().collect())
     }
 
     // Get the absolute path to an extern. Extern can only exist on paths
@@ -85,12 +254,13 @@ impl Workspace {
     fn canonicalize_extern<S>(
         extern_path: S,
         parent: &Path,
+        inputs: &mut Inputs,
     ) -> CalyxResult<PathBuf>
     where
         S: AsRef<Path> + Clone,
     {
         let parent_path = parent.join(extern_path.clone());
-        if parent_path.exists() {
+        if inputs.probe(parent_path.clone()).is_some() {
             return Ok(parent_path);
         }
         Err(Error::invalid_file(format!(
@@ -101,21 +271,21 @@ impl Workspace {
     }
 
     /// Construct a new workspace from an input stream representing a Calyx
-    /// program.
+    /// program. Imports are searched for in `lib_paths`.
     pub fn construct(
         file: &Option<PathBuf>,
-        lib_path: &Path,
+        lib_paths: &[PathBuf],
     ) -> CalyxResult<Self> {
-        Self::construct_with_all_deps(file, lib_path, false)
+        Self::construct_with_all_deps(file, lib_paths, false)
     }
 
     /// Construct the Workspace using the given [NamespaceDef] and ignore all
     /// imported dependencies.
     pub fn construct_shallow(
         file: &Option<PathBuf>,
-        lib_path: &Path,
+        lib_paths: &[PathBuf],
     ) -> CalyxResult<Self> {
-        Self::construct_with_all_deps(file, lib_path, true)
+        Self::construct_with_all_deps(file, lib_paths, true)
     }
 
     fn get_parent(p: &Path) -> PathBuf {
@@ -136,30 +306,105 @@ impl Workspace {
     /// files.
     fn construct_with_all_deps(
         file: &Option<PathBuf>,
-        lib_path: &Path,
+        lib_paths: &[PathBuf],
         // Parse imported components as declarations
         shallow: bool,
     ) -> CalyxResult<Self> {
-        // Construct initial namespace.
-        let namespace = NamespaceDef::construct(file)?;
-        let parent_path = file
-            .as_ref()
-            .map(|p| Self::get_parent(p))
-            .unwrap_or_else(|| PathBuf::from("."));
-
-        // Set of current dependencies
-        let mut dependencies: Vec<PathBuf> = Vec::new();
-        // Set of imports that have already been parsed once.
-        let mut already_imported: HashSet<PathBuf> = HashSet::new();
+        let Some(file) = file else {
+            // Programs read from stdin are not cached.
+            let namespace = NamespaceDef::construct(&None)?;
+            let lib_paths = Self::library_paths(lib_paths)?;
+            let parent = Self::canonical_parent(Path::new("."))?;
+            return Self::from_namespace(
+                namespace,
+                Path::new("<stdin>"),
+                &parent,
+                &lib_paths,
+                shallow,
+                &mut Inputs::default(),
+            );
+        };
+        let source = std::fs::read_to_string(file).map_err(|err| {
+            Error::invalid_file(format!(
+                "Failed to read {}: {err}",
+                file.to_string_lossy(),
+            ))
+        })?;
+        Self::from_source(file, source, lib_paths, shallow)
+    }
 
-        let mut workspace = Workspace::default();
-        let abs_lib_path = lib_path.canonicalize().map_err(|err| {
+    /// Construct the Workspace for the file at `file` using `source` as its
+    /// contents instead of reading it from disk. Components defined by
+    /// imported files are parsed as declarations.
+    pub fn construct_from_source(
+        file: &Path,
+        source: String,
+        lib_paths: &[PathBuf],
+    ) -> CalyxResult<Self> {
+        Self::from_source(file, source, lib_paths, true)
+    }
+
+    /// The canonical path of the directory `parent_path`.
+    fn canonical_parent(parent_path: &Path) -> CalyxResult<PathBuf> {
+        parent_path.canonicalize().map_err(|err| {
             Error::invalid_file(format!(
-                "Failed to canonicalize library path `{}`: {}",
-                lib_path.to_string_lossy(),
+                "Failed to canonicalize parent path `{}`: {}",
+                parent_path.to_string_lossy(),
                 err
             ))
-        })?;
+        })
+    }
+
+    /// Construct the Workspace for the file at `file` with the contents
+    /// `source`. The workspace is loaded from the cache if it is enabled and
+    /// nothing the workspace was constructed from changed.
+    fn from_source(
+        file: &Path,
+        source: String,
+        lib_paths: &[PathBuf],
+        shallow: bool,
+    ) -> CalyxResult<Self> {
+        let lib_paths = Self::library_paths(lib_paths)?;
+        let parent = Self::canonical_parent(&Self::get_parent(file))?;
+        let construct = |source: String, inputs: &mut Inputs| {
+            let namespace = parser::CalyxParser::parse_source(file, source)?;
+            Self::from_namespace(
+                namespace, file, &parent, &lib_paths, shallow, inputs,
+            )
+        };
+        match Cache::from_env() {
+            Some(cache) => cache.construct_workspace(
+                file, source, &parent, &lib_paths, shallow, construct,
+            ),
+            None => construct(source, &mut Inputs::default()),
+        }
+    }
+
+    /// Construct the Workspace from the top-level namespace in `file` by
+    /// transitively parsing all the files it imports. `parent` is the
+    /// canonical path of the directory containing `file` and `lib_paths` are
+    /// the canonical library paths. The files read and the paths looked up
+    /// are recorded in `inputs`.
+    fn from_namespace(
+        namespace: NamespaceDef,
+        file: &Path,
+        parent: &Path,
+        lib_paths: &[PathBuf],
+        // Parse imported components as declarations
+        shallow: bool,
+        inputs: &mut Inputs,
+    ) -> CalyxResult<Self> {
+        // Set of current dependencies and the namespaces they are imported
+        // into.
+        let mut dependencies: Vec<(PathBuf, Option<String>)> = Vec::new();
+        // Set of imports that have already been parsed once.
+        let mut already_imported: HashSet<(PathBuf, Option<String>)> =
+            HashSet::new();
+        // The file that first imported each dependency.
+        let mut importer: HashMap<PathBuf, PathBuf> = HashMap::new();
+
+        let mut workspace = Workspace::default();
+        let cache = Cache::from_env();
 
         // Add original imports to workspace
         workspace.original_imports = namespace.imports.clone();
@@ -169,74 +414,102 @@ impl Workspace {
         workspace.metadata = namespace.metadata.clone();
 
         // Function to merge contents of a namespace into the workspace and
-        // return the dependencies that need to be parsed next.
-        let mut merge_into_ws = |ns: NamespaceDef,
-                                 parent: &Path,
-                                 shallow: bool|
-         -> CalyxResult<Vec<PathBuf>> {
-            // Canonicalize the extern paths and add them
-            for (path, mut exts) in ns.externs {
-                match path {
-                    Some(p) => {
-                        let abs_path = Self::canonicalize_extern(p, parent)?;
-                        workspace
-                            .externs
-                            .entry(Some(abs_path))
-                            .or_default()
-                            .append(&mut exts);
-                    }
-                    None => {
-                        workspace
-                            .externs
-                            .entry(None)
-                            .or_default()
-                            .append(&mut exts);
+        // return the dependencies that need to be parsed next. Definitions
+        // are added to the namespace `prefix`.
+        let mut merge_into_ws =
+            |mut ns: NamespaceDef,
+             parent: &Path,
+             shallow: bool,
+             prefix: Option<&str>,
+             inputs: &mut Inputs|
+             -> CalyxResult<Vec<(PathBuf, Option<String>)>> {
+                Self::check_aliases(&ns.imports)?;
+                if let Some(prefix) = prefix {
+                    Self::qualify(&mut ns, prefix);
+                }
+
+                // Canonicalize the extern paths and add them
+                for (path, mut exts) in ns.externs {
+                    match path {
+                        Some(p) => {
+                            let abs_path =
+                                Self::canonicalize_extern(p, parent, inputs)?;
+                            workspace
+                                .externs
+                                .entry(Some(abs_path))
+                                .or_default()
+                                .append(&mut exts);
+                        }
+                        None => {
+                            workspace
+                                .externs
+                                .entry(None)
+                                .or_default()
+                                .append(&mut exts);
+                        }
                     }
                 }
-            }
 
-            // Add components defined by this namespace to either components or
-            // declarations
-            if shallow {
-                workspace
-                    .declarations
-                    .extend(&mut ns.components.into_iter());
-            } else {
-                workspace.components.extend(&mut ns.components.into_iter());
-            }
+                // Add components defined by this namespace to either components or
+                // declarations
+                if shallow {
+                    workspace
+                        .declarations
+                        .extend(&mut ns.components.into_iter());
+                } else {
+                    workspace.components.extend(&mut ns.components.into_iter());
+                }
 
-            // Return the canonical location of import paths
-            let deps = ns
-                .imports
-                .into_iter()
-                .map(|p| Self::canonicalize_import(p, parent, &abs_lib_path))
-                .collect::<CalyxResult<_>>()?;
+                // Return the canonical location of import paths along with the
+                // namespaces they are imported into. Definitions in files
+                // imported without an alias are not namespaced.
+                let deps = ns
+                    .imports
+                    .iter()
+                    .map(|i| {
+                        let path = Self::canonicalize_import(
+                            i, parent, lib_paths, inputs,
+                        )?;
+                        let ns = i.alias.map(|alias| match prefix {
+                            Some(prefix) => format!("{prefix}::{alias}"),
+                            None => alias.to_string(),
+                        });
+                        Ok((path, ns))
+                    })
+                    .collect::<CalyxResult<_>>()?;
 
-            Ok(deps)
-        };
+                Ok(deps)
+            };
 
         // Merge the initial namespace
-        let parent_canonical = parent_path.canonicalize().map_err(|err| {
-            Error::invalid_file(format!(
-                "Failed to canonicalize parent path `{}`: {}",
-                parent_path.to_string_lossy(),
-                err
-            ))
-        })?;
-        let mut deps = merge_into_ws(namespace, &parent_canonical, false)?;
+        let mut deps = merge_into_ws(namespace, parent, false, None, inputs)?;
+        for (dep, _) in &deps {
+            importer.entry(dep.clone()).or_insert_with(|| file.into());
+        }
         dependencies.append(&mut deps);
 
-        while let Some(p) = dependencies.pop() {
-            if already_imported.contains(&p) {
+        while let Some((p, prefix)) = dependencies.pop() {
+            if already_imported.contains(&(p.clone(), prefix.clone())) {
                 continue;
             }
-            let ns = parser::CalyxParser::parse_file(&p)?;
+            let ns = inputs
+                .read(&p)
+                .and_then(|source| match &cache {
+                    Some(cache) => cache.parse_source(&p, source),
+                    None => parser::CalyxParser::parse_source(&p, source),
+                })
+                .map_err(|err| Self::import_chain(&importer, &p, err))?;
             let parent = Self::get_parent(&p);
 
-            let mut deps = merge_into_ws(ns, &parent, shallow)?;
+            let mut deps =
+                merge_into_ws(ns, &parent, shallow, prefix.as_deref(), inputs)
+                    .map_err(|err| Self::import_chain(&importer, &p, err))?;
+            for (dep, _) in &deps {
+                importer.entry(dep.clone()).or_insert_with(|| p.clone());
+            }
             dependencies.append(&mut deps);
 
-            already_imported.insert(p);
+            already_imported.insert((p, prefix));
         }
         Ok(workspace)
     }
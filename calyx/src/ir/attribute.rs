@@ -1,4 +1,5 @@
 use linked_hash_map::LinkedHashMap;
+use serde::{Deserialize, Serialize};
 use std::{
     convert::TryFrom,
     ops::{Index, IndexMut},
@@ -12,7 +13,7 @@ use crate::{
 use super::Id;
 
 /// Attributes associated with a specific IR structure.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Attributes {
     /// Mapping from the name of the attribute to its value.
     pub(super) attrs: LinkedHashMap<Id, u64>,
@@ -126,6 +127,11 @@ impl Attributes {
         self.span = span;
         self
     }
+
+    /// Replace the span information
+    pub fn set_span(&mut self, span: GPosIdx) {
+        self.span = span;
+    }
 }
 
 impl<T: GetAttributes> WithPos for T {
@@ -1,6 +1,7 @@
 //! IR Builder. Provides convience methods to build various parts of the internal
 //! representation.
 use crate::ir::{self, LibrarySignatures, RRC, WRC};
+use crate::utils::{GPosIdx, WithPos};
 use std::cell::RefCell;
 use std::rc::Rc;
 
@@ -13,6 +14,11 @@ use super::{CellType, PortDef};
 /// them with the `@generated` attribute.
 ///
 /// In order to disable this behavior, call [[ir::Builder::not_generated()]].
+///
+/// Passes that compile a construct into new structure should set the
+/// position of the construct using [[ir::Builder::with_origin()]] or
+/// [[ir::Builder::set_origin()]] so that the cells, groups, and assignments
+/// they build can be traced back to the source program.
 pub struct Builder<'a> {
     /// Component for which this builder is constructing.
     pub component: &'a mut ir::Component,
@@ -23,6 +29,8 @@ pub struct Builder<'a> {
     validate: bool,
     /// Cells added are generated during a compiler pass.
     generated: bool,
+    /// Source position of the construct new structure is derived from.
+    origin: GPosIdx,
 }
 
 impl<'a> Builder<'a> {
@@ -37,6 +45,7 @@ impl<'a> Builder<'a> {
             validate: false,
             // By default, assume that builder is called from a pass
             generated: true,
+            origin: GPosIdx::UNKNOWN,
         }
     }
 
@@ -52,6 +61,28 @@ impl<'a> Builder<'a> {
         self
     }
 
+    /// Mark the structure built by this builder as derived from `origin`.
+    pub fn with_origin<P: WithPos>(mut self, origin: &P) -> Self {
+        self.set_origin(origin);
+        self
+    }
+
+    /// Mark the structure built after this call as derived from `origin`.
+    /// New cells, groups, and assignments inherit the position of `origin`.
+    pub fn set_origin<P: WithPos>(&mut self, origin: &P) {
+        self.origin = origin.copy_span();
+    }
+
+    /// The position inherited by new structure.
+    pub fn origin(&self) -> GPosIdx {
+        self.origin
+    }
+
+    /// The signatures of the primitives available to the builder.
+    pub fn lib(&self) -> &LibrarySignatures {
+        self.lib
+    }
+
     /// Construct a new group and add it to the Component.
     /// The group is guaranteed to start with `prefix`.
     /// Returns a reference to the group.
@@ -68,6 +99,7 @@ impl<'a> Builder<'a> {
 
         // Check if there is a group with the same name.
         let group = Rc::new(RefCell::new(ir::Group::new(name)));
+        group.borrow_mut().attributes.set_span(self.origin);
 
         // Add default holes to the group.
         for (name, width) in &[("go", 1), ("done", 1)] {
@@ -97,7 +129,7 @@ impl<'a> Builder<'a> {
         // Check if there is a group with the same name.
         let group = Rc::new(RefCell::new(ir::CombGroup {
             name,
-            attributes: ir::Attributes::default(),
+            attributes: ir::Attributes::default().add_span(self.origin),
             assignments: vec![],
         }));
 
@@ -172,6 +204,7 @@ impl<'a> Builder<'a> {
             },
             ports,
         );
+        cell.borrow_mut().attributes.set_span(self.origin);
         if self.generated {
             cell.borrow_mut().add_attribute("generated", 1);
         }
@@ -198,6 +231,7 @@ impl<'a> Builder<'a> {
             },
             sig,
         );
+        cell.borrow_mut().attributes.set_span(self.origin);
         if self.generated {
             cell.borrow_mut().add_attribute("generated", 1);
         }
@@ -250,7 +284,7 @@ impl<'a> Builder<'a> {
             dst,
             src,
             guard: Box::new(guard),
-            attributes: ir::Attributes::default(),
+            attributes: ir::Attributes::default().add_span(self.origin),
         }
     }
 
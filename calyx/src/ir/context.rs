@@ -112,6 +112,9 @@ pub struct BackendConf {
     pub enable_verification: bool,
     /// Generate initial assignments for input ports
     pub initialize_inputs: bool,
+    /// Annotate the generated code with the source locations of the
+    /// constructs it was derived from.
+    pub source_locations: bool,
 }
 
 /// The IR Context that represents an entire Calyx program with all of its
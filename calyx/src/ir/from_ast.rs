@@ -1,7 +1,7 @@
 use super::{
-    Assignment, Attributes, BackendConf, Builder, Canonical, CellType,
+    Assignment, Attributes, BackendConf, Builder, Canonical, Cell, CellType,
     Component, Context, Control, Direction, GetAttributes, Guard, Id, Invoke,
-    LibrarySignatures, Port, PortDef, RESERVED_NAMES, RRC,
+    LibrarySignatures, Port, PortDef, PortParent, RESERVED_NAMES, RRC,
 };
 use crate::{
     errors::{CalyxResult, Error},
@@ -134,11 +134,19 @@ pub fn ast_to_ir(mut workspace: frontend::Workspace) -> CalyxResult<Context> {
         sig_ctx.comp_sigs.insert(comp.name, sig.clone());
     }
 
+    // Build all the components so that the errors in each of them are
+    // reported together.
+    let mut errors = vec![];
     let comps: Vec<Component> = workspace
         .components
         .into_iter()
-        .map(|comp| build_component(comp, &mut sig_ctx))
-        .collect::<Result<_, _>>()?;
+        .filter_map(|comp| {
+            build_component(comp, &mut sig_ctx)
+                .map_err(|err| errors.push(err))
+                .ok()
+        })
+        .collect();
+    Error::collect(errors)?;
 
     // Find the entrypoint for the program.
     let entrypoint = comps
@@ -164,28 +172,32 @@ fn validate_component(
 ) -> CalyxResult<()> {
     let mut cells: HashMap<Id, GPosIdx> = HashMap::new();
     let mut groups: HashMap<Id, GPosIdx> = HashMap::new();
+    let mut errors = vec![];
 
     for cell in &comp.cells {
         let attrs = &cell.attributes;
         if let Some(pos) = cells.get(&cell.name) {
-            let prev =
-                pos.into_option().map(|s| s.format("Previous definition"));
-            return Err(Error::already_bound(cell.name, "cell".to_string())
-                .with_pos(attrs)
-                .with_post_msg(prev));
+            errors.push(
+                Error::already_bound(cell.name, "cell".to_string())
+                    .with_pos(attrs)
+                    .with_secondary(pos, "previous definition"),
+            );
+        } else {
+            cells.insert(cell.name, cell.attributes.copy_span());
         }
-        cells.insert(cell.name, cell.attributes.copy_span());
 
         let proto_name = cell.prototype.name;
 
         if sig_ctx.lib.find_primitive(proto_name).is_none()
             && !sig_ctx.comp_sigs.contains_key(&proto_name)
         {
-            return Err(Error::undefined(
-                proto_name,
-                "primitive or component".to_string(),
-            )
-            .with_pos(attrs));
+            errors.push(
+                Error::undefined(
+                    proto_name,
+                    "primitive or component".to_string(),
+                )
+                .with_pos(attrs),
+            );
         }
     }
 
@@ -193,30 +205,23 @@ fn validate_component(
         let name = &group.name;
         let attrs = &group.attributes;
         if let Some(pos) = groups.get(name) {
-            let prev =
-                pos.into_option().map(|s| s.format("Previous definition"));
-            return Err(Error::already_bound(*name, "group".to_string())
-                .with_pos(attrs)
-                .with_post_msg(prev));
-        }
-        if let Some(pos) = cells.get(name) {
-            let prev =
-                pos.into_option().map(|s| s.format("Previous definition"));
-            return Err(Error::already_bound(*name, "cell".to_string())
-                .with_pos(attrs)
-                .with_post_msg(prev));
-        }
-        if let Some(pos) = cells.get(name) {
-            let prev =
-                pos.into_option().map(|s| s.format("Previous definition"));
-            return Err(Error::already_bound(*name, "cell".to_string())
-                .with_pos(attrs)
-                .with_post_msg(prev));
+            errors.push(
+                Error::already_bound(*name, "group".to_string())
+                    .with_pos(attrs)
+                    .with_secondary(pos, "previous definition"),
+            );
+        } else if let Some(pos) = cells.get(name) {
+            errors.push(
+                Error::already_bound(*name, "cell".to_string())
+                    .with_pos(attrs)
+                    .with_secondary(pos, "previous definition"),
+            );
+        } else {
+            groups.insert(*name, group.attributes.copy_span());
         }
-        groups.insert(*name, group.attributes.copy_span());
     }
 
-    Ok(())
+    Error::collect(errors)
 }
 
 /// Build an `ir::component::Component` using an `frontend::ast::ComponentDef`.
@@ -238,13 +243,17 @@ fn build_component(
         .into_iter()
         .for_each(|cell| add_cell(cell, sig_ctx, &mut builder));
 
-    comp.groups
+    let mut errors = comp
+        .groups
         .into_iter()
-        .try_for_each(|g| add_group(g, &mut builder))?;
+        .filter_map(|g| add_group(g, &mut builder).err())
+        .collect::<Vec<_>>();
 
-    let continuous_assignments =
-        build_assignments(comp.continuous_assignments, &mut builder)?;
-    builder.component.continuous_assignments = continuous_assignments;
+    match build_assignments(comp.continuous_assignments, &mut builder) {
+        Ok(assigns) => builder.component.continuous_assignments = assigns,
+        Err(err) => errors.push(err),
+    }
+    Error::collect(errors)?;
 
     // Build the Control ast using ast::Control.
     let control =
@@ -343,9 +352,13 @@ fn get_port_ref(port: ast::Port, comp: &Component) -> CalyxResult<RRC<Port>> {
 /// If the atom is a number and the context doesn't already contain a cell
 /// for this constant, instantiate the constant node and get the "out" port
 /// from it.
+/// Inline expressions are desugared into cells of the `core.futil`
+/// primitives. The assignments to the inputs of these cells are added to
+/// `assigns`.
 fn atom_to_port(
     atom: ast::Atom,
     builder: &mut Builder,
+    assigns: &mut Vec<Assignment>,
 ) -> CalyxResult<RRC<Port>> {
     match atom {
         ast::Atom::Num(n) => {
@@ -353,9 +366,120 @@ fn atom_to_port(
             Ok(Rc::clone(&port))
         }
         ast::Atom::Port(p) => get_port_ref(p, builder.component),
+        ast::Atom::BinOp {
+            op,
+            left,
+            right,
+            span,
+        } => {
+            let left = ensure_direction(
+                atom_to_port(*left, builder, assigns)?,
+                Direction::Output,
+            )?;
+            let right = ensure_direction(
+                atom_to_port(*right, builder, assigns)?,
+                Direction::Output,
+            )?;
+            let (lw, rw) = (left.borrow().width, right.borrow().width);
+            if lw != rw {
+                return Err(Error::malformed_structure(format!(
+                    "Mismatched widths for `{}`. Left operand has size {} while right operand has size {}.",
+                    op.symbol(),
+                    lw,
+                    rw
+                ))
+                .with_pos(&span));
+            }
+            let cell =
+                add_inline_primitive(builder, op.primitive(), &[lw], span)?;
+            let cell = cell.borrow();
+            inline_assign(builder, cell.get("left"), left, span, assigns);
+            inline_assign(builder, cell.get("right"), right, span, assigns);
+            Ok(cell.get("out"))
+        }
+        ast::Atom::Slice { atom, hi, lo, span } => {
+            let port = ensure_direction(
+                atom_to_port(*atom, builder, assigns)?,
+                Direction::Output,
+            )?;
+            let width = port.borrow().width;
+            if lo > hi || hi >= width {
+                return Err(Error::malformed_structure(format!(
+                    "Invalid slice `[{}:{}]` of a value with size {}.",
+                    hi, lo, width
+                ))
+                .with_pos(&span));
+            }
+            if lo == 0 && hi + 1 == width {
+                return Ok(port);
+            }
+            // Shift the bits of the slice down before truncating the value.
+            let port = if lo == 0 {
+                port
+            } else {
+                let rsh =
+                    add_inline_primitive(builder, "std_rsh", &[width], span)?;
+                let rsh = rsh.borrow();
+                let amount =
+                    builder.add_constant(lo, width).borrow().get("out");
+                inline_assign(builder, rsh.get("left"), port, span, assigns);
+                inline_assign(builder, rsh.get("right"), amount, span, assigns);
+                rsh.get("out")
+            };
+            let slice = add_inline_primitive(
+                builder,
+                "std_slice",
+                &[width, hi - lo + 1],
+                span,
+            )?;
+            let slice = slice.borrow();
+            inline_assign(builder, slice.get("in"), port, span, assigns);
+            Ok(slice.get("out"))
+        }
     }
 }
 
+/// Add a cell of `primitive` for an inline expression at `span`.
+fn add_inline_primitive(
+    builder: &mut Builder,
+    primitive: &str,
+    params: &[u64],
+    span: GPosIdx,
+) -> CalyxResult<RRC<Cell>> {
+    if builder.lib().find_primitive(primitive).is_none() {
+        return Err(Error::malformed_structure(format!(
+            "Inline expression requires the primitive `{}`. Import `primitives/core.futil` to use inline expressions.",
+            primitive
+        ))
+        .with_pos(&span));
+    }
+    // Names like `and` are reserved in Verilog.
+    let mut prefix = primitive.strip_prefix("std_").unwrap_or(primitive);
+    if RESERVED_NAMES.contains(&prefix) {
+        prefix = primitive;
+    }
+    let origin = builder.origin();
+    builder.set_origin(&span);
+    let cell = builder.add_primitive(prefix, primitive, params);
+    builder.set_origin(&origin);
+    Ok(cell)
+}
+
+/// Add an assignment of `src` to the input `dst` of a cell built for an inline
+/// expression at `span`.
+fn inline_assign(
+    builder: &mut Builder,
+    dst: RRC<Port>,
+    src: RRC<Port>,
+    span: GPosIdx,
+    assigns: &mut Vec<Assignment>,
+) {
+    let origin = builder.origin();
+    builder.set_origin(&span);
+    assigns.push(builder.build_assignment(dst, src, Guard::True));
+    builder.set_origin(&origin);
+}
+
 /// Ensures that the given port has the required direction.
 fn ensure_direction(pr: RRC<Port>, dir: Direction) -> CalyxResult<RRC<Port>> {
     let port_dir = pr.borrow().direction.clone();
@@ -380,12 +504,15 @@ fn ensure_direction(pr: RRC<Port>, dir: Direction) -> CalyxResult<RRC<Port>> {
 
 /// Build an ir::Assignment from ast::Wire.
 /// The Assignment contains pointers to the relevant ports.
+/// Assignments to the inputs of cells built for inline expressions in the wire
+/// are added to `assigns`.
 fn build_assignment(
     wire: ast::Wire,
     builder: &mut Builder,
+    assigns: &mut Vec<Assignment>,
 ) -> CalyxResult<Assignment> {
     let src_port: RRC<Port> = ensure_direction(
-        atom_to_port(wire.src.expr, builder)?,
+        atom_to_port(wire.src.expr, builder, assigns)?,
         Direction::Output,
     )?;
     let dst_port: RRC<Port> = ensure_direction(
@@ -398,10 +525,29 @@ fn build_assignment(
             src_port.borrow().width,
             dst_port.borrow().width,
         );
-        return Err(Error::malformed_structure(msg).with_pos(&wire.attributes));
+        let mut err =
+            Error::malformed_structure(msg).with_pos(&wire.attributes);
+        // Point to the cells that define the widths of the ports.
+        for port in [&dst_port, &src_port] {
+            let port = port.borrow();
+            if let PortParent::Cell(cell_ref) = &port.parent {
+                let cell_ref = cell_ref.upgrade();
+                let cell = cell_ref.borrow();
+                err = err.with_secondary(
+                    &cell.attributes,
+                    format!(
+                        "`{}.{}` has width {}",
+                        cell.name(),
+                        port.name,
+                        port.width
+                    ),
+                );
+            }
+        }
+        return Err(err);
     }
     let guard = match wire.src.guard {
-        Some(g) => build_guard(g, builder)?,
+        Some(g) => build_guard(g, builder, assigns)?,
         None => Guard::True,
     };
 
@@ -414,34 +560,58 @@ fn build_assignments(
     assigns: Vec<ast::Wire>,
     builder: &mut Builder,
 ) -> CalyxResult<Vec<Assignment>> {
-    assigns
-        .into_iter()
-        .map(|w| {
-            let attrs = w.attributes.clone();
-            build_assignment(w, builder).map_err(|err| err.with_pos(&attrs))
-        })
-        .collect::<CalyxResult<Vec<_>>>()
+    let mut errors = vec![];
+    let mut built = vec![];
+    for w in assigns {
+        let attrs = w.attributes.clone();
+        let mut inline = vec![];
+        match build_assignment(w, builder, &mut inline) {
+            Ok(assign) => {
+                built.extend(inline);
+                built.push(assign);
+            }
+            // Errors in inline expressions point to the expression.
+            Err(err) if err.location().is_some() => errors.push(err),
+            Err(err) => errors.push(err.with_pos(&attrs)),
+        }
+    }
+    Error::collect(errors)?;
+    Ok(built)
 }
 
 /// Transform an ast::GuardExpr to an ir::Guard.
-fn build_guard(guard: ast::GuardExpr, bd: &mut Builder) -> CalyxResult<Guard> {
+/// Assignments to the inputs of cells built for inline expressions in the
+/// guard are added to `assigns`.
+fn build_guard(
+    guard: ast::GuardExpr,
+    bd: &mut Builder,
+    assigns: &mut Vec<Assignment>,
+) -> CalyxResult<Guard> {
     use ast::GuardExpr as GE;
 
-    let into_box_guard = |g: Box<GE>, bd: &mut Builder| -> CalyxResult<_> {
-        Ok(Box::new(build_guard(*g, bd)?))
-    };
-
     Ok(match guard {
         GE::Atom(atom) => Guard::port(ensure_direction(
-            atom_to_port(atom, bd)?,
+            atom_to_port(atom, bd, assigns)?,
             Direction::Output,
         )?),
-        GE::Or(l, r) => Guard::or(build_guard(*l, bd)?, build_guard(*r, bd)?),
-        GE::And(l, r) => Guard::and(build_guard(*l, bd)?, build_guard(*r, bd)?),
-        GE::Not(g) => Guard::Not(into_box_guard(g, bd)?),
+        GE::Or(l, r) => Guard::or(
+            build_guard(*l, bd, assigns)?,
+            build_guard(*r, bd, assigns)?,
+        ),
+        GE::And(l, r) => Guard::and(
+            build_guard(*l, bd, assigns)?,
+            build_guard(*r, bd, assigns)?,
+        ),
+        GE::Not(g) => Guard::Not(Box::new(build_guard(*g, bd, assigns)?)),
         GE::CompOp(op, l, r) => {
-            let nl = ensure_direction(atom_to_port(l, bd)?, Direction::Output)?;
-            let nr = ensure_direction(atom_to_port(r, bd)?, Direction::Output)?;
+            let nl = ensure_direction(
+                atom_to_port(l, bd, assigns)?,
+                Direction::Output,
+            )?;
+            let nr = ensure_direction(
+                atom_to_port(r, bd, assigns)?,
+                Direction::Output,
+            )?;
             let nop = match op {
                 ast::GuardComp::Eq => PortComp::Eq,
                 ast::GuardComp::Neq => PortComp::Neq,
@@ -491,10 +661,12 @@ fn build_control(
                 })?,
             );
 
+            // Invoke arguments cannot contain inline expressions so no
+            // assignments are generated for them.
             let inputs = inputs
                 .into_iter()
                 .map(|(id, port)| {
-                    atom_to_port(port, builder)
+                    atom_to_port(port, builder, &mut vec![])
                         .and_then(|pr| ensure_direction(pr, Direction::Output))
                         .map(|p| (id, p))
                 })
@@ -502,7 +674,7 @@ fn build_control(
             let outputs = outputs
                 .into_iter()
                 .map(|(id, port)| {
-                    atom_to_port(port, builder)
+                    atom_to_port(port, builder, &mut vec![])
                         .and_then(|pr| ensure_direction(pr, Direction::Input))
                         .map(|p| (id, p))
                 })
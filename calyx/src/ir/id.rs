@@ -16,6 +16,19 @@ impl Id {
             id: GSym::from(id.to_string()),
         }
     }
+
+    /// The name without the namespaces of the imports that define it. For
+    /// example, `lib::pe` becomes `pe`.
+    pub fn unqualified(&self) -> &'static str {
+        self.id.as_str().rsplit("::").next().unwrap()
+    }
+
+    /// The name as an identifier that is valid in generated code. The
+    /// namespaces of qualified names are joined with `__`: `lib::pe` becomes
+    /// `lib__pe`.
+    pub fn mangled(&self) -> String {
+        self.id.as_str().replace("::", "__")
+    }
 }
 
 /* =================== Impls for Id to make them easier to use ============== */
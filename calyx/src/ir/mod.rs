@@ -15,6 +15,7 @@ mod context;
 mod control;
 mod guard;
 mod id;
+mod numeric_format;
 mod primitives;
 mod printer;
 mod reserved_names;
@@ -32,6 +33,7 @@ pub use control::{
 };
 pub use guard::{Guard, PortComp};
 pub use id::Id;
+pub use numeric_format::NumericFormat;
 pub use primitives::{PortDef, Primitive, Width};
 pub use printer::Printer;
 pub use reserved_names::RESERVED_NAMES;
@@ -0,0 +1,117 @@
+//! Numeric formats of the values on ports and in cells.
+//!
+//! Formats are declared with attributes:
+//! - `@signed` marks two's complement integers.
+//! - `@unsigned` marks unsigned integers.
+//! - `@frac_width(n)` marks fixed-point numbers with `n` fractional bits.
+//!   Fixed-point numbers are unsigned unless they are also marked `@signed`.
+//!
+//! Ports without a format use the format of their cell if the port carries
+//! the values of the cell, which means that its width is the `WIDTH`
+//! parameter of the cell.
+use super::{Attributes, Cell, Port, PortParent};
+use crate::errors::{CalyxResult, Error};
+use std::fmt::Display;
+
+/// How the bits of a value are interpreted as a number.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NumericFormat {
+    /// Unsigned integer.
+    Unsigned,
+    /// Two's complement integer.
+    Signed,
+    /// Unsigned fixed-point number with the given number of fractional bits.
+    UFixed(u64),
+    /// Two's complement fixed-point number with the given number of
+    /// fractional bits.
+    SFixed(u64),
+}
+
+impl NumericFormat {
+    /// The format declared by the attributes, if any.
+    /// Returns an error if the attributes declare conflicting formats.
+    pub fn from_attributes(attrs: &Attributes) -> CalyxResult<Option<Self>> {
+        let signed = attrs.has("signed");
+        if signed && attrs.has("unsigned") {
+            return Err(Error::malformed_structure(
+                "Value is marked both `@signed` and `@unsigned`",
+            )
+            .with_pos(attrs));
+        }
+        Ok(match (attrs.get("frac_width"), signed) {
+            (Some(&frac), true) => Some(Self::SFixed(frac)),
+            (Some(&frac), false) => Some(Self::UFixed(frac)),
+            (None, true) => Some(Self::Signed),
+            (None, false) if attrs.has("unsigned") => Some(Self::Unsigned),
+            (None, false) => None,
+        })
+    }
+
+    /// The format of the values stored in `cell`, if it declares one.
+    pub fn of_cell(cell: &Cell) -> Option<Self> {
+        Self::from_attributes(&cell.attributes).ok().flatten()
+    }
+
+    /// The format of the values on `port`. Uses the format of the parent
+    /// cell if the port does not declare one and carries the values of the
+    /// cell.
+    pub fn of_port(port: &Port) -> Option<Self> {
+        if let Some(format) =
+            Self::from_attributes(&port.attributes).ok().flatten()
+        {
+            return Some(format);
+        }
+        match &port.parent {
+            PortParent::Cell(cell) => {
+                let cell_ref = cell.upgrade();
+                let cell = cell_ref.borrow();
+                if cell.get_parameter("WIDTH") == Some(port.width) {
+                    Self::of_cell(&cell)
+                } else {
+                    None
+                }
+            }
+            PortParent::Group(_) => None,
+        }
+    }
+
+    /// Returns true if the format represents negative numbers.
+    pub fn is_signed(&self) -> bool {
+        matches!(self, Self::Signed | Self::SFixed(_))
+    }
+
+    /// The number of fractional bits of fixed-point formats.
+    pub fn frac_width(&self) -> Option<u64> {
+        match self {
+            Self::UFixed(frac) | Self::SFixed(frac) => Some(*frac),
+            Self::Unsigned | Self::Signed => None,
+        }
+    }
+
+    /// Returns true if values in this format can be used where values in
+    /// `other` are expected. Integers are compatible with fixed-point numbers
+    /// of the same signedness since primitives such as `std_fp_sadd` only
+    /// declare the signedness of their ports.
+    pub fn is_compatible(&self, other: &Self) -> bool {
+        self.is_signed() == other.is_signed()
+            && match (self.frac_width(), other.frac_width()) {
+                (Some(l), Some(r)) => l == r,
+                _ => true,
+            }
+    }
+}
+
+impl Display for NumericFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Unsigned => write!(f, "unsigned"),
+            Self::Signed => write!(f, "signed"),
+            Self::UFixed(frac) => {
+                write!(f, "unsigned fixed-point with {frac} fractional bits")
+            }
+            Self::SFixed(frac) => {
+                write!(f, "signed fixed-point with {frac} fractional bits")
+            }
+        }
+    }
+}
@@ -1,6 +1,7 @@
 use super::{Attributes, Direction, GetName, Id};
 use crate::errors::{CalyxResult, Error};
 use linked_hash_map::LinkedHashMap;
+use serde::{Deserialize, Serialize};
 use smallvec::SmallVec;
 
 /// Representation of a external primitive definition.
@@ -16,7 +17,7 @@ use smallvec::SmallVec;
 ///
 /// The signature of a port is represented using [PortDef] which also specify
 /// the direction of the port.
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Primitive {
     /// Name of this primitive.
     pub name: Id,
@@ -90,7 +91,7 @@ impl GetName for Primitive {
 /// Definition of a port parameterized by a width type.
 /// Ports on Primitives can be parameteris and use [Width].
 /// Ports on Components cannot be parameterized and therefore use `u64`.
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct PortDef<W> {
     /// The name of the port.
     pub name: Id,
@@ -132,7 +133,7 @@ where
 }
 
 /// Represents an abstract width of a primitive signature.
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub enum Width {
     /// The width is a constant.
     Const { value: u64 },
@@ -2,12 +2,13 @@
 
 use super::{Attributes, GetAttributes, Guard, Id, PortDef, RRC, WRC};
 use itertools::Itertools;
+use serde::{Deserialize, Serialize};
 use smallvec::{smallvec, SmallVec};
 use std::hash::Hash;
 use std::rc::Rc;
 
 /// Direction of a port on a cell.
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum Direction {
     /// Input port.
     Input,
@@ -444,6 +445,16 @@ impl Assignment {
     }
 }
 
+impl GetAttributes for Assignment {
+    fn get_attributes(&self) -> &Attributes {
+        &self.attributes
+    }
+
+    fn get_mut_attributes(&mut self) -> &mut Attributes {
+        &mut self.attributes
+    }
+}
+
 /// A Group of assignments that perform a logical action.
 #[derive(Debug)]
 pub struct Group {
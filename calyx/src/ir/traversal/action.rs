@@ -1,6 +1,7 @@
 //! Actions control the traversal of control programs.
 use crate::errors::CalyxResult;
-use crate::ir::Control;
+use crate::ir::{self, Control, GetAttributes};
+use crate::utils::{GPosIdx, WithPos};
 
 /// Result of performing a visit.
 pub type VisResult = CalyxResult<Action>;
@@ -39,10 +40,17 @@ impl Action {
     }
 
     /// Applies the Change action if `self` is a Change action.
-    /// Otherwise passes the action through unchanged
-    pub(super) fn apply_change(self, con: &mut Control) -> Action {
+    /// Otherwise passes the action through unchanged.
+    /// Nodes in the new control program without a position inherit `span`,
+    /// the position of the node they replace.
+    pub(super) fn apply_change(
+        self,
+        con: &mut Control,
+        span: GPosIdx,
+    ) -> Action {
         match self {
-            Action::Change(c) => {
+            Action::Change(mut c) => {
+                inherit_span(&mut c, span);
                 *con = *c;
                 Action::Continue
             }
@@ -60,3 +68,26 @@ impl Action {
         }
     }
 }
+
+/// Set the position of `con` and its children that do not have a position.
+/// Children inherit the position of their closest ancestor with one.
+fn inherit_span(con: &mut Control, span: GPosIdx) {
+    if con.copy_span() == GPosIdx::UNKNOWN {
+        con.get_mut_attributes().set_span(span);
+    }
+    let span = con.copy_span();
+    match con {
+        Control::Seq(ir::Seq { stmts, .. })
+        | Control::Par(ir::Par { stmts, .. }) => {
+            stmts.iter_mut().for_each(|stmt| inherit_span(stmt, span))
+        }
+        Control::If(ir::If {
+            tbranch, fbranch, ..
+        }) => {
+            inherit_span(tbranch, span);
+            inherit_span(fbranch, span);
+        }
+        Control::While(ir::While { body, .. }) => inherit_span(body, span),
+        Control::Enable(_) | Control::Invoke(_) | Control::Empty(_) => {}
+    }
+}
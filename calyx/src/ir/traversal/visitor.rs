@@ -7,6 +7,7 @@ use super::action::{Action, VisResult};
 use super::{CompTraversal, Order};
 use crate::errors::CalyxResult;
 use crate::ir::{self, Component, Context, Control, LibrarySignatures};
+use crate::utils::WithPos;
 use std::collections::HashSet;
 use std::rc::Rc;
 
@@ -132,6 +133,7 @@ pub trait Visitor {
     where
         Self: Sized,
     {
+        let span = comp.control.borrow().copy_span();
         self.start(comp, signatures, components)?
             .and_then(|| {
                 // Create a clone of the reference to the Control
@@ -148,7 +150,7 @@ pub trait Visitor {
                 Ok(Action::Continue)
             })?
             .and_then(|| self.finish(comp, signatures, components))?
-            .apply_change(&mut comp.control.borrow_mut());
+            .apply_change(&mut comp.control.borrow_mut(), span);
         Ok(())
     }
 
@@ -182,6 +184,12 @@ pub trait Visitor {
         })?;
         context.components = po.take();
 
+        self.finish_context(context)
+    }
+
+    /// Executed after all the components have been visited by
+    /// [Visitor::do_pass].
+    fn finish_context(&mut self, _ctx: &mut Context) -> CalyxResult<()> {
         Ok(())
     }
 
@@ -365,6 +373,8 @@ impl Visitable for Control {
         sigs: &LibrarySignatures,
         comps: &[ir::Component],
     ) -> VisResult {
+        // Visitors may move the attributes out of the node they replace.
+        let span = self.copy_span();
         let res = match self {
             Control::Seq(ctrl) => visitor
                 .start_seq(ctrl, component, sigs, comps)?
@@ -407,7 +417,7 @@ impl Visitable for Control {
                 visitor.invoke(data, component, sigs, comps)?
             }
         };
-        Ok(res.apply_change(self))
+        Ok(res.apply_change(self, span))
     }
 }
 
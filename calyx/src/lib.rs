@@ -19,9 +19,9 @@
 //!   // File to parse
 //!   let file: std::path::PathBuf = "../tests/correctness/seq.futil".into();
 //!   // Location of the calyx repository
-//!   let lib_path: std::path::PathBuf = "../".into();
+//!   let lib_paths: Vec<std::path::PathBuf> = vec!["../".into()];
 //!   // Parse the calyx program
-//!   let ws = frontend::Workspace::construct(&Some(file), &lib_path)?;
+//!   let ws = frontend::Workspace::construct(&Some(file), &lib_paths)?;
 //!   // Convert it into an ir::Context
 //!   let mut ctx = ir::from_ast::ast_to_ir(ws)?;
 //!   // Print out the components in the program
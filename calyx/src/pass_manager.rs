@@ -151,6 +151,20 @@ impl PassManager {
         Ok((passes, excl_set))
     }
 
+    /// Returns the names of the passes a plan runs, in order, leaving out the
+    /// excluded passes.
+    pub fn resolve_plan(
+        &self,
+        incl: &[String],
+        excl: &[String],
+    ) -> CalyxResult<Vec<String>> {
+        let (passes, excl_set) = self.create_plan(incl, excl)?;
+        Ok(passes
+            .into_iter()
+            .filter(|name| !excl_set.contains(name))
+            .collect())
+    }
+
     /// Executes a given "plan" constructed using the incl and excl lists.
     pub fn execute_plan(
         &self,
@@ -4,6 +4,7 @@ use crate::ir::{
     LibrarySignatures,
 };
 use ir::traversal::{Action, VisResult};
+use itertools::Itertools;
 
 #[derive(Default)]
 /// Adds assignments from a components `clk` port to every
@@ -27,22 +28,19 @@ impl Visitor for ClkInsertion {
         sigs: &LibrarySignatures,
         _comps: &[ir::Component],
     ) -> VisResult {
-        let builder = ir::Builder::new(comp, sigs);
+        let mut builder = ir::Builder::new(comp, sigs);
 
-        for cell_ref in builder.component.cells.iter() {
+        let cells = builder.component.cells.iter().cloned().collect_vec();
+        for cell_ref in cells {
             let cell = cell_ref.borrow();
             if let Some(port) = cell.find_with_attr("clk") {
-                builder.component.continuous_assignments.push(
-                    builder.build_assignment(
-                        port,
-                        builder
-                            .component
-                            .signature
-                            .borrow()
-                            .get_with_attr("clk"),
-                        ir::Guard::True,
-                    ),
-                )
+                builder.set_origin(&cell.attributes);
+                let assign = builder.build_assignment(
+                    port,
+                    builder.component.signature.borrow().get_with_attr("clk"),
+                    ir::Guard::True,
+                );
+                builder.component.continuous_assignments.push(assign);
             }
         }
 
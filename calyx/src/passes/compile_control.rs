@@ -274,7 +274,8 @@ impl Visitor for CompileControl {
         comp: &mut ir::Component,
         ctx: &LibrarySignatures,
     ) -> VisResult {
-        let mut builder = ir::Builder::new(comp, ctx);
+        let mut builder =
+            ir::Builder::new(comp, ctx).with_origin(&s.attributes);
 
         // Create a new group for the seq related structure.
         let seq_group = builder.add_group("seq");
@@ -354,7 +355,8 @@ impl Visitor for CompileControl {
         comp: &mut ir::Component,
         ctx: &LibrarySignatures,
     ) -> VisResult {
-        let mut builder = ir::Builder::new(comp, ctx);
+        let mut builder =
+            ir::Builder::new(comp, ctx).with_origin(&s.attributes);
 
         // Name of the parent group.
         let par_group = builder.add_group("par");
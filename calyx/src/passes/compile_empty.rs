@@ -37,7 +37,7 @@ impl Named for CompileEmpty {
 impl Visitor for CompileEmpty {
     fn empty(
         &mut self,
-        _s: &mut ir::Empty,
+        s: &mut ir::Empty,
         comp: &mut Component,
         sigs: &LibrarySignatures,
         _comps: &[ir::Component],
@@ -45,7 +45,8 @@ impl Visitor for CompileEmpty {
         let group_ref = match self.group_name {
             Some(g) => comp.find_group(g).unwrap(),
             None => {
-                let mut builder = ir::Builder::new(comp, sigs);
+                let mut builder =
+                    ir::Builder::new(comp, sigs).with_origin(&s.attributes);
 
                 // Build the new group
                 let empty_group = builder.add_group("_empty");
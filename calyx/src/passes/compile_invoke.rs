@@ -27,7 +27,8 @@ impl Visitor for CompileInvoke {
         ctx: &LibrarySignatures,
         _comps: &[ir::Component],
     ) -> VisResult {
-        let mut builder = ir::Builder::new(comp, ctx);
+        let mut builder =
+            ir::Builder::new(comp, ctx).with_origin(&s.attributes);
 
         let invoke_group = builder.add_group("invoke");
 
@@ -345,7 +345,8 @@ impl Visitor for CompileSync {
         sigs: &ir::LibrarySignatures,
         _comps: &[ir::Component],
     ) -> VisResult {
-        let mut builder = ir::Builder::new(comp, sigs);
+        let mut builder =
+            ir::Builder::new(comp, sigs).with_origin(&s.attributes);
         let mut barrier_count: HashMap<u64, u64> = HashMap::new();
         for stmt in s.stmts.iter_mut() {
             let mut cnt: HashSet<u64> = HashSet::new();
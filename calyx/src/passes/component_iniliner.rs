@@ -322,6 +322,8 @@ impl Visitor for ComponentInliner {
         for cell_ref in &inline_cells {
             let cell = cell_ref.borrow();
             if cell.is_component() {
+                // The inlined structure is derived from the instance.
+                builder.set_origin(&cell.attributes);
                 let comp_name = cell.type_name().unwrap();
                 let (control, rewrites) = Self::inline_component(
                     &mut builder,
@@ -186,6 +186,7 @@ impl Visitor for GroupToInvoke {
         let mut builder = ir::Builder::new(comp, sigs);
         'groups: for g in &groups {
             let group = g.borrow();
+            builder.set_origin(&group.attributes);
 
             let mut writes = ReadWriteSet::write_set(group.assignments.iter())
                 .filter(|cell| match cell.borrow().prototype {
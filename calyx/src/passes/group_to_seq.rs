@@ -36,6 +36,7 @@ impl Visitor for GroupToSeq {
         let groups: Vec<ir::RRC<ir::Group>> = comp.groups.drain().collect();
         let mut builder = ir::Builder::new(comp, sigs);
         for g in groups.iter() {
+            builder.set_origin(&g.borrow().attributes);
             if let Some((group1, group2)) =
                 SplitAnalysis::get_split(g, &mut builder)
             {
@@ -121,7 +121,8 @@ impl Visitor for HoleInliner {
         };
 
         let this_comp = Rc::clone(&comp.signature);
-        let mut builder = ir::Builder::new(comp, sigs);
+        let mut builder = ir::Builder::new(comp, sigs)
+            .with_origin(&top_level.borrow().attributes);
 
         // add top_level[go] = this.go
         let mut asgns = vec![
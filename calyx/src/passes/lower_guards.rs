@@ -114,6 +114,7 @@ fn lower_assigns(
     let mut new_assigns = Vec::with_capacity(assigns.len() * 2);
     for mut assign in assigns {
         let g = std::mem::take(&mut assign.guard);
+        builder.set_origin(&assign.attributes);
         let mut assigns = vec![];
         let port = lower_guard(*g, &mut assigns, builder);
         assign.guard = Box::new(port.into());
@@ -89,7 +89,8 @@ impl Visitor for MergeStaticPar {
 
         for (key, value) in static_group {
             if value.len() != 1 {
-                let mut builder = ir::Builder::new(comp, sigs);
+                let mut builder =
+                    ir::Builder::new(comp, sigs).with_origin(&s.attributes);
                 let grp = builder.add_group("msp");
                 let mut assignments: Vec<ir::Assignment> = Vec::new();
                 for group in value.iter() {
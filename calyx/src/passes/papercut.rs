@@ -148,9 +148,15 @@ impl Visitor for Papercut {
                     if *is_comb && !self.cont_cells.contains(&cell.name()) {
                         let msg = format!("Port `{}.{}` is an output port on combinational primitive `{}` and will always output 0. Add a `with` statement to the `while` statement to ensure it has a valid value during execution.", cell.name(), port.name, prim_name);
                         // Use dummy Id to get correct source location for error
-                        return Err(
-                            Error::papercut(msg).with_pos(&s.attributes)
-                        );
+                        return Err(Error::papercut(msg)
+                            .with_pos(&s.attributes)
+                            .with_secondary(
+                                &cell.attributes,
+                                format!(
+                                    "`{}` is not driven by continuous assignments",
+                                    cell.name()
+                                ),
+                            ));
                     }
                 }
             }
@@ -180,9 +186,15 @@ impl Visitor for Papercut {
                     if *is_comb && !self.cont_cells.contains(&cell.name()) {
                         let msg = format!("Port `{}.{}` is an output port on combinational primitive `{}` and will always output 0. Add a `with` statement to the `if` statement to ensure it has a valid value during execution.", cell.name(), port.name, prim_name);
                         // Use dummy Id to get correct source location for error
-                        return Err(
-                            Error::papercut(msg).with_pos(&s.attributes)
-                        );
+                        return Err(Error::papercut(msg)
+                            .with_pos(&s.attributes)
+                            .with_secondary(
+                                &cell.attributes,
+                                format!(
+                                    "`{}` is not driven by continuous assignments",
+                                    cell.name()
+                                ),
+                            ));
                     }
                 }
             }
@@ -192,6 +204,37 @@ impl Visitor for Papercut {
 }
 
 impl Papercut {
+    /// Point to the first assignment in `assigns` that writes to (if `write`
+    /// is true) or reads from the port `cell.port`.
+    fn with_use(
+        err: Error,
+        assigns: &[ir::Assignment],
+        (cell, port): (ir::Id, ir::Id),
+        write: bool,
+    ) -> Error {
+        let is_port = |pr: &ir::RRC<ir::Port>| {
+            pr.borrow().canonical() == ir::Canonical(cell, port)
+        };
+        let used = assigns.iter().find(|assign| {
+            if write {
+                is_port(&assign.dst)
+            } else {
+                analysis::ReadWriteSet::port_read_set(std::iter::once(*assign))
+                    .any(|pr| is_port(&pr))
+            }
+        });
+        match used {
+            Some(assign) => err.with_secondary(
+                &assign.attributes,
+                format!(
+                    "`{cell}.{port}` is {} here",
+                    if write { "written" } else { "read" }
+                ),
+            ),
+            None => err,
+        }
+    }
+
     fn check_specs(&mut self, assigns: &[ir::Assignment]) -> VisResult {
         let all_writes = analysis::ReadWriteSet::port_write_set(assigns.iter())
             .filter_map(port_information)
@@ -217,13 +260,18 @@ impl Papercut {
                             .join(", ");
                         let msg =
                             format!("Required signal not driven inside the group.\
-                                        \nWhen read the port `{}.{}', the ports [{}] must be written to.\
-                                        \nThe primitive type `{}' requires this invariant.",
+                                        \nWhen read the port `{}.{}', the ports [{}] must be written to.",
                                     inst,
                                     read,
-                                    missing,
-                                    comp_type);
-                        return Err(Error::papercut(msg));
+                                    missing);
+                        return Err(Self::with_use(
+                            Error::papercut(msg).with_note(format!(
+                                "the primitive type `{comp_type}' requires this invariant"
+                            )),
+                            assigns,
+                            (inst, *read),
+                            false,
+                        ));
                     }
                 }
             }
@@ -246,13 +294,18 @@ impl Papercut {
                             .join(", ");
                         let msg =
                             format!("Required signal not driven inside the group.\
-                                        \nWhen writing to the port `{}.{}', the ports [{}] must also be written to.\
-                                        \nThe primitive type `{}' requires this invariant.",
+                                        \nWhen writing to the port `{}.{}', the ports [{}] must also be written to.",
                                     inst,
                                     first,
-                                    missing,
-                                    comp_type);
-                        return Err(Error::papercut(msg));
+                                    missing);
+                        return Err(Self::with_use(
+                            Error::papercut(msg).with_note(format!(
+                                "the primitive type `{comp_type}' requires this invariant"
+                            )),
+                            assigns,
+                            (inst, *first),
+                            true,
+                        ));
                     }
                 }
             }
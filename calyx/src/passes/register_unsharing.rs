@@ -113,6 +113,9 @@ impl Bookkeeper {
 
         for (name, sets) in &overlap {
             if sets.len() > 1 {
+                // The new registers are derived from the shared one.
+                let reg = builder.component.find_cell(*name).unwrap();
+                builder.set_origin(&reg.borrow().attributes);
                 for defs in &sets[1..] {
                     let new_name = builder
                         .add_primitive(
@@ -124,6 +124,7 @@ impl Visitor for RemoveCombGroups {
                 })?;
 
                 // Group generated to replace this comb group.
+                builder.set_origin(&cg_ref.borrow().attributes);
                 let group_ref = builder.add_group(name);
                 let mut group = group_ref.borrow_mut();
                 // Attach assignmens from comb group
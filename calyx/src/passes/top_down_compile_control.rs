@@ -2,6 +2,7 @@ use super::math_utilities::get_bit_width_from;
 use crate::errors::CalyxResult;
 use crate::ir::traversal::ConstructVisitor;
 use crate::ir::GetAttributes;
+use crate::utils::WithPos;
 use crate::{build_assignments, guard, passes, structure};
 use crate::{
     errors::Error,
@@ -411,6 +412,12 @@ impl Schedule<'_, '_> {
             let not_done = !guard!(group["done"]);
             let signal_on = self.builder.add_constant(1, 1);
 
+            // The assignments that activate the group are derived from this
+            // enable.
+            let origin = self.builder.origin();
+            self.builder
+                .set_origin(&attributes.copy_span().into_option().unwrap_or(origin));
+
             // Activate this group in the current state
             let en_go = build_assignments!(self.builder;
                 group["go"] = not_done ? signal_on["out"];
@@ -433,6 +440,7 @@ impl Schedule<'_, '_> {
                     self.enables.entry(*st).or_default().extend(early_go);
                 }
             }
+            self.builder.set_origin(&origin);
 
             let transitions = prev_states
                 .into_iter()
@@ -832,7 +840,8 @@ impl Visitor for TopDownCompileControl {
         if !s.attributes.has("new_fsm") {
             return Ok(Action::Continue);
         }
-        let mut builder = ir::Builder::new(comp, sigs);
+        let mut builder =
+            ir::Builder::new(comp, sigs).with_origin(&s.attributes);
         let mut sch = Schedule::from(&mut builder);
         sch.calculate_states_seq(s, self.early_transitions)?;
         // Compile schedule and return the group.
@@ -857,7 +866,8 @@ impl Visitor for TopDownCompileControl {
         if !i.attributes.has("new_fsm") {
             return Ok(Action::Continue);
         }
-        let mut builder = ir::Builder::new(comp, sigs);
+        let mut builder =
+            ir::Builder::new(comp, sigs).with_origin(&i.attributes);
         let mut sch = Schedule::from(&mut builder);
 
         // Compile schedule and return the group.
@@ -883,7 +893,8 @@ impl Visitor for TopDownCompileControl {
         if !w.attributes.has("new_fsm") {
             return Ok(Action::Continue);
         }
-        let mut builder = ir::Builder::new(comp, sigs);
+        let mut builder =
+            ir::Builder::new(comp, sigs).with_origin(&w.attributes);
         let mut sch = Schedule::from(&mut builder);
         sch.calculate_states_while(w, self.early_transitions)?;
 
@@ -907,7 +918,8 @@ impl Visitor for TopDownCompileControl {
         sigs: &LibrarySignatures,
         _comps: &[ir::Component],
     ) -> VisResult {
-        let mut builder = ir::Builder::new(comp, sigs);
+        let mut builder =
+            ir::Builder::new(comp, sigs).with_origin(&s.attributes);
 
         // Compilation group
         let par_group = builder.add_group("par");
@@ -928,9 +940,12 @@ impl Visitor for TopDownCompileControl {
                 }
                 // Compile complex schedule and return the group.
                 _ => {
+                    builder.set_origin(con);
                     let mut sch = Schedule::from(&mut builder);
                     sch.calculate_states(con, self.early_transitions)?;
-                    sch.realize_schedule(self.dump_fsm)
+                    let group = sch.realize_schedule(self.dump_fsm);
+                    builder.set_origin(&s.attributes);
+                    group
                 }
             };
 
@@ -997,7 +1012,8 @@ impl Visitor for TopDownCompileControl {
     ) -> VisResult {
         let control = Rc::clone(&comp.control);
         // IRPrinter::write_control(&control.borrow(), 0, &mut std::io::stderr());
-        let mut builder = ir::Builder::new(comp, sigs);
+        let mut builder =
+            ir::Builder::new(comp, sigs).with_origin(&*control.borrow());
         let mut sch = Schedule::from(&mut builder);
         // Add assignments for the final states
         sch.calculate_states(&control.borrow(), self.early_transitions)?;
@@ -10,6 +10,7 @@ use crate::ir::{
 use crate::ir::{CloneName, GetAttributes};
 use crate::passes::top_down_static_timing::{ComputeStates, Normalize};
 use crate::passes::{self, math_utilities::get_bit_width_from};
+use crate::utils::WithPos;
 use crate::{build_assignments, guard, structure};
 use itertools::Itertools;
 use std::cell::RefCell;
@@ -782,9 +783,11 @@ impl TopDownStaticTiming {
             return Ok(());
         }
         if let Some(time) = con.get_attribute("static") {
+            builder.set_origin(con);
             let group = Schedule::compile(con, builder, dump_fsm)?;
             let mut en = ir::Control::enable(group);
             en.get_mut_attributes()["static"] = time;
+            en.get_mut_attributes().set_span(con.copy_span());
             *con = en;
         } else {
             match con {
@@ -867,10 +870,12 @@ impl Visitor for TopDownStaticTiming {
                 ir::Control::Enable(_) => {}
                 con => {
                     let time = con.get_attribute("static").unwrap();
+                    builder.set_origin(con);
                     let group =
                         Schedule::compile(con, &mut builder, self.dump_fsm)?;
                     let mut en = ir::Control::enable(group);
                     en.get_mut_attributes()["static"] = time;
+                    en.get_mut_attributes().set_span(con.copy_span());
                     *con = en;
                 }
             }
@@ -3,7 +3,7 @@ use crate::ir::traversal::ConstructVisitor;
 use crate::ir::traversal::{Action, Named, VisResult, Visitor};
 use crate::ir::{
     self, CellType, CloneName, Component, GetAttributes, LibrarySignatures,
-    RESERVED_NAMES,
+    NumericFormat, RESERVED_NAMES,
 };
 use crate::utils::WithPos;
 use itertools::Itertools;
@@ -48,6 +48,13 @@ impl ActiveAssignments {
 /// 5. Invoking components with unmentioned ref cells.
 /// 6. Invoking components with wrong ref cell name.
 /// 7. Invoking components with impatible fed-in cell type for ref cells.
+/// 8. Cells and ports with conflicting numeric format annotations.
+///
+/// Also warns about assignments between ports with different numeric formats
+/// and guards that compare signed values using unsigned comparisons.
+///
+/// All the errors in the program are reported together once every component
+/// has been checked.
 pub struct WellFormed {
     /// Reserved names
     reserved_names: HashSet<ir::Id>,
@@ -59,6 +66,8 @@ pub struct WellFormed {
     ref_cell_types: HashMap<ir::Id, LinkedHashMap<ir::Id, CellType>>,
     /// Stack of currently active combinational groups
     active_comb: ActiveAssignments,
+    /// Errors found so far.
+    errors: Vec<Error>,
 }
 
 impl ConstructVisitor for WellFormed {
@@ -68,10 +77,11 @@ impl ConstructVisitor for WellFormed {
     {
         let reserved_names =
             RESERVED_NAMES.iter().map(|s| ir::Id::from(*s)).collect();
+        let mut errors = vec![];
 
         for prim in ctx.lib.signatures() {
             if prim.attributes.has("static") {
-                return Err(Error::malformed_structure(format!("Primitive `{}`: Defining @static attributes on components is deprecated. Place the @static attribute on the port marked as @go", prim.name)));
+                errors.push(Error::malformed_structure(format!("Primitive `{}`: Defining @static attributes on components is deprecated. Place the @static attribute on the port marked as @go", prim.name)));
             }
         }
 
@@ -79,17 +89,19 @@ impl ConstructVisitor for WellFormed {
         for comp in ctx.components.iter() {
             // Defining @static on the component is meaningless
             if comp.attributes.has("static") {
-                return Err(Error::malformed_structure(format!("Component `{}`: Defining @static attributes on components is deprecated. Place the @static attribute on the port marked as @go", comp.name)));
+                errors.push(Error::malformed_structure(format!("Component `{}`: Defining @static attributes on components is deprecated. Place the @static attribute on the port marked as @go", comp.name)));
             }
 
             // Main component cannot use `ref` cells
             if comp.name == ctx.entrypoint {
                 for cell in comp.cells.iter() {
                     if cell.borrow().is_reference() {
-                        return Err(Error::malformed_structure(
-                            "ref cells are not allowed for main component",
-                        )
-                        .with_pos(cell.borrow().get_attributes()));
+                        errors.push(
+                            Error::malformed_structure(
+                                "ref cells are not allowed for main component",
+                            )
+                            .with_pos(cell.borrow().get_attributes()),
+                        );
                     }
                 }
             }
@@ -104,14 +116,15 @@ impl ConstructVisitor for WellFormed {
                     if cell.attributes.has("external")
                         && comp.name != ctx.entrypoint
                     {
-                        Some(Err(Error::malformed_structure("Cell cannot be marked `@external` in non-entrypoint component").with_pos(&cell.attributes)))
+                        errors.push(Error::malformed_structure("Cell cannot be marked `@external` in non-entrypoint component").with_pos(&cell.attributes));
+                        None
                     } else if cell.is_reference() {
-                        Some(Ok((cell.clone_name(), cell.prototype.clone())))
+                        Some((cell.clone_name(), cell.prototype.clone()))
                     } else {
                         None
                     }
                 })
-                .collect::<CalyxResult<_>>()?;
+                .collect();
             ref_cell_types.insert(comp.name, cellmap);
         }
 
@@ -121,6 +134,7 @@ impl ConstructVisitor for WellFormed {
             used_comb_groups: HashSet::new(),
             ref_cell_types,
             active_comb: ActiveAssignments::default(),
+            errors,
         };
 
         Ok(w_f)
@@ -154,28 +168,122 @@ where
         .sorted_by(|(dst1, _), (dst2, _)| ir::Canonical::cmp(dst1, dst2))
         .group_by(|(dst, _)| dst.clone());
 
-    for (_, group) in &dst_grps {
+    for (dst, group) in &dst_grps {
         let assigns = group.map(|(_, a)| a).collect_vec();
         if assigns.len() > 1 {
-            let msg = assigns
-                .into_iter()
-                .map(|a| {
-                    a.attributes
-                        .copy_span()
-                        .into_option()
-                        .map(|s| s.show())
-                        .unwrap_or_else(|| ir::Printer::assignment_to_str(a))
-                })
-                .join("");
-            return Err(Error::malformed_structure(format!(
-                "Obviously conflicting assignments found:\n{}",
-                msg
-            )));
+            // Assignments without a source location are shown in the message.
+            let (with_pos, without_pos): (Vec<_>, Vec<_>) =
+                assigns.into_iter().partition(|a| {
+                    a.attributes.copy_span().into_option().is_some()
+                });
+            let mut msg = "Obviously conflicting assignments found".to_string();
+            if !without_pos.is_empty() {
+                msg = format!(
+                    "{msg}:\n{}",
+                    without_pos
+                        .into_iter()
+                        .map(ir::Printer::assignment_to_str)
+                        .join("\n")
+                );
+            }
+            let mut err = Error::malformed_structure(msg);
+            let mut with_pos = with_pos.into_iter();
+            if let Some(first) = with_pos.next() {
+                err = err.with_pos(&first.attributes);
+            }
+            for assign in with_pos {
+                err = err.with_secondary(
+                    &assign.attributes,
+                    format!("`{dst}` is also driven here"),
+                );
+            }
+            return Err(err);
         }
     }
     Ok(())
 }
 
+/// Returns an error if the numeric format annotations in `attrs` conflict or
+/// have more fractional bits than the `width` of the value.
+fn check_format_annotation(
+    attrs: &ir::Attributes,
+    width: Option<u64>,
+) -> CalyxResult<()> {
+    let format = NumericFormat::from_attributes(attrs)?;
+    match (format.and_then(|f| f.frac_width()), width) {
+        (Some(frac), Some(width)) if frac > width => {
+            Err(Error::malformed_structure(format!(
+                "Value has {frac} fractional bits but is only {width} bits wide"
+            ))
+            .with_pos(attrs))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Emits a warning located at `attrs`.
+fn warn_at(attrs: &ir::Attributes, msg: String) {
+    match attrs.copy_span().into_option() {
+        Some(span) => log::warn!("{}", span.format(msg)),
+        None => log::warn!("{msg}"),
+    }
+}
+
+/// Warns if `guard` compares signed values using an unsigned comparison.
+fn check_guard_formats(guard: &ir::Guard, attrs: &ir::Attributes) {
+    match guard {
+        ir::Guard::Or(l, r) | ir::Guard::And(l, r) => {
+            check_guard_formats(l, attrs);
+            check_guard_formats(r, attrs);
+        }
+        ir::Guard::Not(g) => check_guard_formats(g, attrs),
+        ir::Guard::CompOp(
+            ir::PortComp::Gt
+            | ir::PortComp::Lt
+            | ir::PortComp::Geq
+            | ir::PortComp::Leq,
+            l,
+            r,
+        ) => {
+            for port in [l, r] {
+                let port = port.borrow();
+                if let Some(format) = NumericFormat::of_port(&port) {
+                    if format.is_signed() {
+                        warn_at(attrs, format!(
+                            "`{}` is {format} but guard `{}` compares it as unsigned",
+                            ir::Printer::port_to_str(&port),
+                            ir::Printer::guard_str(guard),
+                        ));
+                    }
+                }
+            }
+        }
+        ir::Guard::CompOp(..) | ir::Guard::Port(_) | ir::Guard::True => (),
+    }
+}
+
+/// Warns if `assign` moves a value between ports with incompatible numeric
+/// formats or compares signed values in its guard.
+fn check_assignment_formats(assign: &ir::Assignment) {
+    let src = assign.src.borrow();
+    let dst = assign.dst.borrow();
+    if let (Some(src_format), Some(dst_format)) =
+        (NumericFormat::of_port(&src), NumericFormat::of_port(&dst))
+    {
+        if !src_format.is_compatible(&dst_format) {
+            warn_at(
+                &assign.attributes,
+                format!(
+                    "`{}` is {src_format} but is assigned to `{}`, which is {dst_format}",
+                    ir::Printer::port_to_str(&src),
+                    ir::Printer::port_to_str(&dst),
+                ),
+            );
+        }
+    }
+    check_guard_formats(&assign.guard, &assign.attributes);
+}
+
 fn same_type(proto_out: &CellType, proto_in: &CellType) -> CalyxResult<()> {
     if proto_out != proto_in {
         Err(Error::malformed_control(format!(
@@ -199,16 +307,20 @@ impl Visitor for WellFormed {
             let cell = cell_ref.borrow();
             // Check if any of the cells use a reserved name.
             if self.reserved_names.contains(&cell.name()) {
-                return Err(Error::reserved_name(cell.clone_name())
-                    .with_pos(cell.get_attributes()));
+                self.errors.push(
+                    Error::reserved_name(cell.clone_name())
+                        .with_pos(cell.get_attributes()),
+                );
             }
             // Check if a `ref` cell is invalid
             if cell.is_reference() {
                 if cell.is_primitive(Some("std_const")) {
-                    return Err(Error::malformed_structure(
-                        "constant not allowed for ref cells".to_string(),
-                    )
-                    .with_pos(cell.get_attributes()));
+                    self.errors.push(
+                        Error::malformed_structure(
+                            "constant not allowed for ref cells".to_string(),
+                        )
+                        .with_pos(cell.get_attributes()),
+                    );
                 }
                 if matches!(cell.prototype, CellType::ThisComponent) {
                     unreachable!(
@@ -216,23 +328,38 @@ impl Visitor for WellFormed {
                     );
                 }
             }
+            if let Err(err) = check_format_annotation(
+                &cell.attributes,
+                cell.get_parameter("WIDTH"),
+            ) {
+                self.errors.push(err);
+            }
+        }
+        for port in comp.signature.borrow().ports.iter() {
+            let port = port.borrow();
+            if let Err(err) =
+                check_format_annotation(&port.attributes, Some(port.width))
+            {
+                self.errors.push(err);
+            }
         }
+        comp.for_each_assignment(|assign| check_assignment_formats(assign));
 
         // If the component is combinational, make sure all cells are also combinational,
         // there are no group or comb group definitions, and the control program is empty
         if comp.is_comb {
             if !matches!(&*comp.control.borrow(), ir::Control::Empty(..)) {
-                return Err(Error::malformed_structure(format!("Component `{}` is marked combinational but has a non-empty control program", comp.name)));
+                self.errors.push(Error::malformed_structure(format!("Component `{}` is marked combinational but has a non-empty control program", comp.name)));
             }
 
-            if !comp.groups.is_empty() {
-                let group = comp.groups.iter().next().unwrap().borrow();
-                return Err(Error::malformed_structure(format!("Component `{}` is marked combinational but contains a group `{}`", comp.name, group.clone_name())).with_pos(&group.attributes));
+            for group in comp.groups.iter() {
+                let group = group.borrow();
+                self.errors.push(Error::malformed_structure(format!("Component `{}` is marked combinational but contains a group `{}`", comp.name, group.clone_name())).with_pos(&group.attributes));
             }
 
-            if !comp.comb_groups.is_empty() {
-                let group = comp.comb_groups.iter().next().unwrap().borrow();
-                return Err(Error::malformed_structure(format!("Component `{}` is marked combinational but contains a group `{}`", comp.name, group.clone_name())).with_pos(&group.attributes));
+            for group in comp.comb_groups.iter() {
+                let group = group.borrow();
+                self.errors.push(Error::malformed_structure(format!("Component `{}` is marked combinational but contains a group `{}`", comp.name, group.clone_name())).with_pos(&group.attributes));
             }
 
             for cell_ref in comp.cells.iter() {
@@ -251,7 +378,7 @@ impl Visitor for WellFormed {
                     _ => false,
                 };
                 if !is_comb {
-                    return Err(Error::malformed_structure(format!("Component `{}` is marked combinational but contains non-combinational cell `{}`", comp.name, cell.name())).with_pos(&cell.attributes));
+                    self.errors.push(Error::malformed_structure(format!("Component `{}` is marked combinational but contains non-combinational cell `{}`", comp.name, cell.name())).with_pos(&cell.attributes));
                 }
             }
         }
@@ -261,50 +388,71 @@ impl Visitor for WellFormed {
         for gr in comp.groups.iter() {
             let group = gr.borrow();
             let gname = group.name();
-            let mut has_done = false;
+            let mut first_done: Option<&ir::Assignment> = None;
             // Find an assignment writing to this group's done condition.
             for assign in &group.assignments {
                 let dst = assign.dst.borrow();
                 if dst.is_hole() && dst.name == "done" {
                     // Group has multiple done conditions
-                    if has_done {
-                        return Err(Error::malformed_structure(format!(
-                            "Group `{}` has multiple done conditions",
-                            gname
-                        ))
-                        .with_pos(&assign.attributes));
+                    if let Some(first) = first_done {
+                        self.errors.push(
+                            Error::malformed_structure(format!(
+                                "Group `{}` has multiple done conditions",
+                                gname
+                            ))
+                            .with_pos(&assign.attributes)
+                            .with_secondary(
+                                &first.attributes,
+                                "first done condition",
+                            ),
+                        );
                     } else {
-                        has_done = true;
+                        first_done = Some(assign);
                     }
                     // Group uses another group's done condition
-                    if gname != dst.get_parent_name() {
-                        return Err(Error::malformed_structure(
+                    let other = dst.get_parent_name();
+                    if gname != other {
+                        let mut err = Error::malformed_structure(
                             format!("Group `{}` refers to the done condition of another group (`{}`).",
                             gname,
-                            dst.get_parent_name())).with_pos(&dst.attributes));
+                            other)).with_pos(&assign.attributes);
+                        if let Some(other_group) = comp.find_group(other) {
+                            err = err.with_secondary(
+                                &other_group.borrow().attributes,
+                                format!("`{other}` is defined here"),
+                            );
+                        }
+                        self.errors.push(err);
                     }
                 }
             }
 
             // Group does not have a done condition
-            if !has_done {
-                return Err(Error::malformed_structure(format!(
-                    "No writes to the `done' hole for group `{gname}'",
-                ))
-                .with_pos(&group.attributes));
+            if first_done.is_none() {
+                self.errors.push(
+                    Error::malformed_structure(format!(
+                        "No writes to the `done' hole for group `{gname}'",
+                    ))
+                    .with_pos(&group.attributes),
+                );
             }
         }
 
         // Check for obvious conflicting assignments in the continuous assignments
-        obvious_conflicts(comp.continuous_assignments.iter())?;
+        if let Err(err) = obvious_conflicts(comp.continuous_assignments.iter())
+        {
+            self.errors.push(err);
+        }
         // Check for obvious conflicting assignments between the continuous assignments and the groups
         for cgr in comp.comb_groups.iter() {
-            obvious_conflicts(
+            if let Err(err) = obvious_conflicts(
                 cgr.borrow()
                     .assignments
                     .iter()
                     .chain(comp.continuous_assignments.iter()),
-            )?;
+            ) {
+                self.errors.push(err);
+            }
         }
 
         Ok(Action::Continue)
@@ -317,43 +465,52 @@ impl Visitor for WellFormed {
         _ctx: &LibrarySignatures,
         _comps: &[ir::Component],
     ) -> VisResult {
-        self.used_groups.insert(s.group.clone_name());
+        // Errors about the group itself are only reported when it is first
+        // enabled.
+        let first_use = self.used_groups.insert(s.group.clone_name());
 
         let group = s.group.borrow();
-        let asgn = group.done_cond();
-        let const_done_assign =
-            asgn.guard.is_true() && asgn.src.borrow().is_constant(1, 1);
+        // Groups without a done condition have already been reported.
+        let const_done_assign = group
+            .assignments
+            .iter()
+            .find(|asgn| {
+                let dst = asgn.dst.borrow();
+                dst.is_hole() && dst.name == "done"
+            })
+            .map(|asgn| {
+                asgn.guard.is_true() && asgn.src.borrow().is_constant(1, 1)
+            })
+            .unwrap_or(false);
 
-        if const_done_assign {
-            return Err(Error::malformed_structure("Group with constant done condition is invalid. Use `comb group` instead to define a combinational group.").with_pos(&group.attributes));
+        if first_use && const_done_assign {
+            self.errors.push(Error::malformed_structure("Group with constant done condition is invalid. Use `comb group` instead to define a combinational group.").with_pos(&group.attributes));
         }
 
         // A group with "static"=0 annotation
-        if group
-            .attributes
-            .get("static")
-            .map(|v| *v == 0)
-            .unwrap_or(false)
+        if first_use
+            && group
+                .attributes
+                .get("static")
+                .map(|v| *v == 0)
+                .unwrap_or(false)
         {
-            return Err(Error::malformed_structure("Group with annotation \"static\"=0 is invalid. Use `comb group` instead to define a combinational group or if the group's done condition is not constant, provide the correct \"static\" annotation.").with_pos(&group.attributes));
+            self.errors.push(Error::malformed_structure("Group with annotation \"static\"=0 is invalid. Use `comb group` instead to define a combinational group or if the group's done condition is not constant, provide the correct \"static\" annotation.").with_pos(&group.attributes));
         }
 
         // Check if the group has obviously conflicting assignments with the continuous assignments and the active combinational groups
-        obvious_conflicts(
+        if let Err(err) = obvious_conflicts(
             group
                 .assignments
                 .iter()
                 .chain(comp.continuous_assignments.iter())
                 .chain(self.active_comb.iter()),
-        )
-        .map_err(|err| {
-            let msg = s
-                .attributes
-                .copy_span()
-                .into_option()
-                .map(|s| s.format("Assigments activated by group enable"));
-            err.with_post_msg(msg)
-        })?;
+        ) {
+            self.errors.push(err.with_secondary(
+                &s.attributes,
+                "assignments activated by group enable",
+            ));
+        }
 
         Ok(Action::Continue)
     }
@@ -373,21 +530,22 @@ impl Visitor for WellFormed {
         let ports: HashSet<_> =
             cell.ports.iter().map(|p| p.borrow().name).collect();
 
-        s.inputs
-            .iter()
-            .chain(s.outputs.iter())
-            .try_for_each(|(port, _)| {
-                if !ports.contains(port) {
-                    Err(Error::malformed_structure(format!(
+        for (port, _) in s.inputs.iter().chain(s.outputs.iter()) {
+            if !ports.contains(port) {
+                self.errors.push(
+                    Error::malformed_structure(format!(
                         "`{}` does not have port named `{}`",
                         cell.name(),
                         port
                     ))
-                    .with_pos(&s.attributes))
-                } else {
-                    Ok(())
-                }
-            })?;
+                    .with_pos(&s.attributes)
+                    .with_secondary(
+                        &cell.attributes,
+                        format!("`{}` is defined here", cell.name()),
+                    ),
+                );
+            }
+        }
 
         if let CellType::Component { name: id } = &cell.prototype {
             let cellmap = &self.ref_cell_types[id];
@@ -395,11 +553,18 @@ impl Visitor for WellFormed {
             for (outcell, incell) in s.ref_cells.iter() {
                 if let Some(t) = cellmap.get(outcell) {
                     let proto = incell.borrow().prototype.clone();
-                    same_type(t, &proto)
-                        .map_err(|err| err.with_pos(&s.attributes))?;
+                    if let Err(err) = same_type(t, &proto) {
+                        let incell = incell.borrow();
+                        self.errors.push(
+                            err.with_pos(&s.attributes).with_secondary(
+                                &incell.attributes,
+                                format!("`{}` is defined here", incell.name()),
+                            ),
+                        );
+                    }
                     mentioned_cells.insert(outcell);
                 } else {
-                    return Err(Error::malformed_control(format!(
+                    self.errors.push(Error::malformed_control(format!(
                         "{} does not have ref cell named {}",
                         id, outcell
                     )));
@@ -407,11 +572,13 @@ impl Visitor for WellFormed {
             }
             for id in cellmap.keys() {
                 if mentioned_cells.get(id).is_none() {
-                    return Err(Error::malformed_control(format!(
-                        "unmentioned ref cell: {}",
-                        id
-                    ))
-                    .with_pos(&s.attributes));
+                    self.errors.push(
+                        Error::malformed_control(format!(
+                            "unmentioned ref cell: {}",
+                            id
+                        ))
+                        .with_pos(&s.attributes),
+                    );
                 }
             }
         }
@@ -430,14 +597,17 @@ impl Visitor for WellFormed {
             let cg = cgr.borrow();
             let assigns = &cg.assignments;
             // Check if the combinational group conflicts with the active combinational groups
-            obvious_conflicts(assigns.iter().chain(self.active_comb.iter()))
-                .map_err(|err| {
-                    let msg = s.attributes.copy_span().format(format!(
-                        "Assignments from `{}' are actived here",
+            if let Err(err) =
+                obvious_conflicts(assigns.iter().chain(self.active_comb.iter()))
+            {
+                self.errors.push(err.with_secondary(
+                    &s.attributes,
+                    format!(
+                        "assignments from `{}' are activated here",
                         cg.name()
-                    ));
-                    err.with_post_msg(Some(msg))
-                })?;
+                    ),
+                ));
+            }
             // Push the combinational group to the stack of active groups
             self.active_comb.push(assigns);
         }
@@ -471,14 +641,17 @@ impl Visitor for WellFormed {
             let cg = cgr.borrow();
             let assigns = &cg.assignments;
             // Check if the combinational group conflicts with the active combinational groups
-            obvious_conflicts(assigns.iter().chain(self.active_comb.iter()))
-                .map_err(|err| {
-                    let msg = s.attributes.copy_span().format(format!(
-                        "Assignments from `{}' are actived here",
+            if let Err(err) =
+                obvious_conflicts(assigns.iter().chain(self.active_comb.iter()))
+            {
+                self.errors.push(err.with_secondary(
+                    &s.attributes,
+                    format!(
+                        "assignments from `{}' are activated here",
                         cg.name()
-                    ));
-                    err.with_post_msg(Some(msg))
-                })?;
+                    ),
+                ));
+            }
             // Push the combinational group to the stack of active groups
             self.active_comb.push(assigns);
         }
@@ -519,28 +692,28 @@ impl Visitor for WellFormed {
         });
 
         // Find unused groups
-        let all_groups: HashSet<ir::Id> =
-            comp.groups.iter().map(|g| g.clone_name()).collect();
-        if let Some(group) =
-            all_groups.difference(&self.used_groups).into_iter().next()
-        {
-            let gr = comp.find_group(*group).unwrap();
+        for gr in comp.groups.iter() {
             let gr = gr.borrow();
-            return Err(Error::unused(*group, "group").with_pos(&gr.attributes));
-        };
+            if !self.used_groups.contains(&gr.name()) {
+                self.errors.push(
+                    Error::unused(gr.name(), "group").with_pos(&gr.attributes),
+                );
+            }
+        }
 
-        let all_comb_groups: HashSet<ir::Id> =
-            comp.comb_groups.iter().map(|g| g.clone_name()).collect();
-        if let Some(comb_group) = all_comb_groups
-            .difference(&self.used_comb_groups)
-            .into_iter()
-            .next()
-        {
-            let cgr = comp.find_comb_group(*comb_group).unwrap();
+        for cgr in comp.comb_groups.iter() {
             let cgr = cgr.borrow();
-            return Err(Error::unused(*comb_group, "combinational group")
-                .with_pos(&cgr.attributes));
+            if !self.used_comb_groups.contains(&cgr.name()) {
+                self.errors.push(
+                    Error::unused(cgr.name(), "combinational group")
+                        .with_pos(&cgr.attributes),
+                );
+            }
         }
         Ok(Action::Continue)
     }
+
+    fn finish_context(&mut self, _ctx: &mut ir::Context) -> CalyxResult<()> {
+        Error::collect(std::mem::take(&mut self.errors))
+    }
 }
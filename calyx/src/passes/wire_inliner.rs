@@ -92,6 +92,7 @@ impl Visitor for WireInliner {
             .iter()
             .map(|gr| {
                 let name = gr.clone_name();
+                builder.set_origin(&gr.borrow().attributes);
                 let go = builder.add_primitive(
                     format!("{}_go", name),
                     "std_wire",
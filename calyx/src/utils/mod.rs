@@ -11,6 +11,7 @@ pub use global_sym::GSym;
 pub use namegenerator::NameGenerator;
 pub use out_file::OutputFile;
 pub use position::{
-    FileIdx, GPosIdx, GlobalPositionTable, PosIdx, PositionTable, WithPos,
+    Checkpoint, FileIdx, GPosIdx, GlobalPositionTable, PosIdx, PositionTable,
+    WithPos,
 };
 pub use weight_graph::{BoolIdx, Idx, WeightGraph};
@@ -1,7 +1,8 @@
 //! Definitions for tracking source position information of Calyx programs
 
 use itertools::Itertools;
-use std::{cmp, fmt::Write, mem, sync};
+use serde::{de::Error as _, Deserialize, Deserializer, Serialize, Serializer};
+use std::{cell::RefCell, cmp, fmt::Write, mem, sync};
 
 #[derive(Clone, Copy, PartialEq, Eq, Debug)]
 /// Handle to a position in a [PositionTable]
@@ -31,6 +32,14 @@ struct PosData {
     end: usize,
 }
 
+/// The number of files and positions in a [PositionTable] at some point.
+/// Everything added after it can be removed with [PositionTable::truncate].
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Checkpoint {
+    files: usize,
+    indices: usize,
+}
+
 /// Source position information for a Calyx program.
 pub struct PositionTable {
     /// The source files of the program
@@ -74,6 +83,11 @@ impl PositionTable {
         &self.files[file.0 as usize]
     }
 
+    /// The name of the file with the given index
+    pub fn get_name(&self, file: FileIdx) -> &str {
+        &self.get_file_data(file).name
+    }
+
     pub fn get_source(&self, file: FileIdx) -> &str {
         &self.get_file_data(file).source
     }
@@ -94,6 +108,23 @@ impl PositionTable {
     fn get_pos(&self, pos: PosIdx) -> &PosData {
         &self.indices[pos.0 as usize]
     }
+
+    /// The current size of the table
+    pub fn checkpoint(&self) -> Checkpoint {
+        Checkpoint {
+            files: self.files.len(),
+            indices: self.indices.len(),
+        }
+    }
+
+    /// Remove the files and positions added since `checkpoint`. Long running
+    /// tools that parse programs over and over use this to avoid growing the
+    /// table without bound. Any [PosIdx] or [FileIdx] created after the
+    /// checkpoint must no longer be used.
+    pub fn truncate(&mut self, checkpoint: Checkpoint) {
+        self.files.truncate(checkpoint.files);
+        self.indices.truncate(checkpoint.indices);
+    }
 }
 
 /// The global position table
@@ -128,10 +159,81 @@ impl GlobalPositionTable {
 /// A position index backed by a global [PositionTable]
 pub struct GPosIdx(pub PosIdx);
 
+thread_local! {
+    /// The files of the positions serialized by [GPosIdx::serialize_files].
+    static SERIALIZE_FILES: RefCell<Option<Vec<FileIdx>>> =
+        const { RefCell::new(None) };
+    /// The files that positions deserialized by [GPosIdx::deserialize_in]
+    /// refer to.
+    static DESERIALIZE_FILES: RefCell<Vec<FileIdx>> =
+        const { RefCell::new(Vec::new()) };
+}
+
+/// Positions are serialized as the index of their file in the files recorded
+/// by [GPosIdx::serialize_files] and the byte offsets of the span in the file.
+/// Positions serialized outside of [GPosIdx::serialize_files] are unknown.
+impl Serialize for GPosIdx {
+    fn serialize<S: Serializer>(&self, ser: S) -> Result<S::Ok, S::Error> {
+        let pos = self.into_option().and_then(|pos| {
+            let pos_d = GlobalPositionTable::as_ref().get_pos(pos.0);
+            SERIALIZE_FILES.with(|files| {
+                let mut files = files.borrow_mut();
+                let files = files.as_mut()?;
+                let idx = match files.iter().position(|f| *f == pos_d.file) {
+                    Some(idx) => idx,
+                    None => {
+                        files.push(pos_d.file);
+                        files.len() - 1
+                    }
+                };
+                Some((idx, pos_d.start, pos_d.end))
+            })
+        });
+        pos.serialize(ser)
+    }
+}
+
+impl<'de> Deserialize<'de> for GPosIdx {
+    fn deserialize<D: Deserializer<'de>>(de: D) -> Result<Self, D::Error> {
+        let Some((idx, start, end)) =
+            Option::<(usize, usize, usize)>::deserialize(de)?
+        else {
+            return Ok(GPosIdx::UNKNOWN);
+        };
+        let file = DESERIALIZE_FILES
+            .with(|files| files.borrow().get(idx).copied())
+            .ok_or_else(|| {
+                D::Error::custom(format!("position in unknown file {idx}"))
+            })?;
+        Ok(GPosIdx(
+            GlobalPositionTable::as_mut().add_pos(file, start, end),
+        ))
+    }
+}
+
 impl GPosIdx {
     /// Symbol for the unknown position
     pub const UNKNOWN: GPosIdx = GPosIdx(PosIdx(0));
 
+    /// Run `f` and return the files of the positions it serializes. The
+    /// serialized positions refer to the files by their index in the list.
+    pub fn serialize_files<T>(f: impl FnOnce() -> T) -> (T, Vec<FileIdx>) {
+        let prev = SERIALIZE_FILES.with(|files| files.replace(Some(vec![])));
+        let out = f();
+        let files = SERIALIZE_FILES.with(|files| files.replace(prev));
+        (out, files.unwrap_or_default())
+    }
+
+    /// Run `f` so that the positions it deserializes are added to the global
+    /// position table as spans in `files`, the files returned by
+    /// [GPosIdx::serialize_files] when the positions were serialized.
+    pub fn deserialize_in<T>(files: &[FileIdx], f: impl FnOnce() -> T) -> T {
+        let prev = DESERIALIZE_FILES.with(|cur| cur.replace(files.to_vec()));
+        let out = f();
+        DESERIALIZE_FILES.with(|cur| cur.replace(prev));
+        out
+    }
+
     /// Convert the position into an optional.
     /// Returns `None` if the position is the unknown position.
     pub fn into_option(self) -> Option<Self> {
@@ -142,11 +244,44 @@ impl GPosIdx {
         }
     }
 
+    /// Returns the name of the file containing this span and the byte
+    /// offsets of the start and end of the span.
+    pub fn get_location(&self) -> (&'static str, usize, usize) {
+        let table = GlobalPositionTable::as_ref();
+        let pos_d = table.get_pos(self.0);
+        let name = &table.get_file_data(pos_d.file).name;
+        (name, pos_d.start, pos_d.end)
+    }
+
+    /// Returns the 1-indexed line and column of the start and end of this
+    /// span.
+    pub fn get_line_col(&self) -> ((usize, usize), (usize, usize)) {
+        let table = GlobalPositionTable::as_ref();
+        let pos_d = table.get_pos(self.0);
+        let source = &table.get_file_data(pos_d.file).source;
+        let line_col = |offset: usize| {
+            let before = &source[..offset.min(source.len())];
+            let line_start = before.rfind('\n').map_or(0, |idx| idx + 1);
+            (
+                before.matches('\n').count() + 1,
+                before[line_start..].chars().count() + 1,
+            )
+        };
+        (line_col(pos_d.start), line_col(pos_d.end))
+    }
+
+    /// Returns the source text covered by this span.
+    pub fn get_text(&self) -> &'static str {
+        let table = GlobalPositionTable::as_ref();
+        let pos_d = table.get_pos(self.0);
+        &table.get_file_data(pos_d.file).source[pos_d.start..pos_d.end]
+    }
+
     /// Returns the
     /// 1. lines associated with this span
     /// 2. start position of the first line in span
     /// 3. line number of the span
-    fn get_lines(&self) -> (Vec<&str>, usize, usize) {
+    fn get_lines(&self) -> (Vec<&'static str>, usize, usize) {
         let table = GlobalPositionTable::as_ref();
         let pos_d = table.get_pos(self.0);
         let file = &table.get_file_data(pos_d.file).source;
@@ -178,6 +313,20 @@ impl GPosIdx {
         (buf, out_idx, out_line)
     }
 
+    /// Returns the
+    /// 1. line number of the span
+    /// 2. first line in the span
+    /// 3. byte offsets of the start and end of the span in that line. Spans
+    ///    covering multiple lines end at the end of the first line.
+    pub fn get_first_line(&self) -> (usize, &'static str, usize, usize) {
+        let pos_d = GlobalPositionTable::as_ref().get_pos(self.0);
+        let (lines, pos, linum) = self.get_lines();
+        let l = lines[0];
+        let start = pos_d.start - pos;
+        let end = start + cmp::min(pos_d.end - pos_d.start, l.len() - start);
+        (linum, l, start, end)
+    }
+
     /// Format this position with a the error message `err_msg`
     pub fn format<S: AsRef<str>>(&self, err_msg: S) -> String {
         let table = GlobalPositionTable::as_ref();
@@ -223,3 +372,9 @@ pub trait WithPos {
     /// Copy the span associated with this node.
     fn copy_span(&self) -> GPosIdx;
 }
+
+impl WithPos for GPosIdx {
+    fn copy_span(&self) -> GPosIdx {
+        *self
+    }
+}
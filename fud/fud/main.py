@@ -101,6 +101,13 @@ def register_stages(registry):
             "Generate the XML metadata for Xilinx",
         )
     )
+    registry.register(
+        futil.FutilStage(
+            "xilinx-host",
+            "-b xilinx-host",
+            "Generate the C++ XRT host program for Xilinx",
+        )
+    )
     registry.register(
         futil.FutilStage(
             "interpreter",
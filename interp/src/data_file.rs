@@ -0,0 +1,395 @@
+//! Reading the data files used to initialize the external memories of a
+//! program. A data file maps the name of each memory to its contents, given
+//! in one of three ways:
+//! - an array of base64 encoded little-endian values,
+//! - the numbers along with their format, like the data files used by fud,
+//!   `{"data": [[1, 2], [3, 4]], "format": {...}}`,
+//! - a file holding the values, `{"file": "weights.npy", "format": {...}}`.
+//!   Raw little-endian binary, Verilog hex as read by `$readmemh` and NumPy
+//!   arrays are supported.
+
+use crate::errors::{InterpreterError, InterpreterResult};
+use crate::values::{InputNumber, Value};
+use calyx::ir::Id;
+use ibig::UBig;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+fn invalid<S: ToString>(msg: S) -> InterpreterError {
+    InterpreterError::InvalidDataFile(msg.to_string())
+}
+
+/// The format of the values of a memory. Fixed point numbers need two of
+/// `width`, `int_width` and `frac_width`.
+#[derive(Deserialize)]
+struct DataFormat {
+    numeric_type: String,
+    is_signed: bool,
+    width: Option<u64>,
+    int_width: Option<u64>,
+    frac_width: Option<u64>,
+}
+
+/// How the values of a memory are stored in a file
+#[derive(Deserialize, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+enum Encoding {
+    /// Consecutive little-endian values, each taking the fewest bytes that
+    /// fit the width of the memory
+    Binary,
+    /// One hexadecimal value per word, as read by `$readmemh`
+    Hex,
+    /// A NumPy array
+    Npy,
+}
+
+impl Encoding {
+    fn of_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()? {
+            "bin" | "raw" => Some(Encoding::Binary),
+            "dat" | "hex" | "mem" => Some(Encoding::Hex),
+            "npy" => Some(Encoding::Npy),
+            _ => None,
+        }
+    }
+}
+
+/// The entry of a memory in a data file
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum MemoryData {
+    Encoded(Vec<Value>),
+    Formatted {
+        data: serde_json::Value,
+        format: DataFormat,
+    },
+    File {
+        file: PathBuf,
+        /// Inferred from the extension of the file if missing
+        encoding: Option<Encoding>,
+        format: DataFormat,
+    },
+}
+
+/// A number read from a data file, before it is converted to a value
+#[derive(Clone, Copy)]
+enum Number {
+    Signed(i64),
+    Unsigned(u64),
+    Float(f64),
+}
+
+impl Number {
+    fn of_json(num: &serde_json::Value) -> Option<Self> {
+        num.as_u64()
+            .map(Number::Unsigned)
+            .or_else(|| num.as_i64().map(Number::Signed))
+            .or_else(|| num.as_f64().map(Number::Float))
+    }
+
+    fn as_f64(self) -> f64 {
+        match self {
+            Number::Signed(n) => n as f64,
+            Number::Unsigned(n) => n as f64,
+            Number::Float(f) => f,
+        }
+    }
+}
+
+/// Converts `n` into a value of the given width. Fails if it does not fit
+/// instead of truncating it.
+fn checked_value<N: Into<InputNumber>>(
+    n: N,
+    width: u64,
+    idx: usize,
+) -> Result<Value, String> {
+    match Value::from_checked(n, width) {
+        (value, false) => Ok(value),
+        (_, true) => {
+            Err(format!("element {} does not fit in {} bits", idx, width))
+        }
+    }
+}
+
+impl DataFormat {
+    /// The total width and the width of the fraction of the values
+    fn widths(&self) -> Result<(u64, u64), String> {
+        let missing = || {
+            "the format needs two of `width`, `int_width` and `frac_width`"
+                .to_string()
+        };
+        let frac_width = match self.numeric_type.as_str() {
+            "bitnum" => {
+                let width = self.width.ok_or("the format has no `width`")?;
+                return Ok((width, 0));
+            }
+            "fixed_point" => {
+                match (self.frac_width, self.width, self.int_width) {
+                    (Some(frac_width), _, _) => frac_width,
+                    (None, Some(width), Some(int_width)) => {
+                        width.checked_sub(int_width).ok_or_else(|| {
+                            format!(
+                                "`int_width` {} is larger than `width` {}",
+                                int_width, width
+                            )
+                        })?
+                    }
+                    _ => return Err(missing()),
+                }
+            }
+            other => return Err(format!("unknown numeric type `{}`", other)),
+        };
+        let width = match self.width {
+            Some(width) => width,
+            None => self
+                .int_width
+                .ok_or_else(missing)?
+                .checked_add(frac_width)
+                .ok_or("the width of the format is too large")?,
+        };
+        if frac_width > width {
+            return Err(format!(
+                "`frac_width` {} is larger than `width` {}",
+                frac_width, width
+            ));
+        }
+        Ok((width, frac_width))
+    }
+
+    fn width(&self) -> Result<u64, String> {
+        self.widths().map(|(width, _)| width)
+    }
+
+    /// Converts the number at index `idx` of a memory into a value of this
+    /// format
+    fn to_value(&self, num: Number, idx: usize) -> Result<Value, String> {
+        let (width, frac_width) = self.widths()?;
+        let num = if self.numeric_type == "fixed_point" {
+            let scaled = (num.as_f64() * 2f64.powi(frac_width as i32)).round();
+            if self.is_signed {
+                Number::Signed(scaled as i64)
+            } else {
+                Number::Unsigned(scaled as u64)
+            }
+        } else {
+            num
+        };
+        let mismatch = || format!("element {} does not match the format", idx);
+        match num {
+            Number::Signed(n) if self.is_signed => checked_value(n, width, idx),
+            Number::Signed(n) => checked_value(
+                u64::try_from(n).map_err(|_| mismatch())?,
+                width,
+                idx,
+            ),
+            Number::Unsigned(n) => checked_value(n, width, idx),
+            Number::Float(_) => Err(mismatch()),
+        }
+    }
+
+    /// Flattens the nested arrays of a memory in row-major order
+    fn flatten(
+        &self,
+        data: &serde_json::Value,
+        out: &mut Vec<Value>,
+    ) -> Result<(), String> {
+        match data {
+            serde_json::Value::Array(items) => {
+                items.iter().try_for_each(|item| self.flatten(item, out))
+            }
+            num => {
+                let idx = out.len();
+                let num = Number::of_json(num).ok_or_else(|| {
+                    format!("element {} is not a number", idx)
+                })?;
+                out.push(self.to_value(num, idx)?);
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Reads consecutive little-endian values of the given width
+fn read_binary(bytes: &[u8], width: u64) -> Result<Vec<Value>, String> {
+    let word = width.div_ceil(8) as usize;
+    if word == 0 || !bytes.len().is_multiple_of(word) {
+        return Err(format!(
+            "the file has {} bytes, which is not a multiple of the {} bytes of each value",
+            bytes.len(),
+            word
+        ));
+    }
+    bytes
+        .chunks(word)
+        .enumerate()
+        .map(|(idx, chunk)| {
+            checked_value(UBig::from_le_bytes(chunk), width, idx)
+        })
+        .collect()
+}
+
+/// Reads whitespace separated hexadecimal values, skipping `//` comments
+fn read_hex(bytes: &[u8], width: u64) -> Result<Vec<Value>, String> {
+    let text = std::str::from_utf8(bytes).map_err(|e| e.to_string())?;
+    let mut values = vec![];
+    for line in text.lines() {
+        let line = line.split("//").next().unwrap_or_default();
+        for word in line.split_whitespace() {
+            if word.starts_with('@') {
+                return Err(format!(
+                    "address `{}` is not supported, values must be consecutive",
+                    word
+                ));
+            }
+            let digits = word.replace('_', "");
+            let n = UBig::from_str_radix(&digits, 16).map_err(|_| {
+                format!("`{}` is not a hexadecimal value", word)
+            })?;
+            values.push(checked_value(n, width, values.len())?);
+        }
+    }
+    Ok(values)
+}
+
+/// Reads a NumPy array of integers or floats stored in row-major order and
+/// converts its elements to the format of the memory
+fn read_npy(bytes: &[u8], format: &DataFormat) -> Result<Vec<Value>, String> {
+    let bad = |msg: &str| format!("malformed NumPy file: {}", msg);
+    if bytes.len() < 10 || &bytes[..6] != b"\x93NUMPY" {
+        return Err(bad("missing magic string"));
+    }
+    let (header_len, start) = match bytes[6] {
+        1 => (u16::from_le_bytes([bytes[8], bytes[9]]) as usize, 10),
+        2 | 3 if bytes.len() >= 12 => (
+            u32::from_le_bytes([bytes[8], bytes[9], bytes[10], bytes[11]])
+                as usize,
+            12,
+        ),
+        _ => return Err(bad("unsupported version")),
+    };
+    let header = bytes
+        .get(start..start + header_len)
+        .and_then(|h| std::str::from_utf8(h).ok())
+        .ok_or_else(|| bad("truncated header"))?;
+    let field = |key: &str| {
+        let rest =
+            &header[header.find(&format!("'{}'", key))? + key.len() + 2..];
+        Some(rest.trim_start_matches(|c: char| c == ':' || c.is_whitespace()))
+    };
+
+    if field("fortran_order").is_none_or(|v| !v.starts_with("False")) {
+        return Err(bad("only arrays in row-major (C) order are supported"));
+    }
+    let descr = field("descr")
+        .and_then(|v| v.strip_prefix('\'')?.split('\'').next())
+        .ok_or_else(|| bad("missing descr"))?;
+    let shape = field("shape")
+        .and_then(|v| v.strip_prefix('(')?.split(')').next())
+        .ok_or_else(|| bad("missing shape"))?;
+    let count = shape
+        .split(',')
+        .map(str::trim)
+        .filter(|dim| !dim.is_empty())
+        .try_fold(1usize, |acc, dim| Some(acc * dim.parse::<usize>().ok()?))
+        .ok_or_else(|| bad("malformed shape"))?;
+
+    let mut chars = descr.chars();
+    let big_endian = match chars.next() {
+        Some('<') | Some('|') | Some('=') => false,
+        Some('>') => true,
+        _ => return Err(bad("malformed descr")),
+    };
+    let kind = chars.next();
+    let size: usize =
+        chars.as_str().parse().map_err(|_| bad("malformed descr"))?;
+    let data = &bytes[start + header_len..];
+    if data.len() != count * size {
+        return Err(bad("the data does not match the shape"));
+    }
+
+    let unsupported = || format!("unsupported element type `{}`", descr);
+    if size == 0 || size > 8 {
+        return Err(unsupported());
+    }
+    let mut values = Vec::with_capacity(count);
+    for chunk in data.chunks(size) {
+        let mut buf = [0u8; 8];
+        buf[..size].copy_from_slice(chunk);
+        if big_endian {
+            buf[..size].reverse();
+        }
+        let bits = u64::from_le_bytes(buf);
+        let shift = 64 - 8 * size as u32;
+        let num = match (kind, size) {
+            (Some('u'), _) | (Some('b'), 1) => Number::Unsigned(bits),
+            // Sign extend the element
+            (Some('i'), _) => Number::Signed(((bits << shift) as i64) >> shift),
+            (Some('f'), 4) => Number::Float(f32::from_bits(bits as u32) as f64),
+            (Some('f'), 8) => Number::Float(f64::from_bits(bits)),
+            _ => return Err(unsupported()),
+        };
+        let idx = values.len();
+        values.push(
+            format
+                .to_value(num, idx)
+                .map_err(|msg| format!("element type `{}`: {}", descr, msg))?,
+        );
+    }
+    Ok(values)
+}
+
+/// Reads the values of a memory from a file, resolving its path relative to
+/// the directory of the data file
+fn read_file(
+    dir: &Path,
+    file: &Path,
+    encoding: Option<Encoding>,
+    format: &DataFormat,
+) -> Result<Vec<Value>, String> {
+    let encoding = encoding.or_else(|| Encoding::of_path(file)).ok_or_else(|| {
+        format!(
+            "cannot infer the encoding of `{}`, set `encoding` to one of binary, hex, npy",
+            file.display()
+        )
+    })?;
+    let path = dir.join(file);
+    let bytes = fs::read(&path)
+        .map_err(|e| format!("cannot read `{}`: {}", path.display(), e))?;
+    let width = format.width()?;
+    match encoding {
+        Encoding::Binary => read_binary(&bytes, width),
+        Encoding::Hex => read_hex(&bytes, width),
+        Encoding::Npy => read_npy(&bytes, format),
+    }
+    .map_err(|msg| format!("{}: {}", path.display(), msg))
+}
+
+/// Reads the data file at `path` into the initial values of each memory
+pub(crate) fn read(path: &Path) -> InterpreterResult<HashMap<Id, Vec<Value>>> {
+    let v = fs::read(path)?;
+    let file_contents = std::str::from_utf8(&v)?;
+    let entries: HashMap<Id, MemoryData> = serde_json::from_str(file_contents)
+        .map_err(|e| invalid(e.to_string()))?;
+    let dir = path.parent().unwrap_or_else(|| Path::new(""));
+
+    let mut map = HashMap::new();
+    for (name, entry) in entries {
+        let values = match entry {
+            MemoryData::Encoded(values) => Ok(values),
+            MemoryData::Formatted { data, format } => {
+                let mut values = vec![];
+                format.flatten(&data, &mut values).map(|()| values)
+            }
+            MemoryData::File {
+                file,
+                encoding,
+                format,
+            } => read_file(dir, &file, encoding, &format),
+        }
+        .map_err(|msg| invalid(format!("memory `{}`: {}", name, msg)))?;
+        map.insert(name, values);
+    }
+    Ok(map)
+}
@@ -1,33 +1,71 @@
 use std::{cell::Ref, collections::HashMap, rc::Rc};
 
 use super::{
-    commands::{Command, PrintCode, PrintMode},
+    commands::{
+        Command, Condition, Operand, PrintCode, PrintMode, ValueTarget,
+    },
     context::DebuggingContext,
     interactive_errors::DebuggerError,
-    io_utils::Input,
+    io_utils::{Input, Output, PrintedValue},
+    name_tree::ActiveTreeNode,
 };
 use crate::debugger::source::SourceMap;
-use crate::environment::{InterpreterState, PrimitiveMap};
+use crate::environment::{InterpreterState, PrimitiveMap, Snapshot};
 use crate::errors::{InterpreterError, InterpreterResult};
 use crate::interpreter::{ComponentInterpreter, ConstCell, Interpreter};
 use crate::structures::names::{CompGroupName, ComponentQualifiedInstanceName};
 use crate::structures::state_views::StateView;
 use crate::utils::AsRaw;
-use crate::{interpreter_ir as iir, primitives::Serializable};
+use crate::values::Value;
+use crate::{
+    interpreter_ir as iir,
+    primitives::{Entry, Serializable},
+};
 use calyx::ir::{self, Id, RRC};
+use fraction::ToPrimitive;
+use ibig::IBig;
+use rustyline::error::ReadlineError;
 use std::fmt::Write;
+use std::path::Path;
 
 /// Constant amount of space used for debugger messages
 pub(super) const SPACING: &str = "    ";
 
+/// Number of steps between the checkpoints kept to go back in time, until
+/// there are too many of them
+const CHECKPOINT_INTERVAL: u64 = 1000;
+
+/// Maximum number of checkpoints kept at once. Once there are more, every
+/// other checkpoint is dropped and the interval between them doubles.
+const MAX_CHECKPOINTS: usize = 64;
+
+/// A copy of the program taken while it runs. To go back in time, the
+/// debugger replays the program from the last checkpoint before the target.
+pub(super) struct Checkpoint {
+    interp: ComponentInterpreter,
+    /// The hits of the breakpoints up to the checkpoint
+    hits: HashMap<CompGroupName, u64>,
+    /// The [DebuggingContext::breakpoints_version] the hits were counted with
+    version: u64,
+}
+
 /// The interactive Calyx debugger. The debugger itself is run with the
 /// [Debugger::main_loop] function while this struct holds auxilliary
 /// information used to coordinate the debugging process.
 pub struct Debugger {
-    _context: iir::ComponentCtx,
-    main_component: Rc<iir::Component>,
-    debugging_ctx: DebuggingContext,
-    source_map: Option<SourceMap>,
+    pub(super) context: iir::ComponentCtx,
+    pub(super) main_component: Rc<iir::Component>,
+    pub(super) debugging_ctx: DebuggingContext,
+    pub(super) source_map: Option<SourceMap>,
+    pub(super) output: Output,
+    /// The checkpoint at each multiple of `checkpoint_interval` steps that
+    /// the program has reached so far
+    checkpoints: Vec<Checkpoint>,
+    checkpoint_interval: u64,
+    /// The [DebuggingContext::breakpoints_version] that the current hits of
+    /// the breakpoints count the hits since the start of the program for.
+    /// Breakpoints changed since then only count the hits after the change.
+    counted_version: u64,
 }
 
 impl Debugger {
@@ -37,341 +75,825 @@ impl Debugger {
         source_map: Option<SourceMap>,
     ) -> Self {
         Self {
-            _context: Rc::clone(context),
+            context: Rc::clone(context),
             main_component: Rc::clone(main_component),
             debugging_ctx: DebuggingContext::new(context, &main_component.name),
             source_map,
+            output: Output::interactive(),
+            checkpoints: vec![],
+            checkpoint_interval: CHECKPOINT_INTERVAL,
+            counted_version: 0,
         }
     }
 
+    /// Runs the debugger on the program starting from `env`.
     pub fn main_loop(
         &mut self,
         env: InterpreterState,
     ) -> InterpreterResult<InterpreterState> {
-        let qin = ComponentQualifiedInstanceName::new_single(
-            &self.main_component,
-            self.main_component.name,
-        );
-        let mut component_interpreter = ComponentInterpreter::from_component(
-            &self.main_component,
-            env,
-            qin,
-        );
-        component_interpreter.set_go_high();
-
-        component_interpreter.converge()?;
-
-        let mut input_stream = Input::new()?;
-        println!("== Calyx Interactive Debugger ==");
-        while !component_interpreter.is_done() {
-            let comm = input_stream.next_command();
-            let comm = match comm {
-                Ok(c) => c,
-                Err(e) => match *e {
-                    InterpreterError::InvalidCommand(_)
-                    | InterpreterError::UnknownCommand(_)
-                    | InterpreterError::ParseError(_) => {
-                        println!("Error: {}", e);
-                        continue;
-                    }
-                    _ => return Err(e),
-                },
-            };
-
-            match comm {
-                Command::Step(n) => {
-                    for _ in 0..n {
-                        component_interpreter.step()?;
-                    }
-                    component_interpreter.converge()?;
-                }
-                Command::Continue => {
-                    self.debugging_ctx.set_current_time(
-                        component_interpreter.currently_executing_group(),
-                    );
-
-                    let mut ctx = std::mem::replace(
-                        &mut self.debugging_ctx,
-                        DebuggingContext::new(
-                            &self._context,
-                            &self.main_component.name,
-                        ),
-                    );
+        self.run(env, Input::new()?)
+    }
 
-                    let mut breakpoints: Vec<CompGroupName> = vec![];
+    /// Runs the commands in the file at `script` and returns them as JSON
+    /// along with the messages and values that each of them printed.
+    pub fn run_script(
+        &mut self,
+        env: InterpreterState,
+        script: &Path,
+    ) -> InterpreterResult<String> {
+        self.output = Output::recorded();
+        let res = self.run(env, Input::from_script(script)?);
+        let json = self.output.to_json().unwrap();
+        self.output = Output::interactive();
+        match res {
+            Err(e)
+                if !matches!(
+                    *e,
+                    InterpreterError::Exit
+                        | InterpreterError::ReadlineError(ReadlineError::Eof)
+                ) =>
+            {
+                Err(e)
+            }
+            _ => Ok(json),
+        }
+    }
 
-                    while breakpoints.is_empty()
-                        && !component_interpreter.is_done()
-                    {
-                        component_interpreter.step()?;
-                        let current_exec =
-                            component_interpreter.currently_executing_group();
+    fn run(
+        &mut self,
+        env: InterpreterState,
+        mut input_stream: Input,
+    ) -> InterpreterResult<InterpreterState> {
+        let mut component_interpreter = self.start(env)?;
+        // Number of steps taken since the start of the program
+        let mut cycle: u64 = 0;
 
-                        ctx.advance_time(current_exec);
-
-                        for watch in ctx.process_watchpoints() {
-                            for target in watch.target() {
-                                if let Ok(msg) = self.do_print(
-                                    target,
-                                    watch.print_code(),
-                                    component_interpreter.get_env(),
-                                    watch.print_mode(),
-                                ) {
-                                    println!("{}", msg);
-                                }
-                            }
+        if input_stream.is_interactive() {
+            outln!(self.output, "== Calyx Interactive Debugger ==");
+        }
+        'debug: loop {
+            while !component_interpreter.is_done() {
+                let comm = input_stream.next_command(&self.output);
+                let comm = match comm {
+                    Ok(c) => c,
+                    Err(e) => match *e {
+                        InterpreterError::InvalidCommand(_)
+                        | InterpreterError::UnknownCommand(_)
+                        | InterpreterError::ParseError(_) => {
+                            outln!(self.output, "Error: {}", e);
+                            continue;
                         }
+                        _ => return Err(e),
+                    },
+                };
 
-                        breakpoints = ctx
-                            .hit_breakpoints()
-                            .into_iter()
-                            .cloned()
-                            .collect();
+                match comm {
+                    Command::Step(n) => {
+                        for _ in 0..n {
+                            self.step(&mut component_interpreter, &mut cycle)?;
+                        }
+                        component_interpreter.converge()?;
                     }
+                    Command::Continue => {
+                        let (breakpoints, _) = self.continue_execution(
+                            &mut component_interpreter,
+                            &mut cycle,
+                        )?;
 
-                    self.debugging_ctx = ctx;
-
-                    if !component_interpreter.is_done() {
-                        for breakpoint in breakpoints {
-                            println!("Hit breakpoint: {}", breakpoint);
+                        if !component_interpreter.is_done() {
+                            for breakpoint in breakpoints {
+                                outln!(
+                                    self.output,
+                                    "Hit breakpoint: {}",
+                                    breakpoint
+                                );
+                            }
+                            component_interpreter.converge()?;
                         }
-                        component_interpreter.converge()?;
                     }
-                }
-                Command::Empty => {}
-                Command::Display => {
-                    let state = component_interpreter.get_env();
-                    println!("{}", state.state_as_str());
-                }
-                Command::Print(print_lists, code, print_mode) => {
-                    for target in print_lists {
-                        match self.do_print(
-                            &target,
-                            &code,
-                            component_interpreter.get_env(),
-                            &print_mode,
-                        ) {
-                            Ok(msg) => println!("{}", msg),
-                            Err(e) => println!("{}", e),
+                    Command::StepBack(_) | Command::ReverseContinue => {
+                        component_interpreter =
+                            self.go_back(&comm, &mut cycle)?;
+                    }
+                    Command::Empty => {}
+                    Command::Display => {
+                        self.display(component_interpreter.get_env());
+                    }
+                    Command::Print(print_lists, code, print_mode) => {
+                        for target in print_lists {
+                            match self.do_print(
+                                &target,
+                                &code,
+                                component_interpreter.get_env(),
+                                &print_mode,
+                            ) {
+                                Ok(printed) => self.output.value(printed),
+                                Err(e) => outln!(self.output, "{}", e),
+                            }
                         }
                     }
-                }
-                Command::Help => {
-                    print!("{}", Command::get_help_string())
-                }
-                Command::Break(targets) => {
-                    if targets.is_empty() {
-                        println!("Error: command requires a target");
-                        continue;
+                    Command::Help => {
+                        out!(self.output, "{}", Command::get_help_string())
                     }
+                    Command::Break(targets, condition, skip) => {
+                        if targets.is_empty() {
+                            outln!(
+                                self.output,
+                                "Error: command requires a target"
+                            );
+                            continue;
+                        }
 
-                    for target in targets {
-                        let currently_executing =
-                            component_interpreter.currently_executing_group();
-                        let target =
-                            self.debugging_ctx.concretize_group_name(target);
-
-                        if self
-                            .debugging_ctx
-                            .is_group_running(currently_executing, &target)
-                        {
-                            println!("Warning: the group {} is already running. This breakpoint will not trigger until the next time the group runs.", &target)
+                        if let Some(condition) = &condition {
+                            if let Err(e) = self.eval_condition(
+                                condition,
+                                component_interpreter.get_env(),
+                            ) {
+                                outln!(self.output, "Error: {}", e);
+                                continue;
+                            }
                         }
 
-                        self.debugging_ctx.add_breakpoint(target);
-                    }
-                }
-                Command::Exit => return Err(InterpreterError::Exit.into()),
-                Command::InfoBreak => self.debugging_ctx.print_breakpoints(),
-                Command::Delete(targets) => {
-                    if targets.is_empty() {
-                        println!("Error: command requires a target");
-                        continue;
-                    }
-                    for t in targets {
-                        self.debugging_ctx.remove_breakpoint(t)
-                    }
-                }
-                Command::DeleteWatch(targets) => {
-                    if targets.is_empty() {
-                        println!("Error: command requires a target");
-                        continue;
+                        for target in targets {
+                            let currently_executing = component_interpreter
+                                .currently_executing_group();
+                            let target = self
+                                .debugging_ctx
+                                .concretize_group_name(target);
+
+                            if self
+                                .debugging_ctx
+                                .is_group_running(currently_executing, &target)
+                            {
+                                outln!(self.output, "Warning: the group {} is already running. This breakpoint will not trigger until the next time the group runs.", &target)
+                            }
+
+                            self.debugging_ctx.add_breakpoint(
+                                target,
+                                condition.clone(),
+                                skip,
+                                &self.output,
+                            );
+                        }
                     }
-                    for target in targets {
-                        self.debugging_ctx.remove_watchpoint(target)
+                    Command::Exit => return Err(InterpreterError::Exit.into()),
+                    Command::InfoBreak => {
+                        self.debugging_ctx.print_breakpoints(&self.output)
                     }
-                }
-                Command::Disable(targets) => {
-                    if targets.is_empty() {
-                        println!("Error: command requires a target");
-                        continue;
+                    Command::Delete(targets) => {
+                        if targets.is_empty() {
+                            outln!(
+                                self.output,
+                                "Error: command requires a target"
+                            );
+                            continue;
+                        }
+                        for t in targets {
+                            self.debugging_ctx
+                                .remove_breakpoint(t, &self.output)
+                        }
                     }
-                    for t in targets {
-                        self.debugging_ctx.disable_breakpoint(t)
+                    Command::DeleteWatch(targets) => {
+                        if targets.is_empty() {
+                            outln!(
+                                self.output,
+                                "Error: command requires a target"
+                            );
+                            continue;
+                        }
+                        for target in targets {
+                            self.debugging_ctx.remove_watchpoint(target)
+                        }
                     }
-                }
-                Command::Enable(targets) => {
-                    if targets.is_empty() {
-                        println!("Error: command requires a target");
-                        continue;
+                    Command::Disable(targets) => {
+                        if targets.is_empty() {
+                            outln!(
+                                self.output,
+                                "Error: command requires a target"
+                            );
+                            continue;
+                        }
+                        for t in targets {
+                            self.debugging_ctx
+                                .disable_breakpoint(t, &self.output)
+                        }
                     }
-                    for t in targets {
-                        self.debugging_ctx.enable_breakpoint(t)
+                    Command::Enable(targets) => {
+                        if targets.is_empty() {
+                            outln!(
+                                self.output,
+                                "Error: command requires a target"
+                            );
+                            continue;
+                        }
+                        for t in targets {
+                            self.debugging_ctx
+                                .enable_breakpoint(t, &self.output)
+                        }
                     }
-                }
-                Command::StepOver(target) => {
-                    let mut current =
-                        component_interpreter.currently_executing_group();
-                    let target =
-                        self.debugging_ctx.concretize_group_name(target);
-
-                    if !self.debugging_ctx.is_group_running(current, &target) {
-                        println!("Group is not running")
-                    } else {
-                        component_interpreter.step()?;
-                        current =
+                    Command::StepOver(target) => {
+                        let mut current =
                             component_interpreter.currently_executing_group();
-                        while self
+                        let target =
+                            self.debugging_ctx.concretize_group_name(target);
+
+                        if !self
                             .debugging_ctx
                             .is_group_running(current, &target)
                         {
-                            component_interpreter.step()?;
+                            outln!(self.output, "Group is not running")
+                        } else {
+                            self.step(&mut component_interpreter, &mut cycle)?;
                             current = component_interpreter
                                 .currently_executing_group();
+                            while self
+                                .debugging_ctx
+                                .is_group_running(current, &target)
+                            {
+                                self.step(
+                                    &mut component_interpreter,
+                                    &mut cycle,
+                                )?;
+                                current = component_interpreter
+                                    .currently_executing_group();
+                            }
                         }
                     }
-                }
-                Command::Watch(
-                    group,
-                    watch_pos,
-                    print_target,
-                    print_code,
-                    print_mode,
-                ) => {
-                    let mut error_occurred = false;
-
-                    for target in print_target.iter() {
-                        if let Err(e) = self.do_print(
-                            target,
-                            &print_code,
-                            component_interpreter.get_env(),
-                            &print_mode,
-                        ) {
-                            error_occurred = true;
-                            println!("{}", e);
-                        }
-                    }
-
-                    if error_occurred {
-                        continue;
-                    }
-
-                    self.debugging_ctx.add_watchpoint(
+                    Command::Watch(
                         group,
                         watch_pos,
-                        (print_target, print_code, print_mode),
-                    )
-                }
-                Command::InfoWatch => self.debugging_ctx.print_watchpoints(),
-                Command::PrintPC => {
-                    if let Some(map) = &self.source_map {
-                        let mut printed = false;
-                        for x in component_interpreter
-                            .get_active_tree()
-                            .remove(0)
-                            .flat_set()
-                            .into_iter()
-                        {
-                            if let Some(output) = map.lookup(x) {
-                                printed = true;
-                                println!("{}", output);
+                        print_target,
+                        print_code,
+                        print_mode,
+                    ) => {
+                        let mut error_occurred = false;
+
+                        for target in print_target.iter() {
+                            if let Err(e) = self.do_print(
+                                target,
+                                &print_code,
+                                component_interpreter.get_env(),
+                                &print_mode,
+                            ) {
+                                error_occurred = true;
+                                outln!(self.output, "{}", e);
                             }
                         }
 
-                        if !printed {
-                            println!("Falling back to Calyx");
-                            print!(
-                                "{}",
-                                component_interpreter
-                                    .get_active_tree()
-                                    .remove(0)
-                                    .format_tree::<true>(0)
-                            )
+                        if error_occurred {
+                            continue;
                         }
-                    } else {
-                        print!(
-                            "{}",
-                            component_interpreter
-                                .get_active_tree()
-                                .remove(0)
-                                .format_tree::<true>(0)
+
+                        self.debugging_ctx.add_watchpoint(
+                            group,
+                            watch_pos,
+                            (print_target, print_code, print_mode),
                         )
                     }
+                    Command::WatchData(target) => match self
+                        .read_value(&target, component_interpreter.get_env())
+                    {
+                        Ok((value, _)) => self
+                            .debugging_ctx
+                            .add_data_watchpoint(target, value),
+                        Err(e) => outln!(self.output, "Error: {}", e),
+                    },
+                    Command::InfoWatch => {
+                        self.debugging_ctx.print_watchpoints(&self.output)
+                    }
+                    Command::PrintPC => self.print_pc(
+                        component_interpreter.get_active_tree().remove(0),
+                    ),
+                    Command::Explain => {
+                        out!(self.output, "{}", Command::get_explain_string())
+                    }
                 }
-                Command::Explain => print!("{}", Command::get_explain_string()),
             }
-        }
 
-        let final_env = component_interpreter.deconstruct()?;
+            let final_env = component_interpreter.deconstruct()?;
 
-        println!("Main component has finished executing. Debugger is now in inspection mode.");
+            outln!(self.output, "Main component has finished executing. Debugger is now in inspection mode.");
 
-        loop {
-            let comm = input_stream.next_command();
-            let comm = match comm {
-                Ok(c) => c,
-                Err(e) => match *e {
-                    InterpreterError::InvalidCommand(_)
-                    | InterpreterError::UnknownCommand(_)
-                    | InterpreterError::ParseError(_) => {
-                        println!("Error: {}", e);
-                        continue;
-                    }
-                    _ => return Err(e),
-                },
-            };
+            loop {
+                let comm = input_stream.next_command(&self.output);
+                let comm = match comm {
+                    Ok(c) => c,
+                    Err(e) => match *e {
+                        InterpreterError::InvalidCommand(_)
+                        | InterpreterError::UnknownCommand(_)
+                        | InterpreterError::ParseError(_) => {
+                            outln!(self.output, "Error: {}", e);
+                            continue;
+                        }
+                        _ => return Err(e),
+                    },
+                };
 
-            match comm {
-                Command::Empty => {}
-                Command::Display => {
-                    let state = final_env.as_state_view();
-                    println!("{}", state.state_as_str());
-                }
-                Command::Print(print_lists, code, print_mode) => {
-                    for target in print_lists {
-                        match self.do_print(
-                            &target,
-                            &code,
-                            final_env.as_state_view(),
-                            &print_mode,
-                        ) {
-                            Ok(msg) => println!("{}", msg),
-                            Err(e) => println!("{}", e),
+                match comm {
+                    Command::Empty => {}
+                    Command::Display => {
+                        self.display(final_env.as_state_view());
+                    }
+                    Command::Print(print_lists, code, print_mode) => {
+                        for target in print_lists {
+                            match self.do_print(
+                                &target,
+                                &code,
+                                final_env.as_state_view(),
+                                &print_mode,
+                            ) {
+                                Ok(printed) => self.output.value(printed),
+                                Err(e) => outln!(self.output, "{}", e),
+                            }
                         }
                     }
-                }
 
-                Command::Help => {
-                    print!("{}", Command::get_help_string())
-                }
-                Command::Exit => return Err(InterpreterError::Exit.into()),
-                Command::Explain => print!("{}", Command::get_explain_string()),
-                _ => {
-                    println!(
+                    Command::Help => {
+                        out!(self.output, "{}", Command::get_help_string())
+                    }
+                    Command::Exit => return Err(InterpreterError::Exit.into()),
+                    Command::Explain => {
+                        out!(self.output, "{}", Command::get_explain_string())
+                    }
+                    Command::StepBack(_) | Command::ReverseContinue => {
+                        component_interpreter =
+                            self.go_back(&comm, &mut cycle)?;
+                        continue 'debug;
+                    }
+                    _ => {
+                        outln!(self.output,
                         "This command is unavailable after program termination"
                     )
+                    }
+                }
+            }
+        }
+    }
+
+    /// Goes back in time from step `cycle` for a [Command::StepBack] or
+    /// [Command::ReverseContinue]. Returns the interpreter at the step it went
+    /// back to and sets `cycle` to that step.
+    pub(super) fn go_back(
+        &mut self,
+        command: &Command,
+        cycle: &mut u64,
+    ) -> InterpreterResult<ComponentInterpreter> {
+        let mut ctx = self.take_ctx();
+        let res = match command {
+            Command::StepBack(n) => {
+                let target = cycle.saturating_sub(*n);
+                self.replay(&mut ctx, target, false)
+                    .map(|(interp, _, _)| (interp, target))
+            }
+            Command::ReverseContinue => self.reverse_continue(&mut ctx, *cycle),
+            _ => unreachable!("not a command that goes back in time"),
+        };
+        self.debugging_ctx = ctx;
+        let (interp, target) = res?;
+        *cycle = target;
+        Ok(interp)
+    }
+
+    /// Runs the program until a breakpoint is hit, the value of a data
+    /// watchpoint changes or the program finishes. Returns the breakpoints
+    /// that were hit and whether a data watchpoint changed.
+    pub(super) fn continue_execution(
+        &mut self,
+        component_interpreter: &mut ComponentInterpreter,
+        cycle: &mut u64,
+    ) -> InterpreterResult<(Vec<CompGroupName>, bool)> {
+        self.debugging_ctx.set_current_time(
+            component_interpreter.currently_executing_group(),
+        );
+
+        let mut ctx = self.take_ctx();
+
+        // Changes made while stepping do not trigger the data watchpoints.
+        self.refresh_data_watchpoints(&mut ctx, component_interpreter);
+
+        let mut breakpoints: Vec<CompGroupName> = vec![];
+        let mut value_changed = false;
+
+        let mut res = Ok(());
+        while breakpoints.is_empty()
+            && !value_changed
+            && !component_interpreter.is_done()
+            && res.is_ok()
+        {
+            res = self
+                .advance(&mut ctx, component_interpreter, cycle, true)
+                .map(|hit| (breakpoints, value_changed) = hit);
+        }
+
+        self.debugging_ctx = ctx;
+        res.map(|_| (breakpoints, value_changed))
+    }
+
+    /// Advances the execution by a step without reporting anything. The
+    /// breakpoints and data watchpoints are checked like when continuing so
+    /// that their hits and values are the same as when the program is
+    /// replayed.
+    pub(super) fn step(
+        &mut self,
+        component_interpreter: &mut ComponentInterpreter,
+        cycle: &mut u64,
+    ) -> InterpreterResult<()> {
+        let mut ctx = self.take_ctx();
+        let res = self.advance(&mut ctx, component_interpreter, cycle, false);
+        self.debugging_ctx = ctx;
+        res.map(|_| ())
+    }
+
+    /// Advances the execution from `cycle` by a step with
+    /// [Debugger::step_and_check] and keeps a checkpoint if one is due.
+    fn advance(
+        &mut self,
+        ctx: &mut DebuggingContext,
+        component_interpreter: &mut ComponentInterpreter,
+        cycle: &mut u64,
+        report: bool,
+    ) -> InterpreterResult<(Vec<CompGroupName>, bool)> {
+        // Nothing has been hit at the start of the program, whichever
+        // breakpoints are set.
+        if *cycle == 0 {
+            self.counted_version = ctx.breakpoints_version();
+        }
+        let hit = self.step_and_check(ctx, component_interpreter, report)?;
+        *cycle += 1;
+        self.checkpoint(ctx, component_interpreter, *cycle);
+        Ok(hit)
+    }
+
+    /// Keeps a checkpoint of the program at `cycle` if it is a multiple of the
+    /// checkpoint interval. The program always runs the same way, so
+    /// checkpoints remain valid after going back in time and only the hits of
+    /// the breakpoints are updated when the program reaches them again.
+    /// Checkpoints share the unchanged parts of the program with each other
+    /// and with the running program.
+    fn checkpoint(
+        &mut self,
+        ctx: &DebuggingContext,
+        component_interpreter: &ComponentInterpreter,
+        cycle: u64,
+    ) {
+        if !cycle.is_multiple_of(self.checkpoint_interval) {
+            return;
+        }
+        let idx = (cycle / self.checkpoint_interval) as usize;
+        let hits = ctx.hits();
+        let version = self.counted_version;
+        if let Some(checkpoint) = self.checkpoints.get_mut(idx) {
+            checkpoint.hits = hits;
+            checkpoint.version = version;
+        } else if idx == self.checkpoints.len() {
+            self.checkpoints.push(Checkpoint {
+                interp: component_interpreter
+                    .snapshot(&mut Snapshot::default()),
+                hits,
+                version,
+            });
+            if self.checkpoints.len() > MAX_CHECKPOINTS {
+                let mut idx = 0;
+                self.checkpoints.retain(|_| {
+                    idx += 1;
+                    idx % 2 == 1
+                });
+                self.checkpoint_interval *= 2;
+            }
+        }
+    }
+
+    /// Shows the source of the active groups in `tree`.
+    pub(super) fn print_pc(&self, tree: ActiveTreeNode) {
+        let mut printed = false;
+        if let Some(map) = &self.source_map {
+            for x in tree.clone().flat_set().into_iter() {
+                if let Some(output) = map.lookup(x) {
+                    printed = true;
+                    outln!(self.output, "{}", output);
+                }
+            }
+        }
+
+        // Use the positions of the active groups in the Calyx source when the
+        // metadata does not describe them.
+        if !printed {
+            for (name, span) in tree.clone().source_positions() {
+                printed = true;
+                let (file, _, _) = span.get_location();
+                let ((line, _), _) = span.get_line_col();
+                outln!(self.output, "{name} ({file}:{line})");
+                out!(self.output, "{SPACING}{}", span.show());
+            }
+        }
+
+        if !printed {
+            if self.source_map.is_some() {
+                outln!(self.output, "Falling back to Calyx");
+            }
+            out!(self.output, "{}", tree.format_tree::<true>(0))
+        }
+    }
+
+    pub(super) fn display(&self, state: StateView) {
+        self.output.value(PrintedValue {
+            name: self.main_component.name.to_string(),
+            value: serde_json::to_value(state.gen_serializer(false)).unwrap(),
+            text: state.state_as_str(),
+        })
+    }
+
+    /// Constructs the interpreter for the main component and starts it.
+    pub(super) fn start(
+        &mut self,
+        env: InterpreterState,
+    ) -> InterpreterResult<ComponentInterpreter> {
+        let qin = ComponentQualifiedInstanceName::new_single(
+            &self.main_component,
+            self.main_component.name,
+        );
+        let mut component_interpreter = ComponentInterpreter::from_component(
+            &self.main_component,
+            env,
+            qin,
+        );
+        component_interpreter.set_go_high();
+        component_interpreter.converge()?;
+        self.debugging_ctx.set_current_time(
+            component_interpreter.currently_executing_group(),
+        );
+        self.checkpoints = vec![Checkpoint {
+            interp: component_interpreter.snapshot(&mut Snapshot::default()),
+            hits: HashMap::new(),
+            version: self.debugging_ctx.breakpoints_version(),
+        }];
+        self.checkpoint_interval = CHECKPOINT_INTERVAL;
+        Ok(component_interpreter)
+    }
+
+    /// Moves the debugging context out of the debugger so that it can be
+    /// updated while the debugger is borrowed.
+    pub(super) fn take_ctx(&mut self) -> DebuggingContext {
+        std::mem::replace(
+            &mut self.debugging_ctx,
+            DebuggingContext::new(&self.context, &self.main_component.name),
+        )
+    }
+
+    fn refresh_data_watchpoints(
+        &self,
+        ctx: &mut DebuggingContext,
+        component_interpreter: &ComponentInterpreter,
+    ) {
+        for watch in ctx.data_watchpoints_mut() {
+            if let Ok((value, _)) =
+                self.read_value(&watch.target, component_interpreter.get_env())
+            {
+                watch.value = value;
+            }
+        }
+    }
+
+    /// Advances the execution by a step and checks the watchpoints and
+    /// breakpoints. Returns the breakpoints that were hit and whether the value
+    /// of a data watchpoint changed. Messages are only printed if `report` is
+    /// set.
+    fn step_and_check(
+        &self,
+        ctx: &mut DebuggingContext,
+        component_interpreter: &mut ComponentInterpreter,
+        report: bool,
+    ) -> InterpreterResult<(Vec<CompGroupName>, bool)> {
+        component_interpreter.step()?;
+        let current_exec = component_interpreter.currently_executing_group();
+
+        ctx.advance_time(current_exec);
+
+        if report {
+            for watch in ctx.process_watchpoints() {
+                for target in watch.target() {
+                    if let Ok(printed) = self.do_print(
+                        target,
+                        watch.print_code(),
+                        component_interpreter.get_env(),
+                        watch.print_mode(),
+                    ) {
+                        self.output.value(printed);
+                    }
+                }
+            }
+        }
+
+        let breakpoints = ctx.hit_breakpoints(|condition| {
+            // conditions which cannot be evaluated never hold
+            self.eval_condition(condition, component_interpreter.get_env())
+                .unwrap_or(false)
+        });
+
+        let mut value_changed = false;
+        for watch in ctx.data_watchpoints_mut() {
+            let Ok((value, format)) =
+                self.read_value(&watch.target, component_interpreter.get_env())
+            else {
+                continue;
+            };
+            if value != watch.value {
+                if report {
+                    let code = format.map(PrintCode::from).unwrap_or_default();
+                    outln!(
+                        self.output,
+                        "Watchpoint {}: {} changed from {} to {}",
+                        watch.id,
+                        watch.target,
+                        Entry::from_val_code(&watch.value, &code),
+                        Entry::from_val_code(&value, &code)
+                    );
                 }
+                watch.value = value;
+                value_changed = true;
+            }
+        }
+
+        Ok((breakpoints, value_changed))
+    }
+
+    /// The step of the checkpoint to replay the program from to reach
+    /// `target`. This is the last checkpoint strictly before `target` whose
+    /// hits were counted with the current breakpoints, or the start of the
+    /// program. Replaying from an earlier checkpoint updates the hits of the
+    /// later ones.
+    fn resume_point(&self, ctx: &DebuggingContext, target: u64) -> u64 {
+        let version = ctx.breakpoints_version();
+        let last = ((target.saturating_sub(1) / self.checkpoint_interval)
+            as usize)
+            .min(self.checkpoints.len() - 1);
+        let idx = (1..=last)
+            .rev()
+            .find(|&idx| self.checkpoints[idx].version == version)
+            .unwrap_or(0);
+        idx as u64 * self.checkpoint_interval
+    }
+
+    /// Goes back to step `target` by replaying the program from the
+    /// checkpoint given by [Debugger::resume_point] while keeping track of the
+    /// breakpoints and data watchpoints. Returns the interpreter, the last
+    /// replayed step at which the execution would have stopped, and the
+    /// breakpoints hit by the final step. Only the final step reports
+    /// messages, and only if `report` is set.
+    pub(super) fn replay(
+        &mut self,
+        ctx: &mut DebuggingContext,
+        target: u64,
+        report: bool,
+    ) -> InterpreterResult<(
+        ComponentInterpreter,
+        Option<u64>,
+        Vec<CompGroupName>,
+    )> {
+        let start = self.resume_point(ctx, target);
+        let checkpoint =
+            &self.checkpoints[(start / self.checkpoint_interval) as usize];
+        let mut component_interpreter =
+            checkpoint.interp.snapshot(&mut Snapshot::default());
+        ctx.set_hits(&checkpoint.hits);
+        ctx.set_current_time(component_interpreter.currently_executing_group());
+        self.refresh_data_watchpoints(ctx, &component_interpreter);
+        self.counted_version = ctx.breakpoints_version();
+
+        let mut last_stop = None;
+        let mut breakpoints = vec![];
+        for cycle in start + 1..=target {
+            let value_changed;
+            (breakpoints, value_changed) = self.step_and_check(
+                ctx,
+                &mut component_interpreter,
+                report && cycle == target,
+            )?;
+            if !breakpoints.is_empty() || value_changed {
+                last_stop = Some(cycle);
             }
+            self.checkpoint(ctx, &component_interpreter, cycle);
         }
+        component_interpreter.converge()?;
+
+        Ok((component_interpreter, last_stop, breakpoints))
     }
 
-    fn do_print(
+    /// Goes back to the last step before `cycle` at which a breakpoint or data
+    /// watchpoint would have stopped the execution, or to the start of the
+    /// program if there is none. Returns the interpreter and the step it is at.
+    pub(super) fn reverse_continue(
         &mut self,
+        ctx: &mut DebuggingContext,
+        cycle: u64,
+    ) -> InterpreterResult<(ComponentInterpreter, u64)> {
+        // Search the steps between consecutive checkpoints for a stop, from
+        // the latest to the earliest, and then go back to it.
+        let mut end = cycle.saturating_sub(1);
+        let target = loop {
+            let start = self.resume_point(ctx, end);
+            let (_, last_stop, _) = self.replay(ctx, end, false)?;
+            match last_stop {
+                Some(stop) => break stop,
+                None if start == 0 => break 0,
+                None => end = start,
+            }
+        };
+        let (component_interpreter, _, breakpoints) =
+            self.replay(ctx, target, true)?;
+
+        if target == 0 {
+            outln!(self.output, "Reached the start of the program");
+        }
+        for breakpoint in breakpoints {
+            outln!(self.output, "Hit breakpoint: {}", breakpoint);
+        }
+
+        Ok((component_interpreter, target))
+    }
+
+    pub(super) fn do_print(
+        &self,
         print_list: &[Id],
         code: &Option<PrintCode>,
         root: StateView,
         print_mode: &PrintMode,
-    ) -> Result<String, DebuggerError> {
+    ) -> Result<PrintedValue, DebuggerError> {
+        self.find_target(
+            print_list,
+            root,
+            |cell, state| print_cell(cell, state, code, print_mode),
+            |port, state, prior| print_port(port, state, prior, code),
+        )
+    }
+
+    /// Reads the current value of `target` along with its numeric format.
+    pub(super) fn read_value(
+        &self,
+        target: &ValueTarget,
+        root: StateView,
+    ) -> Result<(Value, Option<ir::NumericFormat>), DebuggerError> {
+        let not_a_value = || DebuggerError::NotAValue(target.to_string());
+        self.find_target(
+            &target.name,
+            root,
+            |cell, state| {
+                let cell_ref = cell.borrow();
+                state
+                    .read_cell_value(&cell_ref, &target.address)
+                    .map(|v| (v, ir::NumericFormat::of_cell(&cell_ref)))
+                    .ok_or_else(not_a_value)
+            },
+            |port, state, _| {
+                if !target.address.is_empty() {
+                    return Err(not_a_value());
+                }
+                let port_ref = port.borrow();
+                Ok((
+                    state.lookup(port_ref.as_raw()).clone(),
+                    ir::NumericFormat::of_port(&port_ref),
+                ))
+            },
+        )?
+    }
+
+    fn eval_operand(
+        &self,
+        operand: &Operand,
+        root: StateView,
+    ) -> Result<IBig, DebuggerError> {
+        match operand {
+            Operand::Literal(n) => Ok(n.clone()),
+            Operand::Target(target) => {
+                let (value, format) = self.read_value(target, root)?;
+                if format.is_some_and(|f| f.is_signed()) {
+                    Ok(value.as_signed())
+                } else {
+                    Ok(value.as_unsigned().into())
+                }
+            }
+        }
+    }
+
+    pub(super) fn eval_condition(
+        &self,
+        condition: &Condition,
+        root: StateView,
+    ) -> Result<bool, DebuggerError> {
+        let left = self.eval_operand(&condition.left, root.clone())?;
+        let right = self.eval_operand(&condition.right, root)?;
+        Ok(condition.op.compare(&left, &right))
+    }
+
+    /// Walks the environment to the cell or port named by `print_list` and
+    /// calls either `on_cell` or `on_port` with it.
+    fn find_target<T>(
+        &self,
+        print_list: &[Id],
+        root: StateView,
+        on_cell: impl FnOnce(&RRC<ir::Cell>, &StateView) -> T,
+        on_port: impl FnOnce(&RRC<ir::Port>, &StateView, Option<Id>) -> T,
+    ) -> Result<T, DebuggerError> {
         let orig_string = print_list
             .iter()
             .map(|s| s.id.as_str())
@@ -398,12 +920,7 @@ impl Debugger {
                 // first look for cell
                 let cell = current_env.get_cell(*target);
                 if let Some(cell) = cell {
-                    return Ok(print_cell(
-                        &cell,
-                        &current_env,
-                        code,
-                        print_mode,
-                    ));
+                    return Ok(on_cell(&cell, &current_env));
                 } else if idx != 0 {
                     let prior = &print_list[idx - 1];
 
@@ -414,12 +931,7 @@ impl Debugger {
                             .iter()
                             .find(|x| x.borrow().name == target);
                         if let Some(port) = pt {
-                            return Ok(print_port(
-                                port,
-                                &current_env,
-                                None,
-                                code,
-                            ));
+                            return Ok(on_port(port, &current_env, None));
                         } else {
                             return Err(DebuggerError::CannotFind(orig_string));
                             // cannot find
@@ -427,11 +939,10 @@ impl Debugger {
                     } else if let Some(port) =
                         current_env.get_comp().signature.borrow().find(target)
                     {
-                        return Ok(print_port(
+                        return Ok(on_port(
                             &port,
                             &current_env,
                             Some(print_list[idx - 1]),
-                            code,
                         ));
                     } else {
                         // cannot find
@@ -469,57 +980,111 @@ impl Debugger {
     }
 }
 
+/// Formats a value with the given print code for interactive sessions and as
+/// JSON for scripted ones.
+pub(super) fn format_value(
+    v: &Value,
+    code: PrintCode,
+) -> (String, serde_json::Value) {
+    match code {
+        PrintCode::Unsigned => {
+            let n = v.as_unsigned();
+            let json = u64::try_from(&n)
+                .map(serde_json::Value::from)
+                .unwrap_or_else(|_| n.to_string().into());
+            (n.to_string(), json)
+        }
+        PrintCode::Signed => {
+            let n = v.as_signed();
+            let json = i64::try_from(&n)
+                .map(serde_json::Value::from)
+                .unwrap_or_else(|_| n.to_string().into());
+            (n.to_string(), json)
+        }
+        PrintCode::UFixed(num) => {
+            let n = v.as_ufp(num);
+            (n.to_string(), n.to_f64().into())
+        }
+        PrintCode::SFixed(num) => {
+            let n = v.as_sfp(num);
+            (n.to_string(), n.to_f64().into())
+        }
+        PrintCode::Binary => (v.to_string(), v.to_string().into()),
+    }
+}
+
 fn print_cell(
     target: &RRC<ir::Cell>,
     state: &StateView,
     code: &Option<PrintCode>,
     mode: &PrintMode,
-) -> String {
+) -> PrintedValue {
     let cell_ref = target.borrow();
 
     match mode {
         PrintMode::State => {
-            let actual_code =
-                code.as_ref().copied().unwrap_or(PrintCode::Binary);
+            // Without an explicit code, use the format the cell is annotated
+            // with.
+            let actual_code = code
+                .or_else(|| {
+                    ir::NumericFormat::of_cell(&cell_ref).map(PrintCode::from)
+                })
+                .unwrap_or(PrintCode::Binary);
             let cell_state = state.get_cell_state(&cell_ref, &actual_code);
             if matches!(&cell_state, &Serializable::Empty) {
                 print_cell(target, state, code, &PrintMode::Port)
             } else {
-                format!("{}{} = {}", SPACING, cell_ref.name(), cell_state)
+                // binary values are serialized with base64, so JSON uses
+                // numbers instead
+                let json_state = match actual_code {
+                    PrintCode::Binary => {
+                        state.get_cell_state(&cell_ref, &PrintCode::Unsigned)
+                    }
+                    _ => cell_state.clone(),
+                };
+                PrintedValue {
+                    name: cell_ref.name().to_string(),
+                    value: serde_json::to_value(&json_state).unwrap(),
+                    text: format!(
+                        "{}{} = {}",
+                        SPACING,
+                        cell_ref.name(),
+                        cell_state
+                    ),
+                }
             }
         }
         PrintMode::Port => {
             let mut output: String = String::new();
+            let mut ports = serde_json::Map::new();
             writeln!(output, "{}{}", SPACING, cell_ref.name())
                 .expect("Something went wrong trying to print the port");
             for port in cell_ref.ports.iter() {
                 let v = state.lookup(port.as_raw());
+                let code = code
+                    .or_else(|| {
+                        ir::NumericFormat::of_port(&port.borrow())
+                            .map(PrintCode::from)
+                    })
+                    .unwrap_or(PrintCode::Binary);
+                let (text, json) = format_value(v, code);
                 writeln!(
                     output,
                     "{}  {} = {}",
                     SPACING,
                     port.borrow().name,
-                    if let Some(code) = code {
-                        match code {
-                            PrintCode::Unsigned => {
-                                format!("{}", v.as_unsigned())
-                            }
-                            PrintCode::Signed => format!("{}", v.as_signed()),
-                            PrintCode::UFixed(num) => {
-                                format!("{}", v.as_ufp(*num))
-                            }
-                            PrintCode::SFixed(num) => {
-                                format!("{}", v.as_sfp(*num))
-                            }
-                            PrintCode::Binary => format!("{}", v),
-                        }
-                    } else {
-                        format!("{}", &v)
-                    }
+                    text
                 )
                 .expect("Something went wrong trying to print the port");
+                ports.insert(port.borrow().name.to_string(), json);
+            }
+            // the last line break is added when printing
+            output.pop();
+            PrintedValue {
+                name: cell_ref.name().to_string(),
+                value: ports.into(),
+                text: output,
             }
-            output
         }
     }
 }
@@ -529,7 +1094,7 @@ fn print_port(
     state: &StateView,
     prior_name: Option<ir::Id>,
     code: &Option<PrintCode>,
-) -> String {
+) -> PrintedValue {
     let port_ref = target.borrow();
     let parent_name = if let Some(prior) = prior_name {
         prior
@@ -538,21 +1103,19 @@ fn print_port(
     };
 
     let v = state.lookup(port_ref.as_raw());
-    let code = code.as_ref().copied().unwrap_or(PrintCode::Binary);
-
-    format!(
-        "{}{}.{} = {}",
-        SPACING,
-        parent_name,
-        port_ref.name,
-        match code {
-            PrintCode::Unsigned => format!("{}", v.as_unsigned()),
-            PrintCode::Signed => format!("{}", v.as_signed()),
-            PrintCode::UFixed(num) => format!("{}", v.as_ufp(num)),
-            PrintCode::SFixed(num) => format!("{}", v.as_sfp(num)),
-            PrintCode::Binary => format!("{}", v),
-        }
-    )
+    let code = code
+        .or_else(|| ir::NumericFormat::of_port(&port_ref).map(PrintCode::from))
+        .unwrap_or(PrintCode::Binary);
+    let (text, value) = format_value(v, code);
+
+    PrintedValue {
+        name: format!("{}.{}", parent_name, port_ref.name),
+        value,
+        text: format!(
+            "{}{}.{} = {}",
+            SPACING, parent_name, port_ref.name, text
+        ),
+    }
 }
 
 enum CurrentTarget<'a> {
@@ -1,4 +1,5 @@
-use calyx::ir::Id;
+use calyx::ir::{Id, NumericFormat};
+use ibig::IBig;
 use itertools::{self, Itertools};
 use lazy_static::lazy_static;
 use std::{
@@ -83,6 +84,17 @@ impl Default for PrintCode {
     }
 }
 
+impl From<NumericFormat> for PrintCode {
+    fn from(format: NumericFormat) -> Self {
+        match format {
+            NumericFormat::Unsigned => Self::Unsigned,
+            NumericFormat::Signed => Self::Signed,
+            NumericFormat::UFixed(n) => Self::UFixed(n as usize),
+            NumericFormat::SFixed(n) => Self::SFixed(n as usize),
+        }
+    }
+}
+
 impl Display for PrintCode {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
@@ -165,16 +177,106 @@ impl Display for PrintTuple {
     }
 }
 
+/// A value in the program that conditions and data watchpoints refer to. This
+/// is either a port or a cell, along with an address for memories.
+#[derive(Debug, Clone)]
+pub struct ValueTarget {
+    pub name: Vec<Id>,
+    pub address: Vec<u64>,
+}
+
+impl Display for ValueTarget {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name.iter().join("."))?;
+        for idx in &self.address {
+            write!(f, "[{}]", idx)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum CompareOp {
+    Eq,
+    Neq,
+    Lt,
+    Gt,
+    Leq,
+    Geq,
+}
+
+impl CompareOp {
+    pub fn compare(&self, left: &IBig, right: &IBig) -> bool {
+        match self {
+            CompareOp::Eq => left == right,
+            CompareOp::Neq => left != right,
+            CompareOp::Lt => left < right,
+            CompareOp::Gt => left > right,
+            CompareOp::Leq => left <= right,
+            CompareOp::Geq => left >= right,
+        }
+    }
+}
+
+impl Display for CompareOp {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                CompareOp::Eq => "==",
+                CompareOp::Neq => "!=",
+                CompareOp::Lt => "<",
+                CompareOp::Gt => ">",
+                CompareOp::Leq => "<=",
+                CompareOp::Geq => ">=",
+            }
+        )
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum Operand {
+    Target(ValueTarget),
+    Literal(IBig),
+}
+
+impl Display for Operand {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Operand::Target(t) => write!(f, "{}", t),
+            Operand::Literal(n) => write!(f, "{}", n),
+        }
+    }
+}
+
+/// The condition of a conditional breakpoint. Values are compared as signed
+/// numbers if their format is signed and as unsigned numbers otherwise.
+#[derive(Debug, Clone)]
+pub struct Condition {
+    pub left: Operand,
+    pub op: CompareOp,
+    pub right: Operand,
+}
+
+impl Display for Condition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {} {}", self.left, self.op, self.right)
+    }
+}
+
 pub enum Command {
-    Step(u64), // Step execution
-    Continue,  // Execute until breakpoint
-    Empty,     // Empty command, does nothing
-    Display,   // Display full environment contents
+    Step(u64),       // Step execution
+    StepBack(u64),   // Go back by a number of steps
+    ReverseContinue, // Go back until a breakpoint
+    Continue,        // Execute until breakpoint
+    Empty,           // Empty command, does nothing
+    Display,         // Display full environment contents
     Print(Vec<Vec<calyx::ir::Id>>, Option<PrintCode>, PrintMode), // Print something
-    Break(Vec<ParsedGroupName>), // Create a breakpoint
-    Help,                        // Help message
-    Exit,                        // Exit the debugger
-    InfoBreak,                   // List breakpoints
+    Break(Vec<ParsedGroupName>, Option<Condition>, u64), // Create a breakpoint
+    Help,                                                // Help message
+    Exit,                                                // Exit the debugger
+    InfoBreak,                                           // List breakpoints
     InfoWatch,
     Disable(Vec<BreakPointId>),
     Enable(Vec<BreakPointId>),
@@ -188,6 +290,7 @@ pub enum Command {
         Option<PrintCode>,
         PrintMode,
     ),
+    WatchData(ValueTarget), // Stop when a value changes
     PrintPC,
     Explain,
 }
@@ -241,6 +344,15 @@ lazy_static! {
                 .invocation("s")
                 .description("Advance the execution by a step. If provided a number, it will advance by that many steps (skips breakpoints).")
                 .usage("> s").usage("> s 5").build(),
+            // step-back
+            CIBuilder::new().invocation("step-back")
+                .invocation("sb")
+                .description("Go back by a step. If provided a number, it will go back by that many steps. The program is replayed from the last checkpoint to get there.")
+                .usage("> sb").usage("> sb 5").build(),
+            // reverse-continue
+            CIBuilder::new().invocation("reverse-continue")
+                .invocation("rc")
+                .description("Go back until the last point where a breakpoint or data watchpoint would have stopped the execution, or to the start of the program").build(),
             // step-over
             CIBuilder::new().invocation("step-over")
                 .description("Advance the execution over a given group.")
@@ -264,8 +376,8 @@ lazy_static! {
                 .usage("> watch after GROUP with print-state \\s mem").build(),
             // watch
             CIBuilder::new().invocation("watch")
-                .description("Watch a given group with a print statement. Takes an optional position (before/after)")
-                .usage("> watch GROUP with p \\u reg.in").usage("> watch after GROUP with print-state \\s mem").build(),
+                .description("Watch a given group with a print statement. Takes an optional position (before/after). Given only a port or memory location, stops execution when its value changes")
+                .usage("> watch GROUP with p \\u reg.in").usage("> watch after GROUP with print-state \\s mem").usage("> watch reg.out").usage("> watch mem[2][3]").build(),
             // where
             CIBuilder::new().invocation("where")
                 .invocation("pc")
@@ -277,8 +389,8 @@ lazy_static! {
             // break
             CIBuilder::new().invocation("break")
                 .invocation("br")
-                .description("Create a breakpoint")
-                .usage("> br do_add",).usage("> br subcomp::let0").build(),
+                .description("Create a breakpoint. Takes an optional condition and a number of hits to skip")
+                .usage("> br do_add",).usage("> br subcomp::let0").usage("> br do_add if reg.out > 10").usage("> br do_add after 5").build(),
             // info break
             CIBuilder::new().invocation("info break")
                 .invocation("ib")
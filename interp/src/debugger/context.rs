@@ -1,10 +1,13 @@
 use super::cidr::SPACING;
 use super::commands::{
-    BreakPointId, ParsedGroupName, PrintTuple, WatchPosition,
+    BreakPointId, Condition, ParsedGroupName, PrintTuple, ValueTarget,
+    WatchPosition,
 };
+use super::io_utils::Output;
 
 use crate::interpreter_ir as iir;
 use crate::structures::names::{CompGroupName, GroupQIN};
+use crate::values::Value;
 use calyx::ir::Id;
 use std::collections::HashMap;
 use std::collections::HashSet;
@@ -41,6 +44,12 @@ struct BreakPoint {
     id: u64,
     name: CompGroupName, // Name of the group (may not strictly be needed)
     state: BreakPointState,
+    /// Only stop when this condition holds
+    condition: Option<Condition>,
+    /// Number of hits to pass over before stopping
+    skip: u64,
+    /// Number of times the group started while the condition held
+    hits: u64,
 }
 
 impl BreakPoint {
@@ -59,6 +68,13 @@ impl BreakPoint {
     pub fn is_deleted(&self) -> bool {
         matches!(self.state, BreakPointState::Deleted)
     }
+
+    /// Records a hit of the breakpoint and returns true if execution should
+    /// stop.
+    fn hit(&mut self) -> bool {
+        self.hits += 1;
+        self.hits > self.skip
+    }
 }
 
 #[derive(Debug)]
@@ -73,6 +89,20 @@ impl Display for WatchPoint {
     }
 }
 
+/// A watchpoint which stops execution when the value of its target changes.
+pub(super) struct DataWatchPoint {
+    pub id: u64,
+    pub target: ValueTarget,
+    /// The value of the target when it was last checked
+    pub value: Value,
+}
+
+impl Display for DataWatchPoint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}.  {}", self.id, self.target)
+    }
+}
+
 impl std::fmt::Debug for BreakPoint {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
@@ -85,7 +115,14 @@ impl std::fmt::Debug for BreakPoint {
                 BreakPointState::Disabled => "disabled",
                 BreakPointState::Deleted => "deleted",
             }
-        )
+        )?;
+        if let Some(condition) = &self.condition {
+            write!(f, "  if {}", condition)?;
+        }
+        if self.skip > 0 {
+            write!(f, "  after {}", self.skip)?;
+        }
+        write!(f, "  hits: {}", self.hits)
     }
 }
 
@@ -139,9 +176,14 @@ impl BreakpointAction {
         }
     }
 
-    fn take_action_with_feedback(&self, breakpoint: &mut BreakPoint) {
+    fn take_action_with_feedback(
+        &self,
+        breakpoint: &mut BreakPoint,
+        out: &Output,
+    ) {
         self.take_action(breakpoint);
-        println!(
+        outln!(
+            out,
             "{} '{}'",
             match self {
                 BreakpointAction::Enable => "enabled",
@@ -159,12 +201,15 @@ pub(super) struct DebuggingContext {
         HashMap<CompGroupName, (BreakPointState, Vec<WatchPoint>)>,
     watchpoints_after:
         HashMap<CompGroupName, (BreakPointState, Vec<WatchPoint>)>,
+    data_watchpoints: Vec<DataWatchPoint>,
     count: Counter,
     watch_count: Counter,
     group_exec_info: GroupExecutionInfo<CompGroupName>,
     // used primarially for checking if a given group exists
     comp_ctx: HashMap<Id, Rc<iir::Component>>,
     main_comp_name: Id,
+    /// Changes whenever the set of active breakpoints changes
+    breakpoints_version: u64,
 }
 
 impl DebuggingContext {
@@ -175,42 +220,43 @@ impl DebuggingContext {
             breakpoints: HashMap::new(),
             watchpoints_before: HashMap::new(),
             watchpoints_after: HashMap::new(),
+            data_watchpoints: Vec::new(),
             group_exec_info: GroupExecutionInfo::new(),
             main_comp_name: *main_component,
+            breakpoints_version: 0,
 
             comp_ctx: ctx.iter().map(|x| (x.name, Rc::clone(x))).collect(),
         }
     }
 
-    pub fn add_breakpoint<N>(&mut self, target: N)
-    where
+    pub fn add_breakpoint<N>(
+        &mut self,
+        target: N,
+        condition: Option<Condition>,
+        skip: u64,
+        out: &Output,
+    ) where
         N: ConcretizableName,
     {
         let target = target.concretize(self);
-        let component_ref = self.comp_ctx.get(&target.component_name);
 
-        if component_ref.is_none() {
-            println!(
+        if !self.comp_ctx.contains_key(&target.component_name) {
+            outln!(
+                out,
                 "{} Error: there is no component named {}",
-                SPACING, target.component_name
+                SPACING,
+                target.component_name
             );
             return;
         }
 
-        let component_ref = component_ref.unwrap();
-
-        let group_exists = {
-            let exists = component_ref.groups.find(target.group_name).is_some();
-            // if there is no non-comb group, check comb groups
-            if !exists {
-                component_ref.comb_groups.find(target.group_name).is_some()
-            } else {
-                true
-            }
-        };
-
-        if !group_exists {
-            println!("{} Error: the group {} does not exit", SPACING, target);
+        if !self.group_exists(&target) {
+            outln!(
+                out,
+                "{} Error: the group {} does not exit",
+                SPACING,
+                target
+            );
             return;
         }
 
@@ -221,10 +267,31 @@ impl DebuggingContext {
                 id: self.count.next(),
                 name: target,
                 state: BreakPointState::Enabled,
+                condition,
+                skip,
+                hits: 0,
             };
             e.insert(br);
+            self.breakpoints_version += 1;
         } else {
-            println!("A breakpoint already exists for \"{}\"", &target)
+            outln!(out, "A breakpoint already exists for \"{}\"", &target)
+        }
+    }
+
+    /// Whether the group or combinational group named by `target` exists.
+    pub fn group_exists(&self, target: &CompGroupName) -> bool {
+        self.comp_ctx
+            .get(&target.component_name)
+            .is_some_and(|comp| {
+                comp.groups.find(target.group_name).is_some()
+                    || comp.comb_groups.find(target.group_name).is_some()
+            })
+    }
+
+    /// Deletes the breakpoint on `target`, if there is one.
+    pub fn clear_breakpoint(&mut self, target: &CompGroupName) {
+        if self.breakpoints.remove(target).is_some() {
+            self.breakpoints_version += 1;
         }
     }
 
@@ -261,32 +328,70 @@ impl DebuggingContext {
         }
     }
 
+    pub fn add_data_watchpoint(&mut self, target: ValueTarget, value: Value) {
+        self.data_watchpoints.push(DataWatchPoint {
+            id: self.watch_count.next(),
+            target,
+            value,
+        })
+    }
+
+    /// Identifies the current set of active breakpoints. The hits of the
+    /// breakpoints only account for the whole execution if it has not changed
+    /// since the start of the program.
+    pub fn breakpoints_version(&self) -> u64 {
+        self.breakpoints_version
+    }
+
+    /// The number of times each breakpoint has been hit.
+    pub fn hits(&self) -> HashMap<CompGroupName, u64> {
+        self.breakpoints
+            .iter()
+            .map(|(name, brk)| (name.clone(), brk.hits))
+            .collect()
+    }
+
+    /// Restores the hits of the breakpoints from [DebuggingContext::hits].
+    /// Breakpoints missing from `hits` have not been hit.
+    pub fn set_hits(&mut self, hits: &HashMap<CompGroupName, u64>) {
+        for (name, brk) in self.breakpoints.iter_mut() {
+            brk.hits = hits.get(name).copied().unwrap_or_default();
+        }
+    }
+
+    pub fn data_watchpoints_mut(&mut self) -> &mut Vec<DataWatchPoint> {
+        &mut self.data_watchpoints
+    }
+
     fn act_breakpoint(
         &mut self,
         target: BreakPointId,
         action: BreakpointAction,
+        out: &Output,
     ) {
+        self.breakpoints_version += 1;
         match target {
             BreakPointId::Name(target) => {
                 let key = self.concretize_group_name(target);
 
                 if let Some(breakpoint) = self.breakpoints.get_mut(&key) {
-                    action.take_action_with_feedback(breakpoint);
+                    action.take_action_with_feedback(breakpoint, out);
                 } else {
-                    println!("Error: There is no breakpoint named '{}'", key)
+                    outln!(out, "Error: There is no breakpoint named '{}'", key)
                 };
             }
             BreakPointId::Number(target) => {
                 let mut found = false;
                 for x in self.breakpoints.values_mut() {
                     if x.id == target {
-                        action.take_action_with_feedback(x);
+                        action.take_action_with_feedback(x, out);
                         found = true;
                         break;
                     }
                 }
                 if !found {
-                    println!(
+                    outln!(
+                        out,
                         "Error: There is no breakpoint numbered {}",
                         target
                     )
@@ -295,14 +400,14 @@ impl DebuggingContext {
         }
     }
 
-    pub fn enable_breakpoint(&mut self, target: BreakPointId) {
-        self.act_breakpoint(target, BreakpointAction::Enable)
+    pub fn enable_breakpoint(&mut self, target: BreakPointId, out: &Output) {
+        self.act_breakpoint(target, BreakpointAction::Enable, out)
     }
-    pub fn disable_breakpoint(&mut self, target: BreakPointId) {
-        self.act_breakpoint(target, BreakpointAction::Disable)
+    pub fn disable_breakpoint(&mut self, target: BreakPointId, out: &Output) {
+        self.act_breakpoint(target, BreakpointAction::Disable, out)
     }
-    pub fn remove_breakpoint(&mut self, target: BreakPointId) {
-        self.act_breakpoint(target, BreakpointAction::Delete);
+    pub fn remove_breakpoint(&mut self, target: BreakPointId, out: &Output) {
+        self.act_breakpoint(target, BreakpointAction::Delete, out);
         self.cleanup_deleted_breakpoints()
     }
 
@@ -334,6 +439,7 @@ impl DebuggingContext {
         {
             watchpoints.1.retain(|x| x.id != target);
         }
+        self.data_watchpoints.retain(|x| x.id != target);
     }
 
     #[inline]
@@ -356,16 +462,25 @@ impl DebuggingContext {
         self.group_exec_info.shift_current(current);
     }
 
-    pub fn hit_breakpoints(&self) -> Vec<&CompGroupName> {
-        self.group_exec_info
-            .groups_new_on()
-            .filter(|x| {
-                if let Some(brk) = self.breakpoints.get(x) {
-                    return brk.state.enabled();
+    /// Returns the breakpoints of the groups which just started and should
+    /// stop execution. `check` evaluates the conditions of conditional
+    /// breakpoints.
+    pub fn hit_breakpoints<F>(&mut self, mut check: F) -> Vec<CompGroupName>
+    where
+        F: FnMut(&Condition) -> bool,
+    {
+        let mut hit = vec![];
+        for group in self.group_exec_info.groups_new_on() {
+            if let Some(brk) = self.breakpoints.get_mut(group) {
+                if brk.state.enabled()
+                    && brk.condition.as_ref().is_none_or(&mut check)
+                    && brk.hit()
+                {
+                    hit.push(group.clone());
                 }
-                false
-            })
-            .collect()
+            }
+        }
+        hit
     }
 
     pub fn process_watchpoints(&self) -> Vec<&'_ PrintTuple> {
@@ -415,35 +530,43 @@ impl DebuggingContext {
         current.contains(target)
     }
 
-    pub fn print_breakpoints(&self) {
-        println!("{}Current breakpoints:", SPACING);
+    pub fn print_breakpoints(&self, out: &Output) {
+        outln!(out, "{}Current breakpoints:", SPACING);
         for breakpoint in self.breakpoints.values() {
-            println!("{}{:?}", SPACING, breakpoint)
+            outln!(out, "{}{:?}", SPACING, breakpoint)
         }
     }
 
-    pub fn print_watchpoints(&self) {
-        println!("{}Current watchpoints:", SPACING);
+    pub fn print_watchpoints(&self, out: &Output) {
+        outln!(out, "{}Current watchpoints:", SPACING);
         let inner_spacing = format!("{}    ", SPACING);
         let outer_spacing = format!("{}  ", SPACING);
 
         for (group, (_brk, watchpoints)) in self.watchpoints_before.iter() {
-            println!("{}Before {}:", outer_spacing, group);
+            outln!(out, "{}Before {}:", outer_spacing, group);
             for watchpoint in watchpoints.iter() {
-                println!("{}{}", inner_spacing, watchpoint);
+                outln!(out, "{}{}", inner_spacing, watchpoint);
             }
         }
 
-        println!();
+        outln!(out);
 
         for (group, (_brk, watchpoints)) in self.watchpoints_after.iter() {
             if !watchpoints.is_empty() {
-                println!("{}After {}:", outer_spacing, group);
+                outln!(out, "{}After {}:", outer_spacing, group);
                 for watchpoint in watchpoints.iter() {
-                    println!("{}{}", inner_spacing, watchpoint);
+                    outln!(out, "{}{}", inner_spacing, watchpoint);
                 }
             }
         }
+
+        if !self.data_watchpoints.is_empty() {
+            outln!(out);
+            outln!(out, "{}On change:", outer_spacing);
+            for watchpoint in self.data_watchpoints.iter() {
+                outln!(out, "{}{}", inner_spacing, watchpoint);
+            }
+        }
     }
 }
 
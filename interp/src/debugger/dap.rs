@@ -0,0 +1,898 @@
+//! A server for the [Debug Adapter Protocol][dap] which lets editors such as
+//! VS Code drive the [Debugger]. Messages are read from stdin and written to
+//! stdout.
+//!
+//! [dap]: https://microsoft.github.io/debug-adapter-protocol/
+
+use super::cidr::{format_value, Debugger};
+use super::commands::{Command, ParsedGroupName, PrintCode, PrintMode};
+use super::io_utils::Output;
+use super::parser::{parse_command, parse_condition, parse_value_target};
+use crate::environment::InterpreterState;
+use crate::errors::InterpreterResult;
+use crate::interpreter::{ComponentInterpreter, Interpreter};
+use crate::interpreter_ir as iir;
+use crate::structures::names::{CompGroupName, GroupQualifiedInstanceName};
+use calyx::ir::{self, Id};
+use calyx::utils::{GPosIdx, WithPos};
+use serde::Deserialize;
+use serde_json::{json, Value as Json};
+use std::collections::{HashMap, HashSet};
+use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+/// The program is reported to the client as a single thread.
+const THREAD_ID: u64 = 1;
+
+#[derive(Deserialize)]
+struct Request {
+    seq: u64,
+    command: String,
+    #[serde(default)]
+    arguments: Json,
+}
+
+/// Reads a message framed by a `Content-Length` header. Returns `None` once
+/// the input is closed.
+fn read_message<R: BufRead>(input: &mut R) -> io::Result<Option<Request>> {
+    let mut length = None;
+    loop {
+        let mut line = String::new();
+        if input.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            if length.is_some() {
+                break;
+            }
+            continue;
+        }
+        if let Some((key, value)) = line.split_once(':') {
+            if key.trim().eq_ignore_ascii_case("Content-Length") {
+                length = Some(value.trim().parse::<usize>().map_err(|e| {
+                    io::Error::new(io::ErrorKind::InvalidData, e)
+                })?);
+            }
+        }
+    }
+    let mut body = vec![0; length.unwrap()];
+    input.read_exact(&mut body)?;
+    Ok(Some(serde_json::from_slice(&body)?))
+}
+
+/// Writes messages to the client.
+struct Connection<W: Write> {
+    out: W,
+    seq: u64,
+}
+
+impl<W: Write> Connection<W> {
+    fn send(&mut self, mut message: Json) -> io::Result<()> {
+        self.seq += 1;
+        message["seq"] = self.seq.into();
+        let body = message.to_string();
+        write!(self.out, "Content-Length: {}\r\n\r\n{}", body.len(), body)?;
+        self.out.flush()
+    }
+
+    fn respond(&mut self, request: &Request, body: Json) -> io::Result<()> {
+        self.send(json!({
+            "type": "response",
+            "request_seq": request.seq,
+            "success": true,
+            "command": request.command,
+            "body": body,
+        }))
+    }
+
+    fn respond_error(
+        &mut self,
+        request: &Request,
+        message: String,
+    ) -> io::Result<()> {
+        self.send(json!({
+            "type": "response",
+            "request_seq": request.seq,
+            "success": false,
+            "command": request.command,
+            "message": message,
+        }))
+    }
+
+    fn event(&mut self, event: &str, body: Json) -> io::Result<()> {
+        self.send(json!({
+            "type": "event",
+            "event": event,
+            "body": body,
+        }))
+    }
+}
+
+/// A stack frame shown to the client. There is a frame for every active group.
+struct Frame {
+    /// Names of the instances leading to the component running the group
+    path: Vec<Id>,
+    component: Rc<iir::Component>,
+}
+
+/// What the client sees when it expands a variable.
+#[derive(Clone)]
+enum Variables {
+    /// The cells of an instance of `component`
+    Cells(Vec<Id>, Rc<iir::Component>),
+    /// The ports of a primitive cell
+    Ports(Vec<Id>),
+}
+
+struct Session<'a, W: Write> {
+    debugger: &'a mut Debugger,
+    conn: Connection<W>,
+    /// `None` once the program has finished
+    interp: Option<ComponentInterpreter>,
+    /// Number of steps taken since the start of the program
+    cycle: u64,
+    /// The groups enabled or defined on each line of the source files
+    lines: HashMap<(PathBuf, usize), Vec<CompGroupName>>,
+    /// The breakpoints set for each source file
+    source_breakpoints: HashMap<PathBuf, Vec<CompGroupName>>,
+    /// The breakpoints set by group name
+    function_breakpoints: Vec<CompGroupName>,
+    stop_on_entry: bool,
+    /// Frames and variables are only valid until the execution resumes
+    frames: Vec<Frame>,
+    variables: Vec<Variables>,
+}
+
+impl Debugger {
+    /// Serves the Debug Adapter Protocol on stdin and stdout until the client
+    /// disconnects, debugging the program starting from `env`.
+    pub fn serve_dap(
+        &mut self,
+        env: InterpreterState,
+    ) -> InterpreterResult<()> {
+        let stdin = io::stdin();
+        let stdout = io::stdout();
+        self.serve(env, stdin.lock(), stdout.lock())
+    }
+
+    fn serve<R: BufRead, W: Write>(
+        &mut self,
+        env: InterpreterState,
+        mut input: R,
+        out: W,
+    ) -> InterpreterResult<()> {
+        self.output = Output::buffered();
+        let res = Session::new(self, env, out).and_then(|mut session| {
+            while let Some(request) = read_message(&mut input)? {
+                if !session.handle(&request)? {
+                    break;
+                }
+            }
+            Ok(())
+        });
+        self.output = Output::interactive();
+        res
+    }
+}
+
+impl<'a, W: Write> Session<'a, W> {
+    fn new(
+        debugger: &'a mut Debugger,
+        env: InterpreterState,
+        out: W,
+    ) -> InterpreterResult<Self> {
+        let interp = debugger.start(env)?;
+        let lines = enabled_lines(&debugger.context);
+        Ok(Self {
+            debugger,
+            conn: Connection { out, seq: 0 },
+            interp: Some(interp),
+            cycle: 0,
+            lines,
+            source_breakpoints: HashMap::new(),
+            function_breakpoints: vec![],
+            stop_on_entry: false,
+            frames: vec![],
+            variables: vec![],
+        })
+    }
+
+    /// Handles a request and returns false once the client disconnects.
+    fn handle(&mut self, request: &Request) -> InterpreterResult<bool> {
+        let args = &request.arguments;
+        match request.command.as_str() {
+            "initialize" => {
+                self.conn.respond(
+                    request,
+                    json!({
+                        "supportsConfigurationDoneRequest": true,
+                        "supportsFunctionBreakpoints": true,
+                        "supportsConditionalBreakpoints": true,
+                        "supportsHitConditionalBreakpoints": true,
+                        "supportsEvaluateForHovers": true,
+                        "supportsStepBack": true,
+                    }),
+                )?;
+                self.conn.event("initialized", json!({}))?;
+            }
+            "launch" | "attach" => {
+                self.stop_on_entry =
+                    args["stopOnEntry"].as_bool().unwrap_or(false);
+                self.conn.respond(request, Json::Null)?;
+            }
+            "setBreakpoints" => {
+                let breakpoints = self.set_source_breakpoints(args);
+                self.conn
+                    .respond(request, json!({ "breakpoints": breakpoints }))?;
+            }
+            "setFunctionBreakpoints" => {
+                let breakpoints = self.set_function_breakpoints(args);
+                self.conn
+                    .respond(request, json!({ "breakpoints": breakpoints }))?;
+            }
+            "setExceptionBreakpoints" => {
+                self.conn.respond(request, json!({ "breakpoints": [] }))?;
+            }
+            "configurationDone" => {
+                self.conn.respond(request, Json::Null)?;
+                if self.stop_on_entry {
+                    self.report_stop("entry", None)?;
+                } else {
+                    self.resume()?;
+                }
+            }
+            "threads" => {
+                let name = self.debugger.main_component.name.to_string();
+                self.conn.respond(
+                    request,
+                    json!({ "threads": [{ "id": THREAD_ID, "name": name }] }),
+                )?;
+            }
+            "stackTrace" => match self.stack_trace() {
+                Some(frames) => self.conn.respond(
+                    request,
+                    json!({
+                        "stackFrames": frames,
+                        "totalFrames": frames.len(),
+                    }),
+                )?,
+                None => self.finished_error(request)?,
+            },
+            "scopes" => {
+                let frame = args["frameId"]
+                    .as_u64()
+                    .and_then(|id| self.frames.get(id as usize));
+                match frame {
+                    Some(frame) => {
+                        let scope = Variables::Cells(
+                            frame.path.clone(),
+                            Rc::clone(&frame.component),
+                        );
+                        let reference = self.add_variables(scope);
+                        self.conn.respond(
+                            request,
+                            json!({ "scopes": [{
+                                "name": "Cells",
+                                "variablesReference": reference,
+                                "expensive": false,
+                            }] }),
+                        )?
+                    }
+                    None => self
+                        .conn
+                        .respond_error(request, "unknown frame".to_string())?,
+                }
+            }
+            "variables" => {
+                let variables = args["variablesReference"]
+                    .as_u64()
+                    .and_then(|r| {
+                        self.variables.get((r as usize).wrapping_sub(1))
+                    })
+                    .cloned();
+                match variables.and_then(|v| self.list_variables(v)) {
+                    Some(list) => self
+                        .conn
+                        .respond(request, json!({ "variables": list }))?,
+                    None => self.conn.respond_error(
+                        request,
+                        "unknown variables".to_string(),
+                    )?,
+                }
+            }
+            "evaluate" => {
+                let expression = args["expression"].as_str().unwrap_or("");
+                let repl = args["context"].as_str() == Some("repl");
+                match self.evaluate(expression, repl) {
+                    Ok(result) => self.conn.respond(
+                        request,
+                        json!({ "result": result, "variablesReference": 0 }),
+                    )?,
+                    Err(e) => self.conn.respond_error(request, e)?,
+                }
+            }
+            "continue" | "next" | "stepIn" | "stepOut" | "stepBack"
+            | "reverseContinue" => {
+                if self.interp.is_none() {
+                    self.finished_error(request)?;
+                } else {
+                    self.conn.respond(
+                        request,
+                        json!({ "allThreadsContinued": true }),
+                    )?;
+                    self.execute(&request.command)?;
+                }
+            }
+            // The execution only runs while a request is being handled so
+            // there is nothing to interrupt.
+            "pause" => self.conn.respond(request, Json::Null)?,
+            "disconnect" | "terminate" => {
+                self.conn.respond(request, Json::Null)?;
+                return Ok(false);
+            }
+            command => self.conn.respond_error(
+                request,
+                format!("unsupported request {}", command),
+            )?,
+        }
+        self.flush_output()?;
+        Ok(true)
+    }
+
+    fn finished_error(&mut self, request: &Request) -> io::Result<()> {
+        self.conn
+            .respond_error(request, "the program has finished".to_string())
+    }
+
+    /// Forwards the messages printed by the debugger to the client.
+    fn flush_output(&mut self) -> io::Result<()> {
+        let text = self.debugger.output.take_buffer();
+        if text.is_empty() {
+            return Ok(());
+        }
+        self.conn
+            .event("output", json!({ "category": "console", "output": text }))
+    }
+
+    /// Runs the program until the next breakpoint or the end of the program.
+    fn resume(&mut self) -> InterpreterResult<()> {
+        self.execute("continue")
+    }
+
+    fn execute(&mut self, command: &str) -> InterpreterResult<()> {
+        let debugger = &mut *self.debugger;
+        let interp = self.interp.as_mut().unwrap();
+        let reason = match command {
+            "continue" => {
+                let (breakpoints, value_changed) =
+                    debugger.continue_execution(interp, &mut self.cycle)?;
+                interp.converge()?;
+                if !breakpoints.is_empty() {
+                    let names = breakpoints
+                        .iter()
+                        .map(|b| b.to_string())
+                        .collect::<Vec<_>>()
+                        .join(", ");
+                    return self.report_stop("breakpoint", Some(names));
+                } else if value_changed {
+                    "data breakpoint"
+                } else {
+                    "pause"
+                }
+            }
+            "next" | "stepIn" => {
+                debugger.step(interp, &mut self.cycle)?;
+                interp.converge()?;
+                "step"
+            }
+            // Runs until the groups which are currently running have finished
+            "stepOut" => {
+                let running: HashSet<CompGroupName> = interp
+                    .currently_executing_group()
+                    .into_iter()
+                    .map(CompGroupName::from)
+                    .collect();
+                loop {
+                    debugger.step(interp, &mut self.cycle)?;
+                    let now: HashSet<CompGroupName> = interp
+                        .currently_executing_group()
+                        .into_iter()
+                        .map(CompGroupName::from)
+                        .collect();
+                    if interp.is_done() || now.is_disjoint(&running) {
+                        break;
+                    }
+                }
+                interp.converge()?;
+                "step"
+            }
+            "stepBack" => {
+                let interp =
+                    debugger.go_back(&Command::StepBack(1), &mut self.cycle)?;
+                self.interp = Some(interp);
+                "step"
+            }
+            "reverseContinue" => {
+                let interp = debugger
+                    .go_back(&Command::ReverseContinue, &mut self.cycle)?;
+                self.interp = Some(interp);
+                if self.cycle == 0 {
+                    "entry"
+                } else {
+                    "breakpoint"
+                }
+            }
+            _ => unreachable!("not an execution request"),
+        };
+        self.report_stop(reason, None)
+    }
+
+    /// Tells the client that the execution stopped, or that the program
+    /// finished.
+    fn report_stop(
+        &mut self,
+        reason: &str,
+        description: Option<String>,
+    ) -> InterpreterResult<()> {
+        self.frames.clear();
+        self.variables.clear();
+        self.flush_output()?;
+
+        if self.interp.as_ref().is_some_and(|i| i.is_done()) {
+            self.interp = None;
+            self.conn.event(
+                "output",
+                json!({
+                    "category": "console",
+                    "output": "Main component has finished executing.\n",
+                }),
+            )?;
+            self.conn.event("exited", json!({ "exitCode": 0 }))?;
+            self.conn.event("terminated", json!({}))?;
+        } else {
+            let mut body = json!({
+                "reason": reason,
+                "threadId": THREAD_ID,
+                "allThreadsStopped": true,
+            });
+            if let Some(description) = description {
+                body["description"] =
+                    format!("Hit breakpoint: {}", description).into();
+                body["text"] = description.into();
+            }
+            self.conn.event("stopped", body)?;
+        }
+        Ok(())
+    }
+
+    fn set_source_breakpoints(&mut self, args: &Json) -> Vec<Json> {
+        let path =
+            canonical(Path::new(args["source"]["path"].as_str().unwrap_or("")));
+        let ctx = &mut self.debugger.debugging_ctx;
+        for old in self.source_breakpoints.remove(&path).unwrap_or_default() {
+            ctx.clear_breakpoint(&old);
+        }
+
+        let mut groups = vec![];
+        let mut verified = vec![];
+        for brk in args["breakpoints"].as_array().into_iter().flatten() {
+            let line = brk["line"].as_u64().unwrap_or(0);
+            let Some(targets) = self.lines.get(&(path.clone(), line as usize))
+            else {
+                verified.push(json!({
+                    "verified": false,
+                    "line": line,
+                    "message": "no group is defined or enabled on this line",
+                }));
+                continue;
+            };
+            let result = self.add_breakpoints(targets.clone(), brk);
+            verified.push(match result {
+                Ok(added) => {
+                    groups.extend(added);
+                    json!({ "verified": true, "line": line })
+                }
+                Err(e) => {
+                    json!({ "verified": false, "line": line, "message": e })
+                }
+            });
+        }
+        self.source_breakpoints.insert(path, groups);
+        verified
+    }
+
+    fn set_function_breakpoints(&mut self, args: &Json) -> Vec<Json> {
+        let ctx = &mut self.debugger.debugging_ctx;
+        for old in std::mem::take(&mut self.function_breakpoints) {
+            ctx.clear_breakpoint(&old);
+        }
+
+        let mut verified = vec![];
+        for brk in args["breakpoints"].as_array().into_iter().flatten() {
+            let name = brk["name"].as_str().unwrap_or("");
+            let parsed = match name.split_once("::") {
+                Some((comp, group)) => ParsedGroupName::from_comp_and_group(
+                    comp.into(),
+                    group.into(),
+                ),
+                None => ParsedGroupName::from_group_name(name.into()),
+            };
+            let target =
+                self.debugger.debugging_ctx.concretize_group_name(parsed);
+            if !self.debugger.debugging_ctx.group_exists(&target) {
+                verified.push(json!({
+                    "verified": false,
+                    "message": format!("there is no group named {}", target),
+                }));
+                continue;
+            }
+            verified.push(match self.add_breakpoints(vec![target], brk) {
+                Ok(added) => {
+                    self.function_breakpoints.extend(added);
+                    json!({ "verified": true })
+                }
+                Err(e) => json!({ "verified": false, "message": e }),
+            });
+        }
+        verified
+    }
+
+    /// Adds breakpoints on `targets` with the condition and hit count of
+    /// `brk`.
+    fn add_breakpoints(
+        &mut self,
+        targets: Vec<CompGroupName>,
+        brk: &Json,
+    ) -> Result<Vec<CompGroupName>, String> {
+        let condition = match brk["condition"].as_str() {
+            Some(c) if !c.trim().is_empty() => {
+                let condition =
+                    parse_condition(c.trim()).map_err(|e| e.to_string())?;
+                if let Some(interp) = &self.interp {
+                    self.debugger
+                        .eval_condition(&condition, interp.get_env())
+                        .map_err(|e| e.to_string())?;
+                }
+                Some(condition)
+            }
+            _ => None,
+        };
+        let skip = match brk["hitCondition"].as_str() {
+            Some(h) if !h.trim().is_empty() => parse_hit_condition(h)?,
+            _ => 0,
+        };
+
+        for target in targets.iter() {
+            self.debugger.debugging_ctx.add_breakpoint(
+                target.clone(),
+                condition.clone(),
+                skip,
+                &self.debugger.output,
+            );
+        }
+        Ok(targets)
+    }
+
+    fn stack_trace(&mut self) -> Option<Vec<Json>> {
+        let interp = self.interp.as_ref()?;
+        let tree = interp.get_active_tree().remove(0);
+        let active: Vec<GroupQualifiedInstanceName> =
+            tree.flatten().into_iter().collect();
+
+        let mut frames = vec![];
+        self.frames.clear();
+        // The innermost groups come first
+        for name in active.into_iter().rev() {
+            let mut frame = json!({
+                "id": self.frames.len(),
+                "name": name.format_name(),
+                "line": 0,
+                "column": 0,
+            });
+            self.locate(&name, &mut frame);
+            frames.push(frame);
+            self.frames.push(Frame {
+                path: name.prefix.iter().map(|x| x.instance).collect(),
+                component: Rc::clone(&name.prefix.last().unwrap().component_id),
+            });
+        }
+
+        if frames.is_empty() {
+            let main = &self.debugger.main_component;
+            frames.push(json!({
+                "id": 0,
+                "name": main.name.to_string(),
+                "line": 0,
+                "column": 0,
+            }));
+            self.frames.push(Frame {
+                path: vec![main.name],
+                component: Rc::clone(main),
+            });
+        }
+        Some(frames)
+    }
+
+    /// Fills in the source location of the frame for the active group `name`.
+    /// Entries of the `metadata` block are used when they start with a
+    /// `file:line` location and otherwise added to the name of the frame.
+    fn locate(&self, name: &GroupQualifiedInstanceName, frame: &mut Json) {
+        let entry = self.debugger.source_map.as_ref().and_then(|map| {
+            name.pos_tag
+                .and_then(|tag| map.lookup((tag, name.format_name())))
+        });
+
+        if let Some(entry) = entry {
+            if let Some((file, line)) = parse_location(entry) {
+                frame["source"] = source(Path::new(file));
+                frame["line"] = line.into();
+                return;
+            }
+            let first = entry.lines().next().unwrap_or("").trim();
+            frame["name"] = format!("{}: {}", name.format_name(), first).into();
+        }
+
+        if let Some(span) = name.span.into_option() {
+            let (file, _, _) = span.get_location();
+            let ((line, column), _) = span.get_line_col();
+            frame["source"] = source(Path::new(file));
+            frame["line"] = line.into();
+            frame["column"] = column.into();
+        }
+    }
+
+    fn add_variables(&mut self, variables: Variables) -> usize {
+        self.variables.push(variables);
+        self.variables.len()
+    }
+
+    fn list_variables(&mut self, variables: Variables) -> Option<Vec<Json>> {
+        let interp = self.interp.as_ref()?;
+        let debugger = &*self.debugger;
+        // (name, value, children)
+        let mut entries: Vec<(String, String, Option<Variables>)> = vec![];
+
+        match variables {
+            Variables::Cells(path, component) => {
+                for cell in component.cells.iter() {
+                    let cell_ref = cell.borrow();
+                    let mut cell_path = path.clone();
+                    cell_path.push(cell_ref.name());
+
+                    if let ir::CellType::Component { name } =
+                        &cell_ref.prototype
+                    {
+                        let sub = debugger
+                            .context
+                            .iter()
+                            .find(|comp| comp.name == name)
+                            .map(|comp| {
+                                Variables::Cells(cell_path, Rc::clone(comp))
+                            });
+                        entries.push((
+                            cell_ref.name().to_string(),
+                            name.to_string(),
+                            sub,
+                        ));
+                        continue;
+                    }
+
+                    let value = debugger
+                        .do_print(
+                            &cell_path,
+                            &None,
+                            interp.get_env(),
+                            &PrintMode::State,
+                        )
+                        .map(|printed| show(&printed.value))
+                        .unwrap_or_else(|e| e.to_string());
+                    entries.push((
+                        cell_ref.name().to_string(),
+                        value,
+                        Some(Variables::Ports(cell_path)),
+                    ));
+                }
+            }
+            Variables::Ports(path) => {
+                let printed = debugger
+                    .do_print(&path, &None, interp.get_env(), &PrintMode::Port)
+                    .ok()?;
+                if let Json::Object(ports) = printed.value {
+                    for (port, value) in ports {
+                        entries.push((port, show(&value), None));
+                    }
+                }
+            }
+        }
+
+        Some(
+            entries
+                .into_iter()
+                .map(|(name, value, children)| {
+                    let reference =
+                        children.map_or(0, |v| self.add_variables(v));
+                    json!({
+                        "name": name,
+                        "value": value,
+                        "variablesReference": reference,
+                    })
+                })
+                .collect(),
+        )
+    }
+
+    /// Evaluates a port, register or memory location. In the debug console
+    /// the debugger commands which inspect the program can be used as well.
+    fn evaluate(
+        &mut self,
+        expression: &str,
+        repl: bool,
+    ) -> Result<String, String> {
+        let interp = self
+            .interp
+            .as_ref()
+            .ok_or_else(|| "the program has finished".to_string())?;
+        let debugger = &*self.debugger;
+        let out = &debugger.output;
+
+        if repl {
+            let handled = match parse_command(expression) {
+                Ok(Command::Print(targets, code, mode)) => {
+                    for target in targets {
+                        match debugger.do_print(
+                            &target,
+                            &code,
+                            interp.get_env(),
+                            &mode,
+                        ) {
+                            Ok(printed) => out.value(printed),
+                            Err(e) => outln!(out, "{}", e),
+                        }
+                    }
+                    true
+                }
+                Ok(Command::Display) => {
+                    debugger.display(interp.get_env());
+                    true
+                }
+                Ok(Command::PrintPC) => {
+                    debugger.print_pc(interp.get_active_tree().remove(0));
+                    true
+                }
+                Ok(Command::InfoBreak) => {
+                    debugger.debugging_ctx.print_breakpoints(out);
+                    true
+                }
+                Ok(Command::InfoWatch) => {
+                    debugger.debugging_ctx.print_watchpoints(out);
+                    true
+                }
+                Ok(Command::Help) => {
+                    out!(out, "{}", Command::get_help_string());
+                    true
+                }
+                Ok(_) => return Err(
+                    "only commands which inspect the program can be used here"
+                        .to_string(),
+                ),
+                Err(_) => false,
+            };
+            if handled {
+                return Ok(out.take_buffer().trim_end().to_string());
+            }
+        }
+
+        let target =
+            parse_value_target(expression).map_err(|e| e.to_string())?;
+        match debugger.read_value(&target, interp.get_env()) {
+            Ok((value, format)) => {
+                let code = format.map(PrintCode::from).unwrap_or_default();
+                Ok(format_value(&value, code).0)
+            }
+            Err(_) if target.address.is_empty() => debugger
+                .do_print(
+                    &target.name,
+                    &None,
+                    interp.get_env(),
+                    &PrintMode::State,
+                )
+                .map(|printed| show(&printed.value))
+                .map_err(|e| e.to_string()),
+            Err(e) => Err(e.to_string()),
+        }
+    }
+}
+
+/// Parses a hit condition. Execution stops from the given hit onwards.
+fn parse_hit_condition(condition: &str) -> Result<u64, String> {
+    let count = condition.trim().trim_start_matches(">=").trim();
+    count
+        .parse::<u64>()
+        .map(|n| n.saturating_sub(1))
+        .map_err(|_| format!("invalid hit count {}", condition))
+}
+
+/// Parses a `file:line` location at the start of a metadata entry.
+fn parse_location(entry: &str) -> Option<(&str, usize)> {
+    let first = entry.split_whitespace().next()?;
+    let (file, line) = first.rsplit_once(':')?;
+    let line = line.trim_end_matches(':').parse().ok()?;
+    (!file.is_empty()).then_some((file, line))
+}
+
+fn canonical(path: &Path) -> PathBuf {
+    path.canonicalize().unwrap_or_else(|_| path.to_path_buf())
+}
+
+fn source(file: &Path) -> Json {
+    let path = canonical(file);
+    json!({
+        "name": path.file_name().map(|n| n.to_string_lossy().to_string()),
+        "path": path.to_string_lossy(),
+    })
+}
+
+/// Shows a value printed by the debugger to the client.
+fn show(value: &Json) -> String {
+    match value {
+        Json::String(s) => s.clone(),
+        v => v.to_string(),
+    }
+}
+
+/// Finds the groups defined or enabled on each line of the program.
+fn enabled_lines(
+    ctx: &iir::ComponentCtx,
+) -> HashMap<(PathBuf, usize), Vec<CompGroupName>> {
+    let mut lines: HashMap<(PathBuf, usize), Vec<CompGroupName>> =
+        HashMap::new();
+
+    for comp in ctx.iter() {
+        let mut add = |span: GPosIdx, group: Id| {
+            if let Some(span) = span.into_option() {
+                let (file, _, _) = span.get_location();
+                let ((line, _), _) = span.get_line_col();
+                let groups = lines
+                    .entry((canonical(Path::new(file)), line))
+                    .or_default();
+                let name = CompGroupName::new(group, comp.name);
+                if !groups.contains(&name) {
+                    groups.push(name);
+                }
+            }
+        };
+
+        for group in comp.groups.iter() {
+            let group = group.borrow();
+            add(group.attributes.copy_span(), group.name());
+        }
+        for group in comp.comb_groups.iter() {
+            let group = group.borrow();
+            add(group.attributes.copy_span(), group.name());
+        }
+        visit_enables(&comp.control, &mut add);
+    }
+    lines
+}
+
+fn visit_enables<F: FnMut(GPosIdx, Id)>(control: &iir::Control, f: &mut F) {
+    match control {
+        iir::Control::Seq(s) => {
+            s.stmts.iter().for_each(|stmt| visit_enables(stmt, f))
+        }
+        iir::Control::Par(p) => {
+            p.stmts.iter().for_each(|stmt| visit_enables(stmt, f))
+        }
+        iir::Control::If(i) => {
+            visit_enables(&i.tbranch, f);
+            visit_enables(&i.fbranch, f);
+        }
+        iir::Control::While(w) => visit_enables(&w.body, f),
+        iir::Control::Enable(e) => {
+            f(e.attributes.copy_span(), e.group.borrow().name())
+        }
+        iir::Control::Invoke(_) | iir::Control::Empty(_) => {}
+    }
+}
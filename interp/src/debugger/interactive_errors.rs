@@ -4,4 +4,6 @@ use thiserror::Error;
 pub enum DebuggerError {
     #[error("cannot find {0}")]
     CannotFind(String),
+    #[error("{0} is not a port, register or memory location")]
+    NotAValue(String),
 }
@@ -1,30 +1,226 @@
 use super::commands::Command;
 use super::parser::parse_command;
-use crate::errors::InterpreterResult;
+use crate::errors::{InterpreterError, InterpreterResult};
+use rustyline::error::ReadlineError;
 use rustyline::Editor;
+use serde::Serialize;
+use std::cell::RefCell;
 use std::collections::VecDeque;
+use std::fmt::{Display, Write};
+use std::path::Path;
 
 const SHELL_PROMPT: &str = " > ";
 
+/// Writes to the debugger [Output] like `print!`.
+macro_rules! out {
+    ($out:expr, $($arg:tt)*) => {
+        $out.print(format_args!($($arg)*))
+    };
+}
+
+/// Writes a line to the debugger [Output] like `println!`.
+macro_rules! outln {
+    ($out:expr) => {
+        $out.print(format_args!("\n"))
+    };
+    ($out:expr, $($arg:tt)*) => {
+        $out.print(format_args!("{}\n", format_args!($($arg)*)))
+    };
+}
+
+enum InputSource {
+    Interactive(Box<Editor<()>>),
+    /// The remaining lines of a script
+    Script(VecDeque<String>),
+}
+
 pub struct Input {
-    buffer: Editor<()>,
+    source: InputSource,
     command_buffer: VecDeque<Command>,
 }
 
 impl Input {
     pub fn new() -> InterpreterResult<Self> {
         Ok(Self {
-            buffer: Editor::new()?,
+            source: InputSource::Interactive(Box::new(Editor::new()?)),
             command_buffer: VecDeque::default(),
         })
     }
-    pub fn next_command(&mut self) -> InterpreterResult<Command> {
+
+    /// Reads commands from a file with one command per line. Empty lines and
+    /// lines starting with `#` are skipped.
+    pub fn from_script(path: &Path) -> InterpreterResult<Self> {
+        let lines = std::fs::read_to_string(path)?
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(String::from)
+            .collect();
+        Ok(Self {
+            source: InputSource::Script(lines),
+            command_buffer: VecDeque::default(),
+        })
+    }
+
+    pub fn is_interactive(&self) -> bool {
+        matches!(self.source, InputSource::Interactive(_))
+    }
+
+    pub fn next_command(
+        &mut self,
+        output: &Output,
+    ) -> InterpreterResult<Command> {
         if !self.command_buffer.is_empty() {
             return Ok(self.command_buffer.pop_front().unwrap());
         }
 
-        let result = self.buffer.readline(SHELL_PROMPT)?;
-        self.buffer.add_history_entry(result.clone());
+        let result = match &mut self.source {
+            InputSource::Interactive(buffer) => {
+                let result = buffer.readline(SHELL_PROMPT)?;
+                buffer.add_history_entry(result.clone());
+                result
+            }
+            InputSource::Script(lines) => {
+                let line = lines.pop_front().ok_or(
+                    InterpreterError::ReadlineError(ReadlineError::Eof),
+                )?;
+                output.start_command(&line);
+                line
+            }
+        };
         parse_command(&result)
     }
 }
+
+/// A value shown by `print`, `print-state` or a watchpoint.
+#[derive(Serialize)]
+pub struct PrintedValue {
+    /// The name of the port or cell
+    pub name: String,
+    pub value: serde_json::Value,
+    /// How the value is shown in interactive sessions
+    #[serde(skip)]
+    pub text: String,
+}
+
+/// The messages and values produced by a command of a script.
+#[derive(Serialize)]
+struct CommandLog {
+    command: Option<String>,
+    messages: Vec<String>,
+    values: Vec<PrintedValue>,
+}
+
+enum Sink {
+    /// Printed right away
+    Terminal,
+    /// Recorded along with the command that produced it
+    Recorded(RefCell<Vec<CommandLog>>),
+    /// Kept until it is taken with [Output::take_buffer]
+    Buffered(RefCell<String>),
+}
+
+/// Where the debugger writes its messages. Interactive sessions print them
+/// right away while scripted sessions record them along with the command that
+/// produced them so that they can be written as JSON.
+pub struct Output {
+    sink: Sink,
+}
+
+impl Output {
+    pub fn interactive() -> Self {
+        Self {
+            sink: Sink::Terminal,
+        }
+    }
+
+    pub fn recorded() -> Self {
+        Self {
+            sink: Sink::Recorded(RefCell::new(vec![])),
+        }
+    }
+
+    /// Keeps the text that would be printed so that it can be forwarded
+    /// elsewhere.
+    pub fn buffered() -> Self {
+        Self {
+            sink: Sink::Buffered(RefCell::new(String::new())),
+        }
+    }
+
+    fn with_current<F: FnOnce(&mut CommandLog)>(
+        &self,
+        log: &RefCell<Vec<CommandLog>>,
+        f: F,
+    ) {
+        let mut log = log.borrow_mut();
+        if log.is_empty() {
+            log.push(CommandLog {
+                command: None,
+                messages: vec![],
+                values: vec![],
+            });
+        }
+        f(log.last_mut().unwrap())
+    }
+
+    fn start_command(&self, command: &str) {
+        if let Sink::Recorded(log) = &self.sink {
+            log.borrow_mut().push(CommandLog {
+                command: Some(command.to_string()),
+                messages: vec![],
+                values: vec![],
+            })
+        }
+    }
+
+    /// Writes the text as is. Recorded messages are split into lines and
+    /// empty lines are dropped.
+    pub fn print<T: Display>(&self, text: T) {
+        match &self.sink {
+            Sink::Terminal => print!("{}", text),
+            Sink::Recorded(log) => self.with_current(log, |current| {
+                current.messages.extend(
+                    text.to_string()
+                        .lines()
+                        .map(str::trim)
+                        .filter(|line| !line.is_empty())
+                        .map(String::from),
+                )
+            }),
+            Sink::Buffered(buffer) => {
+                write!(buffer.borrow_mut(), "{}", text).unwrap()
+            }
+        }
+    }
+
+    pub fn value(&self, value: PrintedValue) {
+        match &self.sink {
+            Sink::Terminal => println!("{}", value.text),
+            Sink::Recorded(log) => {
+                self.with_current(log, |current| current.values.push(value))
+            }
+            Sink::Buffered(buffer) => {
+                writeln!(buffer.borrow_mut(), "{}", value.text).unwrap()
+            }
+        }
+    }
+
+    /// The recorded commands as JSON, if the output is recorded.
+    pub fn to_json(&self) -> Option<String> {
+        match &self.sink {
+            Sink::Recorded(log) => {
+                Some(serde_json::to_string_pretty(&*log.borrow()).unwrap())
+            }
+            _ => None,
+        }
+    }
+
+    /// Removes and returns the buffered text, if the output is buffered.
+    pub fn take_buffer(&self) -> String {
+        match &self.sink {
+            Sink::Buffered(buffer) => std::mem::take(&mut *buffer.borrow_mut()),
+            _ => String::new(),
+        }
+    }
+}
@@ -1,8 +1,10 @@
+#[macro_use]
+mod io_utils;
 mod cidr;
-mod commands;
+pub(crate) mod commands;
 mod context;
+mod dap;
 mod interactive_errors;
-mod io_utils;
 pub(crate) mod name_tree;
 pub(crate) mod parser;
 pub mod source;
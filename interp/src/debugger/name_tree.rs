@@ -1,6 +1,7 @@
 use serde::Serialize;
 
 use crate::structures::names::GroupQualifiedInstanceName;
+use calyx::utils::GPosIdx;
 use std::{collections::HashSet, fmt::Write, iter::once};
 
 #[derive(Debug, Clone)]
@@ -74,6 +75,15 @@ impl ActiveTreeNode {
     pub fn flat_set(self) -> ActiveSet {
         self.flatten().into()
     }
+
+    /// The names of the active nodes with a known source position along with
+    /// their positions.
+    pub fn source_positions(self) -> Vec<(String, GPosIdx)> {
+        self.flatten()
+            .into_iter()
+            .filter_map(|x| x.span.into_option().map(|s| (x.format_name(), s)))
+            .collect()
+    }
 }
 
 #[derive(Debug)]
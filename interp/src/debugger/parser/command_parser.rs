@@ -1,5 +1,9 @@
-use super::super::commands::{BreakPointId, Command, ParsedGroupName};
+use super::super::commands::{
+    BreakPointId, Command, CompareOp, Condition, Operand, ParsedGroupName,
+    ValueTarget,
+};
 use calyx::ir::Id;
+use ibig::IBig;
 use pest_consume::{match_nodes, Error, Parser};
 
 type ParseResult<T> = std::result::Result<T, Error<Rule>>;
@@ -38,6 +42,17 @@ impl CommandParser {
         ))
     }
 
+    fn step_back(input: Node) -> ParseResult<Command> {
+        Ok(match_nodes!(input.into_children();
+            [num(n)] => Command::StepBack(n),
+            [] => Command::StepBack(1)
+        ))
+    }
+
+    fn reverse_cont(_input: Node) -> ParseResult<Command> {
+        Ok(Command::ReverseContinue)
+    }
+
     fn display(_input: Node) -> ParseResult<Command> {
         Ok(Command::Display)
     }
@@ -133,9 +148,116 @@ impl CommandParser {
         ))
     }
 
+    fn address(input: Node) -> ParseResult<u64> {
+        Ok(match_nodes!(input.into_children();
+            [num(n)] => n
+        ))
+    }
+
+    fn value_target(input: Node) -> ParseResult<ValueTarget> {
+        Ok(match_nodes!(input.into_children();
+            [name(name), address(addr)..] => ValueTarget {
+                name,
+                address: addr.collect(),
+            }
+        ))
+    }
+
+    fn cmp_eq(_input: Node) -> ParseResult<()> {
+        Ok(())
+    }
+
+    fn cmp_neq(_input: Node) -> ParseResult<()> {
+        Ok(())
+    }
+
+    fn cmp_leq(_input: Node) -> ParseResult<()> {
+        Ok(())
+    }
+
+    fn cmp_geq(_input: Node) -> ParseResult<()> {
+        Ok(())
+    }
+
+    fn cmp_lt(_input: Node) -> ParseResult<()> {
+        Ok(())
+    }
+
+    fn cmp_gt(_input: Node) -> ParseResult<()> {
+        Ok(())
+    }
+
+    fn compare_op(input: Node) -> ParseResult<CompareOp> {
+        Ok(match_nodes!(input.into_children();
+            [cmp_eq(_)] => CompareOp::Eq,
+            [cmp_neq(_)] => CompareOp::Neq,
+            [cmp_leq(_)] => CompareOp::Leq,
+            [cmp_geq(_)] => CompareOp::Geq,
+            [cmp_lt(_)] => CompareOp::Lt,
+            [cmp_gt(_)] => CompareOp::Gt,
+        ))
+    }
+
+    fn literal(input: Node) -> ParseResult<IBig> {
+        input
+            .as_str()
+            .parse::<IBig>()
+            .map_err(|_| input.error("Expected a number"))
+    }
+
+    fn operand(input: Node) -> ParseResult<Operand> {
+        Ok(match_nodes!(input.into_children();
+            [literal(n)] => Operand::Literal(n),
+            [value_target(t)] => Operand::Target(t),
+        ))
+    }
+
+    fn condition(input: Node) -> ParseResult<Condition> {
+        Ok(match_nodes!(input.into_children();
+            [operand(left), compare_op(op), operand(right)] => Condition {
+                left,
+                op,
+                right,
+            }
+        ))
+    }
+
+    fn condition_input(input: Node) -> ParseResult<Condition> {
+        Ok(match_nodes!(input.into_children();
+            [condition(c), EOI(_)] => c
+        ))
+    }
+
+    fn value_target_input(input: Node) -> ParseResult<ValueTarget> {
+        Ok(match_nodes!(input.into_children();
+            [value_target(t), EOI(_)] => t
+        ))
+    }
+
+    fn brk_cond(input: Node) -> ParseResult<Condition> {
+        Ok(match_nodes!(input.into_children();
+            [condition(c)] => c
+        ))
+    }
+
+    fn brk_skip(input: Node) -> ParseResult<u64> {
+        Ok(match_nodes!(input.into_children();
+            [num(n)] => n
+        ))
+    }
+
+    fn brk_targets(input: Node) -> ParseResult<Vec<ParsedGroupName>> {
+        Ok(match_nodes!(input.into_children();
+            [group(g)..] => g.collect()
+        ))
+    }
+
     fn brk(input: Node) -> ParseResult<Command> {
         Ok(match_nodes!(input.into_children();
-            [group(g)..] => Command::Break(g.collect()),
+            [brk_targets(g), brk_cond(c), brk_skip(n)] => Command::Break(g, Some(c), n),
+            [brk_targets(g), brk_cond(c)] => Command::Break(g, Some(c), 0),
+            [brk_targets(g), brk_skip(n)] => Command::Break(g, None, n),
+            [brk_targets(g)] => Command::Break(g, None, 0),
         ))
     }
 
@@ -234,14 +356,27 @@ impl CommandParser {
         ))
     }
 
+    fn watch_keyword(_input: Node) -> ParseResult<()> {
+        Ok(())
+    }
+
+    fn watch_data(input: Node) -> ParseResult<Command> {
+        Ok(match_nodes!(input.into_children();
+            [watch_keyword(_), value_target(t)] => Command::WatchData(t)
+        ))
+    }
+
     fn command(input: Node) -> ParseResult<Command> {
         Ok(match_nodes!(input.into_children();
             [watch(w), EOI(_)] => w,
+            [watch_data(w), EOI(_)] => w,
             [print_state(p), EOI(_)] => p,
             [print(p), EOI(_)] => p,
             [print_fail(err), EOI(_)] => ParseResult::Err(err)?,
             [step_over(s), EOI(_)] => s,
+            [step_back(s), EOI(_)] => s,
             [step(s), EOI(_)] => s,
+            [reverse_cont(r), EOI(_)] => r,
             [cont(c), EOI(_)] => c,
             [comm_where(w), EOI(_)] => w,
             [help(h), EOI(_)] => h,
@@ -265,3 +400,15 @@ pub fn parse_command(input_str: &str) -> InterpreterResult<Command> {
     let input = inputs.single()?;
     Ok(CommandParser::command(input)?)
 }
+
+pub fn parse_condition(input_str: &str) -> InterpreterResult<Condition> {
+    let inputs = CommandParser::parse(Rule::condition_input, input_str)?;
+    let input = inputs.single()?;
+    Ok(CommandParser::condition_input(input)?)
+}
+
+pub fn parse_value_target(input_str: &str) -> InterpreterResult<ValueTarget> {
+    let inputs = CommandParser::parse(Rule::value_target_input, input_str)?;
+    let input = inputs.single()?;
+    Ok(CommandParser::value_target_input(input)?)
+}
@@ -1,2 +1,2 @@
 pub(crate) mod command_parser;
-pub use command_parser::parse_command;
+pub use command_parser::{parse_command, parse_condition, parse_value_target};
@@ -0,0 +1,176 @@
+//! Finds the compiler pass that changes the behavior of a program by
+//! interpreting the program before any pass runs and again after each pass of
+//! a plan.
+
+use crate::{
+    configuration::Config,
+    environment::InterpreterState,
+    errors::{BoxedInterpreterError, InterpreterError, InterpreterResult},
+    interpreter::ComponentInterpreter,
+    interpreter_ir as iir,
+    utils::MemoryMap,
+};
+use calyx::{errors::CalyxResult, ir, pass_manager::PassManager};
+use std::{fmt, rc::Rc};
+
+/// How the interpretation after a pass differs from that of the original
+/// program
+pub enum Difference {
+    /// The final contents of these external memories and registers differ
+    Memories(Vec<ir::Id>),
+    /// The program could no longer be interpreted
+    Failed(BoxedInterpreterError),
+}
+
+/// The first pass after which the program behaves differently
+pub struct Divergence {
+    pub pass: String,
+    /// The passes run just before `pass` whose output could not be
+    /// interpreted, any of which may have caused the difference instead
+    pub unchecked: Vec<String>,
+    pub difference: Difference,
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.difference {
+            Difference::Memories(names) => write!(
+                f,
+                "{} changed the final contents of {}",
+                self.pass,
+                names
+                    .iter()
+                    .map(|x| x.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            Difference::Failed(e) => {
+                write!(
+                    f,
+                    "interpreting the program after {} failed: {}",
+                    self.pass, e
+                )
+            }
+        }?;
+        if !self.unchecked.is_empty() {
+            write!(
+                f,
+                " (the output of {} could not be interpreted, so they may be \
+                 responsible instead)",
+                self.unchecked.join(", ")
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Whether a group of the program assigns to the holes of another group. The
+/// interpreter only runs the groups enabled by the control program so it
+/// can't interpret the output of the control compilation passes until the
+/// groups have been inlined away.
+fn enables_groups_structurally(ctx: &ir::Context) -> bool {
+    ctx.components.iter().any(|comp| {
+        comp.groups.iter().any(|group| {
+            let group = group.borrow();
+            group.assignments.iter().any(|assign| {
+                let dst = assign.dst.borrow();
+                dst.is_hole() && dst.get_parent_name() != group.name()
+            })
+        })
+    })
+}
+
+/// Interprets the main component of the given context
+fn interpret(
+    ctx: ir::Context,
+    mems: &Option<MemoryMap>,
+    config: &Config,
+) -> InterpreterResult<InterpreterState> {
+    let entry_point = ctx.entrypoint;
+    let components: iir::ComponentCtx = Rc::new(
+        ctx.components
+            .into_iter()
+            .map(|x| Rc::new(x.into()))
+            .collect(),
+    );
+    let main_component = components
+        .iter()
+        .find(|&cm| cm.name == entry_point)
+        .ok_or(InterpreterError::MissingMainComponent)?;
+    let env = InterpreterState::init_top_level(
+        &components,
+        main_component,
+        &mut mems.clone(),
+        config,
+    )?;
+    ComponentInterpreter::interpret_program(env, main_component)
+}
+
+/// Runs the passes of the plan described by `incl` and `excl` and returns
+/// the first one after which the final memories of the program differ from
+/// those of interpreting the original program. As the IR can't be copied,
+/// `construct` is called to build the original program again before each
+/// interpretation.
+///
+/// The pass is found by bisecting the plan, so the program is interpreted a
+/// logarithmic number of times. This assumes that once the behavior of the
+/// program changes, later passes do not change it back.
+pub fn find_divergence<F>(
+    construct: F,
+    pm: &PassManager,
+    incl: &[String],
+    excl: &[String],
+    mems: &Option<MemoryMap>,
+    config: &Config,
+) -> InterpreterResult<Option<Divergence>>
+where
+    F: Fn() -> CalyxResult<ir::Context>,
+{
+    let plan = pm.resolve_plan(incl, excl)?;
+    let expected = interpret(construct()?, mems, config)?;
+
+    // The prefixes of the plan are described by their lengths. The program
+    // behaves the same after running `plan[..same]`, and differently after
+    // running `plan[..differs]` if `differs` is in the plan. None of the
+    // programs after running the prefixes from `unchecked` up to `differs`
+    // can be interpreted.
+    let mut same = 0;
+    let mut differs = plan.len() + 1;
+    let mut unchecked = differs;
+    let mut difference = None;
+    while same + 1 < unchecked {
+        let mid = (same + unchecked) / 2;
+        let mut ctx = construct()?;
+        pm.execute_plan(&mut ctx, &plan[..mid], &[])?;
+        // Run the following passes until the program can be interpreted
+        let mut len = mid;
+        while enables_groups_structurally(&ctx) && len + 1 < unchecked {
+            pm.execute_plan(&mut ctx, &plan[len..=len], &[])?;
+            len += 1;
+        }
+        if enables_groups_structurally(&ctx) {
+            unchecked = mid;
+            continue;
+        }
+        let diff = match interpret(ctx, mems, config) {
+            Ok(env) => {
+                let names = env.differing_memories(&expected);
+                if names.is_empty() {
+                    same = len;
+                    continue;
+                }
+                Difference::Memories(names)
+            }
+            Err(e) => Difference::Failed(e),
+        };
+        differs = len;
+        unchecked = len;
+        difference = Some(diff);
+    }
+
+    Ok(difference.map(|difference| Divergence {
+        pass: plan[differs - 1].clone(),
+        unchecked: plan[same..differs - 1].to_vec(),
+        difference,
+    }))
+}
@@ -156,6 +156,15 @@ pub enum InterpreterError {
 
     #[error(transparent)]
     IOError(#[from] std::io::Error),
+
+    /// The data file used to initialize memories is malformed
+    #[error("invalid data file - {0}")]
+    InvalidDataFile(String),
+
+    /// The lowered program finished with different memories than the program
+    /// it was compiled from
+    #[error("after lowering, the final contents of {} differ from those of the original program", .0.iter().map(|x| x.to_string()).collect::<Vec<_>>().join(", "))]
+    LoweringMismatch(Vec<Id>),
 }
 
 impl InterpreterError {
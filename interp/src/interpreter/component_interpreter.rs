@@ -9,7 +9,7 @@ use super::{
 };
 use crate::{
     debugger::{name_tree::ActiveTreeNode, PrintCode},
-    environment::InterpreterState,
+    environment::{InterpreterState, Snapshot},
     errors::InterpreterResult,
     interpreter_ir as iir,
     primitives::{Named, Primitive},
@@ -220,6 +220,54 @@ impl ComponentInterpreter {
         main_comp.set_go_low();
         main_comp.deconstruct()
     }
+
+    /// Interpret a calyx program from the root and count the cycles it takes
+    /// to finish
+    pub fn interpret_program_cycles(
+        env: InterpreterState,
+        comp: &Rc<iir::Component>,
+    ) -> InterpreterResult<(InterpreterState, u64)> {
+        let qin = ComponentQualifiedInstanceName::new_single(comp, comp.name);
+        let mut main_comp = Self::from_component(comp, env, qin);
+        let mut cycles = 0;
+        main_comp.set_go_high();
+        main_comp.converge()?;
+        while !main_comp.is_done() {
+            main_comp.step()?;
+            // a structural step leaves the combinational logic unsettled
+            main_comp.converge()?;
+            cycles += 1;
+        }
+        main_comp.set_go_low();
+        Ok((main_comp.deconstruct()?, cycles))
+    }
+
+    /// Copies the interpreter along with the state of the component and its
+    /// sub-components. Running the copy does not affect the original, which
+    /// lets the debugger go back to the point where the copy was made.
+    pub fn snapshot(&self, snap: &mut Snapshot) -> Self {
+        let interp = match &self.interp {
+            StructuralOrControl::Structural(interp) => {
+                StructuralOrControl::Structural(Box::new(interp.snapshot(snap)))
+            }
+            StructuralOrControl::Control(interp) => {
+                StructuralOrControl::Control(interp.snapshot(snap))
+            }
+            StructuralOrControl::Nothing => StructuralOrControl::Nothing,
+        };
+        Self {
+            interp,
+            input_ports: self.input_ports.clone(),
+            output_ports: self.output_ports.clone(),
+            comp_ref: Rc::clone(&self.comp_ref),
+            control_ref: self.control_ref.clone(),
+            done_port: Rc::clone(&self.done_port),
+            go_port: Rc::clone(&self.go_port),
+            input_hash_set: Rc::clone(&self.input_hash_set),
+            qual_name: self.qual_name.clone(),
+            full_name_clone: self.full_name_clone,
+        }
+    }
 }
 
 impl Interpreter for ComponentInterpreter {
@@ -344,6 +392,10 @@ impl Named for ComponentInterpreter {
 }
 
 impl Primitive for ComponentInterpreter {
+    fn snapshot(&self, snap: &mut Snapshot) -> Box<dyn Primitive> {
+        Box::new(ComponentInterpreter::snapshot(self, snap))
+    }
+
     fn do_tick(&mut self) -> InterpreterResult<Vec<(ir::Id, Value)>> {
         let currently_done = self.done_is_high();
 
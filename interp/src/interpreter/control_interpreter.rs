@@ -10,7 +10,7 @@ use crate::structures::names::{
 };
 use crate::utils::AsRaw;
 use crate::{
-    environment::InterpreterState,
+    environment::{InterpreterState, Snapshot},
     errors::InterpreterResult,
     interpreter::utils::ConstPort,
     structures::state_views::{
@@ -19,7 +19,7 @@ use crate::{
     values::Value,
 };
 use calyx::ir::{self, Assignment, Guard, RRC};
-use calyx::utils::WithPos;
+use calyx::utils::{GPosIdx, WithPos};
 use std::collections::HashSet;
 use std::rc::Rc;
 
@@ -55,6 +55,12 @@ impl EmptyInterpreter {
     pub fn new(env: InterpreterState) -> Self {
         Self { env }
     }
+
+    pub fn snapshot(&self, snap: &mut Snapshot) -> Self {
+        Self {
+            env: self.env.snapshot(snap),
+        }
+    }
 }
 
 impl Interpreter for EmptyInterpreter {
@@ -128,6 +134,16 @@ impl EnableHolder {
             EnableHolder::Enable(e) => e.attributes.get(POS_TAG).cloned(),
         }
     }
+
+    /// The source position of the enabled group.
+    fn span(&self) -> GPosIdx {
+        match self {
+            EnableHolder::Vec(_) => GPosIdx::UNKNOWN,
+            EnableHolder::Group(g) => g.borrow().attributes.copy_span(),
+            EnableHolder::CombGroup(cg) => cg.borrow().attributes.copy_span(),
+            EnableHolder::Enable(e) => e.attributes.copy_span(),
+        }
+    }
 }
 
 impl From<RRC<ir::Group>> for EnableHolder {
@@ -206,6 +222,15 @@ impl EnableInterpreter {
             qin: qin.clone(),
         }
     }
+
+    pub fn snapshot(&self, snap: &mut Snapshot) -> Self {
+        Self {
+            enable: self.enable.clone(),
+            group_name: self.group_name,
+            interp: self.interp.snapshot(snap),
+            qin: self.qin.clone(),
+        }
+    }
 }
 
 impl EnableInterpreter {
@@ -266,7 +291,10 @@ impl Interpreter for EnableInterpreter {
             None => GroupQualifiedInstanceName::new_empty(&self.qin),
         };
 
-        vec![ActiveTreeNode::new(name.with_tag(self.enable.pos_tag()))]
+        vec![ActiveTreeNode::new(
+            name.with_tag(self.enable.pos_tag())
+                .with_span(self.enable.span()),
+        )]
     }
 }
 
@@ -307,6 +335,21 @@ impl SeqInterpreter {
             info,
         }
     }
+
+    pub fn snapshot(&self, snap: &mut Snapshot) -> Self {
+        let internal_state = match &self.internal_state {
+            SeqFsm::Err => SeqFsm::Err,
+            SeqFsm::Iterating(interp, idx) => {
+                SeqFsm::Iterating(interp.snapshot(snap), *idx)
+            }
+            SeqFsm::Done(env) => SeqFsm::Done(env.snapshot(snap)),
+        };
+        Self {
+            internal_state,
+            info: self.info.clone(),
+            seq: Rc::clone(&self.seq),
+        }
+    }
 }
 
 impl Interpreter for SeqInterpreter {
@@ -469,6 +512,18 @@ impl ParInterpreter {
             info,
         }
     }
+
+    pub fn snapshot(&self, snap: &mut Snapshot) -> Self {
+        Self {
+            interpreters: self
+                .interpreters
+                .iter()
+                .map(|interp| interp.snapshot(snap))
+                .collect(),
+            in_state: self.in_state.snapshot(snap),
+            info: self.info.clone(),
+        }
+    }
 }
 
 impl Interpreter for ParInterpreter {
@@ -589,6 +644,25 @@ impl IfInterpreter {
             info,
         }
     }
+
+    pub fn snapshot(&self, snap: &mut Snapshot) -> Self {
+        let state = match &self.state {
+            IfFsm::Err => IfFsm::Err,
+            IfFsm::ConditionWith(interp) => {
+                IfFsm::ConditionWith(Box::new(interp.snapshot(snap)))
+            }
+            IfFsm::ConditionPort(env) => {
+                IfFsm::ConditionPort(env.snapshot(snap))
+            }
+            IfFsm::Body(interp) => IfFsm::Body(interp.snapshot(snap)),
+            IfFsm::Done(env) => IfFsm::Done(env.snapshot(snap)),
+        };
+        Self {
+            state,
+            ctrl_if: Rc::clone(&self.ctrl_if),
+            info: self.info.clone(),
+        }
+    }
 }
 
 impl Interpreter for IfInterpreter {
@@ -764,6 +838,7 @@ impl Default for WhileFsm {
     }
 }
 
+#[derive(Clone)]
 struct BoundValidator {
     target: u64,
     current: u64,
@@ -868,6 +943,24 @@ impl WhileInterpreter {
             self.state = WhileFsm::Body(interp);
         }
     }
+
+    pub fn snapshot(&self, snap: &mut Snapshot) -> Self {
+        let state = match &self.state {
+            WhileFsm::Err => WhileFsm::Err,
+            WhileFsm::CondWith(interp) => {
+                WhileFsm::CondWith(Box::new(interp.snapshot(snap)))
+            }
+            WhileFsm::CondPort(env) => WhileFsm::CondPort(env.snapshot(snap)),
+            WhileFsm::Body(interp) => WhileFsm::Body(interp.snapshot(snap)),
+            WhileFsm::Done(env) => WhileFsm::Done(env.snapshot(snap)),
+        };
+        Self {
+            state,
+            wh: Rc::clone(&self.wh),
+            info: self.info.clone(),
+            bound: self.bound.clone(),
+        }
+    }
 }
 
 impl Interpreter for WhileInterpreter {
@@ -1067,6 +1160,14 @@ impl InvokeInterpreter {
             qin,
         }
     }
+
+    pub fn snapshot(&self, snap: &mut Snapshot) -> Self {
+        Self {
+            invoke: Rc::clone(&self.invoke),
+            assign_interp: self.assign_interp.snapshot(snap),
+            qin: self.qin.clone(),
+        }
+    }
 }
 
 impl Interpreter for InvokeInterpreter {
@@ -1118,7 +1219,9 @@ impl Interpreter for InvokeInterpreter {
 
         let pos_tag = self.invoke.attributes.get(POS_TAG).cloned();
 
-        vec![ActiveTreeNode::new(name.with_tag(pos_tag))]
+        let span = self.invoke.attributes.copy_span();
+
+        vec![ActiveTreeNode::new(name.with_tag(pos_tag).with_span(span))]
     }
 }
 
@@ -1190,6 +1293,20 @@ impl ControlInterpreter {
             }
         }
     }
+
+    /// Copies the interpreter along with the state of the program, so that
+    /// the copy can run independently of the original.
+    pub fn snapshot(&self, snap: &mut Snapshot) -> Self {
+        match self {
+            Self::Empty(i) => Self::Empty(Box::new(i.snapshot(snap))),
+            Self::Enable(i) => Self::Enable(Box::new(i.snapshot(snap))),
+            Self::Seq(i) => Self::Seq(Box::new(i.snapshot(snap))),
+            Self::Par(i) => Self::Par(Box::new(i.snapshot(snap))),
+            Self::If(i) => Self::If(Box::new(i.snapshot(snap))),
+            Self::While(i) => Self::While(Box::new(i.snapshot(snap))),
+            Self::Invoke(i) => Self::Invoke(Box::new(i.snapshot(snap))),
+        }
+    }
 }
 
 impl Interpreter for ControlInterpreter {
@@ -1260,6 +1377,14 @@ impl StructuralInterpreter {
             done_port: done_raw,
         }
     }
+
+    pub fn snapshot(&self, snap: &mut Snapshot) -> Self {
+        Self {
+            interp: self.interp.snapshot(snap),
+            continuous: Rc::clone(&self.continuous),
+            done_port: self.done_port,
+        }
+    }
 }
 
 impl Interpreter for StructuralInterpreter {
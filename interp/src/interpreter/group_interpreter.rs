@@ -1,5 +1,5 @@
 use super::utils::{self, ConstCell, ConstPort};
-use crate::environment::InterpreterState;
+use crate::environment::{InterpreterState, Snapshot};
 use crate::errors::{InterpreterError, InterpreterResult};
 use crate::interpreter::utils::get_dest_cells;
 use crate::utils::{AsRaw, PortAssignment, RcOrConst};
@@ -12,6 +12,7 @@ use std::rc::Rc;
 use super::control_interpreter::EnableHolder;
 use crate::interpreter_ir as iir;
 
+#[derive(Clone)]
 pub enum AssignmentHolder {
     CombGroup(RRC<ir::CombGroup>),
     Group(RRC<ir::Group>),
@@ -467,6 +468,20 @@ impl AssignmentInterpreter {
     pub fn get_mut_env(&mut self) -> &mut InterpreterState {
         &mut self.state
     }
+
+    /// Copies the interpreter along with the state of the program.
+    pub fn snapshot(&self, snap: &mut Snapshot) -> Self {
+        Self {
+            state: self.state.snapshot(snap),
+            done_port: self.done_port,
+            assigns: self.assigns.clone(),
+            cont_assigns: Rc::clone(&self.cont_assigns),
+            cells: self.cells.clone(),
+            val_changed: self.val_changed,
+            possible_ports: self.possible_ports.clone(),
+            port_lookup_map: self.port_lookup_map.clone(),
+        }
+    }
 }
 
 /// Evaluates the primitives corresponding to the given iterator of cells, based
@@ -4,8 +4,10 @@ mod component_interpreter;
 mod control_interpreter;
 mod group_interpreter;
 mod interpreter_trait;
+mod profiler;
 mod utils;
 
 pub use component_interpreter::ComponentInterpreter;
 pub use interpreter_trait::Interpreter;
+pub use profiler::{Profile, ProfileSummary};
 pub use utils::{ConstCell, ConstPort};
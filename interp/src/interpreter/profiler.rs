@@ -0,0 +1,166 @@
+//! Counts the cycles in which groups and component instances are active.
+
+use super::{ComponentInterpreter, Interpreter};
+use crate::{
+    environment::InterpreterState,
+    errors::InterpreterResult,
+    interpreter_ir as iir,
+    structures::names::{ComponentQualifiedInstanceName, GroupQIN},
+};
+use calyx::ir::Id;
+use itertools::Itertools;
+use serde::Serialize;
+use std::{collections::HashMap, io::Write, rc::Rc};
+
+/// The instance names leading from the main component to an instance
+type InstancePath = Vec<Id>;
+
+/// The number of cycles in which the groups and component instances of a
+/// program were active. Groups which run in parallel are each counted.
+#[derive(Default)]
+pub struct Profile {
+    cycles: u64,
+    /// Cycles in which no group was active
+    idle: u64,
+    groups: HashMap<(InstancePath, Id), u64>,
+    /// Cycles in which a group of the instance or of one of its children was
+    /// active, along with the name of the instantiated component
+    instances: HashMap<InstancePath, (Id, u64)>,
+}
+
+#[derive(Serialize)]
+pub struct GroupSummary {
+    instance: String,
+    component: String,
+    group: String,
+    cycles: u64,
+}
+
+#[derive(Serialize)]
+pub struct InstanceSummary {
+    instance: String,
+    component: String,
+    cycles: u64,
+}
+
+/// The profile in the form written as JSON. Entries are sorted with the most
+/// active first.
+#[derive(Serialize)]
+pub struct ProfileSummary {
+    cycles: u64,
+    instances: Vec<InstanceSummary>,
+    groups: Vec<GroupSummary>,
+}
+
+impl Profile {
+    /// Records the groups active during a cycle.
+    fn record<I: IntoIterator<Item = GroupQIN>>(&mut self, active: I) {
+        self.cycles += 1;
+
+        let mut instances: HashMap<InstancePath, Id> = HashMap::new();
+        for group in active {
+            let prefix = group.prefix();
+            let path: InstancePath =
+                prefix.iter().map(|x| x.instance).collect();
+            for (len, inst) in prefix.iter().enumerate() {
+                instances
+                    .entry(path[..=len].to_vec())
+                    .or_insert(inst.component_id.name);
+            }
+            *self.groups.entry((path, group.name())).or_default() += 1;
+        }
+
+        if instances.is_empty() {
+            self.idle += 1;
+        }
+        for (path, component) in instances {
+            self.instances.entry(path).or_insert((component, 0)).1 += 1;
+        }
+    }
+
+    pub fn summary(&self) -> ProfileSummary {
+        let instances = self
+            .instances
+            .iter()
+            .map(|(path, (component, cycles))| InstanceSummary {
+                instance: path.iter().join("."),
+                component: component.to_string(),
+                cycles: *cycles,
+            })
+            .sorted_by(|a, b| {
+                b.cycles
+                    .cmp(&a.cycles)
+                    .then_with(|| a.instance.cmp(&b.instance))
+            })
+            .collect();
+
+        let groups = self
+            .groups
+            .iter()
+            .map(|((path, group), cycles)| GroupSummary {
+                instance: path.iter().join("."),
+                component: self.instances[path].0.to_string(),
+                group: group.to_string(),
+                cycles: *cycles,
+            })
+            .sorted_by(|a, b| {
+                b.cycles
+                    .cmp(&a.cycles)
+                    .then_with(|| a.instance.cmp(&b.instance))
+                    .then_with(|| a.group.cmp(&b.group))
+            })
+            .collect();
+
+        ProfileSummary {
+            cycles: self.cycles,
+            instances,
+            groups,
+        }
+    }
+
+    /// Writes the profile as folded stacks, the input format of flame graph
+    /// tools. Each line names the instances leading to a group followed by the
+    /// number of cycles the group was active. Idle cycles are attributed to
+    /// the main component.
+    pub fn write_folded<W: Write>(
+        &self,
+        main: Id,
+        out: &mut W,
+    ) -> std::io::Result<()> {
+        if self.idle > 0 {
+            writeln!(out, "{} {}", main, self.idle)?;
+        }
+        let stacks = self
+            .groups
+            .iter()
+            .map(|((path, group), cycles)| {
+                (format!("{};{}", path.iter().join(";"), group), cycles)
+            })
+            .sorted();
+        for (stack, cycles) in stacks {
+            writeln!(out, "{} {}", stack, cycles)?;
+        }
+        Ok(())
+    }
+}
+
+impl ComponentInterpreter {
+    /// Interpret a calyx program from the root while recording which groups
+    /// are active in every cycle
+    pub fn profile_program(
+        env: InterpreterState,
+        comp: &Rc<iir::Component>,
+    ) -> InterpreterResult<(InterpreterState, Profile)> {
+        let qin = ComponentQualifiedInstanceName::new_single(comp, comp.name);
+        let mut main_comp = Self::from_component(comp, env, qin);
+        let mut profile = Profile::default();
+        main_comp.set_go_high();
+        main_comp.converge()?;
+        while !main_comp.is_done() {
+            profile.record(main_comp.currently_executing_group());
+            main_comp.step()?;
+        }
+        main_comp.set_go_low();
+        Ok((main_comp.deconstruct()?, profile))
+    }
+}
@@ -2,7 +2,9 @@ pub mod interpreter;
 pub mod primitives;
 pub use utils::MemoryMap;
 pub mod configuration;
+mod data_file;
 pub mod debugger;
+pub mod differential;
 pub mod errors;
 pub mod interpreter_ir;
 pub mod logging;
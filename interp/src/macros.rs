@@ -142,6 +142,10 @@ macro_rules! comb_primitive {
 
             fn is_comb(&self) -> bool { true }
 
+            fn snapshot(&self, _snap: &mut $crate::environment::Snapshot) -> Box<dyn $crate::primitives::Primitive> {
+                Box::new(self.clone())
+            }
+
             fn validate(
                 &self,
                 inputs: &[(calyx::ir::Id, &$crate::values::Value)]
@@ -273,6 +277,10 @@ macro_rules! comb_primitive {
 
             fn is_comb(&self) -> bool { true }
 
+            fn snapshot(&self, _snap: &mut $crate::environment::Snapshot) -> Box<dyn $crate::primitives::Primitive> {
+                Box::new(self.clone())
+            }
+
             fn validate(
                 &self,
                 inputs: &[(calyx::ir::Id, &$crate::values::Value)]
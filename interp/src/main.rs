@@ -2,9 +2,10 @@ use calyx::{frontend, ir, pass_manager::PassManager, utils::OutputFile};
 use interp::{
     configuration,
     debugger::{source::SourceMap, Debugger},
+    differential,
     environment::InterpreterState,
     errors::{InterpreterError, InterpreterResult},
-    interpreter::ComponentInterpreter,
+    interpreter::{ComponentInterpreter, Profile},
     interpreter_ir as iir,
 };
 
@@ -12,6 +13,7 @@ use argh::FromArgs;
 use rustyline::error::ReadlineError;
 use slog::warn;
 use std::{
+    fs::File,
     path::{Path, PathBuf},
     rc::Rc,
 };
@@ -32,9 +34,9 @@ pub struct Opts {
     )]
     pub output: OutputFile,
 
-    /// path to the primitives library
-    #[argh(option, short = 'l', default = "Path::new(\"..\").into()")]
-    pub lib_path: PathBuf,
+    /// paths searched for imported files, in order (default: ..)
+    #[argh(option, short = 'l')]
+    pub lib_path: Vec<PathBuf>,
 
     /// path to optional datafile used to initialze memories. If it is not
     /// provided memories will be initialzed with zeros
@@ -65,6 +67,17 @@ pub struct Opts {
     #[argh(switch, long = "raw")]
     raw: bool,
 
+    /// print only the external memories, in the format of the data files
+    /// used to initialize them
+    #[argh(switch, long = "data-out")]
+    data_out: bool,
+
+    /// write the cycles each group and component instance was active for to
+    /// the given path with the extensions .folded, as folded stacks for flame
+    /// graph tools, and .json
+    #[argh(option, from_str_fn(read_path))]
+    profile: Option<PathBuf>,
+
     #[argh(subcommand)]
     comm: Option<Command>,
 }
@@ -77,6 +90,8 @@ fn read_path(path: &str) -> Result<PathBuf, String> {
 enum Command {
     Interpret(CommandInterpret),
     Debug(CommandDebug),
+    Lowered(CommandLowered),
+    CheckPasses(CommandCheckPasses),
 }
 
 #[derive(FromArgs)]
@@ -87,16 +102,54 @@ struct CommandInterpret {}
 #[derive(FromArgs)]
 #[argh(subcommand, name = "debug")]
 /// Interpret the given program with the interactive debugger
-struct CommandDebug {}
+struct CommandDebug {
+    /// file of debugger commands to run instead of reading them from the
+    /// terminal. The outputs of the commands are printed as JSON.
+    #[argh(option, from_str_fn(read_path))]
+    script: Option<PathBuf>,
+
+    /// serve the Debug Adapter Protocol on stdin and stdout instead of
+    /// starting the interactive debugger
+    #[argh(switch)]
+    dap: bool,
+}
+
+#[derive(FromArgs)]
+#[argh(subcommand, name = "lowered")]
+/// Interpret the given program after compiling it with the full lowering
+/// pipeline, report the cycles it takes, and check its final memories against
+/// those of interpreting the original program
+struct CommandLowered {
+    /// pass or alias to leave out of the lowering pipeline
+    #[argh(option, long = "disable-pass")]
+    disable_pass: Vec<String>,
+}
+
+#[derive(FromArgs)]
+#[argh(subcommand, name = "check-passes")]
+/// Interpret the given program after each pass of a compilation plan and
+/// report the first pass which changes its final memories
+struct CommandCheckPasses {
+    /// passes or aliases to run, in order (default: all)
+    #[argh(option, short = 'p', long = "pass")]
+    pass: Vec<String>,
+
+    /// pass or alias to leave out of the plan
+    #[argh(option, long = "disable-pass")]
+    disable_pass: Vec<String>,
+}
 
 #[inline]
 fn print_res(
     res: InterpreterResult<InterpreterState>,
     raw: bool,
+    data_out: bool,
 ) -> InterpreterResult<()> {
     match res {
         Ok(env) => {
-            if raw {
+            if data_out {
+                env.print_data()
+            } else if raw {
                 env.print_env_raw()
             } else {
                 env.print_env()
@@ -114,9 +167,39 @@ fn print_res(
     }
 }
 
+fn write_profile(
+    profile: &Profile,
+    main: ir::Id,
+    path: &Path,
+) -> InterpreterResult<()> {
+    let mut folded = File::create(path.with_extension("folded"))?;
+    profile.write_folded(main, &mut folded)?;
+    let summary = File::create(path.with_extension("json"))?;
+    serde_json::to_writer_pretty(summary, &profile.summary())
+        .map_err(std::io::Error::from)?;
+    Ok(())
+}
+
+fn into_component_ctx(components: Vec<ir::Component>) -> iir::ComponentCtx {
+    Rc::new(components.into_iter().map(|x| Rc::new(x.into())).collect())
+}
+
+fn find_main(
+    components: &iir::ComponentCtx,
+    entry_point: ir::Id,
+) -> InterpreterResult<&Rc<iir::Component>> {
+    components
+        .iter()
+        .find(|&cm| cm.name == entry_point)
+        .ok_or_else(|| InterpreterError::MissingMainComponent.into())
+}
+
 /// Interpret a group from a Calyx program
 fn main() -> InterpreterResult<()> {
-    let opts: Opts = argh::from_env();
+    let mut opts: Opts = argh::from_env();
+    if opts.lib_path.is_empty() {
+        opts.lib_path = vec![Path::new("..").into()];
+    }
 
     let builder = configuration::ConfigBuilder::new();
 
@@ -148,42 +231,122 @@ fn main() -> InterpreterResult<()> {
 
     let metadata = ctx.metadata;
 
-    let components: iir::ComponentCtx = Rc::new(
-        ctx.components
-            .into_iter()
-            .map(|x| Rc::new(x.into()))
-            .collect(),
-    );
+    let components = into_component_ctx(ctx.components);
 
-    let main_component = components
-        .iter()
-        .find(|&cm| cm.name == entry_point)
-        .ok_or(InterpreterError::MissingMainComponent)?;
+    let main_component = find_main(&components, entry_point)?;
 
     let mut mems = interp::MemoryMap::inflate_map(&opts.data_file)?;
 
-    let env = InterpreterState::init_top_level(
-        &components,
-        main_component,
-        &mut mems,
-        &config,
-    )?;
     let res = match opts.comm.unwrap_or(Command::Interpret(CommandInterpret {}))
     {
         Command::Interpret(_) => {
-            ComponentInterpreter::interpret_program(env, main_component)
+            let env = InterpreterState::init_top_level(
+                &components,
+                main_component,
+                &mut mems,
+                &config,
+            )?;
+            if let Some(path) = &opts.profile {
+                let (env, profile) =
+                    ComponentInterpreter::profile_program(env, main_component)?;
+                write_profile(&profile, main_component.name, path)?;
+                Ok(env)
+            } else {
+                ComponentInterpreter::interpret_program(env, main_component)
+            }
         }
-        Command::Debug(CommandDebug {}) => {
+        Command::Debug(CommandDebug { script, dap }) => {
             let map = metadata.map(SourceMap::from_string);
             let map = if let Some(map_res) = map {
                 Some(map_res?)
             } else {
                 None
             };
+            let env = InterpreterState::init_top_level(
+                &components,
+                main_component,
+                &mut mems,
+                &config,
+            )?;
             let mut cidb = Debugger::new(&components, main_component, map);
+            if let Some(script) = script {
+                println!("{}", cidb.run_script(env, &script)?);
+                return Ok(());
+            }
+            if dap {
+                return cidb.serve_dap(env);
+            }
             cidb.main_loop(env)
         }
+        Command::CheckPasses(CommandCheckPasses {
+            mut pass,
+            disable_pass,
+        }) => {
+            if pass.is_empty() {
+                pass = vec!["all".to_string()];
+            }
+            let construct = || {
+                let ws =
+                    frontend::Workspace::construct(&opts.file, &opts.lib_path)?;
+                ir::from_ast::ast_to_ir(ws)
+            };
+            let divergence = differential::find_divergence(
+                construct,
+                &pm,
+                &pass,
+                &disable_pass,
+                &mems,
+                &config,
+            )?;
+            if let Some(divergence) = divergence {
+                println!("{}", divergence);
+                std::process::exit(1);
+            }
+            println!("no pass changed the final memories");
+            return Ok(());
+        }
+        Command::Lowered(CommandLowered { disable_pass }) => {
+            let ws =
+                frontend::Workspace::construct(&opts.file, &opts.lib_path)?;
+            let mut lowered = ir::from_ast::ast_to_ir(ws)?;
+            pm.execute_plan(&mut lowered, &["all".to_string()], &disable_pass)?;
+            let lowered_components = into_component_ctx(lowered.components);
+            let lowered_main = find_main(&lowered_components, entry_point)?;
+
+            let env = InterpreterState::init_top_level(
+                &components,
+                main_component,
+                &mut mems.clone(),
+                &config,
+            )?;
+            let (expected, cycles) =
+                ComponentInterpreter::interpret_program_cycles(
+                    env,
+                    main_component,
+                )?;
+            let env = InterpreterState::init_top_level(
+                &lowered_components,
+                lowered_main,
+                &mut mems,
+                &config,
+            )?;
+            let (env, lowered_cycles) =
+                ComponentInterpreter::interpret_program_cycles(
+                    env,
+                    lowered_main,
+                )?;
+
+            eprintln!("interpreted: {} cycles", cycles);
+            eprintln!("lowered: {} cycles", lowered_cycles);
+            let differing = env.differing_memories(&expected);
+            if !differing.is_empty() {
+                return Err(
+                    InterpreterError::LoweringMismatch(differing).into()
+                );
+            }
+            Ok(env)
+        }
     };
 
-    print_res(res, opts.raw)
+    print_res(res, opts.raw, opts.data_out)
 }
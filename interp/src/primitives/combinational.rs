@@ -5,6 +5,7 @@ use super::{
     primitive_traits::Named,
     Primitive,
 };
+use crate::environment::Snapshot;
 use crate::logging::warn;
 use crate::values::Value;
 use crate::{comb_primitive, errors::InterpreterError};
@@ -13,7 +14,7 @@ use calyx::ir;
 use std::ops::Not;
 
 /// A constant.
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct StdConst {
     value: Value,
     full_name: ir::Id,
@@ -58,6 +59,10 @@ impl Primitive for StdConst {
         true
     }
 
+    fn snapshot(&self, _snap: &mut Snapshot) -> Box<dyn Primitive> {
+        Box::new(self.clone())
+    }
+
     fn validate(&self, _inputs: &[(ir::Id, &Value)]) {}
 
     fn execute(
@@ -84,10 +89,15 @@ impl Primitive for StdConst {
             &code,
         ))
     }
+
+    fn read_value(&self, address: &[u64]) -> Option<Value> {
+        address.is_empty().then(|| self.value.clone())
+    }
 }
 
 // ===================== New core ======================
 
+#[derive(Clone)]
 pub struct StdMux {
     width: u64,
     name: ir::Id,
@@ -117,6 +127,10 @@ impl Primitive for StdMux {
         true
     }
 
+    fn snapshot(&self, _snap: &mut Snapshot) -> Box<dyn Primitive> {
+        Box::new(self.clone())
+    }
+
     fn validate(&self, inputs: &[(ir::Id, &Value)]) {
         for (id, v) in inputs {
             match id.as_ref() {
@@ -276,8 +290,9 @@ comb_primitive!(StdLsh[WIDTH](left: WIDTH, right: WIDTH) -> (out: WIDTH) {
         //first push the requisite # of zeroes
         for (index, bit) in right.iter().enumerate() {
             if bit {
-                //not possible for bit to be 1 after the 64th bit
-                for _ in 0..u64::pow(2, index as u32) {
+                //not possible for bit to be 1 after the 64th bit. Shifting by
+                //more than the width only produces zeroes, so stop there
+                for _ in 0..u64::pow(2, index as u32).min(WIDTH) {
                     if tr.len() < WIDTH as usize {
                         tr.push(false);
                     }
@@ -314,7 +329,7 @@ comb_primitive!(StdRsh[WIDTH](left: WIDTH, right: WIDTH) -> (out: WIDTH) {
         //first remove [right] bits
         for (index, bit) in right.iter().enumerate() {
             if bit {
-                for _ in 0..u64::pow(2, index as u32) {
+                for _ in 0..u64::pow(2, index as u32).min(WIDTH) {
                     if !tr.is_empty() {
                         tr.remove(0);
                     }
@@ -1,4 +1,5 @@
 use crate::{
+    environment::Snapshot,
     errors::InterpreterResult,
     interpreter::ComponentInterpreter,
     structures::state_views::{FullySerialize, StateView},
@@ -58,6 +59,13 @@ pub trait Primitive: Named {
         Serializable::Empty
     }
 
+    /// The value stored at `address` in this primitive. Registers and
+    /// constants store a single value at the empty address. Returns None if
+    /// the primitive does not store a value at `address`.
+    fn read_value(&self, _address: &[u64]) -> Option<Value> {
+        None
+    }
+
     // more efficient to override this with true in stateful cases
     fn has_serializeable_state(&self) -> bool {
         self.serialize(None).has_state()
@@ -70,6 +78,10 @@ pub trait Primitive: Named {
     fn get_comp_interpreter(&self) -> Option<&ComponentInterpreter> {
         None
     }
+
+    /// Copies the primitive along with its state. Used to take snapshots of
+    /// the interpreter.
+    fn snapshot(&self, snap: &mut Snapshot) -> Box<dyn Primitive>;
 }
 
 /// An enum wrapping over a tuple representing the shape of a multi-dimensional
@@ -87,6 +99,25 @@ impl Shape {
         matches!(self, Shape::D1(_))
     }
 
+    /// The position of the element at `address` in the flattened array.
+    /// Returns None unless the address has an in-bounds index for each
+    /// dimension.
+    pub(crate) fn flat_index(&self, address: &[u64]) -> Option<usize> {
+        let dims = match self {
+            Shape::D1((d0,)) => vec![*d0],
+            Shape::D2((d0, d1)) => vec![*d0, *d1],
+            Shape::D3((d0, d1, d2)) => vec![*d0, *d1, *d2],
+            Shape::D4((d0, d1, d2, d3)) => vec![*d0, *d1, *d2, *d3],
+        };
+        if dims.len() != address.len() {
+            return None;
+        }
+        dims.iter().zip(address).try_fold(0, |acc, (dim, idx)| {
+            let idx = *idx as usize;
+            (idx < *dim).then_some(acc * dim + idx)
+        })
+    }
+
     pub(crate) fn dim_str(&self) -> String {
         match self {
             Shape::D1(_) => String::from("1D"),
@@ -132,10 +163,24 @@ impl From<(usize, usize, usize, usize)> for Shape {
 pub enum Entry {
     U(u64),
     I(i64),
+    #[serde(serialize_with = "serialize_fraction")]
     Frac(Fraction),
     Value(Value),
 }
 
+/// Serializes fixed-point values as plain numbers, which is how data files
+/// represent them.
+fn serialize_fraction<S>(
+    frac: &Fraction,
+    serializer: S,
+) -> Result<S::Ok, S::Error>
+where
+    S: serde::Serializer,
+{
+    use fraction::ToPrimitive;
+    serializer.serialize_f64(frac.to_f64().unwrap_or(f64::NAN))
+}
+
 impl From<u64> for Entry {
     fn from(u: u64) -> Self {
         Self::U(u)
@@ -1,6 +1,7 @@
 use super::super::prim_utils::{get_inputs, get_param, ShiftBuffer};
 use super::super::primitive_traits::Named;
 use super::super::{Entry, Primitive, Serializable};
+use crate::environment::Snapshot;
 use crate::errors::{InterpreterError, InterpreterResult};
 use crate::logging::{self, warn};
 use crate::utils::PrintCode;
@@ -12,6 +13,7 @@ use ibig::{ibig, ubig, IBig, UBig};
 
 const DECIMAL_PRINT_WIDTH: usize = 7;
 
+#[derive(Clone)]
 enum BinOpUpdate {
     None,
     Reset,
@@ -34,6 +36,7 @@ impl BinOpUpdate {
 /// The product associated with a given input will be output on the third [Primitive::do_tick()].
 /// Note: Calling [Primitive::execute] multiple times before [Primitive::do_tick] has no effect; only the last
 /// set of inputs prior to the [Primitive::do_tick] will be saved.
+#[derive(Clone)]
 pub struct StdMultPipe<const SIGNED: bool, const DEPTH: usize> {
     width: u64,
     product: Value,
@@ -156,6 +159,10 @@ impl<const SIGNED: bool, const DEPTH: usize> Primitive
         false
     }
 
+    fn snapshot(&self, _snap: &mut Snapshot) -> Box<dyn Primitive> {
+        Box::new(self.clone())
+    }
+
     fn validate(&self, inputs: &[(calyx::ir::Id, &Value)]) {
         for (id, v) in inputs {
             match id.as_ref() {
@@ -212,6 +219,10 @@ impl<const SIGNED: bool, const DEPTH: usize> Primitive
             1.into(),
         )
     }
+
+    fn read_value(&self, address: &[u64]) -> Option<Value> {
+        (address == [0]).then(|| self.product.clone())
+    }
 }
 
 ///Pipelined Division (3 cycles)
@@ -223,6 +234,7 @@ impl<const SIGNED: bool, const DEPTH: usize> Primitive
 ///be output on the third [Primitive::do_tick].
 ///Note: Calling [Primitive::execute] multiple times before [Primitive::do_tick] has no effect; only
 ///the last set of inputs prior to the [Primitive::do_tick] will be saved.
+#[derive(Clone)]
 pub struct StdDivPipe<const SIGNED: bool> {
     pub width: u64,
     pub quotient: Value,
@@ -365,6 +377,10 @@ impl<const SIGNED: bool> Primitive for StdDivPipe<SIGNED> {
         false
     }
 
+    fn snapshot(&self, _snap: &mut Snapshot) -> Box<dyn Primitive> {
+        Box::new(self.clone())
+    }
+
     fn validate(&self, inputs: &[(calyx::ir::Id, &Value)]) {
         for (id, v) in inputs {
             match id.as_ref() {
@@ -423,8 +439,17 @@ impl<const SIGNED: bool> Primitive for StdDivPipe<SIGNED> {
             2.into(),
         )
     }
+
+    fn read_value(&self, address: &[u64]) -> Option<Value> {
+        match address {
+            [0] => Some(self.quotient.clone()),
+            [1] => Some(self.remainder.clone()),
+            _ => None,
+        }
+    }
 }
 
+#[derive(Clone)]
 pub struct StdFpMultPipe<const SIGNED: bool> {
     pub width: u64,
     pub int_width: u64,
@@ -584,6 +609,10 @@ impl<const SIGNED: bool> Primitive for StdFpMultPipe<SIGNED> {
         false
     }
 
+    fn snapshot(&self, _snap: &mut Snapshot) -> Box<dyn Primitive> {
+        Box::new(self.clone())
+    }
+
     fn validate(&self, inputs: &[(ir::Id, &Value)]) {
         validate![inputs;
             left: self.width,
@@ -627,6 +656,7 @@ impl<const SIGNED: bool> Primitive for StdFpMultPipe<SIGNED> {
     }
 }
 
+#[derive(Clone)]
 pub struct StdFpDivPipe<const SIGNED: bool> {
     pub width: u64,
     pub int_width: u64,
@@ -759,6 +789,10 @@ impl<const SIGNED: bool> Primitive for StdFpDivPipe<SIGNED> {
         false
     }
 
+    fn snapshot(&self, _snap: &mut Snapshot) -> Box<dyn Primitive> {
+        Box::new(self.clone())
+    }
+
     fn validate(&self, inputs: &[(ir::Id, &Value)]) {
         validate![inputs;
             left: self.width,
@@ -838,6 +872,7 @@ pub(crate) fn int_sqrt(i: &UBig) -> UBig {
 
 type SqrtUpdate = super::memories::RegUpdate;
 
+#[derive(Clone)]
 pub struct StdSqrt<const FP: bool> {
     pub width: u64,
     pub output: Value,
@@ -912,6 +947,10 @@ impl<const FP: bool> Primitive for StdSqrt<FP> {
         false
     }
 
+    fn snapshot(&self, _snap: &mut Snapshot) -> Box<dyn Primitive> {
+        Box::new(self.clone())
+    }
+
     fn validate(&self, inputs: &[(ir::Id, &Value)]) {
         validate![inputs;
             r#in: self.width,
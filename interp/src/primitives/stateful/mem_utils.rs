@@ -10,7 +10,7 @@ use crate::{
     values::Value,
 };
 
-pub trait MemBinder: Sized {
+pub trait MemBinder: Sized + Clone + 'static {
     fn new(params: &ir::Binding, full_name: ir::Id) -> Self;
 
     fn get_idx(
@@ -26,6 +26,7 @@ pub trait MemBinder: Sized {
     fn get_array_length(&self) -> usize;
 }
 
+#[derive(Clone)]
 pub struct MemD1 {
     size: u64,
     idx_size: u64,
@@ -83,6 +84,7 @@ impl MemBinder for MemD1 {
     }
 }
 
+#[derive(Clone)]
 pub struct MemD2 {
     d0_size: u64,
     d1_size: u64,
@@ -150,6 +152,7 @@ impl MemBinder for MemD2 {
     }
 }
 
+#[derive(Clone)]
 pub struct MemD3 {
     d0_size: u64,
     d1_size: u64,
@@ -230,6 +233,7 @@ impl MemBinder for MemD3 {
     }
 }
 
+#[derive(Clone)]
 pub struct MemD4 {
     d0_size: u64,
     d1_size: u64,
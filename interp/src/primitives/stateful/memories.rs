@@ -3,6 +3,7 @@ use calyx::ir;
 use super::mem_utils::{MemBinder, MemD1, MemD2, MemD3, MemD4};
 use crate::{
     debugger::PrintCode,
+    environment::Snapshot,
     errors::{InterpreterError, InterpreterResult},
     primitives::{
         prim_utils::{get_inputs, get_param, output},
@@ -12,7 +13,9 @@ use crate::{
     validate, validate_friendly,
     values::Value,
 };
+use std::rc::Rc;
 
+#[derive(Clone)]
 pub(super) enum RegUpdate {
     None,
     Reset,
@@ -30,6 +33,7 @@ impl RegUpdate {
 }
 
 /// A register.
+#[derive(Clone)]
 pub struct StdReg {
     pub width: u64,
     pub data: [Value; 1],
@@ -93,6 +97,10 @@ impl Primitive for StdReg {
         false
     }
 
+    fn snapshot(&self, _snap: &mut Snapshot) -> Box<dyn Primitive> {
+        Box::new(self.clone())
+    }
+
     fn validate(&self, inputs: &[(calyx::ir::Id, &Value)]) {
         for (id, v) in inputs {
             match id.as_ref() {
@@ -141,6 +149,29 @@ impl Primitive for StdReg {
         let code = signed.unwrap_or_default();
         Serializable::Val(Entry::from_val_code(&self.data[0], &code))
     }
+
+    fn read_value(&self, address: &[u64]) -> Option<Value> {
+        address.is_empty().then(|| self.data[0].clone())
+    }
+}
+
+/// Copies the result of computing the index of an access. Errors cannot be
+/// cloned, but the only ones computing an index produces are invalid accesses.
+fn copy_idx(idx: &InterpreterResult<u64>) -> InterpreterResult<u64> {
+    match idx {
+        Ok(idx) => Ok(*idx),
+        Err(err) => match &**err {
+            InterpreterError::InvalidMemoryAccess { access, dims, name } => {
+                Err(InterpreterError::InvalidMemoryAccess {
+                    access: access.clone(),
+                    dims: dims.clone(),
+                    name: *name,
+                }
+                .into())
+            }
+            err => unreachable!("Unexpected error for a memory index: {}", err),
+        },
+    }
 }
 
 enum StdMemAction {
@@ -167,7 +198,8 @@ impl Default for StdMemAction {
 pub struct StdMem<T: MemBinder> {
     mem_binder: T,
     width: u64,
-    data: Vec<Value>,
+    /// Shared with the snapshots of the memory until it is written
+    data: Rc<Vec<Value>>,
     full_name: ir::Id,
     allow_invalid_memory_access: bool,
     update: StdMemAction,
@@ -183,8 +215,10 @@ impl<T: MemBinder> StdMem<T> {
         let width =
             get_param(params, "WIDTH").expect("Missing WIDTH param for memory");
 
-        let data =
-            vec![Value::zeroes(width as usize); mem_binder.get_array_length()];
+        let data = Rc::new(vec![
+            Value::zeroes(width as usize);
+            mem_binder.get_array_length()
+        ]);
 
         Self {
             mem_binder,
@@ -221,6 +255,7 @@ impl<T: MemBinder> StdMem<T> {
         for val in data.iter_mut() {
             val.truncate_in_place(width as usize);
         }
+        let data = Rc::new(data);
 
         Ok(Self {
             mem_binder,
@@ -244,6 +279,24 @@ impl<T: MemBinder> Primitive for StdMem<T> {
         false
     }
 
+    fn snapshot(&self, _snap: &mut Snapshot) -> Box<dyn Primitive> {
+        let update = match &self.update {
+            StdMemAction::None => StdMemAction::None,
+            StdMemAction::Read(idx) => StdMemAction::Read(copy_idx(idx)),
+            StdMemAction::Write(idx, v) => {
+                StdMemAction::Write(copy_idx(idx), v.clone())
+            }
+        };
+        Box::new(Self {
+            mem_binder: self.mem_binder.clone(),
+            width: self.width,
+            data: Rc::clone(&self.data),
+            full_name: self.full_name,
+            allow_invalid_memory_access: self.allow_invalid_memory_access,
+            update,
+        })
+    }
+
     fn validate(&self, inputs: &[(ir::Id, &Value)]) {
         validate_friendly![inputs;
             write_en: 1,
@@ -317,7 +370,7 @@ impl<T: MemBinder> Primitive for StdMem<T> {
                 if idx >= self.data.len() {
                     output![("read_data", v), ("done", Value::bit_high())]
                 } else {
-                    self.data[idx] = v.clone();
+                    Rc::make_mut(&mut self.data)[idx] = v.clone();
                     output![("read_data", v), ("done", Value::bit_high())]
                 }
             }
@@ -346,6 +399,11 @@ impl<T: MemBinder> Primitive for StdMem<T> {
         )
     }
 
+    fn read_value(&self, address: &[u64]) -> Option<Value> {
+        let idx = self.mem_binder.get_dimensions().flat_index(address)?;
+        Some(self.data[idx].clone())
+    }
+
     fn has_serializeable_state(&self) -> bool {
         true
     }
@@ -474,7 +532,8 @@ pub struct SeqMem<T: MemBinder> {
     // parameters
     width: u64,
     // Internal Details
-    data: Vec<Value>,
+    /// Shared with the snapshots of the memory until it is written
+    data: Rc<Vec<Value>>,
     full_name: ir::Id,
     allow_invalid_memory_access: bool,
     // I/O
@@ -492,8 +551,10 @@ impl<T: MemBinder> SeqMem<T> {
         let width =
             get_param(params, "WIDTH").expect("Missing WIDTH param for memory");
 
-        let data =
-            vec![Value::zeroes(width as usize); mem_binder.get_array_length()];
+        let data = Rc::new(vec![
+            Value::zeroes(width as usize);
+            mem_binder.get_array_length()
+        ]);
 
         Self {
             mem_binder,
@@ -531,6 +592,7 @@ impl<T: MemBinder> SeqMem<T> {
         for val in data.iter_mut() {
             val.truncate_in_place(width as usize);
         }
+        let data = Rc::new(data);
 
         Ok(Self {
             mem_binder,
@@ -554,6 +616,27 @@ impl<T: MemBinder> Primitive for SeqMem<T> {
         false
     }
 
+    fn snapshot(&self, _snap: &mut Snapshot) -> Box<dyn Primitive> {
+        let update = match &self.update {
+            SeqMemAction::None => SeqMemAction::None,
+            SeqMemAction::Read(idx) => SeqMemAction::Read(copy_idx(idx)),
+            SeqMemAction::Write(idx, v) => {
+                SeqMemAction::Write(copy_idx(idx), v.clone())
+            }
+            SeqMemAction::Reset => SeqMemAction::Reset,
+            SeqMemAction::Error => SeqMemAction::Error,
+        };
+        Box::new(Self {
+            mem_binder: self.mem_binder.clone(),
+            width: self.width,
+            data: Rc::clone(&self.data),
+            full_name: self.full_name,
+            allow_invalid_memory_access: self.allow_invalid_memory_access,
+            read_out: self.read_out.clone(),
+            update,
+        })
+    }
+
     fn validate(&self, inputs: &[(ir::Id, &Value)]) {
         validate![inputs;
             read_en: 1,
@@ -614,7 +697,7 @@ impl<T: MemBinder> Primitive for SeqMem<T> {
             SeqMemAction::Write(idx, v) => {
                 let idx = idx? as usize;
                 if idx < self.data.len() {
-                    self.data[idx] = v;
+                    Rc::make_mut(&mut self.data)[idx] = v;
                 }
 
                 self.read_out = Value::zeroes(self.width);
@@ -667,6 +750,11 @@ impl<T: MemBinder> Primitive for SeqMem<T> {
         )
     }
 
+    fn read_value(&self, address: &[u64]) -> Option<Value> {
+        let idx = self.mem_binder.get_dimensions().flat_index(address)?;
+        Some(self.data[idx].clone())
+    }
+
     fn has_serializeable_state(&self) -> bool {
         true
     }
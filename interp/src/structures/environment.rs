@@ -32,13 +32,22 @@ type ConstPort = *const ir::Port;
 
 /// A map defining primitive implementations for Cells. As it is keyed by
 /// ConstCell the lifetime of the keys is independent of the actual cells.
-pub(crate) type PrimitiveMap = RRC<HashMap<ConstCell, Box<dyn Primitive>>>;
+pub(crate) type PrimitiveMap = RRC<CellPrimitives>;
+type CellPrimitives = HashMap<ConstCell, Box<dyn Primitive>>;
 
 /// A map defining values for ports. As it is keyed by ConstPort, the lifetime of
 /// the keys is independent of the ports. However as a result it is flat, rather
 /// than hierarchical which simplifies the access interface.
 type PortValMap = StackMap<ConstPort, Value>;
 
+/// The copies made while taking a snapshot of an interpreter. Environments
+/// share their primitives with the environments they were forked from, and
+/// the copies of them share the same copies.
+#[derive(Default)]
+pub struct Snapshot {
+    cell_maps: HashMap<*const RefCell<CellPrimitives>, PrimitiveMap>,
+}
+
 /// The environment to interpret a Calyx program.
 pub struct InterpreterState {
     /// Clock count
@@ -69,6 +78,37 @@ pub struct InterpreterState {
 
 /// Helper functions for the environment.
 impl InterpreterState {
+    /// Copies the environment along with the state of its primitives, so that
+    /// the copy evolves independently of the original. The copy shares the
+    /// scopes of the port map and the contents of memories with the original
+    /// until either changes them.
+    pub fn snapshot(&self, snap: &mut Snapshot) -> Self {
+        let cell_map = match snap.cell_maps.get(&Rc::as_ptr(&self.cell_map)) {
+            Some(map) => Rc::clone(map),
+            None => {
+                let map: HashMap<_, _> = self
+                    .cell_map
+                    .borrow()
+                    .iter()
+                    .map(|(cell, prim)| (*cell, prim.snapshot(snap)))
+                    .collect();
+                let map = Rc::new(RefCell::new(map));
+                snap.cell_maps
+                    .insert(Rc::as_ptr(&self.cell_map), Rc::clone(&map));
+                map
+            }
+        };
+        Self {
+            clk: self.clk,
+            cell_map,
+            port_map: self.port_map.snapshot(),
+            context: Rc::clone(&self.context),
+            component: Rc::clone(&self.component),
+            sub_comp_set: Rc::clone(&self.sub_comp_set),
+            allow_par_conflicts: self.allow_par_conflicts,
+        }
+    }
+
     /// Construct an environment
     /// ctx : A context from the IR
     pub fn init_top_level(
@@ -568,6 +608,26 @@ impl InterpreterState {
             serde_json::to_string_pretty(&sv.gen_serializer(true)).unwrap()
         );
     }
+
+    /// The names of the external memories whose final contents differ between
+    /// this state and `other`
+    pub fn differing_memories(&self, other: &Self) -> Vec<ir::Id> {
+        let ours: StateView = self.into();
+        let theirs: StateView = other.into();
+        ours.gen_serializer(true)
+            .differing_memories(&theirs.gen_serializer(true))
+    }
+
+    /// Prints the final contents of the external memories in the format of
+    /// data files.
+    pub fn print_data(&self) {
+        let sv: StateView = self.into();
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&sv.gen_data_file()).unwrap()
+        );
+    }
+
     /// A predicate that checks if the given cell points to a combinational
     /// primitive (or component?)
     pub fn cell_is_comb<C: AsRaw<ir::Cell>>(&self, cell: C) -> bool {
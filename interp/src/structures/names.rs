@@ -1,5 +1,5 @@
 use crate::interpreter_ir as iir;
-use calyx::ir::Id;
+use calyx::{ir::Id, utils::GPosIdx};
 use itertools::Itertools;
 use std::{
     fmt::{Display, Write},
@@ -107,6 +107,15 @@ impl QualifiedInstanceName {
         }
     }
 
+    /// The instance of the component containing the entity
+    pub fn prefix(&self) -> &ComponentQualifiedInstanceName {
+        &self.prefix
+    }
+
+    pub fn name(&self) -> Id {
+        self.name
+    }
+
     pub fn prefix_length(&self) -> usize {
         self.prefix.0.len()
     }
@@ -140,6 +149,8 @@ pub struct GroupQualifiedInstanceName {
     pub prefix: ComponentQualifiedInstanceName,
     pub group: GroupName,
     pub pos_tag: Option<u64>,
+    /// Position of the construct in the source program
+    pub span: GPosIdx,
 }
 
 impl GroupQualifiedInstanceName {
@@ -148,6 +159,7 @@ impl GroupQualifiedInstanceName {
             prefix: comp.clone(),
             group: GroupName::Group(name),
             pos_tag: None,
+            span: GPosIdx::UNKNOWN,
         }
     }
 
@@ -159,6 +171,7 @@ impl GroupQualifiedInstanceName {
             prefix: comp.clone(),
             group: GroupName::Phantom(name),
             pos_tag: None,
+            span: GPosIdx::UNKNOWN,
         }
     }
 
@@ -167,6 +180,7 @@ impl GroupQualifiedInstanceName {
             prefix: comp.clone(),
             group: GroupName::None,
             pos_tag: None,
+            span: GPosIdx::UNKNOWN,
         }
     }
 
@@ -192,6 +206,11 @@ impl GroupQualifiedInstanceName {
         self.pos_tag = tag;
         self
     }
+
+    pub fn with_span(mut self, span: GPosIdx) -> Self {
+        self.span = span;
+        self
+    }
 }
 
 impl std::fmt::Debug for GroupQualifiedInstanceName {
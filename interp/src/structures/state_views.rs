@@ -4,7 +4,7 @@
 //! alone because it is fiddly and not all that interesting.
 
 use std::{
-    collections::{BTreeMap, HashSet},
+    collections::{BTreeMap, BTreeSet, HashSet},
     rc::Rc,
 };
 
@@ -219,6 +219,20 @@ impl<'a> StateView<'a> {
             .unwrap_or(Serializable::Empty)
     }
 
+    /// The value stored at `address` in the given cell. See
+    /// [Primitive::read_value].
+    pub fn read_cell_value<R: AsRaw<ir::Cell>>(
+        &self,
+        cell: R,
+        address: &[u64],
+    ) -> Option<Value> {
+        let map = self.get_cell_map();
+        let map_ref = map.borrow();
+        map_ref
+            .get(&cell.as_raw())
+            .and_then(|x| Primitive::read_value(&**x, address))
+    }
+
     /// Returns a string representing the current state of the environment. This
     /// just serializes the environment to a string and returns that string
     pub fn state_as_str(&self) -> String {
@@ -273,19 +287,12 @@ impl<'a> StateView<'a> {
                             .iter()
                             .map(|port| {
                                 let value = self.lookup(port.as_raw());
+                                let port = port.borrow();
+                                let code = ir::NumericFormat::of_port(&port)
+                                    .map(PrintCode::from)
+                                    .unwrap_or_default();
 
-                                (
-                                    port.borrow().name,
-                                    if port
-                                        .borrow()
-                                        .attributes
-                                        .has("interp_signed")
-                                    {
-                                        value.as_i64().into()
-                                    } else {
-                                        value.as_u64().into()
-                                    },
-                                )
+                                (port.name, Entry::from_val_code(value, &code))
                             })
                             .collect();
                         (cell.borrow().name(), inner_map)
@@ -312,8 +319,15 @@ impl<'a> StateView<'a> {
                                         cell.name(),
                                         Primitive::serialize(
                                             &**prim,
-                                            raw.then_some(PrintCode::Binary),
-                                        ), //TODO Griffin: Fix this
+                                            if raw {
+                                                Some(PrintCode::Binary)
+                                            } else {
+                                                ir::NumericFormat::of_cell(
+                                                    &cell,
+                                                )
+                                                .map(PrintCode::from)
+                                            },
+                                        ),
                                     ));
                                 }
                             }
@@ -330,6 +344,77 @@ impl<'a> StateView<'a> {
             memories: cell_map,
         }
     }
+
+    /// The contents of the external memories of the component, in the format
+    /// of the data files used to initialize them
+    pub fn gen_data_file(&self) -> BTreeMap<ir::Id, MemoryData> {
+        let cell_prim_map = &self.get_cell_map().borrow();
+        self.get_comp()
+            .cells
+            .iter()
+            .filter_map(|cell_ref| {
+                let cell = cell_ref.borrow();
+                cell.get_attribute("external")?;
+                let prim =
+                    cell_prim_map.get(&(&cell as &ir::Cell as ConstCell))?;
+                let format = ir::NumericFormat::of_cell(&cell);
+                let data = Primitive::serialize(
+                    &**prim,
+                    Some(format.map(PrintCode::from).unwrap_or_default()),
+                );
+                // Registers are not part of data files
+                if !matches!(data, Serializable::Array(..)) {
+                    return None;
+                }
+                let width = cell.get_parameter("WIDTH")?;
+                Some((
+                    cell.name(),
+                    MemoryData {
+                        data,
+                        format: DataFormat::new(format, width),
+                    },
+                ))
+            })
+            .collect()
+    }
+}
+
+/// The format of the values of a memory, as described in data files
+#[derive(Serialize, Clone)]
+pub struct DataFormat {
+    numeric_type: &'static str,
+    is_signed: bool,
+    width: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    frac_width: Option<u64>,
+}
+
+impl DataFormat {
+    fn new(format: Option<ir::NumericFormat>, width: u64) -> Self {
+        let (is_signed, frac_width) = match format {
+            Some(ir::NumericFormat::Signed) => (true, None),
+            Some(ir::NumericFormat::UFixed(f)) => (false, Some(f)),
+            Some(ir::NumericFormat::SFixed(f)) => (true, Some(f)),
+            Some(ir::NumericFormat::Unsigned) | None => (false, None),
+        };
+        Self {
+            numeric_type: if frac_width.is_some() {
+                "fixed_point"
+            } else {
+                "bitnum"
+            },
+            is_signed,
+            width,
+            frac_width,
+        }
+    }
+}
+
+/// The contents of a memory as an entry of a data file
+#[derive(Serialize, Clone)]
+pub struct MemoryData {
+    data: Serializable,
+    format: DataFormat,
 }
 
 #[allow(clippy::borrowed_box)]
@@ -343,6 +428,27 @@ pub struct FullySerialize {
     memories: BTreeMap<ir::Id, BTreeMap<ir::Id, Serializable>>,
 }
 
+impl FullySerialize {
+    /// The names of the memories whose contents differ from those in `other`,
+    /// including memories present in only one of the two
+    pub fn differing_memories(&self, other: &Self) -> Vec<ir::Id> {
+        let contents = |s: &Self| -> BTreeMap<ir::Id, serde_json::Value> {
+            s.memories
+                .values()
+                .flatten()
+                .map(|(name, mem)| (*name, serde_json::to_value(mem).unwrap()))
+                .collect()
+        };
+        let (ours, theirs) = (contents(self), contents(other));
+        let names: BTreeSet<_> = ours.keys().chain(theirs.keys()).collect();
+        names
+            .into_iter()
+            .filter(|name| ours.get(name) != theirs.get(name))
+            .copied()
+            .collect()
+    }
+}
+
 impl<'a> Serialize for StateView<'a> {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
@@ -1,3 +1,4 @@
+use std::cell::Cell;
 use std::collections::{HashMap, HashSet};
 use std::convert::TryInto;
 use std::hash::Hash;
@@ -25,6 +26,9 @@ struct Node<T> {
     elem: T,
     /// A (possibly empty) link to the following node in the list
     next: Link<T>,
+    /// Whether the node may be shared with a snapshot. Frozen nodes are
+    /// copied instead of consumed when they are shared.
+    frozen: Cell<bool>,
 }
 
 impl<T> Clone for List<T> {
@@ -131,6 +135,7 @@ impl<T> List<T> {
             head: Some(Rc::new(Node {
                 elem,
                 next: self.head.clone(),
+                frozen: Cell::new(false),
             })),
         }
     }
@@ -148,7 +153,9 @@ impl<T> List<T> {
     ///
     /// # Panics
     /// Because [List::split] consumes self, split panics if multiple lists exist that
-    /// share (references to the same) elements in their **tail**.
+    /// share (references to the same) elements in their **tail**, unless the
+    /// shared elements were frozen by [List::freeze], in which case the head
+    /// is copied.
     /// # Example
     /// Good:
     /// ```
@@ -171,7 +178,10 @@ impl<T> List<T> {
     /// }
     /// l1.push(5);
     /// ```
-    pub fn split(self) -> (Option<T>, Self) {
+    pub fn split(self) -> (Option<T>, Self)
+    where
+        T: Clone,
+    {
         if self.is_empty() {
             (None, self)
         } else {
@@ -180,6 +190,9 @@ impl<T> List<T> {
                     let tail_list = List { head: head.next }; //better: not cloning the tail
                     (Some(head.elem), tail_list)
                 }
+                Err(e) if e.head.as_ref().unwrap().frozen.get() => {
+                    (e.head().cloned(), e.tail())
+                }
                 Err(e) => {
                     panic!("Cannot unwrap the head of this list. You probably tried Smooshing while a fork exists! Current strong count {}", Rc::strong_count(e.head.as_ref().unwrap()));
                 }
@@ -187,6 +200,20 @@ impl<T> List<T> {
         }
     }
 
+    /// Marks every node of the list as frozen so that the list can be shared
+    /// with a snapshot. [List::split] copies frozen nodes which are shared
+    /// rather than panicking, so lists sharing them are unaffected.
+    pub fn freeze(&self) {
+        let mut link = &self.head;
+        while let Some(node) = link {
+            // The rest of the list was frozen along with this node
+            if node.frozen.replace(true) {
+                break;
+            }
+            link = &node.next;
+        }
+    }
+
     /// Returns an Option of a pointer to the head of this list
     pub fn head(&self) -> Option<&T> {
         self.head.as_ref().map(|node| &node.elem)
@@ -496,6 +523,33 @@ impl<K: Eq + Hash, V: Eq> StackMap<K, V> {
         }
     }
 
+    /// Returns a copy of `self` which shares its scopes below the newest one
+    /// with `self`. The shared scopes are frozen, so either copy can still be
+    /// smooshed and merged with its own forks.
+    /// # Example
+    /// ```
+    /// use interp::stk_env::Smoosher;
+    /// let mut a = Smoosher::new();
+    /// a.set("hi!", 1);
+    /// a.new_scope();
+    /// a.set("hi!", 2);
+    /// let b = a.snapshot();
+    /// let a = a.smoosh(1);
+    /// assert_eq!(*a.get(&"hi!").unwrap(), 2);
+    /// assert_eq!(*b.smoosh(1).get(&"hi!").unwrap(), 2);
+    /// ```
+    pub fn snapshot(&self) -> Self
+    where
+        K: Clone,
+        V: Clone,
+    {
+        self.tail.freeze();
+        StackMap {
+            head: self.head.clone(),
+            tail: self.tail.clone(),
+        }
+    }
+
     ///```text
     ///Pushes a new, empty scope onto `self`. Doing so has no effect on the
     ///bindings in `self`, until a new [set] is called
@@ -588,7 +642,11 @@ impl<K: Eq + Hash, V: Eq> StackMap<K, V> {
     /// a.set("bye", 0);
     /// let a = a.smoosh_once(); //not enough scopes to smoosh
     ///```
-    pub fn smoosh_once(self) -> Self {
+    pub fn smoosh_once(self) -> Self
+    where
+        K: Clone,
+        V: Clone,
+    {
         //move head to a sep variable
         let wr_head = self.head;
         //now move the head of the tail into the head of the smoosher
@@ -658,7 +716,11 @@ impl<K: Eq + Hash, V: Eq> StackMap<K, V> {
     /// let a = a.smoosh(1); //cannot smoosh; a fork point exists in a's tail
     /// b.get(&"hi!");
     ///```
-    pub fn smoosh(self, levels: u64) -> Self {
+    pub fn smoosh(self, levels: u64) -> Self
+    where
+        K: Clone,
+        V: Clone,
+    {
         let mut tr = self;
         for _ in 0..levels {
             tr = tr.smoosh_once();
@@ -736,7 +798,11 @@ impl<K: Eq + Hash, V: Eq> StackMap<K, V> {
     /// b.set("hey", 13);
     /// let c = Smoosher::merge(a, b); //there's no common fork point
     /// ```
-    pub fn merge(self, other: Self) -> Self {
+    pub fn merge(self, other: Self) -> Self
+    where
+        K: Clone,
+        V: Clone,
+    {
         //find shared fork point; if doesn't exist, panic
         let mut a = self;
         let mut b = other;
@@ -867,7 +933,11 @@ impl<K: Eq + Hash, V: Eq> StackMap<K, V> {
         other: Vec<Self>,
         overlap_keys: &HashSet<K>,
         allow_par_conflicts: bool,
-    ) -> Result<Self, CollisionError<K, V>> {
+    ) -> Result<Self, CollisionError<K, V>>
+    where
+        K: Clone,
+        V: Clone,
+    {
         if other.is_empty() {
             return Ok(self);
         }
@@ -0,0 +1,77 @@
+#[cfg(test)]
+mod command_parser_test {
+    use crate::debugger::commands::{Command, CompareOp, Operand};
+    use crate::debugger::parser::parse_command;
+
+    #[test]
+    fn conditional_breakpoint() {
+        let Command::Break(groups, Some(cond), skip) =
+            parse_command("break do_add comp::do_mul if mem[1][2] >= -3")
+                .unwrap()
+        else {
+            panic!("expected a conditional breakpoint")
+        };
+        assert_eq!(groups.len(), 2);
+        assert_eq!(skip, 0);
+        assert!(matches!(cond.op, CompareOp::Geq));
+        assert_eq!(cond.left.to_string(), "mem[1][2]");
+        assert!(
+            matches!(cond.right, Operand::Literal(ref n) if *n == (-3).into())
+        );
+    }
+
+    #[test]
+    fn breakpoint_hit_count() {
+        let Command::Break(groups, None, skip) =
+            parse_command("br do_add after 5").unwrap()
+        else {
+            panic!("expected a breakpoint without a condition")
+        };
+        assert_eq!(groups.len(), 1);
+        assert_eq!(skip, 5);
+    }
+
+    #[test]
+    fn breakpoint_on_keyword_prefix() {
+        // groups may start with the keywords
+        let Command::Break(groups, None, 0) =
+            parse_command("br iffy afterwards").unwrap()
+        else {
+            panic!("expected a plain breakpoint")
+        };
+        assert_eq!(groups.len(), 2);
+    }
+
+    #[test]
+    fn data_watchpoint() {
+        let Command::WatchData(target) =
+            parse_command("watch sub.r.out").unwrap()
+        else {
+            panic!("expected a data watchpoint")
+        };
+        assert_eq!(target.to_string(), "sub.r.out");
+        assert!(matches!(
+            parse_command("watch before do_add with p r.out").unwrap(),
+            Command::Watch(..)
+        ));
+        assert!(matches!(parse_command("where").unwrap(), Command::PrintPC));
+        assert!(matches!(
+            parse_command("w wide.out").unwrap(),
+            Command::WatchData(..)
+        ));
+    }
+
+    #[test]
+    fn reverse_commands() {
+        assert!(matches!(
+            parse_command("step-back 3").unwrap(),
+            Command::StepBack(3)
+        ));
+        assert!(matches!(parse_command("sb").unwrap(), Command::StepBack(1)));
+        assert!(matches!(parse_command("s 2").unwrap(), Command::Step(2)));
+        assert!(matches!(
+            parse_command("rc").unwrap(),
+            Command::ReverseContinue
+        ));
+    }
+}
@@ -1,3 +1,4 @@
+mod debugger;
 mod primitives;
 mod stk_env;
 mod values;
@@ -3,7 +3,6 @@ use calyx::ir::{self, Assignment, Binding, Id, Port, RRC};
 use serde::Deserialize;
 use std::cell::Ref;
 use std::collections::HashMap;
-use std::fs;
 use std::hash::{Hash, Hasher};
 use std::ops::{Deref, DerefMut};
 use std::path::PathBuf;
@@ -46,19 +45,17 @@ impl<'a> PortAssignment<'a> {
 
 /// A map representing all the identifiers and its associated values in a
 /// Futil program.
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 #[serde(transparent)]
 pub struct MemoryMap(HashMap<Id, Vec<Value>>);
 
 impl MemoryMap {
+    /// Reads the data file at `path`, if one is given
     pub fn inflate_map(
         path: &Option<PathBuf>,
     ) -> crate::errors::InterpreterResult<Option<Self>> {
         if let Some(path) = path {
-            let v = fs::read(path)?;
-            let file_contents = std::str::from_utf8(&v)?;
-            let map: MemoryMap = serde_json::from_str(file_contents).unwrap();
-            return Ok(Some(map));
+            return Ok(Some(MemoryMap(crate::data_file::read(path)?)));
         }
 
         Ok(None)
@@ -97,7 +97,7 @@ impl MlirBackend {
         writeln!(
             f,
             "calyx.component @{}({}) -> ({}) {{",
-            comp.name.id,
+            comp.name.mangled(),
             Self::format_port_def(&inputs),
             Self::format_port_def(&outputs),
         )?;
@@ -152,7 +152,9 @@ impl MlirBackend {
                     .iter()
                     .map(|(k, v)| (k.as_ref(), *v))
                     .collect();
-                match name.as_ref() {
+                // Primitives correspond to operations in the Calyx dialect
+                // regardless of the namespace they are imported into.
+                match name.unqualified() {
                     "std_reg" => {
                         write!(f, "calyx.register @{}", cell_name)
                     }
@@ -204,7 +206,12 @@ impl MlirBackend {
                 }
             }
             ir::CellType::Component { name } => {
-                write!(f, "calyx.instance @{} of @{}", cell_name, name)
+                write!(
+                    f,
+                    "calyx.instance @{} of @{}",
+                    cell_name,
+                    name.mangled()
+                )
             }
             ir::CellType::Constant { val, .. } => {
                 write!(f, "hw.constant {}", val)
@@ -1,19 +1,24 @@
 //! Resource estimation backend for the Calyx compiler.
-//! Transforms an [`ir::Context`](crate::ir::Context) into a CSV that
-//! counts the number of different primitives in a program and loosely
-//! estimates the total size of the generated hardware.
+//! Transforms an [`ir::Context`](crate::ir::Context) into a hierarchical
+//! report that counts the primitives instantiated by every component and
+//! loosely estimates the size (in bits) of the generated hardware.
+//!
+//! The backend is configured through extra options:
+//! - `-x resources:format=json` emits JSON instead of the default CSV.
+//! - `-x resources:cost-table=<file>` reads a CSV file with a `family,weight`
+//!   header that scales the bit-level estimate of each primitive family.
 
 use std::collections::HashMap;
-use std::fmt::Write;
-use std::vec;
+use std::io::Write;
+use std::path::Path;
 
 use crate::backend::traits::Backend;
-use calyx::ir::Component;
 use calyx::{
-    errors::CalyxResult,
+    errors::{CalyxResult, Error},
     ir::{self, Binding, CellType, Id},
     utils::OutputFile,
 };
+use serde::Serialize;
 
 #[derive(Default)]
 pub struct ResourcesBackend;
@@ -37,226 +42,477 @@ impl Backend for ResourcesBackend {
     }
 
     fn emit(ctx: &ir::Context, file: &mut OutputFile) -> CalyxResult<()> {
-        let main_comp = ctx
-            .components
-            .iter()
-            .find(|comp| comp.name == ctx.entrypoint)
-            .unwrap();
+        let conf = ResourcesConf::from_ctx(ctx)?;
+        let report = Report::build(ctx, &conf.costs);
+        match conf.format {
+            OutputFormat::Csv => report.write_csv(file),
+            OutputFormat::Json => report.write_json(file),
+        }
+    }
+}
 
-        let count_map: &mut HashMap<(Id, Binding, bool), u32> =
-            &mut HashMap::new();
+/// Output formats supported by the backend.
+enum OutputFormat {
+    Csv,
+    Json,
+}
 
-        gen_count_map(ctx, main_comp, count_map);
+/// Options for the resources backend parsed from the `-x resources:<opt>`
+/// command line flags.
+struct ResourcesConf {
+    format: OutputFormat,
+    costs: CostTable,
+}
 
-        write_csv(count_map.clone(), file);
+impl ResourcesConf {
+    fn from_ctx(ctx: &ir::Context) -> CalyxResult<Self> {
+        let mut format = OutputFormat::Csv;
+        let mut costs = CostTable::default();
+        let opts = ctx.extra_opts.iter().filter_map(|opt| {
+            let mut splits = opt.splitn(2, ':');
+            if splits.next() == Some("resources") {
+                splits.next()
+            } else {
+                None
+            }
+        });
+        for opt in opts {
+            match opt.split_once('=') {
+                Some(("format", "csv")) => format = OutputFormat::Csv,
+                Some(("format", "json")) => format = OutputFormat::Json,
+                Some(("format", fmt)) => {
+                    return Err(Error::misc(format!(
+                        "Unknown output format for resources backend: `{}`. Valid options are `csv` or `json`",
+                        fmt
+                    )))
+                }
+                Some(("cost-table", path)) => {
+                    costs.extend_from_file(Path::new(path))?
+                }
+                _ => log::warn!(
+                    "Ignoring unknown option for backend `resources`: {}",
+                    opt
+                ),
+            }
+        }
+        Ok(Self { format, costs })
+    }
+}
 
-        estimated_size(count_map.clone());
+/// Families of primitives that share an estimation model.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+enum Family {
+    Register,
+    Memory,
+    Adder,
+    Multiplier,
+    Divider,
+    Comparator,
+    Logic,
+    Shifter,
+    Wiring,
+    Other,
+}
 
-        Ok(())
+impl Family {
+    const ALL: [Family; 10] = [
+        Family::Register,
+        Family::Memory,
+        Family::Adder,
+        Family::Multiplier,
+        Family::Divider,
+        Family::Comparator,
+        Family::Logic,
+        Family::Shifter,
+        Family::Wiring,
+        Family::Other,
+    ];
+
+    fn as_str(&self) -> &'static str {
+        match self {
+            Family::Register => "register",
+            Family::Memory => "memory",
+            Family::Adder => "adder",
+            Family::Multiplier => "multiplier",
+            Family::Divider => "divider",
+            Family::Comparator => "comparator",
+            Family::Logic => "logic",
+            Family::Shifter => "shifter",
+            Family::Wiring => "wiring",
+            Family::Other => "other",
+        }
+    }
+
+    /// Classify a primitive using its name.
+    fn of_primitive(name: &str) -> Self {
+        // Strip the library prefix and the signed/fixed point markers so that
+        // `std_fp_sadd` and `std_add` end up in the same family.
+        if name.starts_with("std_mem_") || name.starts_with("seq_mem_") {
+            return Family::Memory;
+        }
+        let base = name.strip_prefix("std_").unwrap_or(name);
+        let base = base.strip_prefix("fp_").unwrap_or(base);
+        let base = base.strip_prefix("unsyn_").unwrap_or(base);
+        match base {
+            "reg" | "sync_reg" => Family::Register,
+            "add" | "sadd" | "sub" | "ssub" => Family::Adder,
+            "mult_pipe" | "smult_pipe" | "mult" | "smult" => Family::Multiplier,
+            "div_pipe" | "sdiv_pipe" | "div" | "sdiv" | "mod" | "smod" => {
+                Family::Divider
+            }
+            "eq" | "neq" | "lt" | "gt" | "le" | "ge" | "seq" | "sneq"
+            | "slt" | "sgt" | "sle" | "sge" => Family::Comparator,
+            "and" | "or" | "xor" | "not" | "mux" => Family::Logic,
+            "lsh" | "rsh" | "slsh" | "srsh" => Family::Shifter,
+            "const" | "wire" | "slice" | "pad" | "cat" | "undef" => {
+                Family::Wiring
+            }
+            _ => Family::Other,
+        }
+    }
+
+    /// Estimated size, in bits, of a single primitive of this family with
+    /// parameters `params`.
+    fn bits(&self, params: &Binding) -> u64 {
+        let get = |p: &str| {
+            params
+                .iter()
+                .find(|(id, _)| id == p)
+                .map(|(_, v)| *v)
+                .unwrap_or(0)
+        };
+        let width = get("WIDTH");
+        match self {
+            Family::Register | Family::Adder | Family::Comparator => width,
+            Family::Logic => width,
+            Family::Memory => {
+                let size = get("SIZE");
+                let slots = if size > 0 {
+                    size
+                } else {
+                    params
+                        .iter()
+                        .filter(|(id, _)| {
+                            let id = id.as_ref();
+                            id.ends_with("_SIZE") && !id.ends_with("IDX_SIZE")
+                        })
+                        .map(|(_, v)| *v)
+                        .product()
+                };
+                width * slots
+            }
+            Family::Multiplier | Family::Divider => width * width,
+            Family::Shifter => {
+                // A barrel shifter has one layer of muxes for every bit of
+                // the shift amount.
+                width * (64 - width.saturating_sub(1).leading_zeros()) as u64
+            }
+            Family::Wiring | Family::Other => 0,
+        }
     }
 }
 
-/// Counts the number of each primitive with a given set of parameters
-/// in the program with entrypoint `main_comp`.
-fn gen_count_map(
-    ctx: &ir::Context,
-    main_comp: &Component,
-    count_map: &mut HashMap<(Id, Binding, bool), u32>,
-) {
-    for cell in main_comp.cells.iter() {
-        let cell_ref = cell.borrow();
-        match &cell_ref.prototype {
-            CellType::Primitive {
-                name,
-                param_binding,
-                ..
-            } => {
-                *count_map
-                    .entry((
-                        *name,
-                        (**param_binding).clone(),
-                        cell_ref.get_attribute("external").is_some(),
+/// Weights used to scale the bit-level estimate of each primitive family.
+/// Every family has a weight of `1.0` unless overridden by a cost table.
+struct CostTable {
+    weights: HashMap<Family, f64>,
+}
+
+impl Default for CostTable {
+    fn default() -> Self {
+        Self {
+            weights: Family::ALL.iter().map(|f| (*f, 1.0)).collect(),
+        }
+    }
+}
+
+impl CostTable {
+    /// Override the weights using a CSV file with a `family,weight` header.
+    fn extend_from_file(&mut self, path: &Path) -> CalyxResult<()> {
+        let mut rdr = csv::Reader::from_path(path).map_err(|err| {
+            Error::invalid_file(format!(
+                "Failed to read cost table `{}`: {}",
+                path.display(),
+                err
+            ))
+        })?;
+        for record in rdr.records() {
+            let record = record.map_err(|err| {
+                Error::invalid_file(format!(
+                    "Malformed cost table `{}`: {}",
+                    path.display(),
+                    err
+                ))
+            })?;
+            let (name, weight) =
+                record.get(0).zip(record.get(1)).ok_or_else(|| {
+                    Error::invalid_file(format!(
+                        "Malformed cost table `{}`: expected `family,weight` rows",
+                        path.display()
                     ))
-                    .or_insert(0) += 1;
+                })?;
+            let (name, weight) = (name.trim(), weight.trim());
+            let family = Family::ALL
+                .iter()
+                .find(|f| f.as_str() == name)
+                .ok_or_else(|| {
+                    Error::invalid_file(format!(
+                        "Unknown primitive family `{}` in cost table `{}`. Valid families: {}",
+                        name,
+                        path.display(),
+                        Family::ALL.iter().map(|f| f.as_str()).collect::<Vec<_>>().join(", ")
+                    ))
+                })?;
+            let weight = weight.parse::<f64>().map_err(|_| {
+                Error::invalid_file(format!(
+                    "Invalid weight `{}` for family `{}` in cost table `{}`",
+                    weight,
+                    name,
+                    path.display()
+                ))
+            })?;
+            self.weights.insert(*family, weight);
+        }
+        Ok(())
+    }
+
+    fn estimate(&self, family: Family, params: &Binding) -> u64 {
+        (family.bits(params) as f64 * self.weights[&family]).round() as u64
+    }
+}
+
+/// Primitives with the same name, parameters, and external status used by
+/// a single component.
+#[derive(Serialize)]
+struct PrimitiveUsage {
+    primitive: String,
+    family: &'static str,
+    #[serde(serialize_with = "serialize_params")]
+    parameters: Vec<(String, u64)>,
+    external: bool,
+    /// Number of primitives in one instance of the component.
+    count: u64,
+    /// Number of primitives across all instances of the component.
+    total_count: u64,
+    /// Estimated size of all the primitives across all instances.
+    estimated_size: u64,
+}
+
+/// Resource usage of a component.
+#[derive(Serialize)]
+struct ComponentUsage {
+    name: String,
+    /// Number of times this component is instantiated in the design.
+    instances: u64,
+    primitives: Vec<PrimitiveUsage>,
+    /// Estimated size of one instance of this component, including its
+    /// sub-components.
+    estimated_size_per_instance: u64,
+    /// Estimated size of the primitives defined directly in this component
+    /// across all of its instances.
+    estimated_size: u64,
+    estimated_external_size: u64,
+}
+
+/// Resource usage of the entire design.
+#[derive(Serialize)]
+struct Report {
+    entrypoint: String,
+    components: Vec<ComponentUsage>,
+    estimated_size: u64,
+    estimated_external_size: u64,
+}
+
+impl Report {
+    fn build(ctx: &ir::Context, costs: &CostTable) -> Self {
+        let mut instances: HashMap<Id, u64> = HashMap::new();
+        count_instances(ctx, ctx.entrypoint, 1, &mut instances);
+
+        let mut per_instance: HashMap<Id, u64> = HashMap::new();
+        let mut components = vec![];
+        // Visit sub-components before their users so that their sizes are
+        // known when computing the hierarchical sizes. Components that are
+        // never instantiated by the entrypoint are skipped.
+        let mut order = vec![];
+        post_order(ctx, ctx.entrypoint, &mut order);
+        for comp in order
+            .iter()
+            .filter_map(|name| ctx.components.iter().find(|c| c.name == *name))
+        {
+            let inst = instances[&comp.name];
+            let mut prims: Vec<((Id, Binding, bool), u64)> = vec![];
+            let mut sub_size = 0;
+            for cell in comp.cells.iter() {
+                let cell_ref = cell.borrow();
+                match &cell_ref.prototype {
+                    CellType::Primitive {
+                        name,
+                        param_binding,
+                        ..
+                    } => {
+                        let key = (
+                            *name,
+                            (**param_binding).clone(),
+                            cell_ref.get_attribute("external").is_some(),
+                        );
+                        match prims.iter_mut().find(|(k, _)| *k == key) {
+                            Some((_, count)) => *count += 1,
+                            None => prims.push((key, 1)),
+                        }
+                    }
+                    CellType::Component { name } => {
+                        sub_size += per_instance.get(name).unwrap_or(&0);
+                    }
+                    _ => (),
+                }
             }
-            CellType::Component { name } => {
-                let component = ctx
-                    .components
+            prims.sort_by(|((n1, p1, e1), _), ((n2, p2, e2), _)| {
+                (n1.as_ref(), p1, e1).cmp(&(n2.as_ref(), p2, e2))
+            });
+
+            let primitives = prims
+                .into_iter()
+                .map(|((name, params, external), count)| {
+                    let family = Family::of_primitive(name.as_ref());
+                    let size = costs.estimate(family, &params);
+                    PrimitiveUsage {
+                        primitive: name.to_string(),
+                        family: family.as_str(),
+                        parameters: params
+                            .iter()
+                            .map(|(id, v)| (id.to_string(), *v))
+                            .collect(),
+                        external,
+                        count,
+                        total_count: count * inst,
+                        estimated_size: size * count * inst,
+                    }
+                })
+                .collect::<Vec<_>>();
+
+            let (local, local_external) =
+                primitives.iter().fold((0, 0), |(int, ext), p| {
+                    if p.external {
+                        (int, ext + p.estimated_size / inst)
+                    } else {
+                        (int + p.estimated_size / inst, ext)
+                    }
+                });
+            per_instance.insert(comp.name, local + sub_size);
+            components.push(ComponentUsage {
+                name: comp.name.to_string(),
+                instances: inst,
+                primitives,
+                estimated_size_per_instance: local + sub_size,
+                estimated_size: local * inst,
+                estimated_external_size: local_external * inst,
+            });
+        }
+
+        // Report the components in the order they are defined in.
+        components.sort_by_key(|c| {
+            ctx.components.iter().position(|comp| comp.name == c.name)
+        });
+
+        let estimated_size = components.iter().map(|c| c.estimated_size).sum();
+        let estimated_external_size =
+            components.iter().map(|c| c.estimated_external_size).sum();
+        Report {
+            entrypoint: ctx.entrypoint.to_string(),
+            components,
+            estimated_size,
+            estimated_external_size,
+        }
+    }
+
+    /// Writes one row for every primitive used by every component.
+    fn write_csv(&self, file: &mut OutputFile) -> CalyxResult<()> {
+        let mut wtr = csv::Writer::from_writer(file.get_write());
+        let write_err = |err: csv::Error| Error::write_error(err.to_string());
+        wtr.write_record([
+            "Component",
+            "Instances",
+            "Primitive",
+            "Family",
+            "External?",
+            "Parameters",
+            "Count",
+            "Total Count",
+            "Estimated Size",
+        ])
+        .map_err(write_err)?;
+        for comp in &self.components {
+            for prim in &comp.primitives {
+                let param_vals = prim
+                    .parameters
                     .iter()
-                    .find(|comp| comp.name == name)
-                    .unwrap();
-                gen_count_map(ctx, component, count_map);
+                    .map(|(id, val)| format!("{}: {}. ", id, val))
+                    .collect::<String>();
+                wtr.write_record([
+                    comp.name.clone(),
+                    comp.instances.to_string(),
+                    prim.primitive.clone(),
+                    prim.family.to_string(),
+                    if prim.external { "yes" } else { "no" }.to_string(),
+                    param_vals,
+                    prim.count.to_string(),
+                    prim.total_count.to_string(),
+                    prim.estimated_size.to_string(),
+                ])
+                .map_err(write_err)?;
             }
-            _ => (),
         }
+        wtr.flush()?;
+        Ok(())
+    }
+
+    fn write_json(&self, file: &mut OutputFile) -> CalyxResult<()> {
+        let out = &mut file.get_write();
+        serde_json::to_writer_pretty(&mut *out, self)
+            .map_err(|err| Error::write_error(err.to_string()))?;
+        writeln!(out)?;
+        Ok(())
     }
 }
 
-/// Writes a CSV to stdout with primitive count information
-/// generated by `gen_count_map`.
-fn write_csv(
-    count_map: HashMap<(Id, Binding, bool), u32>,
-    file: &mut OutputFile,
+/// Serialize parameters as a map while preserving their definition order.
+fn serialize_params<S: serde::Serializer>(
+    params: &[(String, u64)],
+    ser: S,
+) -> Result<S::Ok, S::Error> {
+    ser.collect_map(params.iter().map(|(k, v)| (k, v)))
+}
+
+/// Computes the number of times every component is instantiated in the
+/// design rooted at `comp`.
+fn count_instances(
+    ctx: &ir::Context,
+    comp: Id,
+    mult: u64,
+    instances: &mut HashMap<Id, u64>,
 ) {
-    let mut wtr = csv::Writer::from_writer(file.get_write());
-    let header = vec!["Primitive", "Count", "External?", "Parameters"];
-    wtr.write_record(header).unwrap();
-    for ((name, params, is_external), count) in count_map {
-        let mut result = vec![];
-        result.push(name.id.to_string());
-        result.push(count.to_string());
-        result.push(if is_external {
-            "yes".to_string()
-        } else {
-            "no".to_string()
-        });
-        let mut param_vals = String::new();
-        for (id, val) in params {
-            write!(param_vals, "{}: {}. ", &id.id, val).unwrap();
+    *instances.entry(comp).or_insert(0) += mult;
+    if let Some(component) = ctx.components.iter().find(|c| c.name == comp) {
+        for cell in component.cells.iter() {
+            if let CellType::Component { name } = &cell.borrow().prototype {
+                count_instances(ctx, *name, mult, instances);
+            }
         }
-        result.push(param_vals);
-        wtr.write_record(result).unwrap();
     }
-    wtr.flush().ok();
 }
 
-// Prints the estimated size (in bits) of the generated hardware along with a breakdown
-// of which primitives contributed to the total number.
-// TODO (priya): Add other primitives
-fn estimated_size(count_map: HashMap<(Id, Binding, bool), u32>) {
-    let mut estimated_size: u64 = 0;
-    let mut estimated_external_size: u64 = 0;
-    let mut add_size =
-        |is_external: bool, count: u64, bitwidth: u64, slots: Option<u64>| {
-            let size = count * bitwidth * slots.unwrap_or(1);
-            if is_external {
-                estimated_external_size += size;
-            } else {
-                estimated_size += size;
-            }
-        };
-    let externalize_name = |name: Id, is_external: bool| {
-        let id = if is_external {
-            format!("external {}", name)
-        } else {
-            name.to_string()
-        };
-        id
-    };
-    eprintln!("Summary of primitives:");
-    for ((name, params, is_external), count) in count_map {
-        match name.as_ref() {
-            "std_reg" => {
-                add_size(is_external, count as u64, params[0].1, None);
-                eprintln!(
-                    "{} {} primitive(s) with a bitwidth of {}.",
-                    count,
-                    externalize_name(name, is_external),
-                    params[0].1
-                )
-            }
-            "std_mem_d1" => {
-                add_size(
-                    is_external,
-                    count as u64,
-                    params[0].1,
-                    Some(params[1].1),
-                );
-                eprintln!(
-                    "{} {} primitive(s) with {} slot(s) of memory, each {} bit(s) wide.", 
-                    count, externalize_name(name, is_external),
-                    params[1].1, params[0].1);
-            }
-            "std_mem_d2" => {
-                add_size(
-                    is_external,
-                    count as u64,
-                    params[0].1,
-                    Some(params[1].1 * params[2].1),
-                );
-                eprintln!(
-                    "{} {} primitive(s) with {} slot(s) of memory, each {} bit(s) wide.", 
-                    count, externalize_name(name, is_external),
-                    (params[1].1 * params[2].1), params[0].1);
-            }
-            "std_mem_d3" => {
-                add_size(
-                    is_external,
-                    count as u64,
-                    params[0].1,
-                    Some(params[1].1 * params[2].1 * params[3].1),
-                );
-                eprintln!(
-                    "{} {} primitive(s) with {} slot(s) of memory, each {} bit(s) wide.", 
-                    count, externalize_name(name, is_external),
-                    (params[1].1 * params[2].1 * params[3].1), params[0].1);
-            }
-            "std_mem_d4" => {
-                add_size(
-                    is_external,
-                    count as u64,
-                    params[0].1,
-                    Some(params[1].1 * params[2].1 * params[3].1 * params[4].1),
-                );
-                eprintln!(
-                    "{} {} primitive(s) with {} slot(s) of memory, each {} bit(s) wide.", 
-                    count, externalize_name(name, is_external),
-                    (params[1].1 * params[2].1 * params[3].1 * params[4].1), params[0].1);
-            }
-            "seq_mem_d1" => {
-                add_size(
-                    is_external,
-                    count as u64,
-                    params[0].1,
-                    Some(params[1].1),
-                );
-                eprintln!(
-                    "{} {} primitive(s) with {} slot(s) of memory, each {} bit(s) wide.", 
-                    count, externalize_name(name, is_external),
-                    params[1].1, params[0].1);
-            }
-            "seq_mem_d2" => {
-                add_size(
-                    is_external,
-                    count as u64,
-                    params[0].1,
-                    Some(params[1].1 * params[2].1),
-                );
-                eprintln!(
-                    "{} {} primitive(s) with {} slot(s) of memory, each {} bit(s) wide.", 
-                    count, externalize_name(name, is_external),
-                    (params[1].1 * params[2].1), params[0].1);
-            }
-            "seq_mem_d3" => {
-                add_size(
-                    is_external,
-                    count as u64,
-                    params[0].1,
-                    Some(params[1].1 * params[2].1 * params[3].1),
-                );
-                eprintln!(
-                    "{} {} primitive(s) with {} slot(s) of memory, each {} bit(s) wide.", 
-                    count, externalize_name(name, is_external),
-                    (params[1].1 * params[2].1 * params[3].1), params[0].1);
-            }
-            "seq_mem_d4" => {
-                add_size(
-                    is_external,
-                    count as u64,
-                    params[0].1,
-                    Some(params[1].1 * params[2].1 * params[3].1 * params[4].1),
-                );
-                eprintln!(
-                    "{} {} primitive(s) with {} slot(s) of memory, each {} bit(s) wide.", 
-                    count, externalize_name(name, is_external),
-                    (params[1].1 * params[2].1 * params[3].1 * params[4].1), params[0].1);
+/// Appends the components used by the design rooted at `comp` to `order`,
+/// each after all of the components it instantiates.
+fn post_order(ctx: &ir::Context, comp: Id, order: &mut Vec<Id>) {
+    if order.contains(&comp) {
+        return;
+    }
+    if let Some(component) = ctx.components.iter().find(|c| c.name == comp) {
+        for cell in component.cells.iter() {
+            if let CellType::Component { name } = &cell.borrow().prototype {
+                post_order(ctx, *name, order);
             }
-            _ => (),
         }
     }
-    eprintln!("Estimated size in bit(s): {}", estimated_size);
-    eprintln!(
-        "Estimated external size in bit(s): {}",
-        estimated_external_size
-    );
+    order.push(comp);
 }
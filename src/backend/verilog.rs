@@ -7,7 +7,7 @@ use crate::backend::traits::Backend;
 use calyx::{
     errors::{CalyxResult, Error},
     ir,
-    utils::OutputFile,
+    utils::{OutputFile, WithPos},
 };
 use ir::{Control, Group, Guard, RRC};
 use itertools::Itertools;
@@ -139,9 +139,11 @@ impl Backend for VerilogBackend {
             let time = Instant::now();
             let out = emit_component(
                 comp,
+                &ctx.lib,
                 ctx.bc.synthesis_mode,
                 ctx.bc.enable_verification,
                 ctx.bc.initialize_inputs,
+                ctx.bc.source_locations,
                 out,
             );
             log::info!("Generated `{}` in {:?}", comp.name, time.elapsed());
@@ -163,7 +165,7 @@ fn emit_prim_inline<F: io::Write>(
     prim: &ir::Primitive,
     f: &mut F,
 ) -> CalyxResult<()> {
-    write!(f, "module {}", prim.name)?;
+    write!(f, "module {}", prim.name.mangled())?;
     if !prim.params.is_empty() {
         writeln!(f, " #(")?;
         for (idx, param) in prim.params.iter().enumerate() {
@@ -227,12 +229,14 @@ fn emit_prim_inline<F: io::Write>(
 
 fn emit_component<F: io::Write>(
     comp: &ir::Component,
+    lib: &ir::LibrarySignatures,
     synthesis_mode: bool,
     enable_verification: bool,
     initialize_inputs: bool,
+    source_locations: bool,
     f: &mut F,
 ) -> io::Result<()> {
-    writeln!(f, "module {}(", comp.name)?;
+    writeln!(f, "module {}(", comp.name.mangled())?;
 
     let sig = comp.signature.borrow();
     for (idx, port_ref) in sig.ports.iter().enumerate() {
@@ -302,10 +306,15 @@ fn emit_component<F: io::Write>(
     }
 
     // cell instances
-    comp.cells
-        .iter()
-        .filter_map(|cell| cell_instance(&cell.borrow()))
-        .try_for_each(|instance| writeln!(f, "{instance}"))?;
+    for cell in comp.cells.iter() {
+        let cell = cell.borrow();
+        if let Some(instance) = cell_instance(&cell, lib) {
+            if source_locations {
+                emit_source_locations(std::iter::once(&*cell), f)?;
+            }
+            writeln!(f, "{instance}")?;
+        }
+    }
 
     // gather assignments keyed by destination
     let mut map: HashMap<_, (RRC<ir::Port>, Vec<_>)> = HashMap::new();
@@ -327,6 +336,9 @@ fn emit_component<F: io::Write>(
                     checks.add_seq(check);
                 };
             }
+            if source_locations {
+                emit_source_locations(asgns.1.iter().copied(), f)?;
+            }
             let stmt = v::Stmt::new_parallel(emit_assignment(asgns));
             writeln!(f, "{stmt}")
         })?;
@@ -340,6 +352,27 @@ fn emit_component<F: io::Write>(
     Ok(())
 }
 
+/// Emit a `// file:line` comment for each distinct source location of the
+/// `nodes`. Nodes without a known position are skipped.
+fn emit_source_locations<'a, N, F>(
+    nodes: impl Iterator<Item = &'a N>,
+    f: &mut F,
+) -> io::Result<()>
+where
+    N: WithPos + 'a,
+    F: io::Write,
+{
+    nodes
+        .filter_map(|node| node.copy_span().into_option())
+        .map(|span| {
+            let (file, _, _) = span.get_location();
+            let ((line, _), _) = span.get_line_col();
+            format!("{file}:{line}")
+        })
+        .unique()
+        .try_for_each(|loc| writeln!(f, "// {loc}"))
+}
+
 fn wire_decls(cell: &ir::Cell) -> Vec<(String, u64, ir::Direction)> {
     cell.ports
         .iter()
@@ -366,11 +399,27 @@ fn wire_decls(cell: &ir::Cell) -> Vec<(String, u64, ir::Direction)> {
         .collect()
 }
 
-fn cell_instance(cell: &ir::Cell) -> Option<v::Instance> {
+/// The name of the Verilog module implementing the component or primitive
+/// `name`. Qualified names of components and inlined primitives are mangled.
+/// Other primitives are implemented by the Verilog modules in their extern
+/// files which use the unqualified names.
+fn module_name(name: ir::Id, lib: &ir::LibrarySignatures) -> String {
+    match lib.find_primitive(name) {
+        Some(prim) if prim.body.is_none() => name.unqualified().to_string(),
+        _ => name.mangled(),
+    }
+}
+
+fn cell_instance(
+    cell: &ir::Cell,
+    lib: &ir::LibrarySignatures,
+) -> Option<v::Instance> {
     match cell.type_name() {
         Some(ty_name) => {
-            let mut inst =
-                v::Instance::new(cell.name().as_ref(), ty_name.as_ref());
+            let mut inst = v::Instance::new(
+                cell.name().as_ref(),
+                &module_name(ty_name, lib),
+            );
 
             if let ir::CellType::Primitive {
                 name,
@@ -51,7 +51,6 @@ impl AxiChannel {
     /// Synchronize this channel with another channel.
     pub fn then<'a>(&'a self, channel: &'a AxiChannel) -> Synchronization<'a> {
         Synchronization {
-            trigger: None,
             channels: vec![self, channel],
             prefix: String::new(),
         }
@@ -164,9 +163,6 @@ impl AxiInterface {
 /// the read address needs to be sent before any data can be sent.
 /// This struct represents that relationship.
 pub(crate) struct Synchronization<'a> {
-    /// An external condition that must be true before
-    /// we can start handling AXI transactions.
-    trigger: Option<v::Expr>,
     /// The channels that are synchronized.
     channels: Vec<&'a AxiChannel>,
     /// The string prefixed to any internal stage registers generated.
@@ -189,12 +185,6 @@ impl<'a> Synchronization<'a> {
         self
     }
 
-    /// Rust builder style method for setting the trigger.
-    pub fn trigger(mut self, expr: v::Expr) -> Self {
-        self.trigger = Some(expr);
-        self
-    }
-
     /// Add an fsm implementing this synchronization to
     /// the given module.
     pub fn emit(&self, module: &mut v::Module) {
@@ -206,11 +196,6 @@ impl<'a> Synchronization<'a> {
         // create a new fsm
         let mut fsm = fsm::LinearFsm::new(&self.prefix, "ACLK", "ARESET");
 
-        // if there is a trigger, add a state for it
-        if let Some(trigger) = &self.trigger {
-            fsm.add_state("trigger", &[], trigger.clone());
-        }
-
         // add a state in the fsm for all the channels
         for ch in &self.channels {
             fsm.add_state(&ch.prefix, &[ch.ready().into()], ch.valid());
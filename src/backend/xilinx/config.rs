@@ -0,0 +1,93 @@
+use calyx::{
+    errors::{CalyxResult, Error},
+    ir,
+};
+
+/// Options shared by the Xilinx backends. They are passed using
+/// `-x xilinx:<option>=<value>` so that the AXI wrapper and `kernel.xml`
+/// generated for a program agree with each other.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct XilinxConfig {
+    /// Width (in bits) of the data bus of the AXI memory interfaces.
+    pub bus_width: u64,
+    /// Maximum number of beats in a burst issued by the memory controllers.
+    pub max_burst_len: u64,
+}
+
+impl Default for XilinxConfig {
+    fn default() -> Self {
+        Self {
+            bus_width: 512,
+            max_burst_len: 256,
+        }
+    }
+}
+
+impl XilinxConfig {
+    pub fn from_ctx(ctx: &ir::Context) -> CalyxResult<Self> {
+        let mut conf = Self::default();
+        let opts = ctx.extra_opts.iter().filter_map(|opt| {
+            let mut splits = opt.splitn(2, ':');
+            if splits.next() == Some("xilinx") {
+                splits.next()
+            } else {
+                None
+            }
+        });
+        for opt in opts {
+            match opt.split_once('=') {
+                Some(("bus-width", val)) => {
+                    conf.bus_width = parse_opt(opt, val)?;
+                    if !conf.bus_width.is_power_of_two()
+                        || !(32..=1024).contains(&conf.bus_width)
+                    {
+                        return Err(Error::misc(format!(
+                            "AXI bus width must be a power of two between 32 and 1024, got {}",
+                            conf.bus_width
+                        )));
+                    }
+                }
+                Some(("max-burst", val)) => {
+                    conf.max_burst_len = parse_opt(opt, val)?;
+                    if !(1..=256).contains(&conf.max_burst_len) {
+                        return Err(Error::misc(format!(
+                            "AXI bursts must have between 1 and 256 beats, got {}",
+                            conf.max_burst_len
+                        )));
+                    }
+                }
+                _ => log::warn!(
+                    "Ignoring unknown option for the Xilinx backends: {}",
+                    opt
+                ),
+            }
+        }
+        Ok(conf)
+    }
+
+    /// Number of bytes transferred by every beat on the data bus.
+    pub fn bus_bytes(&self) -> u64 {
+        self.bus_width / 8
+    }
+
+    /// Maximum number of beats in a burst. AXI bursts may not cross a 4KB
+    /// boundary so, assuming that the host buffers are 4KB aligned, bursts
+    /// are limited to 4KB.
+    pub fn burst_len(&self) -> u64 {
+        self.max_burst_len.min(4096 / self.bus_bytes())
+    }
+}
+
+fn parse_opt(opt: &str, val: &str) -> CalyxResult<u64> {
+    val.parse().map_err(|_| {
+        Error::misc(format!("Expected a number in option `{}`", opt))
+    })
+}
+
+/// Number of bytes used by an element of a memory with elements of `width`
+/// bits in host memory. Elements are stored using the smallest power of two
+/// number of bytes that fits them so that the layout matches the C and numpy
+/// integer types used by the host.
+pub(crate) fn element_bytes(width: u64) -> u64 {
+    width.div_ceil(8).next_power_of_two()
+}
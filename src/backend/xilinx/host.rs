@@ -0,0 +1,264 @@
+use super::config::element_bytes;
+use super::stream_axi::external_streams;
+use super::toplevel::{external_memories, get_mem_info};
+use crate::backend::traits::Backend;
+use calyx::{
+    errors::{CalyxResult, Error},
+    ir,
+};
+use std::io;
+
+/// Backend that generates a C++ host program for the kernel produced by
+/// [`XilinxInterfaceBackend`](super::XilinxInterfaceBackend).
+///
+/// The host program uses the native XRT API to allocate one buffer for every
+/// `@external` memory, loads the buffers from a JSON data file, runs the
+/// kernel with arguments ordered as in the `kernel.xml` generated by
+/// [`XilinxXmlBackend`](super::XilinxXmlBackend), and writes the final
+/// contents of the memories back out as JSON.
+#[derive(Default)]
+pub struct XilinxHostBackend;
+
+impl Backend for XilinxHostBackend {
+    fn name(&self) -> &'static str {
+        "xilinx-host"
+    }
+
+    fn validate(prog: &ir::Context) -> CalyxResult<()> {
+        let toplevel = prog.entrypoint();
+        // XRT does not allow the host to read or write the streams of a
+        // kernel, so they can only be connected to other kernels.
+        if let Some(stream) = external_streams(toplevel)?.first() {
+            return Err(Error::misc(format!(
+                "Toplevel component has a stream `{}` but the host program only supports kernels without streams. Connect the streams to other kernels and write the host program by hand.",
+                stream.name
+            )));
+        }
+        let memories = external_memories(toplevel);
+        if memories.is_empty() {
+            return Err(Error::misc(
+                    "Program has no memories marked with attribute @external.".to_owned() +
+                    " Please make sure that at least one memory is marked as @external."));
+        }
+        // Buffers use the C integer type that matches the layout of the
+        // elements used by the AXI memory controllers.
+        for (mem, (width, _, _)) in memories.iter().zip(get_mem_info(toplevel))
+        {
+            if width > 64 {
+                return Err(Error::misc(format!(
+                    "External memory `{}` has width {} but the host program only supports memories with width of at most 64 bits.",
+                    mem, width
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    fn link_externs(
+        _prog: &ir::Context,
+        _write: &mut calyx::utils::OutputFile,
+    ) -> CalyxResult<()> {
+        Ok(())
+    }
+
+    fn emit(
+        prog: &ir::Context,
+        file: &mut calyx::utils::OutputFile,
+    ) -> CalyxResult<()> {
+        let toplevel = prog.entrypoint();
+        // XRT does not allow the host to read or write the streams of a
+        // kernel, so they can only be connected to other kernels.
+        if let Some(stream) = external_streams(toplevel)?.first() {
+            return Err(Error::misc(format!(
+                "Toplevel component has a stream `{}` but the host program only supports kernels without streams. Connect the streams to other kernels and write the host program by hand.",
+                stream.name
+            )));
+        }
+        let memories = external_memories(toplevel);
+        let mem_info = get_mem_info(toplevel);
+        let f = &mut file.get_write();
+
+        write!(f, "{}", PRELUDE)?;
+        writeln!(f, "int main(int argc, char **argv) {{")?;
+        writeln!(
+            f,
+            r#"  if (argc < 3) {{
+    std::cerr << "usage: " << argv[0]
+              << " <kernel.xclbin> <data.json> [out.json]" << std::endl;
+    return 1;
+  }}
+
+  xrt::device device(0);
+  auto uuid = device.load_xclbin(argv[1]);
+  xrt::kernel kernel(device, uuid, "Toplevel");
+
+  std::ifstream data_file(argv[2]);
+  json data = json::parse(data_file);
+"#
+        )?;
+
+        // Argument 0 is the timeout register, memories start at argument 1.
+        for (i, (mem, (width, size, _))) in
+            memories.iter().zip(mem_info.iter()).enumerate()
+        {
+            emit_buffer(f, mem, *width, *size, i + 1)?;
+        }
+
+        let args = memories
+            .iter()
+            .map(|mem| format!("{}_bo", mem))
+            .collect::<Vec<_>>()
+            .join(", ");
+        writeln!(
+            f,
+            r#"  // Arguments are ordered as in kernel.xml: timeout, then memories.
+  uint32_t timeout = 0;
+  auto run = kernel(timeout, {args});
+  run.wait();
+"#
+        )?;
+
+        writeln!(f, "  json out;")?;
+        for mem in &memories {
+            writeln!(
+                f,
+                "  {mem}_bo.sync(XCL_BO_SYNC_BO_FROM_DEVICE);\n  out[\"{mem}\"] = store_memory(data, \"{mem}\", {mem}_map, {mem}_size);"
+            )?;
+        }
+        writeln!(
+            f,
+            r#"
+  if (argc > 3) {{
+    std::ofstream out_file(argv[3]);
+    out_file << out.dump(2) << std::endl;
+  }} else {{
+    std::cout << out.dump(2) << std::endl;
+  }}
+  return 0;
+}}"#
+        )?;
+        Ok(())
+    }
+}
+
+/// Emits the allocation, loading, and synchronization of the buffer for
+/// memory `mem` bound to kernel argument `arg`.
+fn emit_buffer(
+    f: &mut dyn io::Write,
+    mem: &str,
+    width: u64,
+    size: u64,
+    arg: usize,
+) -> io::Result<()> {
+    let typ = format!("uint{}_t", 8 * element_bytes(width));
+    writeln!(
+        f,
+        r#"  // Memory `{mem}`: {size} element(s) of {width} bit(s).
+  const size_t {mem}_size = {size};
+  xrt::bo {mem}_bo(device, {mem}_size * sizeof({typ}), kernel.group_id({arg}));
+  auto {mem}_map = {mem}_bo.map<{typ} *>();
+  load_memory(data, "{mem}", {mem}_map, {mem}_size);
+  {mem}_bo.sync(XCL_BO_SYNC_BO_TO_DEVICE);
+"#
+    )
+}
+
+/// Includes and helper functions used by every host program. The helpers
+/// understand the data format used by `fud`: every memory is an object with
+/// a `data` array and a `format` describing how to interpret the values.
+const PRELUDE: &str = r#"// Host program for the Calyx `Toplevel` kernel.
+// Usage: host <kernel.xclbin> <data.json> [out.json]
+#include <cmath>
+#include <cstdint>
+#include <fstream>
+#include <iostream>
+#include <nlohmann/json.hpp>
+#include <stdexcept>
+#include <string>
+#include <vector>
+#include <xrt/xrt_bo.h>
+#include <xrt/xrt_device.h>
+#include <xrt/xrt_kernel.h>
+
+using json = nlohmann::json;
+
+// Number of fractional bits used by memories with a fixed point format.
+static int frac_width(const json &format) {
+  if (format.value("numeric_type", "bitnum") == "fixed_point") {
+    return format.value("frac_width", 0);
+  }
+  return 0;
+}
+
+// Flatten a (possibly nested) array of values into `out`.
+static void flatten(const json &values, std::vector<json> &out) {
+  if (values.is_array()) {
+    for (const auto &v : values) {
+      flatten(v, out);
+    }
+  } else {
+    out.push_back(values);
+  }
+}
+
+template <typename T>
+static void load_memory(const json &data, const std::string &name, T *buf,
+                        size_t size) {
+  if (!data.contains(name)) {
+    throw std::runtime_error("Data file does not contain memory " + name);
+  }
+  const json &mem = data.at(name);
+  json format = mem.value("format", json::object());
+  bool is_fixed = format.value("numeric_type", "bitnum") == "fixed_point";
+  bool is_signed = format.value("is_signed", false);
+  int frac = frac_width(format);
+  std::vector<json> values;
+  flatten(mem.at("data"), values);
+  if (values.size() != size) {
+    throw std::runtime_error("Memory " + name + " expects " +
+                             std::to_string(size) + " value(s) but got " +
+                             std::to_string(values.size()));
+  }
+  for (size_t i = 0; i < size; i++) {
+    // Integers are read exactly since a double cannot represent all 64-bit
+    // values.
+    if (is_fixed) {
+      double scaled = std::ldexp(values[i].get<double>(), frac);
+      buf[i] = static_cast<T>(static_cast<int64_t>(std::llround(scaled)));
+    } else if (is_signed) {
+      buf[i] = static_cast<T>(values[i].get<int64_t>());
+    } else {
+      buf[i] = static_cast<T>(values[i].get<uint64_t>());
+    }
+  }
+}
+
+template <typename T>
+static json store_memory(const json &data, const std::string &name,
+                         const T *buf, size_t size) {
+  json format = data.at(name).value("format", json::object());
+  bool is_signed = format.value("is_signed", false);
+  int width = format.value("width", static_cast<int>(8 * sizeof(T)));
+  int frac = frac_width(format);
+  json values = json::array();
+  for (size_t i = 0; i < size; i++) {
+    uint64_t raw = buf[i];
+    if (width < 64) {
+      raw &= (uint64_t{1} << width) - 1;
+    }
+    int64_t value = static_cast<int64_t>(raw);
+    if (is_signed && width > 0 && width < 64 && (raw >> (width - 1)) & 1) {
+      value -= int64_t{1} << width;
+    }
+    if (frac > 0) {
+      values.push_back(std::ldexp(static_cast<double>(value), -frac));
+    } else if (is_signed) {
+      values.push_back(value);
+    } else {
+      values.push_back(raw);
+    }
+  }
+  return {{"data", values}, {"format", format}};
+}
+
+"#;
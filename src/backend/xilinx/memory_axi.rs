@@ -2,6 +2,7 @@ use vast::v05::ast as v;
 
 use super::{
     axi::{AxiChannel, AxiInterface, ChannelDirection},
+    config::element_bytes,
     fsm,
 };
 use calyx::utils;
@@ -19,6 +20,7 @@ pub trait MemoryInterface {
         name: &str,
         bus_data_width: u64,
         bus_addr_width: u64,
+        max_burst_len: u64,
         data_width: u64,
         memory_size: u64,
         addr_width: u64,
@@ -97,6 +99,7 @@ impl MemoryInterface for AxiInterface {
         name: &str,
         bus_data_width: u64,
         bus_addr_width: u64,
+        max_burst_len: u64,
         data_width: u64,
         memory_size: u64,
         addr_width: u64,
@@ -107,7 +110,8 @@ impl MemoryInterface for AxiInterface {
         module.add_input("ARESET", 1);
 
         // add axi interface ports
-        let axi4 = AxiInterface::memory_channels(bus_addr_width, 512, "");
+        let axi4 =
+            AxiInterface::memory_channels(bus_addr_width, bus_data_width, "");
         axi4.add_ports_to(&mut module);
 
         module.add_input("BASE_ADDRESS", bus_addr_width);
@@ -123,6 +127,20 @@ impl MemoryInterface for AxiInterface {
         module.add_input("WE", 1);
         module.add_output("DONE", 1);
 
+        // Elements are packed into beats of the data bus. Every element uses
+        // `elem_width` bits of the bus and the memory is transferred using
+        // `num_beats` beats split into bursts of at most `max_burst_len`
+        // beats.
+        let layout = Layout {
+            elem_width: 8 * element_bytes(data_width),
+            data_width,
+            per_beat: bus_data_width / (8 * element_bytes(data_width)),
+            size: memory_size,
+            num_beats: memory_size
+                .div_ceil(bus_data_width / (8 * element_bytes(data_width))),
+            max_burst_len,
+        };
+
         // internal signals
         module.add_decl(v::Decl::new_wire("copy_done", 1));
         module.add_stmt(v::Parallel::Assign(
@@ -132,43 +150,14 @@ impl MemoryInterface for AxiInterface {
         module.add_decl(v::Decl::new_wire("send_done", 1));
         module.add_stmt(v::Parallel::Assign(
             "send_done".into(),
-            v::Expr::new_eq("send_addr_offset", memory_size as i32),
+            v::Expr::new_eq("send_beat_count", layout.num_beats as i32),
         ));
 
         // module mode fsm
         let mode_fsm = module_mode_fsm(&mut module);
-        // module.add_stmt(v::Parallel::Assign(
-        //     "SEND_TO_HOST_DONE".into(),
-        //     v::Expr::new_mux(mode_fsm.state_is("send"), "send_done", 0),
-        // ));
-
-        // count the number of read transactions we've received
-        module.add_decl(v::Decl::new_reg(
-            "read_txn_count",
-            utils::math::bits_needed_for(bus_data_width / data_width),
-        ));
-        module.add_stmt(super::utils::cond_non_blk_assign(
-            "ACLK",
-            "read_txn_count",
-            vec![
-                (Some("ARESET".into()), 0.into()),
-                (
-                    Some(axi4.read_data.handshake()),
-                    v::Expr::new_add("read_txn_count", 1),
-                ),
-            ],
-        ));
 
         // bram reading / writing logic
-        bram_logic(
-            name,
-            &axi4,
-            &mut module,
-            &mode_fsm,
-            "read_txn_count".into(),
-            data_width,
-            addr_width,
-        );
+        bram_logic(name, &mut module, &mode_fsm, &layout, addr_width);
         module.add_stmt(v::Parallel::Assign(
             "READ_DATA".into(),
             "bram_read_data".into(),
@@ -176,33 +165,13 @@ impl MemoryInterface for AxiInterface {
 
         // add 1 so offset can count up to memory size inclusively
         let offset_width = utils::math::bits_needed_for(memory_size) + 1;
+        let beats_width = utils::math::bits_needed_for(layout.num_beats) + 1;
 
-        // synchronise channels
-        let read_controller = axi4
-            .read_address
-            .then(&axi4.read_data)
-            .prefix("r")
-            .trigger(mode_fsm.next_state_is("copy"));
-        read_controller.emit(&mut module);
-
-        // increment copy address offset
-        module.add_decl(v::Decl::new_reg("copy_addr_offset", offset_width));
-        incr_addr(
-            &mut module,
-            mode_fsm.state_is("copy"),
-            "copy_addr_offset",
-            axi4.read_data.handshake(),
-        );
-
-        // addresses are byte addressed which means addresses are computed as
-        // base + (offset << shift_by)
-        // TODO(nathanielnrn): Fix the burst size and shifting values based on
-        // pynq(?) input? Or memory size? unclear.
-        let shift_by = 2;
-        let burst_size = v::Expr::new_ulit_dec(
-            3,
-            &utils::math::bits_needed_for(data_width / 8).to_string(),
-        );
+        // addresses are byte addressed and every beat transfers a full data
+        // bus, which means addresses are computed as
+        // base + (beat << log2(bus_data_width / 8))
+        let shift_by = utils::math::bits_needed_for(bus_data_width / 8);
+        let burst_size = v::Expr::new_ulit_dec(3, &shift_by.to_string());
         //AxBURST corresponds to type of burst as follows:
         // 0b00: Fixed
         // 0b01 (default): Incr
@@ -210,106 +179,433 @@ impl MemoryInterface for AxiInterface {
         // 0b11: Reserved
         let burst_type = v::Expr::new_ulit_bin(2, "01");
 
-        module.add_stmt(axi4.read_address.assign("ID", 0));
-
-        //add reg to store value of shift to circumvent order of operations
-        //issue on bit shift
-        let copy_shift = "copy_shift";
-        module.add_decl(v::Decl::new_wire(copy_shift, bus_addr_width));
-        let mut concat = v::ExprConcat::default();
-        concat.add_expr("copy_addr_offset");
-        concat.add_expr(v::Expr::new_repeat(
-            bus_addr_width - offset_width,
-            v::Expr::new_ulit_bin(1, "0"),
+        // ---- Copy from host ----
+        // number of beats that have been requested from the host
+        module.add_decl(v::Decl::new_reg("copy_beats", beats_width));
+        module.add_stmt(super::utils::cond_non_blk_assign(
+            "ACLK",
+            "copy_beats",
+            vec![
+                (Some("ARESET".into()), 0.into()),
+                (
+                    Some(axi4.read_address.handshake()),
+                    burst_end("copy_beats", axi4.read_address.get("LEN")),
+                ),
+            ],
         ));
+
+        // issue a burst whenever there are more beats to request and then
+        // wait for all of its beats.
+        let read_controller = fsm::LinearFsm::new("r", "ACLK", "ARESET")
+            .state(
+                "trigger",
+                &[],
+                v::Expr::new_logical_and(
+                    mode_fsm.state_is("copy"),
+                    v::Expr::new_lt("copy_beats", layout.num_beats as i32),
+                ),
+            )
+            .state(
+                "addr",
+                &[axi4.read_address.ready().into()],
+                axi4.read_address.handshake(),
+            )
+            .state(
+                "data",
+                &[],
+                v::Expr::new_bit_and(
+                    axi4.read_data.handshake(),
+                    axi4.read_data.get("LAST"),
+                ),
+            );
+        read_controller.emit(&mut module);
+        // only accept a new beat once the previous one has been written to
+        // the bram
         module.add_stmt(v::Parallel::Assign(
-            copy_shift.into(),
-            v::Expr::new_shift_left(concat, shift_by),
+            axi4.read_data.ready().into(),
+            v::Expr::new_logical_and(
+                read_controller.state_is("data"),
+                v::Expr::new_not("read_buffer_valid"),
+            ),
         ));
+
+        module.add_stmt(axi4.read_address.assign("ID", 0));
+        let copy_address = beat_address(
+            &mut module,
+            "copy_beats",
+            beats_width,
+            bus_addr_width,
+            shift_by,
+        );
+        module.add_stmt(axi4.read_address.assign("ADDR", copy_address));
         module.add_stmt(
             axi4.read_address
-                .assign("ADDR", v::Expr::new_add("BASE_ADDRESS", copy_shift)),
+                .assign("LEN", burst_len("copy_beats", &layout)),
         );
-        // Currently we do not utilize burst capabilities, therefore AxLEN is 0
-        module.add_stmt(axi4.read_address.assign("LEN", 0));
         module.add_stmt(axi4.read_address.assign("SIZE", burst_size.clone()));
         module.add_stmt(axi4.read_address.assign("BURST", burst_type.clone()));
 
-        let write_controller = axi4
-            .write_address
-            .then(&axi4.write_data)
-            .then(&axi4.write_response)
-            .prefix("w")
-            .trigger(mode_fsm.next_state_is("send"));
-        write_controller.emit(&mut module);
+        // unpack the elements of the last beat into the bram, one element
+        // every cycle
+        unpack_beat(&mut module, &axi4, &layout);
 
-        // increment send address offset
-        module.add_decl(v::Decl::new_reg("send_addr_offset", offset_width));
+        // increment copy address offset
+        module.add_decl(v::Decl::new_reg("copy_addr_offset", offset_width));
         incr_addr(
             &mut module,
-            mode_fsm.state_is("send"),
-            "send_addr_offset",
-            axi4.write_response.handshake(),
+            mode_fsm.state_is("copy"),
+            "copy_addr_offset",
+            "read_buffer_valid".into(),
         );
 
-        module.add_stmt(axi4.write_address.assign("ID", 0));
-        // assign shift to a wire to circumvent `vast` order of operations issues
-        //we shift to convert offset to byte length
-        let send_shift = "send_shift";
-        module.add_decl(v::Decl::new_wire(send_shift, bus_addr_width));
-        let mut concat = v::ExprConcat::default();
-        concat.add_expr("send_addr_offset");
-        concat.add_expr(v::Expr::new_repeat(
-            bus_addr_width - offset_width,
-            v::Expr::new_ulit_bin(1, "0"),
+        // ---- Send to host ----
+        // number of beats whose address has been sent to the host
+        module.add_decl(v::Decl::new_reg("send_beats", beats_width));
+        module.add_stmt(super::utils::cond_non_blk_assign(
+            "ACLK",
+            "send_beats",
+            vec![
+                (Some("ARESET".into()), 0.into()),
+                (
+                    Some(axi4.write_address.handshake()),
+                    burst_end("send_beats", axi4.write_address.get("LEN")),
+                ),
+            ],
         ));
+        // length of the current burst and number of beats sent in it
+        module.add_decl(v::Decl::new_reg("send_burst_len", 8));
+        module.add_stmt(super::utils::cond_non_blk_assign(
+            "ACLK",
+            "send_burst_len",
+            vec![(
+                Some(axi4.write_address.handshake()),
+                axi4.write_address.get("LEN").into(),
+            )],
+        ));
+        module.add_decl(v::Decl::new_reg("send_burst_beat", 8));
+        module.add_stmt(super::utils::cond_non_blk_assign(
+            "ACLK",
+            "send_burst_beat",
+            vec![
+                (Some(axi4.write_address.handshake()), 0.into()),
+                (
+                    Some(axi4.write_data.handshake()),
+                    v::Expr::new_add("send_burst_beat", 1),
+                ),
+            ],
+        ));
+        // number of beats acknowledged by the host
+        module.add_decl(v::Decl::new_reg("send_beat_count", beats_width));
+        module.add_stmt(super::utils::cond_non_blk_assign(
+            "ACLK",
+            "send_beat_count",
+            vec![
+                (Some("ARESET".into()), 0.into()),
+                (
+                    Some(axi4.write_response.handshake()),
+                    burst_end("send_beat_count", "send_burst_len"),
+                ),
+            ],
+        ));
+
+        let write_controller = fsm::LinearFsm::new("w", "ACLK", "ARESET")
+            .state(
+                "trigger",
+                &[],
+                v::Expr::new_logical_and(
+                    mode_fsm.state_is("send"),
+                    v::Expr::new_lt("send_beats", layout.num_beats as i32),
+                ),
+            )
+            .state(
+                "addr",
+                &[axi4.write_address.ready().into()],
+                axi4.write_address.handshake(),
+            )
+            .state(
+                "data",
+                &[],
+                v::Expr::new_bit_and(
+                    axi4.write_data.handshake(),
+                    axi4.write_data.get("LAST"),
+                ),
+            )
+            .state(
+                "resp",
+                &[axi4.write_response.ready().into()],
+                axi4.write_response.valid(),
+            );
+        write_controller.emit(&mut module);
+        // a beat can be sent once all of its elements have been read from
+        // the bram
         module.add_stmt(v::Parallel::Assign(
-            send_shift.into(),
-            v::Expr::new_shift_left(concat, shift_by),
+            axi4.write_data.ready().into(),
+            v::Expr::new_logical_and(
+                write_controller.state_is("data"),
+                "write_buffer_valid",
+            ),
         ));
 
+        module.add_stmt(axi4.write_address.assign("ID", 0));
+        let send_address = beat_address(
+            &mut module,
+            "send_beats",
+            beats_width,
+            bus_addr_width,
+            shift_by,
+        );
+        module.add_stmt(axi4.write_address.assign("ADDR", send_address));
         module.add_stmt(
             axi4.write_address
-                .assign("ADDR", v::Expr::new_add("BASE_ADDRESS", send_shift)),
+                .assign("LEN", burst_len("send_beats", &layout)),
         );
-        // Currently we do not utilize burst capabilities, therefore AxLEN is 0
-        module.add_stmt(axi4.write_address.assign("LEN", 0));
         module.add_stmt(axi4.write_address.assign("SIZE", burst_size));
         module.add_stmt(axi4.write_address.assign("BURST", burst_type));
 
+        // pack elements read from the bram into the next beat, one element
+        // every cycle
+        pack_beat(&mut module, &axi4, &mode_fsm, &layout);
+
+        // increment send address offset
+        module.add_decl(v::Decl::new_reg("send_addr_offset", offset_width));
+        incr_addr(
+            &mut module,
+            mode_fsm.state_is("send"),
+            "send_addr_offset",
+            "pack".into(),
+        );
+
         // write data channel
         module.add_stmt(axi4.write_data.assign("ID", 0));
-        let mut concat = v::ExprConcat::default();
-        concat.add_expr("bram_read_data");
-        concat.add_expr(v::Expr::new_repeat(
-            (bus_data_width / data_width) - 1,
-            v::Expr::new_ulit_bin(32, "0"),
+        module.add_stmt(axi4.write_data.assign("DATA", "write_buffer"));
+        // only write the bytes of the elements packed in this beat. WSTRB bits
+        // refer to entire bytes.
+        let wstrb = v::Expr::new_not(v::Expr::new_shift_left(
+            v::Expr::new_repeat(
+                bus_data_width / 8,
+                v::Expr::new_ulit_bin(1, "1"),
+            ),
+            v::Expr::new_mul("write_idx", (layout.elem_width / 8) as i32),
         ));
-        // used for alignment issues where host reads from WDATA at offset if
-        // AWADDR is not alligned with data-bus-width
-        let wdata = v::Expr::new_shift_left(
-            concat,
-            v::Expr::new_mul("send_addr_offset", data_width as i32),
-        );
-        module.add_stmt(axi4.write_data.assign("DATA", wdata));
-        let mut concat = v::ExprConcat::default();
-        concat.add_expr(v::Expr::new_ulit_hex(4, "F"));
-        concat.add_expr(v::Expr::new_repeat(
-            (bus_data_width / (8 * 4)) - 1,
-            v::Expr::new_ulit_hex(4, "0"),
-        ));
-        let wstrb = v::Expr::new_shift_left(
-            concat,
-            //divided by 8 because WSTRB bits refer to entire bytes
-            v::Expr::new_mul("send_addr_offset", (data_width / 8) as i32),
-        );
         module.add_stmt(axi4.write_data.assign("STRB", wstrb));
-        module.add_stmt(axi4.write_data.assign("LAST", 1));
+        module.add_stmt(axi4.write_data.assign(
+            "LAST",
+            v::Expr::new_eq("send_burst_beat", "send_burst_len"),
+        ));
 
         module
     }
 }
 
+/// Describes how the elements of a memory are laid out in beats on the AXI
+/// data bus.
+struct Layout {
+    /// Number of bits of the bus used by every element.
+    elem_width: u64,
+    /// Width of the elements of the memory.
+    data_width: u64,
+    /// Number of elements transferred in every beat.
+    per_beat: u64,
+    /// Number of elements in the memory.
+    size: u64,
+    /// Number of beats needed to transfer the memory.
+    num_beats: u64,
+    /// Maximum number of beats in a burst.
+    max_burst_len: u64,
+}
+
+/// The beat counter `beats` after a burst with `AxLEN` of `len` completes.
+fn burst_end<E: Into<v::Expr>>(beats: &str, len: E) -> v::Expr {
+    v::Expr::new_add(v::Expr::new_add(beats, len), 1)
+}
+
+/// `AxLEN` for the next burst which starts at beat `beats`. This is one less
+/// than the number of beats in the burst.
+fn burst_len(beats: &str, layout: &Layout) -> v::Expr {
+    let remaining = v::Expr::new_sub(layout.num_beats as i32, beats);
+    v::Expr::new_mux(
+        v::Expr::new_gt(remaining.clone(), layout.max_burst_len as i32),
+        v::Expr::new_int(layout.max_burst_len as i32 - 1),
+        v::Expr::new_sub(remaining, 1),
+    )
+}
+
+/// Generates the byte address of the beat counted by `beats`.
+fn beat_address(
+    module: &mut v::Module,
+    beats: &str,
+    beats_width: u64,
+    bus_addr_width: u64,
+    shift_by: u64,
+) -> v::Expr {
+    //add wire to store value of shift to circumvent order of operations
+    //issue on bit shift
+    let shift = format!("{}_shift", beats);
+    module.add_decl(v::Decl::new_wire(&shift, bus_addr_width));
+    let mut concat = v::ExprConcat::default();
+    concat.add_expr(beats);
+    concat.add_expr(v::Expr::new_repeat(
+        bus_addr_width - beats_width,
+        v::Expr::new_ulit_bin(1, "0"),
+    ));
+    module.add_stmt(v::Parallel::Assign(
+        shift.as_str().into(),
+        v::Expr::new_shift_left(concat, shift_by as i32),
+    ));
+    v::Expr::new_add("BASE_ADDRESS", shift.as_str())
+}
+
+/// Registers holding the last beat received from the host while its elements
+/// are written to the bram. `read_buffer_valid` is high while there are
+/// elements left to write and `read_idx` is the element being written.
+fn unpack_beat(module: &mut v::Module, axi4: &AxiInterface, layout: &Layout) {
+    let bus_width = layout.per_beat * layout.elem_width;
+    module.add_decl(v::Decl::new_reg("read_buffer", bus_width));
+    module.add_stmt(super::utils::cond_non_blk_assign(
+        "ACLK",
+        "read_buffer",
+        vec![(
+            Some(axi4.read_data.handshake()),
+            axi4.read_data.get("DATA").into(),
+        )],
+    ));
+
+    module.add_decl(v::Decl::new_reg(
+        "read_idx",
+        utils::math::bits_needed_for(layout.per_beat),
+    ));
+    module.add_stmt(super::utils::cond_non_blk_assign(
+        "ACLK",
+        "read_idx",
+        vec![
+            (Some("ARESET".into()), 0.into()),
+            (Some(axi4.read_data.handshake()), 0.into()),
+            (
+                Some("read_buffer_valid".into()),
+                v::Expr::new_add("read_idx", 1),
+            ),
+        ],
+    ));
+
+    // the last beat may only be partially filled
+    module.add_decl(v::Decl::new_reg("read_buffer_valid", 1));
+    module.add_stmt(super::utils::cond_non_blk_assign(
+        "ACLK",
+        "read_buffer_valid",
+        vec![
+            (Some("ARESET".into()), 0.into()),
+            (Some(axi4.read_data.handshake()), 1.into()),
+            (
+                Some(v::Expr::new_logical_and(
+                    "read_buffer_valid",
+                    v::Expr::new_logical_or(
+                        v::Expr::new_eq("read_idx", layout.per_beat as i32 - 1),
+                        v::Expr::new_eq(
+                            "copy_addr_offset",
+                            layout.size as i32 - 1,
+                        ),
+                    ),
+                )),
+                0.into(),
+            ),
+        ],
+    ));
+}
+
+/// Registers holding the next beat sent to the host while elements are read
+/// from the bram into it. `pack` is high when an element is added to the
+/// beat, `write_idx` counts the elements in the beat, and
+/// `write_buffer_valid` is high once the beat is complete.
+fn pack_beat(
+    module: &mut v::Module,
+    axi4: &AxiInterface,
+    mode_fsm: &fsm::LinearFsm,
+    layout: &Layout,
+) {
+    let bus_width = layout.per_beat * layout.elem_width;
+    module.add_decl(v::Decl::new_wire("pack", 1));
+    module.add_stmt(v::Parallel::Assign(
+        "pack".into(),
+        v::Expr::new_logical_and(
+            v::Expr::new_logical_and(
+                mode_fsm.state_is("send"),
+                v::Expr::new_not("write_buffer_valid"),
+            ),
+            v::Expr::new_lt("send_addr_offset", layout.size as i32),
+        ),
+    ));
+
+    // elements are zero extended to fill their part of the bus
+    let elem: v::Expr = if layout.elem_width > layout.data_width {
+        let mut concat = v::ExprConcat::default();
+        concat.add_expr("bram_read_data");
+        concat.add_expr(v::Expr::new_repeat(
+            layout.elem_width - layout.data_width,
+            v::Expr::new_ulit_bin(1, "0"),
+        ));
+        concat.into()
+    } else {
+        "bram_read_data".into()
+    };
+    module.add_decl(v::Decl::new_reg("write_buffer", bus_width));
+    module.add_stmt(super::utils::cond(
+        "ACLK",
+        vec![(
+            Some("pack".into()),
+            v::Sequential::new_nonblk_assign(
+                v::Expr::new_index_slice(
+                    "write_buffer",
+                    v::Expr::new_mul("write_idx", layout.elem_width as i32),
+                    layout.elem_width as u32,
+                ),
+                elem,
+            ),
+        )],
+    ));
+
+    // counts up to the number of elements in a beat inclusively
+    module.add_decl(v::Decl::new_reg(
+        "write_idx",
+        utils::math::bits_needed_for(layout.per_beat + 1),
+    ));
+    module.add_stmt(super::utils::cond_non_blk_assign(
+        "ACLK",
+        "write_idx",
+        vec![
+            (Some("ARESET".into()), 0.into()),
+            (Some(axi4.write_data.handshake()), 0.into()),
+            (Some("pack".into()), v::Expr::new_add("write_idx", 1)),
+        ],
+    ));
+
+    // the last beat may only be partially filled
+    module.add_decl(v::Decl::new_reg("write_buffer_valid", 1));
+    module.add_stmt(super::utils::cond_non_blk_assign(
+        "ACLK",
+        "write_buffer_valid",
+        vec![
+            (Some("ARESET".into()), 0.into()),
+            (Some(axi4.write_data.handshake()), 0.into()),
+            (
+                Some(v::Expr::new_logical_and(
+                    "pack",
+                    v::Expr::new_logical_or(
+                        v::Expr::new_eq(
+                            "write_idx",
+                            layout.per_beat as i32 - 1,
+                        ),
+                        v::Expr::new_eq(
+                            "send_addr_offset",
+                            layout.size as i32 - 1,
+                        ),
+                    ),
+                )),
+                1.into(),
+            ),
+        ],
+    ));
+}
+
 fn module_mode_fsm(module: &mut v::Module) -> fsm::LinearFsm {
     // states:
     //  0: idle, start when COPY_TO_HOST
@@ -328,17 +624,15 @@ fn module_mode_fsm(module: &mut v::Module) -> fsm::LinearFsm {
 
 fn bram_logic(
     name: &str, //assumed to be of form [Memory_controller_axi_<suffix>]
-    axi4: &AxiInterface,
     module: &mut v::Module,
     mode_fsm: &fsm::LinearFsm,
-    txn_count: v::Expr,
-    data_width: u64,
+    layout: &Layout,
     addr_width: u64,
 ) {
     module.add_decl(v::Decl::new_wire("bram_addr", addr_width));
-    module.add_decl(v::Decl::new_wire("bram_write_data", data_width));
+    module.add_decl(v::Decl::new_wire("bram_write_data", layout.data_width));
     module.add_decl(v::Decl::new_wire("bram_we", 1));
-    module.add_decl(v::Decl::new_wire("bram_read_data", data_width));
+    module.add_decl(v::Decl::new_wire("bram_read_data", layout.data_width));
     module.add_decl(v::Decl::new_wire("bram_done", 1));
     let suffix_idx = "Memory_controller_axi_".len();
     let suffix = &name[suffix_idx..];
@@ -360,10 +654,7 @@ fn bram_logic(
     let send_address =
         v::Expr::new_slice("send_addr_offset", (addr_width - 1) as i32, 0);
     let mux_address = v::Expr::new_mux(
-        v::Expr::new_logical_and(
-            axi4.read_data.handshake(),
-            mode_fsm.state_is("copy"),
-        ),
+        mode_fsm.state_is("copy"),
         copy_address,
         v::Expr::new_mux(
             mode_fsm.state_is("bram"),
@@ -374,13 +665,10 @@ fn bram_logic(
     module.add_stmt(v::Parallel::Assign("bram_addr".into(), mux_address));
 
     // bram write enable
-    let copy_we: v::Expr = 1.into();
+    let copy_we: v::Expr = "read_buffer_valid".into();
     let bram_we: v::Expr = "WE".into();
     let mux_we = v::Expr::new_mux(
-        v::Expr::new_logical_and(
-            axi4.read_data.handshake(),
-            mode_fsm.state_is("copy"),
-        ),
+        mode_fsm.state_is("copy"),
         copy_we,
         v::Expr::new_mux(mode_fsm.state_is("bram"), bram_we, 0),
     );
@@ -388,16 +676,13 @@ fn bram_logic(
 
     // bram write data
     let copy_data: v::Expr = v::Expr::new_index_slice(
-        &axi4.read_data.get("DATA"),
-        v::Expr::new_mul(txn_count, 32),
-        data_width as u32, // bram data width
+        "read_buffer",
+        v::Expr::new_mul("read_idx", layout.elem_width as i32),
+        layout.data_width as u32, // bram data width
     );
     let bram_data: v::Expr = "WRITE_DATA".into();
     let mux_data = v::Expr::new_mux(
-        v::Expr::new_logical_and(
-            axi4.read_data.handshake(),
-            mode_fsm.state_is("copy"),
-        ),
+        mode_fsm.state_is("copy"),
         copy_data,
         v::Expr::new_mux(mode_fsm.state_is("bram"), bram_data, 0),
     );
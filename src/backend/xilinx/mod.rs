@@ -1,12 +1,18 @@
 //! Backend for generating synthesiable code for Xilinx FPGAs
 mod axi;
 mod axi_address_space;
+mod config;
 mod control_axi;
 mod fsm;
+mod host;
 mod memory_axi;
+#[cfg(test)]
+mod sim;
+mod stream_axi;
 mod toplevel;
 mod utils;
 mod xml;
 
+pub use host::XilinxHostBackend;
 pub use toplevel::XilinxInterfaceBackend;
 pub use xml::XilinxXmlBackend;
@@ -0,0 +1,334 @@
+//! Models of the host side of the AXI interfaces of the Xilinx wrapper: an
+//! AXI-Lite master that accesses the control registers and AXI slaves that
+//! serve memory requests from a local memory stand-in.
+use super::Simulator;
+use ibig::UBig;
+use std::collections::{HashMap, VecDeque};
+
+/// Operations performed by the AXI-Lite master.
+#[derive(Clone, Copy, Debug)]
+pub enum LiteOp {
+    Write(u64, u32),
+    Read(u64),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum LiteState {
+    Idle,
+    WriteAddr,
+    WriteData,
+    WriteResp,
+    ReadAddr,
+    ReadData,
+}
+
+/// AXI-Lite master driving the `s_axi_control` interface.
+pub struct AxiLiteMaster {
+    prefix: String,
+    ops: VecDeque<LiteOp>,
+    state: LiteState,
+    /// Values returned by completed reads.
+    pub reads: Vec<(u64, u32)>,
+}
+
+impl AxiLiteMaster {
+    pub fn new(prefix: &str) -> Self {
+        Self {
+            prefix: prefix.to_string(),
+            ops: VecDeque::new(),
+            state: LiteState::Idle,
+            reads: vec![],
+        }
+    }
+
+    pub fn push(&mut self, op: LiteOp) {
+        self.ops.push_back(op);
+    }
+
+    /// True when all operations have completed.
+    pub fn is_idle(&self) -> bool {
+        self.state == LiteState::Idle && self.ops.is_empty()
+    }
+
+    fn sig(&self, name: &str) -> String {
+        format!("{}{}", self.prefix, name)
+    }
+
+    /// Drive the inputs of the design for the current cycle.
+    pub fn drive(&mut self, sim: &mut Simulator) {
+        if self.state == LiteState::Idle {
+            self.state = match self.ops.front() {
+                Some(LiteOp::Write(..)) => LiteState::WriteAddr,
+                Some(LiteOp::Read(..)) => LiteState::ReadAddr,
+                None => LiteState::Idle,
+            };
+        }
+        let (addr, data) = match self.ops.front() {
+            Some(LiteOp::Write(addr, data)) => (*addr, *data),
+            Some(LiteOp::Read(addr)) => (*addr, 0),
+            None => (0, 0),
+        };
+        let is = |s: LiteState| u64::from(self.state == s);
+        sim.set(&self.sig("AWVALID"), is(LiteState::WriteAddr));
+        sim.set(&self.sig("AWADDR"), addr);
+        sim.set(&self.sig("WVALID"), is(LiteState::WriteData));
+        sim.set(&self.sig("WDATA"), data);
+        sim.set(&self.sig("BREADY"), is(LiteState::WriteResp));
+        sim.set(&self.sig("ARVALID"), is(LiteState::ReadAddr));
+        sim.set(&self.sig("ARADDR"), addr);
+        sim.set(&self.sig("RREADY"), is(LiteState::ReadData));
+    }
+
+    /// Observe the outputs of the design before the clock edge and advance
+    /// the state of the master.
+    pub fn observe(&mut self, sim: &Simulator) {
+        let hs = |valid: &str, ready: &str| {
+            sim.get_u64(&self.sig(valid)) == 1
+                && sim.get_u64(&self.sig(ready)) == 1
+        };
+        self.state = match self.state {
+            LiteState::WriteAddr if hs("AWVALID", "AWREADY") => {
+                LiteState::WriteData
+            }
+            LiteState::WriteData if hs("WVALID", "WREADY") => {
+                LiteState::WriteResp
+            }
+            LiteState::WriteResp if hs("BVALID", "BREADY") => {
+                self.ops.pop_front();
+                LiteState::Idle
+            }
+            LiteState::ReadAddr if hs("ARVALID", "ARREADY") => {
+                LiteState::ReadData
+            }
+            LiteState::ReadData if hs("RVALID", "RREADY") => {
+                if let Some(LiteOp::Read(addr)) = self.ops.pop_front() {
+                    self.reads
+                        .push((addr, sim.get_u64(&self.sig("RDATA")) as u32));
+                }
+                LiteState::Idle
+            }
+            st => st,
+        };
+    }
+}
+
+/// An outstanding burst on a read or write channel.
+#[derive(Clone, Copy, Debug)]
+struct Burst {
+    /// Address of the next beat.
+    addr: u64,
+    /// Number of bytes transferred by every beat.
+    size: u64,
+    /// Number of beats left in the burst.
+    beats: u64,
+}
+
+impl Burst {
+    /// Decode the burst requested on the address channel `prefix`.
+    fn decode(sim: &Simulator, prefix: &str, bus_bytes: u64) -> Self {
+        let burst = Burst {
+            addr: sim.get_u64(&format!("{}ADDR", prefix)),
+            size: 1 << sim.get_u64(&format!("{}SIZE", prefix)),
+            beats: sim.get_u64(&format!("{}LEN", prefix)) + 1,
+        };
+        assert_eq!(sim.get_u64(&format!("{}BURST", prefix)), 1, "INCR burst");
+        assert!(burst.size <= bus_bytes, "Beat is wider than the bus");
+        assert_eq!(burst.addr % burst.size, 0, "Unaligned burst");
+        assert!(
+            burst.addr % 4096 + burst.beats * burst.size <= 4096,
+            "Burst crosses a 4KB boundary"
+        );
+        burst
+    }
+
+    /// Advance to the next beat and return true if the burst is complete.
+    fn next(&mut self) -> bool {
+        self.addr += self.size;
+        self.beats -= 1;
+        self.beats == 0
+    }
+}
+
+/// AXI slave serving the memory requests of an `m<idx>_axi` interface from a
+/// byte-addressed host memory. Supports INCR bursts and narrow transfers.
+pub struct AxiMemory {
+    prefix: String,
+    bus_bytes: u64,
+    read: Option<Burst>,
+    write: Option<Burst>,
+    write_resp: bool,
+}
+
+impl AxiMemory {
+    pub fn new(prefix: &str, bus_width: u64) -> Self {
+        Self {
+            prefix: prefix.to_string(),
+            bus_bytes: bus_width / 8,
+            read: None,
+            write: None,
+            write_resp: false,
+        }
+    }
+
+    fn sig(&self, name: &str) -> String {
+        format!("{}{}", self.prefix, name)
+    }
+
+    pub fn drive(&self, sim: &mut Simulator, mem: &HashMap<u64, u8>) {
+        sim.set(&self.sig("ARREADY"), u64::from(self.read.is_none()));
+        sim.set(&self.sig("RVALID"), u64::from(self.read.is_some()));
+        sim.set(
+            &self.sig("RLAST"),
+            u64::from(matches!(self.read, Some(b) if b.beats == 1)),
+        );
+        // Transfers place the data on the byte lanes selected by the
+        // address within the data bus.
+        let rdata = self.read.map_or(UBig::from(0u8), |burst| {
+            let lane = burst.addr % self.bus_bytes;
+            (0..burst.size).fold(UBig::from(0u8), |acc, i| {
+                let byte = *mem.get(&(burst.addr + i)).unwrap_or(&0);
+                acc | (UBig::from(byte) << (8 * (lane + i) as usize))
+            })
+        });
+        sim.set(&self.sig("RDATA"), rdata);
+        sim.set(
+            &self.sig("AWREADY"),
+            u64::from(self.write.is_none() && !self.write_resp),
+        );
+        sim.set(&self.sig("WREADY"), u64::from(self.write.is_some()));
+        sim.set(&self.sig("BVALID"), u64::from(self.write_resp));
+    }
+
+    pub fn observe(&mut self, sim: &Simulator, mem: &mut HashMap<u64, u8>) {
+        let hs = |valid: &str, ready: &str| {
+            sim.get_u64(&self.sig(valid)) == 1
+                && sim.get_u64(&self.sig(ready)) == 1
+        };
+        let (ar, r) = (hs("ARVALID", "ARREADY"), hs("RVALID", "RREADY"));
+        let (aw, w, b) = (
+            hs("AWVALID", "AWREADY"),
+            hs("WVALID", "WREADY"),
+            hs("BVALID", "BREADY"),
+        );
+        if let Some(burst) = &mut self.read {
+            if r && burst.next() {
+                self.read = None;
+            }
+        } else if ar {
+            self.read =
+                Some(Burst::decode(sim, &self.sig("AR"), self.bus_bytes));
+        }
+
+        if self.write_resp && b {
+            self.write_resp = false;
+        }
+        let wlast = sim.get_u64(&self.sig("WLAST")) == 1;
+        let data = sim.get(&self.sig("WDATA"));
+        let strb = sim.get(&self.sig("WSTRB"));
+        let bus_bytes = self.bus_bytes;
+        if let Some(burst) = &mut self.write {
+            if w {
+                assert_eq!(
+                    wlast,
+                    burst.beats == 1,
+                    "WLAST does not match the burst length"
+                );
+                // Write the bytes enabled by the strobe to the bus-aligned
+                // address.
+                let line = burst.addr - burst.addr % bus_bytes;
+                for lane in 0..bus_bytes as usize {
+                    if strb.bit(lane) {
+                        let byte = (&data >> (8 * lane)) & UBig::from(0xffu8);
+                        mem.insert(
+                            line + lane as u64,
+                            u8::try_from(&byte).unwrap(),
+                        );
+                    }
+                }
+                if burst.next() {
+                    self.write = None;
+                    self.write_resp = true;
+                }
+            }
+        } else if !self.write_resp && aw {
+            self.write =
+                Some(Burst::decode(sim, &self.sig("AW"), self.bus_bytes));
+        }
+    }
+}
+
+/// AXI4-Stream master that sends a sequence of transfers to an `<x>_axis`
+/// slave interface.
+pub struct AxiStreamSource {
+    prefix: String,
+    /// Transfers that have not been accepted yet along with their `TLAST`.
+    pub pending: VecDeque<(u64, bool)>,
+}
+
+impl AxiStreamSource {
+    pub fn new(prefix: &str) -> Self {
+        Self {
+            prefix: prefix.to_string(),
+            pending: VecDeque::new(),
+        }
+    }
+
+    fn sig(&self, name: &str) -> String {
+        format!("{}{}", self.prefix, name)
+    }
+
+    pub fn drive(&self, sim: &mut Simulator) {
+        let (data, last) = self.pending.front().copied().unwrap_or((0, false));
+        sim.set(&self.sig("TVALID"), u64::from(!self.pending.is_empty()));
+        sim.set(&self.sig("TDATA"), data);
+        sim.set(&self.sig("TLAST"), u64::from(last));
+    }
+
+    pub fn observe(&mut self, sim: &Simulator) {
+        if sim.get_u64(&self.sig("TVALID")) == 1
+            && sim.get_u64(&self.sig("TREADY")) == 1
+        {
+            self.pending.pop_front();
+        }
+    }
+}
+
+/// AXI4-Stream slave that records the transfers sent by an `<x>_axis`
+/// master interface. The slave is only ready every `period` cycles to
+/// exercise back pressure.
+pub struct AxiStreamSink {
+    prefix: String,
+    period: u64,
+    /// Received transfers along with their `TLAST`.
+    pub received: Vec<(u64, bool)>,
+}
+
+impl AxiStreamSink {
+    pub fn new(prefix: &str, period: u64) -> Self {
+        Self {
+            prefix: prefix.to_string(),
+            period,
+            received: vec![],
+        }
+    }
+
+    fn sig(&self, name: &str) -> String {
+        format!("{}{}", self.prefix, name)
+    }
+
+    pub fn drive(&self, sim: &mut Simulator) {
+        let ready = sim.cycles().is_multiple_of(self.period);
+        sim.set(&self.sig("TREADY"), u64::from(ready));
+    }
+
+    pub fn observe(&mut self, sim: &Simulator) {
+        if sim.get_u64(&self.sig("TVALID")) == 1
+            && sim.get_u64(&self.sig("TREADY")) == 1
+        {
+            self.received.push((
+                sim.get_u64(&self.sig("TDATA")),
+                sim.get_u64(&self.sig("TLAST")) == 1,
+            ));
+        }
+    }
+}
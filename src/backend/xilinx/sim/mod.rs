@@ -0,0 +1,893 @@
+//! A cycle-based simulator for the subset of Verilog generated by the Xilinx
+//! backend.
+//!
+//! The simulator flattens a module hierarchy described using `vast` into a
+//! set of signals, continuous assignments, combinational `always @(*)` blocks,
+//! and sequential `always @(posedge ..)` blocks. All sequential blocks are
+//! assumed to be in the same clock domain. Modules that are not defined in the
+//! hierarchy (such as the Calyx kernel) are implemented by a [BlackBox]
+//! written in Rust.
+//!
+//! This is only meant to test the generated wrappers without vendor tools and
+//! does not implement four-state logic: all registers start out as zero.
+//! Constructs outside of the supported subset are reported as errors when the
+//! simulator is constructed.
+mod axi_host;
+mod tests;
+
+use calyx::errors::{CalyxResult, Error};
+use ibig::{ubig, UBig};
+use std::collections::HashMap;
+use vast::v05::ast as v;
+
+/// Behavioral model for a module instantiated in the simulated hierarchy.
+pub(crate) trait BlackBox {
+    /// Compute the values of the output ports using the current inputs.
+    fn comb(&self, ports: &Ports) -> Vec<(String, u64)>;
+    /// Update the internal state of the model at the rising clock edge.
+    fn posedge(&mut self, ports: &Ports);
+}
+
+/// Gives a [BlackBox] access to the values of the ports of its instance.
+pub(crate) struct Ports<'a> {
+    sim: &'a Simulator,
+    conns: &'a HashMap<String, Expr>,
+}
+
+impl Ports<'_> {
+    /// The current value of the signal connected to `port`. Unconnected ports
+    /// have the value 0.
+    pub fn get(&self, port: &str) -> u64 {
+        self.conns
+            .get(port)
+            .map(|e| to_u64(&self.sim.eval(e)))
+            .unwrap_or(0)
+    }
+}
+
+/// Binary operators supported by the simulator. Bit and array indexing are
+/// lowered to [Expr::Part] and [Expr::Elem] instead.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum BinOp {
+    LogOr,
+    LogAnd,
+    Add,
+    Sub,
+    Mul,
+    Gt,
+    Lt,
+    Geq,
+    Leq,
+    Equal,
+    NotEqual,
+    BitAnd,
+    BitOr,
+    ShiftLeft,
+}
+
+/// Error for Verilog constructs the simulator does not implement.
+fn unsupported<S: std::fmt::Display>(what: S) -> Error {
+    Error::misc(format!("Cannot simulate {}", what))
+}
+
+/// The name of an operator or radix in a `vast` expression. `vast` declares
+/// these types in a private module so they cannot be named in patterns; the
+/// lowering instead matches on the names of all of their variants.
+fn variant<T: std::fmt::Debug>(op: &T) -> String {
+    format!("{:?}", op)
+}
+
+/// Expressions with names resolved to signal indices.
+#[derive(Clone, Debug)]
+enum Expr {
+    Sig(usize),
+    Const(UBig, u64),
+    Not(Box<Expr>),
+    LogNot(Box<Expr>),
+    /// Reduction or (`|e`), reduction and (`&e`) and reduction xor (`^e`).
+    /// The negated reductions are lowered to [Expr::LogNot] of these.
+    ReduceOr(Box<Expr>),
+    ReduceAnd(Box<Expr>),
+    ReduceXor(Box<Expr>),
+    Bin(BinOp, Box<Expr>, Box<Expr>),
+    Mux(Box<Expr>, Box<Expr>, Box<Expr>),
+    /// Read from an element of an array.
+    Elem(usize, Box<Expr>),
+    /// `e[lo +: width]`
+    Part(Box<Expr>, Box<Expr>, u64),
+    Concat(Vec<Expr>),
+    Repeat(u64, Box<Expr>),
+}
+
+/// Assignment targets with names resolved to signal indices.
+#[derive(Clone, Debug)]
+enum Lhs {
+    Sig(usize),
+    /// `sig[lo +: width]`
+    Part(usize, Expr, u64),
+    Elem(usize, Expr),
+}
+
+#[derive(Clone, Debug)]
+enum Stmt {
+    Assign(Lhs, Expr, bool),
+    If(Expr, Vec<Stmt>, Vec<Stmt>),
+    Case(Expr, Vec<(Expr, Vec<Stmt>)>, Vec<Stmt>),
+}
+
+/// Assignment target after evaluating its indices.
+enum Target {
+    Sig(usize, u64, u64),
+    Elem(usize, usize),
+}
+
+struct BlackBoxInst {
+    model: Box<dyn BlackBox>,
+    conns: HashMap<String, Expr>,
+}
+
+/// Simulator for a flattened module hierarchy.
+pub(crate) struct Simulator {
+    names: HashMap<String, usize>,
+    widths: Vec<u64>,
+    values: Vec<UBig>,
+    array_names: HashMap<String, usize>,
+    arrays: Vec<(u64, Vec<UBig>)>,
+    assigns: Vec<(Lhs, Expr)>,
+    comb: Vec<Vec<Stmt>>,
+    seq: Vec<Vec<Stmt>>,
+    black_boxes: Vec<BlackBoxInst>,
+    cycles: u64,
+}
+
+fn mask(width: u64) -> UBig {
+    (ubig!(1) << width as usize) - ubig!(1)
+}
+
+fn to_u64(val: &UBig) -> u64 {
+    u64::try_from(&(val & mask(64))).unwrap()
+}
+
+fn is_true(val: &UBig) -> bool {
+    *val != ubig!(0)
+}
+
+fn ty_width(ty: &v::Ty) -> u64 {
+    match ty {
+        v::Ty::Int => 32,
+        v::Ty::Width(w) => *w,
+    }
+}
+
+impl Simulator {
+    /// Construct a simulator for the module `top`. Instances of modules that
+    /// are not defined in `modules` must be implemented by a black box
+    /// returned by `black_box`.
+    pub fn new<F>(
+        modules: &[v::Module],
+        top: &str,
+        mut black_box: F,
+    ) -> CalyxResult<Self>
+    where
+        F: FnMut(&str, &str) -> Option<Box<dyn BlackBox>>,
+    {
+        let mut sim = Simulator {
+            names: HashMap::new(),
+            widths: vec![],
+            values: vec![],
+            array_names: HashMap::new(),
+            arrays: vec![],
+            assigns: vec![],
+            comb: vec![],
+            seq: vec![],
+            black_boxes: vec![],
+            cycles: 0,
+        };
+        let modules: HashMap<&str, &v::Module> =
+            modules.iter().map(|m| (m.name.as_str(), m)).collect();
+        let top_module = modules.get(top).ok_or_else(|| {
+            Error::misc(format!("No definition for module `{}'", top))
+        })?;
+        sim.elaborate(&modules, top_module, "", &mut black_box)?;
+        Ok(sim)
+    }
+
+    /// Number of rising clock edges simulated so far.
+    pub fn cycles(&self) -> u64 {
+        self.cycles
+    }
+
+    /// Current value of the signal `name`. Signals in instances are named
+    /// using the instance path, for example `inst_control_axi.int_ap_start`.
+    pub fn get(&self, name: &str) -> UBig {
+        self.values[self.index(name)].clone()
+    }
+
+    pub fn get_u64(&self, name: &str) -> u64 {
+        to_u64(&self.get(name))
+    }
+
+    /// Set the value of the signal `name`. Should only be used for the
+    /// inputs of the top-level module.
+    pub fn set<V: Into<UBig>>(&mut self, name: &str, val: V) {
+        let idx = self.index(name);
+        self.write(&Target::Sig(idx, 0, self.widths[idx]), val.into());
+    }
+
+    /// Read the contents of an element of an array.
+    pub fn get_elem(&self, name: &str, idx: usize) -> UBig {
+        self.arrays[self.array_names[name]].1[idx].clone()
+    }
+
+    /// Index of the signal `name`. Panics if there is no such signal, which
+    /// is only used for the names given to the public accessors.
+    fn index(&self, name: &str) -> usize {
+        self.lookup(name)
+            .unwrap_or_else(|e| panic!("{}", e.message()))
+    }
+
+    fn lookup(&self, name: &str) -> CalyxResult<usize> {
+        self.names
+            .get(name)
+            .copied()
+            .ok_or_else(|| Error::misc(format!("Unknown signal `{}'", name)))
+    }
+
+    fn declare(&mut self, name: String, width: u64) {
+        self.names.insert(name, self.widths.len());
+        self.widths.push(width);
+        self.values.push(ubig!(0));
+    }
+
+    fn declare_decl(
+        &mut self,
+        prefix: &str,
+        decl: &v::Decl,
+    ) -> CalyxResult<()> {
+        match decl {
+            v::Decl::Int(id, ty)
+            | v::Decl::Wire(id, ty)
+            | v::Decl::Reg(id, ty) => {
+                self.declare(format!("{}{}", prefix, id), ty_width(ty))
+            }
+            // Arrays with a single element are printed as plain registers.
+            v::Decl::Array(id, width, size) if ty_width(size) == 1 => {
+                self.declare(format!("{}{}", prefix, id), ty_width(width))
+            }
+            v::Decl::Array(id, width, size) => {
+                self.array_names
+                    .insert(format!("{}{}", prefix, id), self.arrays.len());
+                self.arrays.push((
+                    ty_width(width),
+                    vec![ubig!(0); ty_width(size) as usize],
+                ));
+            }
+            v::Decl::AttributeDecl(_, decl) => {
+                return self.declare_decl(prefix, decl)
+            }
+            v::Decl::Param(id, _) => {
+                return Err(unsupported(format!("parameter `{}'", id)))
+            }
+        }
+        Ok(())
+    }
+
+    fn elaborate<F>(
+        &mut self,
+        modules: &HashMap<&str, &v::Module>,
+        module: &v::Module,
+        prefix: &str,
+        black_box: &mut F,
+    ) -> CalyxResult<()>
+    where
+        F: FnMut(&str, &str) -> Option<Box<dyn BlackBox>>,
+    {
+        if let Some(param) = module.params.first() {
+            return Err(unsupported(format!(
+                "parameter `{}' of module `{}'",
+                decl_name(param),
+                module.name
+            )));
+        }
+        // Declare all signals before lowering any statements since Verilog
+        // allows uses before declarations.
+        for port in &module.ports {
+            match port {
+                v::Port::Input(decl) | v::Port::Output(decl) => {
+                    self.declare_decl(prefix, decl)?
+                }
+            }
+        }
+        for stmt in &module.body {
+            if let v::Stmt::Decl(decl) = stmt {
+                self.declare_decl(prefix, decl)?;
+            }
+        }
+
+        for stmt in &module.body {
+            match stmt {
+                v::Stmt::Decl(_) => (),
+                v::Stmt::RawStr(s) => {
+                    return Err(unsupported(format!(
+                        "raw Verilog string `{}' in module `{}'",
+                        s, module.name
+                    )))
+                }
+                v::Stmt::Parallel(v::Parallel::Assign(lhs, rhs)) => {
+                    let assign = (
+                        self.lower_lhs(prefix, lhs)?,
+                        self.lower(prefix, rhs)?,
+                    );
+                    self.assigns.push(assign);
+                }
+                v::Stmt::Parallel(v::Parallel::Process(proc)) => {
+                    let body = self.lower_body(prefix, &proc.body)?;
+                    match &proc.event {
+                        Some(v::Sequential::Wildcard) => self.comb.push(body),
+                        Some(v::Sequential::Event(v::EventTy::Posedge, _)) => {
+                            self.seq.push(body)
+                        }
+                        Some(v::Sequential::Event(v::EventTy::Negedge, _)) => {
+                            return Err(unsupported(format!(
+                                "negative edge process in module `{}'",
+                                module.name
+                            )))
+                        }
+                        Some(
+                            v::Sequential::Assign(..)
+                            | v::Sequential::IfElse(_)
+                            | v::Sequential::SeqCase(_),
+                        )
+                        | None => {
+                            return Err(unsupported(format!(
+                                "process without an event in module `{}'",
+                                module.name
+                            )))
+                        }
+                    }
+                }
+                v::Stmt::Parallel(v::Parallel::Inst(inst)) => {
+                    self.instantiate(modules, inst, prefix, black_box)?
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn instantiate<F>(
+        &mut self,
+        modules: &HashMap<&str, &v::Module>,
+        inst: &v::Instance,
+        prefix: &str,
+        black_box: &mut F,
+    ) -> CalyxResult<()>
+    where
+        F: FnMut(&str, &str) -> Option<Box<dyn BlackBox>>,
+    {
+        let child_prefix = format!("{}{}.", prefix, inst.id);
+        // Ports connected to `()` are left unconnected.
+        let conns = inst
+            .ports
+            .iter()
+            .filter(|(_, e)| !matches!(e, v::Expr::Ref(r) if r.is_empty()));
+
+        if let Some(module) = modules.get(inst.prim.as_str()) {
+            self.elaborate(modules, module, &child_prefix, black_box)?;
+            for port in &module.ports {
+                let (is_input, name) = match port {
+                    v::Port::Input(d) => (true, decl_name(d)),
+                    v::Port::Output(d) => (false, decl_name(d)),
+                };
+                let expr = match conns.clone().find(|(p, _)| *p == name) {
+                    Some((_, expr)) => expr,
+                    None => continue,
+                };
+                let child = format!("{}{}", child_prefix, name);
+                let assign = if is_input {
+                    (Lhs::Sig(self.lookup(&child)?), self.lower(prefix, expr)?)
+                } else {
+                    (
+                        self.lower_lhs(prefix, expr)?,
+                        Expr::Sig(self.lookup(&child)?),
+                    )
+                };
+                self.assigns.push(assign);
+            }
+        } else {
+            let model = black_box(&inst.prim, &inst.id).ok_or_else(|| {
+                Error::misc(format!(
+                    "No definition or model for module `{}'",
+                    inst.prim
+                ))
+            })?;
+            let conns = conns
+                .map(|(p, e)| Ok((p.clone(), self.lower(prefix, e)?)))
+                .collect::<CalyxResult<_>>()?;
+            self.black_boxes.push(BlackBoxInst { model, conns });
+        }
+        Ok(())
+    }
+
+    fn lower(&self, prefix: &str, expr: &v::Expr) -> CalyxResult<Expr> {
+        let b = |e: &v::Expr| self.lower(prefix, e).map(Box::new);
+        Ok(match expr {
+            v::Expr::Ref(name) => {
+                Expr::Sig(self.lookup(&format!("{}{}", prefix, name))?)
+            }
+            v::Expr::Int(i) => Expr::Const(UBig::from(*i as u32), 32),
+            v::Expr::ULit(width, radix, val) => {
+                let radix = match variant(radix).as_str() {
+                    "Dec" => 10,
+                    "Bin" => 2,
+                    "Hex" => 16,
+                    r => return Err(unsupported(format!("radix {}", r))),
+                };
+                let val = UBig::from_str_radix(val, radix).map_err(|_| {
+                    Error::misc(format!("Malformed literal `{}'", val))
+                })?;
+                Expr::Const(val, *width as u64)
+            }
+            v::Expr::Unop(op, e) => match variant(op).as_str() {
+                "Not" => Expr::Not(b(e)?),
+                "LogNot" => Expr::LogNot(b(e)?),
+                "Or" => Expr::ReduceOr(b(e)?),
+                "And" => Expr::ReduceAnd(b(e)?),
+                "Xor" => Expr::ReduceXor(b(e)?),
+                "Nor" => Expr::LogNot(Box::new(Expr::ReduceOr(b(e)?))),
+                "Nand" => Expr::LogNot(Box::new(Expr::ReduceAnd(b(e)?))),
+                "Xnor" => Expr::LogNot(Box::new(Expr::ReduceXor(b(e)?))),
+                op => {
+                    return Err(unsupported(format!("unary operator {}", op)))
+                }
+            },
+            v::Expr::Binop(op, l, r) => {
+                let op = match variant(op).as_str() {
+                    "LogOr" => BinOp::LogOr,
+                    "LogAnd" => BinOp::LogAnd,
+                    "Add" => BinOp::Add,
+                    "Sub" => BinOp::Sub,
+                    "Mul" => BinOp::Mul,
+                    "Gt" => BinOp::Gt,
+                    "Lt" => BinOp::Lt,
+                    "Geq" => BinOp::Geq,
+                    "Leq" => BinOp::Leq,
+                    "Equal" => BinOp::Equal,
+                    "NotEqual" => BinOp::NotEqual,
+                    "BitAnd" => BinOp::BitAnd,
+                    "BitOr" => BinOp::BitOr,
+                    "ShiftLeft" => BinOp::ShiftLeft,
+                    "IndexBit" => return self.lower_index(prefix, l, r),
+                    op => {
+                        return Err(unsupported(format!(
+                            "binary operator {}",
+                            op
+                        )))
+                    }
+                };
+                Expr::Bin(op, b(l)?, b(r)?)
+            }
+            v::Expr::Terop(op, e, x, y) => match variant(op).as_str() {
+                "Mux" => Expr::Mux(b(e)?, b(x)?, b(y)?),
+                "Slice" => {
+                    let (hi, lo) = (const_int(x)?, const_int(y)?);
+                    Expr::Part(
+                        b(e)?,
+                        Box::new(Expr::Const(lo.into(), 32)),
+                        hi - lo + 1,
+                    )
+                }
+                "IndexSlice" => Expr::Part(b(e)?, b(x)?, const_int(y)?),
+                op => {
+                    return Err(unsupported(format!("ternary operator {}", op)))
+                }
+            },
+            v::Expr::Concat(concat) => Expr::Concat(
+                concat
+                    .exprs
+                    .iter()
+                    .map(|e| self.lower(prefix, e))
+                    .collect::<CalyxResult<_>>()?,
+            ),
+            v::Expr::Repeat(n, e) => Expr::Repeat(*n, b(e)?),
+            v::Expr::X
+            | v::Expr::Str(_)
+            | v::Expr::Signed(_)
+            | v::Expr::IPath(..)
+            | v::Expr::Call(..) => {
+                return Err(unsupported(format!("expression `{}'", expr)))
+            }
+        })
+    }
+
+    /// Lowers `l[r]`, which reads an element if `l` is an array and a bit
+    /// otherwise.
+    fn lower_index(
+        &self,
+        prefix: &str,
+        l: &v::Expr,
+        r: &v::Expr,
+    ) -> CalyxResult<Expr> {
+        let idx = Box::new(self.lower(prefix, r)?);
+        if let v::Expr::Ref(name) = l {
+            let name = format!("{}{}", prefix, name);
+            if let Some(arr) = self.array_names.get(&name) {
+                return Ok(Expr::Elem(*arr, idx));
+            }
+        }
+        Ok(Expr::Part(Box::new(self.lower(prefix, l)?), idx, 1))
+    }
+
+    fn lower_lhs(&self, prefix: &str, expr: &v::Expr) -> CalyxResult<Lhs> {
+        match self.lower(prefix, expr)? {
+            Expr::Sig(idx) => Ok(Lhs::Sig(idx)),
+            Expr::Part(e, lo, width) => match *e {
+                Expr::Sig(idx) => Ok(Lhs::Part(idx, *lo, width)),
+                _ => Err(unsupported(format!("assignment to `{}'", expr))),
+            },
+            Expr::Elem(arr, idx) => Ok(Lhs::Elem(arr, *idx)),
+            _ => Err(unsupported(format!("assignment to `{}'", expr))),
+        }
+    }
+
+    fn lower_body(
+        &self,
+        prefix: &str,
+        body: &[v::Sequential],
+    ) -> CalyxResult<Vec<Stmt>> {
+        let mut stmts = vec![];
+        for seq in body {
+            stmts.extend(self.lower_seq(prefix, seq)?);
+        }
+        Ok(stmts)
+    }
+
+    fn lower_seq(
+        &self,
+        prefix: &str,
+        seq: &v::Sequential,
+    ) -> CalyxResult<Vec<Stmt>> {
+        Ok(match seq {
+            v::Sequential::Assign(lhs, rhs, ty) => vec![Stmt::Assign(
+                self.lower_lhs(prefix, lhs)?,
+                self.lower(prefix, rhs)?,
+                matches!(ty, v::AssignTy::Blocking),
+            )],
+            v::Sequential::IfElse(ifelse) => {
+                let body = self.lower_body(prefix, &ifelse.body)?;
+                let cond = match &ifelse.cond {
+                    Some(cond) => cond,
+                    // A missing condition represents a final `else` branch.
+                    None => return Ok(body),
+                };
+                let elsebr = match &ifelse.elsebr {
+                    Some(s) => self.lower_seq(prefix, s)?,
+                    None => vec![],
+                };
+                vec![Stmt::If(self.lower(prefix, cond)?, body, elsebr)]
+            }
+            v::Sequential::SeqCase(case) => {
+                let mut branches = vec![];
+                for br in &case.branches {
+                    branches.push((
+                        self.lower(prefix, &br.cond)?,
+                        self.lower_body(prefix, &br.body)?,
+                    ));
+                }
+                let default = match &case.default {
+                    Some(d) => self.lower_body(prefix, &d.body)?,
+                    None => vec![],
+                };
+                vec![Stmt::Case(
+                    self.lower(prefix, &case.cond)?,
+                    branches,
+                    default,
+                )]
+            }
+            v::Sequential::Wildcard | v::Sequential::Event(..) => {
+                return Err(unsupported(
+                    "an event control inside of a process body",
+                ))
+            }
+        })
+    }
+
+    fn width(&self, expr: &Expr) -> u64 {
+        match expr {
+            Expr::Sig(idx) => self.widths[*idx],
+            Expr::Const(_, w) => *w,
+            Expr::Not(e) => self.width(e),
+            Expr::LogNot(_)
+            | Expr::ReduceOr(_)
+            | Expr::ReduceAnd(_)
+            | Expr::ReduceXor(_) => 1,
+            Expr::Bin(op, l, r) => match op {
+                BinOp::Add
+                | BinOp::Sub
+                | BinOp::Mul
+                | BinOp::BitAnd
+                | BinOp::BitOr => self.width(l).max(self.width(r)),
+                BinOp::ShiftLeft => self.width(l),
+                BinOp::LogOr
+                | BinOp::LogAnd
+                | BinOp::Gt
+                | BinOp::Lt
+                | BinOp::Geq
+                | BinOp::Leq
+                | BinOp::Equal
+                | BinOp::NotEqual => 1,
+            },
+            Expr::Mux(_, t, f) => self.width(t).max(self.width(f)),
+            Expr::Elem(arr, _) => self.arrays[*arr].0,
+            Expr::Part(_, _, w) => *w,
+            Expr::Concat(es) => es.iter().map(|e| self.width(e)).sum(),
+            Expr::Repeat(n, e) => n * self.width(e),
+        }
+    }
+
+    fn eval(&self, expr: &Expr) -> UBig {
+        let bool_val = |b: bool| if b { ubig!(1) } else { ubig!(0) };
+        match expr {
+            Expr::Sig(idx) => self.values[*idx].clone(),
+            Expr::Const(val, _) => val.clone(),
+            Expr::Not(e) => {
+                mask(self.width(e)) ^ (self.eval(e) & mask(self.width(e)))
+            }
+            Expr::LogNot(e) => bool_val(!is_true(&self.eval(e))),
+            Expr::ReduceOr(e) => {
+                bool_val(is_true(&(self.eval(e) & mask(self.width(e)))))
+            }
+            Expr::ReduceAnd(e) => {
+                let m = mask(self.width(e));
+                bool_val((self.eval(e) & &m) == m)
+            }
+            Expr::ReduceXor(e) => {
+                let val = self.eval(e) & mask(self.width(e));
+                let ones: u32 =
+                    val.to_le_bytes().iter().map(|b| b.count_ones()).sum();
+                bool_val(ones % 2 == 1)
+            }
+            Expr::Bin(op, l, r) => {
+                let (lv, rv) = (self.eval(l), self.eval(r));
+                match op {
+                    BinOp::LogOr => bool_val(is_true(&lv) || is_true(&rv)),
+                    BinOp::LogAnd => bool_val(is_true(&lv) && is_true(&rv)),
+                    BinOp::Add => lv + rv,
+                    BinOp::Sub => {
+                        let m = mask(self.width(expr));
+                        ((lv & &m) + (&m + ubig!(1)) - (rv & &m)) & m
+                    }
+                    BinOp::Mul => lv * rv,
+                    BinOp::Gt => bool_val(lv > rv),
+                    BinOp::Lt => bool_val(lv < rv),
+                    BinOp::Geq => bool_val(lv >= rv),
+                    BinOp::Leq => bool_val(lv <= rv),
+                    BinOp::Equal => bool_val(lv == rv),
+                    BinOp::NotEqual => bool_val(lv != rv),
+                    BinOp::BitAnd => lv & rv,
+                    BinOp::BitOr => lv | rv,
+                    BinOp::ShiftLeft => {
+                        let width = self.width(l);
+                        match usize::try_from(&rv) {
+                            Ok(sh) if (sh as u64) < width => {
+                                (lv << sh) & mask(width)
+                            }
+                            _ => ubig!(0),
+                        }
+                    }
+                }
+            }
+            Expr::Mux(c, t, f) => {
+                if is_true(&self.eval(c)) {
+                    self.eval(t)
+                } else {
+                    self.eval(f)
+                }
+            }
+            Expr::Elem(arr, idx) => {
+                let (_, elems) = &self.arrays[*arr];
+                usize::try_from(&self.eval(idx))
+                    .ok()
+                    .and_then(|i| elems.get(i))
+                    .cloned()
+                    .unwrap_or_default()
+            }
+            Expr::Part(e, lo, width) => match usize::try_from(&self.eval(lo)) {
+                Ok(lo) => (self.eval(e) >> lo) & mask(*width),
+                // Bits beyond any representable index are zero.
+                Err(_) => ubig!(0),
+            },
+            Expr::Concat(es) => {
+                // The first expression in a concatenation is the least
+                // significant one.
+                let mut val = ubig!(0);
+                let mut offset = 0;
+                for e in es {
+                    let w = self.width(e);
+                    val |= (self.eval(e) & mask(w)) << offset;
+                    offset += w as usize;
+                }
+                val
+            }
+            Expr::Repeat(n, e) => {
+                let w = self.width(e) as usize;
+                let v = self.eval(e) & mask(w as u64);
+                (0..*n as usize).fold(ubig!(0), |acc, i| acc | (&v << (i * w)))
+            }
+        }
+    }
+
+    fn target(&self, lhs: &Lhs) -> Target {
+        match lhs {
+            Lhs::Sig(idx) => Target::Sig(*idx, 0, self.widths[*idx]),
+            Lhs::Part(idx, lo, width) => {
+                Target::Sig(*idx, to_u64(&self.eval(lo)), *width)
+            }
+            Lhs::Elem(arr, idx) => {
+                Target::Elem(*arr, to_u64(&self.eval(idx)) as usize)
+            }
+        }
+    }
+
+    /// Write `val` to `target` and return true if the value changed.
+    fn write(&mut self, target: &Target, val: UBig) -> bool {
+        match target {
+            Target::Sig(idx, lo, width) => {
+                let old = &self.values[*idx];
+                let field = mask(*width) << *lo as usize;
+                let new = (old & (mask(self.widths[*idx]) ^ &field))
+                    | ((val & mask(*width)) << *lo as usize);
+                let changed = new != *old;
+                self.values[*idx] = new;
+                changed
+            }
+            Target::Elem(arr, idx) => {
+                let (width, elems) = &mut self.arrays[*arr];
+                // Writes to out of bounds addresses are ignored.
+                if let Some(elem) = elems.get_mut(*idx) {
+                    let new = val & mask(*width);
+                    let changed = new != *elem;
+                    *elem = new;
+                    changed
+                } else {
+                    false
+                }
+            }
+        }
+    }
+
+    /// Execute `stmts`. Blocking assignments are performed immediately while
+    /// non-blocking assignments are added to `deferred`. Returns true if any
+    /// signal changed.
+    fn exec(
+        &mut self,
+        stmts: &[Stmt],
+        deferred: &mut Vec<(Target, UBig)>,
+    ) -> bool {
+        let mut changed = false;
+        for stmt in stmts {
+            match stmt {
+                Stmt::Assign(lhs, rhs, blocking) => {
+                    let target = self.target(lhs);
+                    let val = self.eval(rhs);
+                    if *blocking {
+                        changed |= self.write(&target, val);
+                    } else {
+                        deferred.push((target, val));
+                    }
+                }
+                Stmt::If(cond, tbr, fbr) => {
+                    let br = if is_true(&self.eval(cond)) { tbr } else { fbr };
+                    changed |= self.exec(br, deferred);
+                }
+                Stmt::Case(cond, branches, default) => {
+                    let val = self.eval(cond);
+                    let br = branches
+                        .iter()
+                        .find(|(c, _)| self.eval(c) == val)
+                        .map(|(_, body)| body)
+                        .unwrap_or(default);
+                    changed |= self.exec(br, deferred);
+                }
+            }
+        }
+        changed
+    }
+
+    /// Propagate values through the combinational logic until all signals
+    /// are stable.
+    pub fn settle(&mut self) -> CalyxResult<()> {
+        const MAX_ITERS: usize = 1000;
+        for _ in 0..MAX_ITERS {
+            let mut changed = false;
+            for i in 0..self.assigns.len() {
+                let target = self.target(&self.assigns[i].0);
+                let val = self.eval(&self.assigns[i].1);
+                changed |= self.write(&target, val);
+            }
+            let comb = std::mem::take(&mut self.comb);
+            for body in &comb {
+                changed |= self.exec(body, &mut vec![]);
+            }
+            self.comb = comb;
+            let mut outputs = vec![];
+            for bb in &self.black_boxes {
+                let ports = Ports {
+                    sim: self,
+                    conns: &bb.conns,
+                };
+                for (port, val) in bb.model.comb(&ports) {
+                    outputs.push((self.output(bb, &port)?, val));
+                }
+            }
+            for (idx, val) in outputs {
+                let target = Target::Sig(idx, 0, self.widths[idx]);
+                changed |= self.write(&target, val.into());
+            }
+            if !changed {
+                return Ok(());
+            }
+        }
+        Err(Error::misc(format!(
+            "Combinational logic did not settle after {} iterations",
+            MAX_ITERS
+        )))
+    }
+
+    /// The signal driven by the output `port` of a black box.
+    fn output(&self, bb: &BlackBoxInst, port: &str) -> CalyxResult<usize> {
+        match bb.conns.get(port) {
+            Some(Expr::Sig(idx)) => Ok(*idx),
+            Some(_) => Err(unsupported(format!(
+                "output port `{}' connected to an expression",
+                port
+            ))),
+            None => Err(Error::misc(format!(
+                "Output port `{}' is not connected",
+                port
+            ))),
+        }
+    }
+
+    /// Simulate a rising edge of the clock and propagate the new values of
+    /// the registers.
+    pub fn posedge(&mut self) -> CalyxResult<()> {
+        let mut deferred = vec![];
+        let seq = std::mem::take(&mut self.seq);
+        for body in &seq {
+            self.exec(body, &mut deferred);
+        }
+        self.seq = seq;
+        let mut black_boxes = std::mem::take(&mut self.black_boxes);
+        for bb in &mut black_boxes {
+            let ports = Ports {
+                sim: self,
+                conns: &bb.conns,
+            };
+            bb.model.posedge(&ports);
+        }
+        self.black_boxes = black_boxes;
+        for (target, val) in deferred {
+            self.write(&target, val);
+        }
+        self.cycles += 1;
+        self.settle()
+    }
+}
+
+fn decl_name(decl: &v::Decl) -> &str {
+    match decl {
+        v::Decl::Int(id, _)
+        | v::Decl::Wire(id, _)
+        | v::Decl::Reg(id, _)
+        | v::Decl::Array(id, _, _)
+        | v::Decl::Param(id, _) => id,
+        v::Decl::AttributeDecl(_, decl) => decl_name(decl),
+    }
+}
+
+fn const_int(expr: &v::Expr) -> CalyxResult<u64> {
+    match expr {
+        v::Expr::Int(i) => Ok(*i as u64),
+        e => Err(unsupported(format!("non-constant slice bound `{}'", e))),
+    }
+}
@@ -0,0 +1,446 @@
+//! Tests that drive the generated AXI wrapper using the host models.
+#![cfg(test)]
+use super::axi_host::{
+    AxiLiteMaster, AxiMemory, AxiStreamSink, AxiStreamSource, LiteOp,
+};
+use super::{BlackBox, Ports, Simulator};
+use crate::backend::xilinx::config::XilinxConfig;
+use crate::backend::xilinx::stream_axi::{external_streams, StreamDirection};
+use crate::backend::xilinx::toplevel::{external_memories, wrapper_modules};
+use calyx::{frontend, ir};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use vast::v05::ast as v;
+
+const MAX_CYCLES: u64 = 10_000;
+
+fn load(file: &str) -> ir::Context {
+    let ws = frontend::Workspace::construct(
+        &Some(PathBuf::from(file)),
+        &[PathBuf::from(".")],
+    )
+    .unwrap();
+    ir::from_ast::ast_to_ir(ws).unwrap()
+}
+
+/// Model of the Calyx kernel that computes `out[i] = left[i] + right[i]`
+/// using the memory ports exposed by the wrapper. Each element takes one
+/// cycle.
+struct VectorAdd {
+    left: String,
+    right: String,
+    out: String,
+    size: u64,
+    /// Index of the element being computed.
+    idx: Option<u64>,
+    done: bool,
+}
+
+impl BlackBox for VectorAdd {
+    fn comb(&self, ports: &Ports) -> Vec<(String, u64)> {
+        let idx = self.idx.unwrap_or(0);
+        let sum = ports.get(&format!("{}_read_data", self.left))
+            + ports.get(&format!("{}_read_data", self.right));
+        vec![
+            (format!("{}_addr0", self.left), idx),
+            (format!("{}_addr0", self.right), idx),
+            (format!("{}_addr0", self.out), idx),
+            (format!("{}_write_data", self.out), sum & 0xffff_ffff),
+            (
+                format!("{}_write_en", self.out),
+                u64::from(self.idx.is_some()),
+            ),
+            ("done".to_string(), u64::from(self.done)),
+        ]
+    }
+
+    fn posedge(&mut self, ports: &Ports) {
+        if ports.get("reset") == 1 {
+            self.idx = None;
+            self.done = false;
+            return;
+        }
+        self.done = false;
+        self.idx = match self.idx {
+            None if ports.get("go") == 1 && !self.done => Some(0),
+            Some(i) if i + 1 == self.size => {
+                self.done = true;
+                None
+            }
+            Some(i) => Some(i + 1),
+            None => None,
+        };
+    }
+}
+
+/// Model of a kernel that immediately finishes without touching memories.
+struct Nop {
+    done: bool,
+}
+
+impl BlackBox for Nop {
+    fn comb(&self, _ports: &Ports) -> Vec<(String, u64)> {
+        vec![("done".to_string(), u64::from(self.done))]
+    }
+
+    fn posedge(&mut self, ports: &Ports) {
+        self.done = ports.get("go") == 1 && !self.done;
+    }
+}
+
+/// Model of the kernel in `tests/xilinx/compile/streaming.futil` which
+/// increments every element of stream `x`, writes it to stream `y`, and
+/// finishes after the last element of `x`.
+struct StreamIncr {
+    done: bool,
+}
+
+impl BlackBox for StreamIncr {
+    fn comb(&self, ports: &Ports) -> Vec<(String, u64)> {
+        let active = ports.get("go") == 1 && !self.done;
+        vec![
+            ("y".to_string(), (ports.get("x") + 1) & 0xffff_ffff),
+            (
+                "y_valid".to_string(),
+                u64::from(active) & ports.get("x_valid"),
+            ),
+            ("y_last".to_string(), ports.get("x_last")),
+            (
+                "x_ready".to_string(),
+                u64::from(active) & ports.get("y_ready"),
+            ),
+            ("done".to_string(), u64::from(self.done)),
+        ]
+    }
+
+    fn posedge(&mut self, ports: &Ports) {
+        self.done = ports.get("go") == 1
+            && !self.done
+            && ports.get("x_valid") == 1
+            && ports.get("x_ready") == 1
+            && ports.get("x_last") == 1;
+    }
+}
+
+/// The generated wrapper along with the host it is connected to.
+struct Harness {
+    sim: Simulator,
+    ctrl: AxiLiteMaster,
+    mems: Vec<AxiMemory>,
+    host_mem: HashMap<u64, u8>,
+    sources: Vec<AxiStreamSource>,
+    sinks: Vec<AxiStreamSink>,
+}
+
+impl Harness {
+    fn new(ctx: &ir::Context, kernel: Box<dyn BlackBox>) -> Self {
+        Self::with_config(ctx, &XilinxConfig::default(), kernel)
+    }
+
+    fn with_config(
+        ctx: &ir::Context,
+        config: &XilinxConfig,
+        kernel: Box<dyn BlackBox>,
+    ) -> Self {
+        let toplevel = ctx.entrypoint();
+        let num_mems = external_memories(toplevel).len();
+        let streams = external_streams(toplevel).unwrap();
+        let mut kernel = Some(kernel);
+        let sim = Simulator::new(
+            &wrapper_modules(toplevel, config).unwrap(),
+            "Toplevel",
+            |prim, _| {
+                assert_eq!(prim, "main");
+                kernel.take()
+            },
+        )
+        .unwrap();
+        let mut harness = Self {
+            sim,
+            ctrl: AxiLiteMaster::new("s_axi_control_"),
+            mems: (0..num_mems)
+                .map(|i| {
+                    AxiMemory::new(&format!("m{}_axi_", i), config.bus_width)
+                })
+                .collect(),
+            host_mem: HashMap::new(),
+            sources: streams
+                .iter()
+                .filter(|s| s.direction == StreamDirection::In)
+                .map(|s| AxiStreamSource::new(&format!("{}_", s.interface())))
+                .collect(),
+            sinks: streams
+                .iter()
+                .filter(|s| s.direction == StreamDirection::Out)
+                .map(|s| AxiStreamSink::new(&format!("{}_", s.interface()), 2))
+                .collect(),
+        };
+        harness.sim.set("ap_rst_n", 0u8);
+        harness.step();
+        harness.step();
+        harness.sim.set("ap_rst_n", 1u8);
+        harness
+    }
+
+    fn step(&mut self) {
+        self.ctrl.drive(&mut self.sim);
+        for mem in &self.mems {
+            mem.drive(&mut self.sim, &self.host_mem);
+        }
+        for source in &self.sources {
+            source.drive(&mut self.sim);
+        }
+        for sink in &self.sinks {
+            sink.drive(&mut self.sim);
+        }
+        self.sim.settle().unwrap();
+        self.ctrl.observe(&self.sim);
+        for mem in &mut self.mems {
+            mem.observe(&self.sim, &mut self.host_mem);
+        }
+        for source in &mut self.sources {
+            source.observe(&self.sim);
+        }
+        for sink in &mut self.sinks {
+            sink.observe(&self.sim);
+        }
+        self.sim.posedge().unwrap();
+    }
+
+    /// Perform the control register operation and wait for it to complete.
+    fn control(&mut self, op: LiteOp) {
+        self.ctrl.push(op);
+        while !self.ctrl.is_idle() {
+            assert!(self.sim.cycles() < MAX_CYCLES, "AXI-Lite timeout");
+            self.step();
+        }
+    }
+
+    fn read_reg(&mut self, addr: u64) -> u32 {
+        self.control(LiteOp::Read(addr));
+        self.ctrl.reads.last().unwrap().1
+    }
+
+    /// Store `elems` starting at `base` using `bytes` bytes for every
+    /// element.
+    fn write_elems(&mut self, base: u64, bytes: u64, elems: &[u64]) {
+        for (i, elem) in elems.iter().enumerate() {
+            for (j, byte) in
+                elem.to_le_bytes()[..bytes as usize].iter().enumerate()
+            {
+                self.host_mem
+                    .insert(base + bytes * i as u64 + j as u64, *byte);
+            }
+        }
+    }
+
+    fn read_elems(&self, base: u64, bytes: u64, len: usize) -> Vec<u64> {
+        (0..len as u64)
+            .map(|i| {
+                (0..bytes).rev().fold(0, |acc, j| {
+                    let addr = base + bytes * i + j;
+                    (acc << 8)
+                        | u64::from(*self.host_mem.get(&addr).unwrap_or(&0))
+                })
+            })
+            .collect()
+    }
+
+    fn write_words(&mut self, base: u64, words: &[u32]) {
+        let elems: Vec<u64> = words.iter().map(|w| u64::from(*w)).collect();
+        self.write_elems(base, 4, &elems);
+    }
+
+    fn read_words(&self, base: u64, len: usize) -> Vec<u32> {
+        self.read_elems(base, 4, len)
+            .into_iter()
+            .map(|w| w as u32)
+            .collect()
+    }
+
+    /// Program the base addresses of the memories, start the kernel, and
+    /// poll the control register until it is done.
+    fn run(&mut self, bases: &[u64]) {
+        for (i, base) in bases.iter().enumerate() {
+            let reg = 0x18 + 8 * i as u64;
+            self.control(LiteOp::Write(reg, *base as u32));
+            self.control(LiteOp::Write(reg + 4, (*base >> 32) as u32));
+        }
+        self.control(LiteOp::Write(0x0, 1));
+        while self.read_reg(0x0) & 0b10 == 0 {
+            assert!(self.sim.cycles() < MAX_CYCLES, "Kernel did not finish");
+        }
+    }
+}
+
+#[test]
+fn control_registers() {
+    let ctx = load("examples/futil/vectorized-add.futil");
+    let mut h = Harness::new(&ctx, Box::new(Nop { done: false }));
+    // The kernel is idle after reset.
+    assert_eq!(h.read_reg(0x0), 0b100);
+    h.control(LiteOp::Write(0x10, 0x1234));
+    h.control(LiteOp::Write(0x18, 0xdead_beef));
+    h.control(LiteOp::Write(0x1c, 0x1));
+    h.control(LiteOp::Write(0x2c, 0x2));
+    assert_eq!(h.read_reg(0x10), 0x1234);
+    assert_eq!(h.read_reg(0x18), 0xdead_beef);
+    assert_eq!(h.read_reg(0x1c), 0x1);
+    assert_eq!(h.read_reg(0x2c), 0x2);
+    // The base addresses are forwarded to the memory controllers.
+    assert_eq!(h.sim.get_u64("A0"), 0x1_dead_beef);
+    assert_eq!(h.sim.get_u64("Sum0"), 0x2_0000_0000);
+}
+
+#[test]
+fn memories_round_trip() {
+    let ctx = load("examples/futil/vectorized-add.futil");
+    let mut h = Harness::new(&ctx, Box::new(Nop { done: false }));
+    let bases = [0x1000, 0x2000, 0x3000];
+    let data: Vec<u32> = (0..8).map(|i| 0x0101_0101 * i + 7).collect();
+    for base in bases {
+        h.write_words(base, &data);
+    }
+    h.run(&bases);
+    for base in bases {
+        assert_eq!(h.read_words(base, 8), data);
+    }
+}
+
+#[test]
+fn vectorized_add() {
+    let ctx = load("examples/futil/vectorized-add.futil");
+    let kernel = VectorAdd {
+        left: "A0".to_string(),
+        right: "B0".to_string(),
+        out: "Sum0".to_string(),
+        size: 8,
+        idx: None,
+        done: false,
+    };
+    let mut h = Harness::new(&ctx, Box::new(kernel));
+    let bases = [0x1000, 0x2000, 0x3000];
+    let left: Vec<u32> = (0..8).map(|i| i * 3).collect();
+    let right: Vec<u32> = (0..8).map(|i| 100 + i).collect();
+    h.write_words(bases[0], &left);
+    h.write_words(bases[1], &right);
+    h.run(&bases);
+    let expected: Vec<u32> =
+        left.iter().zip(&right).map(|(l, r)| l + r).collect();
+    assert_eq!(h.read_words(bases[2], 8), expected);
+    // The kernel results are also stored in the local memory.
+    assert_eq!(
+        h.sim
+            .get_elem("inst_mem_controller_axi_2.bram.ram_core", 7)
+            .to_string(),
+        expected[7].to_string()
+    );
+    // Inputs are sent back to the host unchanged.
+    assert_eq!(h.read_words(bases[0], 8), left);
+    // The done bit is cleared after it is read.
+    assert_eq!(h.read_reg(0x0) & 0b10, 0);
+}
+
+#[test]
+fn streams() {
+    let ctx = load("tests/xilinx/compile/streaming.futil");
+    let mut h = Harness::new(&ctx, Box::new(StreamIncr { done: false }));
+    let data = [3, 1, 4, 1, 5];
+    h.sources[0].pending = data
+        .iter()
+        .enumerate()
+        .map(|(i, d)| (*d, i == data.len() - 1))
+        .collect();
+    // Nothing is transferred before the kernel is started.
+    for _ in 0..10 {
+        h.step();
+    }
+    assert_eq!(h.sources[0].pending.len(), data.len());
+    assert!(h.sinks[0].received.is_empty());
+
+    h.run(&[0x1000]);
+    assert!(h.sources[0].pending.is_empty());
+    let expected: Vec<(u64, bool)> = data
+        .iter()
+        .enumerate()
+        .map(|(i, d)| (d + 1, i == data.len() - 1))
+        .collect();
+    assert_eq!(h.sinks[0].received, expected);
+}
+
+#[test]
+fn bursts_and_packing() {
+    let ctx = load("tests/xilinx/compile/widths.futil");
+    // 8 bytes per beat and at most 4 beats per burst.
+    let config = XilinxConfig {
+        bus_width: 64,
+        max_burst_len: 4,
+    };
+    let mut h =
+        Harness::with_config(&ctx, &config, Box::new(Nop { done: false }));
+    // Memories of 8, 16, and 32-bit elements occupy 1, 2, and 4 bytes per
+    // element in host memory.
+    let mems = [(0x1000, 1, 10), (0x2000, 2, 7), (0x3000, 4, 20)];
+    let data: Vec<Vec<u64>> = mems
+        .iter()
+        .map(|(_, bytes, len)| {
+            (0..*len as u64)
+                .map(|i| (0x0102_0304_u64 * (i + 1)) & ((1 << (8 * bytes)) - 1))
+                .collect()
+        })
+        .collect();
+    for ((base, bytes, _), elems) in mems.iter().zip(&data) {
+        // Guard bytes around the memory must not be overwritten.
+        h.write_elems(base - 4, 1, &[0xaa; 4]);
+        h.write_elems(*base, *bytes, elems);
+        h.write_elems(base + bytes * elems.len() as u64, 1, &[0xbb; 4]);
+    }
+    let bases: Vec<u64> = mems.iter().map(|(base, _, _)| *base).collect();
+    h.run(&bases);
+    for (i, ((base, bytes, len), elems)) in mems.iter().zip(&data).enumerate() {
+        // Elements are unpacked into the right place in the local memory.
+        for (j, elem) in elems.iter().enumerate() {
+            assert_eq!(
+                h.sim
+                    .get_elem(
+                        &format!("inst_mem_controller_axi_{}.bram.ram_core", i),
+                        j
+                    )
+                    .to_string(),
+                elem.to_string()
+            );
+        }
+        assert_eq!(h.read_elems(*base, *bytes, *len), *elems);
+        assert_eq!(h.read_elems(base - 4, 1, 4), vec![0xaa; 4]);
+        assert_eq!(
+            h.read_elems(base + bytes * *len as u64, 1, 4),
+            vec![0xbb; 4]
+        );
+    }
+}
+
+#[test]
+fn unsupported_verilog() {
+    let err = |top: v::Module| {
+        Simulator::new(&[top], "top", |_, _| None)
+            .err()
+            .expect("simulator accepted unsupported Verilog")
+            .message()
+    };
+
+    let mut top = v::Module::new("top");
+    top.add_output("out", 32);
+    top.add_stmt(v::Parallel::Assign(
+        v::Expr::new_ref("out"),
+        v::Expr::new_call("$clog2", vec![v::Expr::new_int(4)]),
+    ));
+    assert!(err(top).contains("$clog2"));
+
+    let mut top = v::Module::new("top");
+    top.add_param_uint("WIDTH", 32);
+    assert!(err(top).contains("WIDTH"));
+
+    let mut top = v::Module::new("top");
+    top.add_instance(v::Instance::new("k", "kernel"));
+    assert!(err(top).contains("kernel"));
+}
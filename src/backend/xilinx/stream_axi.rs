@@ -0,0 +1,197 @@
+//! AXI4-Stream interfaces for top-level ports marked with `@stream`.
+//!
+//! A stream named `x` is made up of the following ports on the signature of
+//! the toplevel component:
+//!
+//!  - `@stream x`: The data transferred by the stream.
+//!  - `x_valid`: Driven by the producer when `x` holds valid data.
+//!  - `x_ready`: Driven by the consumer when it can accept data.
+//!  - `x_last` (optional): Driven by the producer to mark the last transfer
+//!    of a packet.
+//!
+//! If `@stream x` is an input of the component, the kernel consumes the
+//! stream and the wrapper exposes an AXI4-Stream slave `x_axis`. Otherwise,
+//! the kernel produces the stream and the wrapper exposes an AXI4-Stream
+//! master `x_axis`.
+use super::axi::{AxiChannel, ChannelDirection};
+use calyx::{
+    errors::{CalyxResult, Error},
+    ir,
+};
+use vast::v05::ast as v;
+
+/// Direction of a stream from the perspective of the kernel.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum StreamDirection {
+    /// The kernel reads from the stream.
+    In,
+    /// The kernel writes to the stream.
+    Out,
+}
+
+/// A stream exposed by the toplevel component.
+#[derive(Clone, Debug)]
+pub(crate) struct Stream {
+    /// Name of the data port of the stream.
+    pub name: String,
+    /// Width of the data port.
+    pub width: u64,
+    pub direction: StreamDirection,
+    /// True if the component defines a `<name>_last` port.
+    pub has_last: bool,
+}
+
+impl Stream {
+    /// Name of the AXI4-Stream interface exposed by the wrapper.
+    pub fn interface(&self) -> String {
+        format!("{}_axis", self.name)
+    }
+
+    /// Width of `TDATA`. AXI4-Stream requires an integer number of bytes.
+    pub fn bus_width(&self) -> u64 {
+        self.width.div_ceil(8) * 8
+    }
+
+    /// The AXI4-Stream channel connected to the host.
+    pub fn channel(&self) -> AxiChannel {
+        AxiChannel {
+            prefix: format!("{}_T", self.interface()),
+            direction: match self.direction {
+                StreamDirection::In => ChannelDirection::Recv,
+                StreamDirection::Out => ChannelDirection::Send,
+            },
+            state: vec![],
+            data_ports: vec![
+                ("DATA".to_string(), self.bus_width()),
+                ("LAST".to_string(), 1),
+            ],
+        }
+    }
+
+    /// Connect this stream to the kernel instance. Handshakes can only occur
+    /// while `running` is high so that no data is lost while the kernel is
+    /// being reset or the memories are being transferred.
+    pub fn connect(
+        &self,
+        module: &mut v::Module,
+        kernel: &mut v::Instance,
+        running: &str,
+    ) {
+        let ch = self.channel();
+        let valid = format!("{}_valid", self.name);
+        let ready = format!("{}_ready", self.name);
+        let last = format!("{}_last", self.name);
+        match self.direction {
+            StreamDirection::In => {
+                module.add_decl(v::Decl::new_wire(&ready, 1));
+                module.add_stmt(v::Parallel::Assign(
+                    ch.ready().into(),
+                    v::Expr::new_bit_and(running, ready.as_str()),
+                ));
+                kernel.connect(
+                    &self.name,
+                    v::Expr::new_slice(
+                        &ch.get("DATA"),
+                        v::Expr::new_int(self.width as i32 - 1),
+                        v::Expr::new_int(0),
+                    ),
+                );
+                kernel
+                    .connect(&valid, v::Expr::new_bit_and(running, ch.valid()));
+                kernel.connect_ref(&ready, &ready);
+                if self.has_last {
+                    kernel.connect_ref(&last, &ch.get("LAST"));
+                }
+            }
+            StreamDirection::Out => {
+                module.add_decl(v::Decl::new_wire(&self.name, self.width));
+                module.add_decl(v::Decl::new_wire(&valid, 1));
+                module.add_stmt(v::Parallel::Assign(
+                    ch.ready().into(),
+                    v::Expr::new_bit_and(running, valid.as_str()),
+                ));
+                // TDATA is zero extended to a whole number of bytes.
+                module.add_stmt(ch.assign("DATA", self.name.as_str()));
+                kernel.connect_ref(&self.name, &self.name);
+                kernel.connect_ref(&valid, &valid);
+                kernel
+                    .connect(&ready, v::Expr::new_bit_and(running, ch.valid()));
+                if self.has_last {
+                    module.add_decl(v::Decl::new_wire(&last, 1));
+                    module.add_stmt(ch.assign("LAST", last.as_str()));
+                    kernel.connect_ref(&last, &last);
+                } else {
+                    module.add_stmt(
+                        ch.assign("LAST", v::Expr::new_ulit_bin(1, "0")),
+                    );
+                }
+            }
+        }
+    }
+}
+
+/// Returns the streams defined by the signature of `comp` in the order in
+/// which they are defined.
+pub(crate) fn external_streams(
+    comp: &ir::Component,
+) -> CalyxResult<Vec<Stream>> {
+    let sig = comp.signature.borrow();
+    sig.find_all_with_attr("stream")
+        .map(|port_ref| {
+            let port = port_ref.borrow();
+            let name = port.name.to_string();
+            // NOTE: The signature port definitions are reversed inside the
+            // component so an `Output` port is an input of the component.
+            let (direction, producer, consumer) = match port.direction {
+                ir::Direction::Output => (
+                    StreamDirection::In,
+                    ir::Direction::Output,
+                    ir::Direction::Input,
+                ),
+                _ => (
+                    StreamDirection::Out,
+                    ir::Direction::Input,
+                    ir::Direction::Output,
+                ),
+            };
+            // Check that the companion port `<name>_<suffix>` is a 1-bit port
+            // in direction `dir` and return true if it exists.
+            let companion = |suffix: &str, dir: ir::Direction| {
+                let companion = format!("{}_{}", name, suffix);
+                match sig.find(companion.as_str()) {
+                    Some(p)
+                        if p.borrow().direction == dir
+                            && p.borrow().width == 1 =>
+                    {
+                        Ok(true)
+                    }
+                    Some(_) => Err(Error::malformed_structure(format!(
+                        "Port `{}' of stream `{}' must be a 1-bit {}",
+                        companion,
+                        name,
+                        if dir == ir::Direction::Output {
+                            "input"
+                        } else {
+                            "output"
+                        }
+                    ))),
+                    None => Ok(false),
+                }
+            };
+            for (suffix, dir) in [("valid", &producer), ("ready", &consumer)] {
+                if !companion(suffix, dir.clone())? {
+                    return Err(Error::malformed_structure(format!(
+                        "Stream `{}' requires a port named `{}_{}'",
+                        name, name, suffix
+                    )));
+                }
+            }
+            Ok(Stream {
+                width: port.width,
+                has_last: companion("last", producer)?,
+                name,
+                direction,
+            })
+        })
+        .collect()
+}
@@ -1,6 +1,11 @@
 use super::{
-    axi, control_axi::ControlInterface, fsm, memory_axi::bram,
-    memory_axi::MemoryInterface, utils,
+    axi,
+    config::{element_bytes, XilinxConfig},
+    control_axi::ControlInterface,
+    fsm,
+    memory_axi::bram,
+    memory_axi::MemoryInterface,
+    stream_axi, utils,
 };
 use crate::backend::traits::Backend;
 use calyx::{
@@ -18,7 +23,21 @@ impl Backend for XilinxInterfaceBackend {
         "xilinx-axi"
     }
 
-    fn validate(_ctx: &ir::Context) -> CalyxResult<()> {
+    fn validate(ctx: &ir::Context) -> CalyxResult<()> {
+        let config = XilinxConfig::from_ctx(ctx)?;
+        let toplevel = ctx.entrypoint();
+        // Every beat on the data bus must fit at least one memory element.
+        for (mem, (width, _, _)) in external_memories(toplevel)
+            .iter()
+            .zip(get_mem_info(toplevel))
+        {
+            if 8 * element_bytes(width) > config.bus_width {
+                return Err(Error::misc(format!(
+                    "External memory `{}` has width {} which does not fit in the {}-bit AXI data bus. Use `-x xilinx:bus-width=<width>` to use a wider bus.",
+                    mem, width, config.bus_width
+                )));
+            }
+        }
         Ok(())
     }
 
@@ -40,42 +59,15 @@ impl Backend for XilinxInterfaceBackend {
             .unwrap();
 
         let memories = external_memories(toplevel);
-        if memories.is_empty() {
+        let streams = stream_axi::external_streams(toplevel)?;
+        if memories.is_empty() && streams.is_empty() {
             return Err(Error::misc(
-                    "Program has no memories marked with attribute @external.".to_owned() +
-                    " Please make sure that at least one memory is marked as @external."));
+                    "Program has no memories marked with attribute @external and no ports marked with attribute @stream.".to_owned() +
+                    " Please make sure that at least one memory is marked as @external or one port is marked as @stream."));
         }
 
-        let mem_info = get_mem_info(toplevel);
-
-        let mut modules = vec![top_level(toplevel)];
-        for (i, _mem) in memories.iter().enumerate() {
-            modules.push(bram(
-                &format!("SINGLE_PORT_BRAM_{}", i),
-                mem_info[i].0,
-                mem_info[i].1,
-                mem_info[i].2,
-            ))
-        }
-
-        modules.push(axi::AxiInterface::control_module(
-            "Control_axi",
-            // XXX(nathanielnrn) seems like these should be hard coded for our controller
-            12,
-            32,
-            &memories,
-        ));
-
-        for (i, _mem) in memories.iter().enumerate() {
-            modules.push(axi::AxiInterface::memory_module(
-                &format!("Memory_controller_axi_{}", i),
-                512,
-                64,
-                mem_info[i].0,
-                mem_info[i].1,
-                mem_info[i].2,
-            ))
-        }
+        let config = XilinxConfig::from_ctx(prog)?;
+        let modules = wrapper_modules(toplevel, &config)?;
 
         let module_string = modules
             .into_iter()
@@ -95,6 +87,47 @@ impl Backend for XilinxInterfaceBackend {
     }
 }
 
+/// Generates the modules that make up the AXI wrapper for `toplevel`.
+pub(super) fn wrapper_modules(
+    toplevel: &ir::Component,
+    config: &XilinxConfig,
+) -> CalyxResult<Vec<v::Module>> {
+    let memories = external_memories(toplevel);
+    let mem_info = get_mem_info(toplevel);
+    let streams = stream_axi::external_streams(toplevel)?;
+
+    let mut modules = vec![top_level(toplevel, config, &streams)];
+    for (i, _mem) in memories.iter().enumerate() {
+        modules.push(bram(
+            &format!("SINGLE_PORT_BRAM_{}", i),
+            mem_info[i].0,
+            mem_info[i].1,
+            mem_info[i].2,
+        ))
+    }
+
+    modules.push(axi::AxiInterface::control_module(
+        "Control_axi",
+        // XXX(nathanielnrn) seems like these should be hard coded for our controller
+        12,
+        32,
+        &memories,
+    ));
+
+    for (i, _mem) in memories.iter().enumerate() {
+        modules.push(axi::AxiInterface::memory_module(
+            &format!("Memory_controller_axi_{}", i),
+            config.bus_width,
+            64,
+            config.burst_len(),
+            mem_info[i].0,
+            mem_info[i].1,
+            mem_info[i].2,
+        ))
+    }
+    Ok(modules)
+}
+
 fn external_memories_cells(
     comp: &ir::Component,
 ) -> Vec<calyx::ir::RRC<ir::Cell>> {
@@ -108,7 +141,7 @@ fn external_memories_cells(
 
 // Returns a vector of tuples containing external memory info of [comp] of form:
 // [(WIDTH, SIZE, IDX_SIZE)]
-fn get_mem_info(comp: &ir::Component) -> Vec<(u64, u64, u64)> {
+pub(super) fn get_mem_info(comp: &ir::Component) -> Vec<(u64, u64, u64)> {
     external_memories_cells(comp)
         .iter()
         .map(|cell_ref| {
@@ -122,17 +155,22 @@ fn get_mem_info(comp: &ir::Component) -> Vec<(u64, u64, u64)> {
 }
 
 // Returns Vec<String> of memory names
-fn external_memories(comp: &ir::Component) -> Vec<String> {
+pub(super) fn external_memories(comp: &ir::Component) -> Vec<String> {
     external_memories_cells(comp)
         .iter()
         .map(|cell_ref| cell_ref.borrow().name().to_string())
         .collect()
 }
 
-fn top_level(toplevel: &ir::Component) -> v::Module {
+fn top_level(
+    toplevel: &ir::Component,
+    config: &XilinxConfig,
+    streams: &[stream_axi::Stream],
+) -> v::Module {
     let memories = &external_memories(toplevel);
     let mem_info = get_mem_info(toplevel);
-    assert!(!memories.is_empty()); // At least 1 memory should exist within the toplevel
+    // At least 1 memory or stream should exist within the toplevel
+    assert!(!memories.is_empty() || !streams.is_empty());
     let mut module = v::Module::new("Toplevel");
 
     // add system signals
@@ -154,8 +192,17 @@ fn top_level(toplevel: &ir::Component) -> v::Module {
 
     // add an axi interface for each external memory
     for (idx, _mem) in memories.iter().enumerate() {
-        axi::AxiInterface::memory_channels(64, 512, &format!("m{}_axi_", idx))
-            .add_ports_to(&mut module);
+        axi::AxiInterface::memory_channels(
+            64,
+            config.bus_width,
+            &format!("m{}_axi_", idx),
+        )
+        .add_ports_to(&mut module);
+    }
+
+    // add an axi stream interface for each stream
+    for stream in streams {
+        stream.channel().add_ports_to(&mut module);
     }
 
     // wires
@@ -271,6 +318,10 @@ fn top_level(toplevel: &ir::Component) -> v::Module {
         kernel_instance.connect_ref(&write_en, &write_en);
         kernel_instance.connect_ref(&clk, "");
     }
+    // streams can only transfer data while the kernel is running
+    for stream in streams {
+        stream.connect(&mut module, &mut kernel_instance, "kernel_start");
+    }
     module.add_instance(kernel_instance);
 
     // add timeout counter
@@ -302,7 +353,7 @@ fn host_transfer_fsm(module: &mut v::Module, memories: &[String]) {
     module.add_stmt(v::Parallel::Assign(
         "memories_copied".into(),
         if memories.is_empty() {
-            panic!("Need some memories")
+            v::Expr::new_ulit_bin(1, "1")
         } else if memories.len() == 1 {
             format!("{}_copy_done", memories[0]).into()
         } else {
@@ -334,12 +385,16 @@ fn host_transfer_fsm(module: &mut v::Module, memories: &[String]) {
     let mut ifelse = v::SequentialIfElse::new(fsm.state_is("send"));
     ifelse.add_seq(v::Sequential::new_nonblk_assign(
         "memories_sent",
-        memories[1..].iter().fold(
-            format!("{}_send_done", memories[0]).into(),
-            |acc, elem| {
-                v::Expr::new_bit_and(acc, format!("{}_send_done", elem))
-            },
-        ),
+        if memories.is_empty() {
+            v::Expr::new_ulit_bin(1, "1")
+        } else {
+            memories[1..].iter().fold(
+                format!("{}_send_done", memories[0]).into(),
+                |acc, elem| {
+                    v::Expr::new_bit_and(acc, format!("{}_send_done", elem))
+                },
+            )
+        },
     ));
 
     ifelse.set_else(v::Sequential::new_nonblk_assign("memories_sent", 0));
@@ -1,3 +1,5 @@
+use super::config::XilinxConfig;
+use super::stream_axi::{self, StreamDirection};
 use crate::backend::traits::Backend;
 use calyx::{errors::CalyxResult, ir};
 use serde::Serialize;
@@ -47,10 +49,13 @@ impl<'a> From<Vec<Port<'a>>> for Ports<'a> {
 struct Port<'a> {
     name: &'a str,
     mode: &'a str,
-    range: &'a str,
+    // Stream ports are not addressable and have no range or base.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    range: Option<&'a str>,
     data_width: u64,
     port_type: &'a str,
-    base: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    base: Option<&'a str>,
 }
 
 #[derive(Serialize)]
@@ -104,13 +109,15 @@ impl Backend for XilinxXmlBackend {
             .find(|comp| comp.name == prog.entrypoint)
             .unwrap();
 
+        let config = XilinxConfig::from_ctx(prog)?;
+
         let mut ports = vec![Port {
             name: "s_axi_control",
             mode: "slave",
-            range: "0x1000",
+            range: Some("0x1000"),
             data_width: 32,
             port_type: "addressable",
-            base: "0x0",
+            base: Some("0x0"),
         }];
 
         let mut args = vec![Arg {
@@ -149,13 +156,12 @@ impl Backend for XilinxXmlBackend {
             ports.push(Port {
                 name: axi_name,
                 mode: "master",
-                range: "0xFFFFFFFFFFFFFFFF",
+                range: Some("0xFFFFFFFFFFFFFFFF"),
                 // Width should match the bus data width of memory modules
-                // described in hardware, for example see
-                // https://github.com/cucapra/calyx/blob/c2b12a0fe6b1ee3aaaae0c66e7c4619ee6c82614/src/backend/xilinx/toplevel.rs#L58
-                data_width: 512,
+                // described in hardware.
+                data_width: config.bus_width,
                 port_type: "addressable",
-                base: "0x0",
+                base: Some("0x0"),
             });
             args.push(Arg {
                 name,
@@ -173,6 +179,43 @@ impl Backend for XilinxXmlBackend {
             });
         }
 
+        // Streams are not controlled by the host so they come after all the
+        // other arguments and do not use any control registers.
+        let streams = stream_axi::external_streams(toplevel)?;
+        let stream_info: Vec<(String, String)> = streams
+            .iter()
+            .map(|stream| {
+                (
+                    stream.interface(),
+                    format!("stream<ap_axiu<{},0,0,0>>&", stream.bus_width()),
+                )
+            })
+            .collect();
+        for (stream, (interface, typ)) in streams.iter().zip(&stream_info) {
+            ports.push(Port {
+                name: interface,
+                mode: match stream.direction {
+                    StreamDirection::In => "read_only",
+                    StreamDirection::Out => "write_only",
+                },
+                range: None,
+                data_width: stream.bus_width(),
+                port_type: "stream",
+                base: None,
+            });
+            args.push(Arg {
+                name: &stream.name,
+                address_qualifier: 4,
+                id: args.len() as u64,
+                port: interface,
+                size: "0x0",
+                offset: "0x0",
+                typ,
+                host_offset: "0x0",
+                host_size: "0x0",
+            });
+        }
+
         let root = Root {
             version_major: 1,
             version_minor: 6,
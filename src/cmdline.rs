@@ -1,7 +1,8 @@
 use crate::backend::traits::Backend;
 use crate::backend::{
     mlir::MlirBackend, resources::ResourcesBackend, verilog::VerilogBackend,
-    xilinx::XilinxInterfaceBackend, xilinx::XilinxXmlBackend,
+    xilinx::XilinxHostBackend, xilinx::XilinxInterfaceBackend,
+    xilinx::XilinxXmlBackend,
 };
 use argh::FromArgs;
 use calyx::errors::Error;
@@ -22,9 +23,9 @@ pub struct Opts {
     #[argh(option, short = 'o', default = "OutputFile::default()")]
     pub output: OutputFile,
 
-    /// path to the primitives library
-    #[argh(option, short = 'l', default = "Path::new(\".\").into()")]
-    pub lib_path: PathBuf,
+    /// paths searched for imported files, in order (default: .)
+    #[argh(option, short = 'l')]
+    pub lib_path: Vec<PathBuf>,
 
     /// compilation mode
     #[argh(option, short = 'm', default = "CompileMode::default()")]
@@ -42,6 +43,10 @@ pub struct Opts {
     #[argh(switch)]
     pub disable_init: bool,
 
+    /// annotate generated code with source locations
+    #[argh(switch)]
+    pub source_locations: bool,
+
     /// select a backend
     #[argh(option, short = 'b', default = "BackendOpt::default()")]
     pub backend: BackendOpt,
@@ -65,6 +70,10 @@ pub struct Opts {
     /// enable verbose printing
     #[argh(option, long = "log", default = "log::LevelFilter::Warn")]
     pub log_level: log::LevelFilter,
+
+    /// format used to report errors: human or json
+    #[argh(option, long = "error-format", default = "ErrorFormat::default()")]
+    pub error_format: ErrorFormat,
 }
 
 fn read_path(path: &str) -> Result<PathBuf, String> {
@@ -98,6 +107,31 @@ impl FromStr for CompileMode {
     }
 }
 
+/// Formats used to report errors
+#[derive(PartialEq, Eq, Default)]
+pub enum ErrorFormat {
+    /// Show the source of each error.
+    #[default]
+    Human,
+    /// Print a JSON array with the location of each error.
+    Json,
+}
+
+impl FromStr for ErrorFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "human" => Ok(ErrorFormat::Human),
+            "json" => Ok(ErrorFormat::Json),
+            s => Err(format!(
+                "Unknown error format: {}. Valid options are `human` or `json`",
+                s
+            )),
+        }
+    }
+}
+
 // ================== Backend Variant and Parsing ===================== //
 
 /// Enumeration of valid backends
@@ -106,6 +140,7 @@ pub enum BackendOpt {
     Verilog,
     Xilinx,
     XilinxXml,
+    XilinxHost,
     Calyx,
     Mlir,
     Resources,
@@ -119,6 +154,7 @@ fn backends() -> Vec<(&'static str, BackendOpt)> {
         ("verilog", BackendOpt::Verilog),
         ("xilinx", BackendOpt::Xilinx),
         ("xilinx-xml", BackendOpt::XilinxXml),
+        ("xilinx-host", BackendOpt::XilinxHost),
         ("futil", BackendOpt::Calyx),
         ("calyx", BackendOpt::Calyx),
         ("mlir", BackendOpt::Mlir),
@@ -169,6 +205,7 @@ impl ToString for BackendOpt {
             Self::Verilog => "verilog",
             Self::Xilinx => "xilinx",
             Self::XilinxXml => "xilinx-xml",
+            Self::XilinxHost => "xilinx-host",
             Self::Calyx => "calyx",
             Self::None => "none",
         }
@@ -200,6 +237,10 @@ impl Opts {
                 let backend = XilinxXmlBackend::default();
                 backend.run(context, self.output)
             }
+            BackendOpt::XilinxHost => {
+                let backend = XilinxHostBackend::default();
+                backend.run(context, self.output)
+            }
             BackendOpt::Calyx => {
                 for (path, prims) in context.lib.externs() {
                     ir::Printer::write_extern(
@@ -241,6 +282,9 @@ impl Opts {
         if opts.pass.is_empty() {
             opts.pass = vec!["all".into()];
         }
+        if opts.lib_path.is_empty() {
+            opts.lib_path = vec![Path::new(".").into()];
+        }
 
         Ok(opts)
     }
@@ -2,13 +2,26 @@ mod backend;
 mod cmdline;
 
 use calyx::{errors::CalyxResult, frontend, ir, pass_manager::PassManager};
-use cmdline::{BackendOpt, CompileMode, Opts};
+use cmdline::{BackendOpt, CompileMode, ErrorFormat, Opts};
 use itertools::Itertools;
 
 fn main() -> CalyxResult<()> {
     // parse the command line arguments into Opts struct
-    let mut opts = Opts::get_opts()?;
+    let opts = Opts::get_opts()?;
 
+    if opts.error_format == ErrorFormat::Json {
+        if let Err(err) = compile(opts) {
+            let errors = err.errors().map(|e| e.to_json()).collect_vec();
+            eprintln!("{}", serde_json::to_string_pretty(&errors).unwrap());
+            std::process::exit(1);
+        }
+        Ok(())
+    } else {
+        compile(opts)
+    }
+}
+
+fn compile(mut opts: Opts) -> CalyxResult<()> {
     // enable tracing
     env_logger::Builder::new()
         .format_timestamp(None)
@@ -36,6 +49,7 @@ fn main() -> CalyxResult<()> {
         synthesis_mode: opts.enable_synthesis,
         enable_verification: !opts.disable_verify,
         initialize_inputs: !opts.disable_init,
+        source_locations: opts.source_locations,
     };
     // Extra options for the passes
     ctx.extra_opts = opts.extra_opts.drain(..).collect();
@@ -71,7 +85,7 @@ fn main() -> CalyxResult<()> {
         } else {
             // Print out the original imports for this file.
             for import in imports {
-                writeln!(out, "import \"{}\";", import)?;
+                writeln!(out, "{}", import)?;
             }
         }
         for comp in &ctx.components {
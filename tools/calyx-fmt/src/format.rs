@@ -0,0 +1,892 @@
+//! Prints the AST of a Calyx program in the canonical style.
+//!
+//! Every line of output is associated with the offset in the source program
+//! that it was printed from. Comments are emitted when the output passes
+//! their offset: comments on their own line are printed before the next line
+//! and comments following code are appended to the line printed before them.
+use crate::source::Source;
+use calyx::{
+    errors::CalyxResult,
+    frontend::{ast, parser::CalyxParser},
+    ir::{self, Attributes},
+    utils::{GPosIdx, WithPos},
+};
+use std::path::Path;
+
+/// Number of spaces used for each level of indentation.
+const INDENT: usize = 2;
+
+/// Format the program `source` read from `path`. Lines are wrapped to fit in
+/// `width` columns where possible.
+pub fn format(
+    path: &Path,
+    source: String,
+    width: usize,
+) -> CalyxResult<String> {
+    let namespace = CalyxParser::parse_source(path, source.clone())?;
+    let mut fmt = Formatter {
+        src: Source::new(&source),
+        width,
+        out: String::new(),
+        indent: 0,
+        comment: 0,
+        last: 0,
+        block_start: true,
+        blank: false,
+        after_comment: false,
+    };
+    fmt.namespace(&namespace);
+    Ok(fmt.out)
+}
+
+/// The byte offsets of the start and end of a span.
+fn offsets(span: GPosIdx) -> (usize, usize) {
+    let (_, start, end) = span.get_location();
+    (start, end)
+}
+
+/// The top-level definitions of a file.
+enum Item<'a> {
+    Extern(&'a str, &'a [ir::Primitive]),
+    Primitive(&'a ir::Primitive),
+    Component(&'a ast::ComponentDef),
+}
+
+struct Formatter<'a> {
+    src: Source<'a>,
+    /// Maximum width of lines.
+    width: usize,
+    out: String,
+    /// Current indentation in spaces.
+    indent: usize,
+    /// Index of the first comment that has not been printed.
+    comment: usize,
+    /// Offset in the source where the last line printed ended.
+    last: usize,
+    /// True if nothing has been printed since the start of the current block.
+    block_start: bool,
+    /// True if a blank line should be printed before the next line.
+    blank: bool,
+    /// True if the last line printed was a comment.
+    after_comment: bool,
+}
+
+impl Formatter<'_> {
+    // ============ Layout ============
+
+    /// Print the comments before `pos`. Comments that follow code are
+    /// appended to the previous line if `attach` is true.
+    fn comments(&mut self, pos: usize, attach: bool) {
+        while let Some(comment) = self.src.comments.get(self.comment) {
+            if comment.start >= pos {
+                break;
+            }
+            let (start, end) = (comment.start, comment.end);
+            let text = self.src.text[start..end].trim_end();
+            self.comment += 1;
+            if attach && comment.trailing && !self.out.is_empty() {
+                self.out.pop();
+                self.out.push(' ');
+                self.out.push_str(text);
+                self.out.push('\n');
+            } else {
+                self.lines(start, end, vec![text.to_string()]);
+                self.after_comment = true;
+            }
+        }
+    }
+
+    /// Separate the top-level definition at `start` from the previous one
+    /// by a blank line. Comments before the definition stay attached to it.
+    fn separate(&mut self, start: usize) {
+        self.comments(start, true);
+        if !self.after_comment {
+            self.blank = true;
+        }
+    }
+
+    /// Append comments that immediately follow `end` on the same line to the
+    /// last line printed.
+    fn trailing(&mut self, mut end: usize) {
+        while let Some(comment) = self.src.comments.get(self.comment) {
+            let gap = self.src.text.get(end..comment.start).unwrap_or("\n");
+            if gap.contains('\n')
+                || gap.chars().any(|c| !c.is_whitespace() && c != ';')
+            {
+                break;
+            }
+            end = comment.end;
+            self.comments(comment.end, true);
+        }
+    }
+
+    /// Print `lines` for the source text between `start` and `end`. Every
+    /// line is indented by the current indentation.
+    fn lines(&mut self, start: usize, end: usize, lines: Vec<String>) {
+        let gap = self.src.text.get(self.last..start).unwrap_or("");
+        let blank = self.blank || has_blank_line(gap);
+        if blank && !self.block_start && !self.out.is_empty() {
+            self.out.push('\n');
+        }
+        for line in lines {
+            self.out.push_str(&" ".repeat(self.indent));
+            self.out.push_str(&line);
+            self.out.push('\n');
+        }
+        self.last = self.last.max(end);
+        self.block_start = false;
+        self.blank = false;
+    }
+
+    /// Print an item spanning `start` to `end` in the source along with the
+    /// comments around it.
+    fn item(&mut self, start: usize, end: usize, lines: Vec<String>) {
+        self.comments(start, true);
+        self.comments(end, false);
+        self.anchored(end, lines.into_iter().map(|l| (start, l)).collect());
+    }
+
+    /// Print an item ending at `end` whose lines are printed from the source
+    /// at the given offsets. Comments between the offsets are printed
+    /// between the lines. Lines may be indented relative to the item.
+    fn anchored(&mut self, end: usize, lines: Vec<(usize, String)>) {
+        let count = lines.len();
+        for (idx, (pos, line)) in lines.into_iter().enumerate() {
+            let text = line.trim_start();
+            let extra = line.len() - text.len();
+            self.indent += extra;
+            self.comments(pos, true);
+            if idx + 1 == count {
+                self.comments(end, false);
+            }
+            self.lines(pos, pos, vec![text.to_string()]);
+            self.indent -= extra;
+        }
+        self.last = self.last.max(end);
+        self.after_comment = false;
+        self.trailing(end);
+    }
+
+    /// Print `header` for the block opened by the `{` at `brace`.
+    fn open(&mut self, brace: usize, header: Vec<(usize, String)>) {
+        self.anchored(brace + 1, header);
+        self.indent += INDENT;
+        self.block_start = true;
+    }
+
+    /// Print the comments in the block closed by the `}` at `brace` and
+    /// leave the block.
+    fn leave(&mut self, brace: usize) {
+        self.comments(brace, true);
+        self.indent -= INDENT;
+        self.blank = false;
+    }
+
+    /// Close the block ended by the `}` at `brace`. Empty blocks are printed
+    /// as `{}`.
+    fn close(&mut self, brace: usize) {
+        self.leave(brace);
+        if self.block_start && self.out.ends_with("{\n") {
+            self.out.pop();
+            self.out.push_str("}\n");
+            self.last = brace + 1;
+            self.block_start = false;
+            self.trailing(brace + 1);
+        } else {
+            self.block_start = true;
+            self.item(brace, brace + 1, vec!["}".to_string()]);
+        }
+    }
+
+    /// Append `parts` to the last of `lines` in parentheses followed by
+    /// `after`. Each part is placed on its own line. `close` is the offset
+    /// of the closing parenthesis.
+    fn parens(
+        lines: &mut Vec<(usize, String)>,
+        parts: Vec<(usize, String)>,
+        close: usize,
+        after: &str,
+    ) {
+        let last = &mut lines.last_mut().unwrap().1;
+        if parts.is_empty() {
+            last.push_str(&format!("(){after}"));
+            return;
+        }
+        last.push('(');
+        let count = parts.len();
+        lines.extend(parts.into_iter().enumerate().map(
+            |(idx, (pos, part))| {
+                let sep = if idx + 1 == count { "" } else { "," };
+                (pos, format!("{}{part}{sep}", " ".repeat(INDENT)))
+            },
+        ));
+        lines.push((close, format!("){after}")));
+    }
+
+    /// The offset of the closing parenthesis of the first list after `pos`
+    /// and the offsets of `names` in that list, which are followed by `next`.
+    fn list<'n>(
+        &self,
+        pos: usize,
+        names: impl Iterator<Item = &'n str>,
+        next: &str,
+    ) -> (usize, Vec<usize>) {
+        let open = self.src.find(pos, "(");
+        let close = self.src.closing(open);
+        let offsets = names
+            .map(|name| self.src.find_name(open, close, name, next))
+            .map(|pos| pos.unwrap_or(open))
+            .collect();
+        (close, offsets)
+    }
+
+    // ============ Definitions ============
+
+    fn namespace(&mut self, ns: &ast::NamespaceDef) {
+        let imports = self.src.top_level("import");
+        for (import, start) in ns.imports.iter().zip(imports) {
+            let end = self.src.find(start, ";") + 1;
+            self.item(start, end, vec![import.to_string()]);
+        }
+
+        let mut items = vec![];
+        let mut externs = self.src.top_level("extern").into_iter();
+        for (path, prims) in &ns.externs {
+            match path {
+                Some(path) => {
+                    let start = externs.next().unwrap_or_default();
+                    items.push((start, Item::Extern(path, prims)));
+                }
+                None => items.extend(prims.iter().map(|prim| {
+                    (
+                        offsets(prim.attributes.copy_span()).0,
+                        Item::Primitive(prim),
+                    )
+                })),
+            }
+        }
+        items.extend(ns.components.iter().map(|comp| {
+            (
+                offsets(comp.attributes.copy_span()).0,
+                Item::Component(comp),
+            )
+        }));
+        items.sort_by_key(|(start, _)| *start);
+
+        for (start, item) in items {
+            self.separate(start);
+            match item {
+                Item::Extern(path, prims) => {
+                    let brace = self.src.find(start, "{");
+                    self.open(
+                        brace,
+                        vec![(start, format!("extern \"{path}\" {{"))],
+                    );
+                    for prim in prims {
+                        self.primitive(prim);
+                    }
+                    self.close(self.src.closing(brace));
+                }
+                Item::Primitive(prim) => self.primitive(prim),
+                Item::Component(comp) => self.component(comp),
+            }
+        }
+
+        if let Some(metadata) = &ns.metadata {
+            let start =
+                self.src.top_level("metadata").pop().unwrap_or_default();
+            let end =
+                self.src.next(start + 1).map_or(start, |(s, t)| s + t.len());
+            self.separate(start);
+            let lines = vec![format!("metadata #{{\n{metadata}\n}}#")];
+            self.item(start, end, lines);
+        }
+        self.comments(self.src.text.len(), true);
+    }
+
+    /// True if there are comments between `start` and `end`.
+    fn has_comments(&self, start: usize, end: usize) -> bool {
+        self.src.comments[self.comment..]
+            .iter()
+            .any(|c| c.start >= start && c.start < end)
+    }
+
+    /// Format the signature of the definition between `start` and `end`.
+    /// Ports are placed on separate lines if the signature does not fit on
+    /// one line or contains comments.
+    fn signature<W: ToString>(
+        &self,
+        (start, end): (usize, usize),
+        prefix: String,
+        ports: &[ir::PortDef<W>],
+        suffix: &str,
+    ) -> Vec<(usize, String)> {
+        let format = |dir: ir::Direction| {
+            ports
+                .iter()
+                .filter(|pd| pd.direction == dir)
+                .map(|pd| {
+                    let port = format!(
+                        "{}{}: {}",
+                        at_attributes(&pd.attributes),
+                        pd.name,
+                        pd.width.to_string()
+                    );
+                    (pd.name.as_ref(), port)
+                })
+                .collect::<Vec<_>>()
+        };
+        let (inputs, outputs) =
+            (format(ir::Direction::Input), format(ir::Direction::Output));
+        let join = |ports: &[(&str, String)]| {
+            ports
+                .iter()
+                .map(|(_, p)| p.as_str())
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        let line = format!(
+            "{prefix}({}) -> ({}){suffix}",
+            join(&inputs),
+            join(&outputs)
+        );
+        // The body of an inline primitive does not count towards the width.
+        let first = line.lines().next().unwrap_or_default();
+        if self.indent + first.len() <= self.width
+            && !self.has_comments(start, end)
+        {
+            return vec![(start, line)];
+        }
+        let mut lines = vec![(start, prefix)];
+        let (close, offsets) =
+            self.list(start, inputs.iter().map(|(n, _)| *n), ":");
+        let ports = offsets.into_iter().zip(inputs.into_iter().map(|(_, p)| p));
+        Self::parens(&mut lines, ports.collect(), close, " -> ");
+        let (close, offsets) =
+            self.list(close + 1, outputs.iter().map(|(n, _)| *n), ":");
+        let ports =
+            offsets.into_iter().zip(outputs.into_iter().map(|(_, p)| p));
+        Self::parens(&mut lines, ports.collect(), close, suffix);
+        lines
+    }
+
+    fn primitive(&mut self, prim: &ir::Primitive) {
+        let (start, end) = offsets(prim.attributes.copy_span());
+        let mut prefix = format!(
+            "{}primitive {}{}",
+            if prim.is_comb { "comb " } else { "" },
+            prim.name,
+            attributes(&prim.attributes)
+        );
+        if !prim.params.is_empty() {
+            let params = prim.params.iter().map(|p| p.to_string());
+            prefix += &format!("[{}]", params.collect::<Vec<_>>().join(", "));
+        }
+        let suffix = match &prim.body {
+            Some(body) => format!(" {{{body}}};"),
+            None => ";".to_string(),
+        };
+        // The bodies of inline primitives are printed unchanged.
+        let lines =
+            self.signature((start, end), prefix, &prim.signature, &suffix);
+        self.comments(start, true);
+        self.anchored(end, lines);
+    }
+
+    fn component(&mut self, comp: &ast::ComponentDef) {
+        let (start, end) = offsets(comp.attributes.copy_span());
+        let prefix = format!(
+            "{}component {}{}",
+            if comp.is_comb { "comb " } else { "" },
+            comp.name,
+            attributes(&comp.attributes)
+        );
+        let brace = self.src.find(start, "{");
+        let header =
+            self.signature((start, brace), prefix, &comp.signature, " {");
+        self.open(brace, header);
+
+        // Cells
+        let (cells, cells_brace) = self.section(brace + 1);
+        self.open(cells_brace, vec![(cells, "cells {".to_string())]);
+        for cell in &comp.cells {
+            self.cell(cell);
+        }
+        let cells_end = self.src.closing(cells_brace);
+        self.close(cells_end);
+
+        // Wires
+        let (wires, wires_brace) = self.section(cells_end + 1);
+        self.open(wires_brace, vec![(wires, "wires {".to_string())]);
+        let mut items =
+            comp.groups
+                .iter()
+                .map(|g| (offsets(g.attributes.copy_span()).0, Some(g), None))
+                .chain(comp.continuous_assignments.iter().map(|w| {
+                    (offsets(w.attributes.copy_span()).0, None, Some(w))
+                }))
+                .collect::<Vec<_>>();
+        items.sort_by_key(|(start, _, _)| *start);
+        for item in items {
+            match item {
+                (_, Some(group), _) => self.group(group),
+                (_, _, Some(wire)) => self.wire(wire),
+                _ => unreachable!(),
+            }
+        }
+        let wires_end = self.src.closing(wires_brace);
+        self.close(wires_end);
+
+        // Control
+        if let Some((control, "control")) = self.src.next(wires_end + 1) {
+            let brace = self.src.find(control, "{");
+            self.open(brace, vec![(control, "control {".to_string())]);
+            self.block(&comp.control);
+            self.close(self.src.closing(brace));
+        }
+        self.close(end - 1);
+    }
+
+    /// The offsets of the keyword and `{` of the section after `pos`.
+    fn section(&self, pos: usize) -> (usize, usize) {
+        let (start, _) = self.src.next(pos).unwrap_or((pos, ""));
+        (start, self.src.find(start, "{"))
+    }
+
+    fn cell(&mut self, cell: &ast::Cell) {
+        let (start, end) = offsets(cell.attributes.copy_span());
+        let params = cell.prototype.params.iter().map(|p| p.to_string());
+        let line = format!(
+            "{}{}{} = {}({});",
+            at_attributes(&cell.attributes),
+            if cell.reference { "ref " } else { "" },
+            cell.name,
+            cell.prototype.name,
+            params.collect::<Vec<_>>().join(", ")
+        );
+        self.item(start, end, vec![line]);
+    }
+
+    fn group(&mut self, group: &ast::Group) {
+        let (start, end) = offsets(group.attributes.copy_span());
+        let brace = self.src.find(start, "{");
+        let header = format!(
+            "{}group {}{} {{",
+            if group.is_comb { "comb " } else { "" },
+            group.name,
+            attributes(&group.attributes)
+        );
+        self.open(brace, vec![(start, header)]);
+        for wire in &group.wires {
+            self.wire(wire);
+        }
+        self.close(end - 1);
+    }
+
+    fn wire(&mut self, wire: &ast::Wire) {
+        let (start, end) = offsets(wire.attributes.copy_span());
+        let dest = format!(
+            "{}{} = ",
+            at_attributes(&wire.attributes),
+            port(&wire.dest)
+        );
+        let src = atom(&wire.src.expr);
+        let Some(guard) = &wire.src.guard else {
+            self.item(start, end, vec![format!("{dest}{src};")]);
+            return;
+        };
+        let line = format!("{dest}{} ? {src};", guard_expr(guard));
+        if self.indent + line.len() <= self.width {
+            self.item(start, end, vec![line]);
+            return;
+        }
+        // Place each operand of the top-level operator on its own line.
+        let (op, mut operands) = match guard {
+            ast::GuardExpr::Or(..) => ("|", vec![]),
+            ast::GuardExpr::And(..) => ("&", vec![]),
+            _ => {
+                self.item(start, end, vec![line]);
+                return;
+            }
+        };
+        flatten(guard, &mut operands);
+        let mut lines = operands
+            .into_iter()
+            .enumerate()
+            .map(|(idx, g)| {
+                if idx == 0 {
+                    format!("{dest}{g}")
+                } else {
+                    format!("{}{op} {g}", " ".repeat(INDENT))
+                }
+            })
+            .collect::<Vec<_>>();
+        *lines.last_mut().unwrap() += &format!(" ? {src};");
+        self.item(start, end, lines);
+    }
+
+    // ============ Control ============
+
+    /// Print the body of a block. The statements of blocks without an
+    /// explicit `seq` are printed directly.
+    fn block(&mut self, con: &ast::Control) {
+        match con {
+            ast::Control::Seq { stmts, attributes }
+                if attributes.copy_span() == GPosIdx::UNKNOWN =>
+            {
+                for stmt in stmts {
+                    self.control(stmt);
+                }
+            }
+            con => self.control(con),
+        }
+    }
+
+    fn control(&mut self, con: &ast::Control) {
+        let attrs = control_attributes(con);
+        let span = attrs.copy_span();
+        if span == GPosIdx::UNKNOWN {
+            return;
+        }
+        let (start, end) = offsets(span);
+        let at = at_attributes(attrs);
+        match con {
+            ast::Control::Seq { stmts, .. }
+            | ast::Control::Par { stmts, .. } => {
+                let kw = if matches!(con, ast::Control::Seq { .. }) {
+                    "seq"
+                } else {
+                    "par"
+                };
+                let brace = self.src.find(start, "{");
+                self.open(brace, vec![(start, format!("{at}{kw} {{"))]);
+                for stmt in stmts {
+                    self.control(stmt);
+                }
+                self.close(end - 1);
+            }
+            ast::Control::If { .. } => self.if_stmt(con, "", start),
+            ast::Control::While {
+                port: p,
+                cond,
+                body,
+                ..
+            } => {
+                let brace = self.src.find(start, "{");
+                let header = format!("{at}while {}{} {{", port(p), with(cond));
+                self.open(brace, vec![(start, header)]);
+                self.block(body);
+                self.close(end - 1);
+            }
+            ast::Control::Enable { comp, .. } => {
+                self.item(start, end, vec![format!("{at}{comp};")])
+            }
+            ast::Control::Invoke {
+                comp,
+                inputs,
+                outputs,
+                comb_group,
+                ref_cells,
+                ..
+            } => {
+                let mut prefix = format!("{at}invoke {comp}");
+                if !ref_cells.is_empty() {
+                    let refs = ref_cells.iter().map(|(outcell, incell)| {
+                        format!("{outcell} = {incell}")
+                    });
+                    prefix +=
+                        &format!("[{}]", refs.collect::<Vec<_>>().join(", "));
+                }
+                let args = |args: &[(ir::Id, ast::Atom)]| {
+                    args.iter()
+                        .map(|(name, a)| format!("{name} = {}", atom(a)))
+                        .collect::<Vec<_>>()
+                };
+                let suffix = format!("{};", with(comb_group));
+                let (ins, outs) = (args(inputs), args(outputs));
+                let line = format!(
+                    "{prefix}({})({}){suffix}",
+                    ins.join(", "),
+                    outs.join(", ")
+                );
+                if self.indent + line.len() <= self.width
+                    && !self.has_comments(start, end)
+                {
+                    self.item(start, end, vec![line]);
+                    return;
+                }
+                // Attributes may contain parentheses.
+                let invoke = self.src.find(start, "invoke");
+                let mut lines = vec![(start, prefix)];
+                let names = inputs.iter().map(|(n, _)| n.as_ref());
+                let (close, offsets) = self.list(invoke, names, "=");
+                Self::parens(
+                    &mut lines,
+                    offsets.into_iter().zip(ins).collect(),
+                    close,
+                    "",
+                );
+                let names = outputs.iter().map(|(n, _)| n.as_ref());
+                let (close, offsets) = self.list(close + 1, names, "=");
+                Self::parens(
+                    &mut lines,
+                    offsets.into_iter().zip(outs).collect(),
+                    close,
+                    &suffix,
+                );
+                self.comments(start, true);
+                self.anchored(end, lines);
+            }
+            ast::Control::Empty { .. } => {
+                self.item(start, end, vec![format!("{};", at.trim_end())])
+            }
+        }
+    }
+
+    /// Print the `if` statement `con` preceded by `prefix`. `else if`
+    /// chains are printed without nesting.
+    fn if_stmt(&mut self, con: &ast::Control, prefix: &str, start: usize) {
+        let ast::Control::If {
+            port: p,
+            cond,
+            tbranch,
+            fbranch,
+            attributes,
+        } = con
+        else {
+            unreachable!()
+        };
+        let (if_start, _) = offsets(attributes.copy_span());
+        let brace = self.src.find(if_start, "{");
+        let header = format!(
+            "{prefix}{}if {}{} {{",
+            at_attributes(attributes),
+            port(p),
+            with(cond)
+        );
+        self.open(brace, vec![(start, header)]);
+        self.block(tbranch);
+        let tclose = self.src.closing(brace);
+        if control_attributes(fbranch).copy_span() == GPosIdx::UNKNOWN
+            && !matches!(**fbranch, ast::Control::Seq { .. })
+        {
+            self.close(tclose);
+            return;
+        }
+        self.leave(tclose);
+        let else_kw = self.src.find(tclose, "else");
+        match self.src.next(else_kw + 1) {
+            Some((_, "{")) | None => {
+                let brace = self.src.find(else_kw, "{");
+                self.block_start = false;
+                self.open(brace, vec![(tclose, "} else {".to_string())]);
+                self.block(fbranch);
+                self.close(self.src.closing(brace));
+            }
+            Some(_) => self.if_stmt(fbranch, "} else ", tclose),
+        }
+    }
+}
+
+/// True if `gap` contains an empty line.
+fn has_blank_line(gap: &str) -> bool {
+    let lines = gap.split('\n').collect::<Vec<_>>();
+    lines.len() > 2
+        && lines[1..lines.len() - 1]
+            .iter()
+            .any(|l| l.trim().is_empty())
+}
+
+fn control_attributes(con: &ast::Control) -> &Attributes {
+    match con {
+        ast::Control::Seq { attributes, .. }
+        | ast::Control::Par { attributes, .. }
+        | ast::Control::If { attributes, .. }
+        | ast::Control::While { attributes, .. }
+        | ast::Control::Enable { attributes, .. }
+        | ast::Control::Invoke { attributes, .. }
+        | ast::Control::Empty { attributes } => attributes,
+    }
+}
+
+/// Attributes sorted by name.
+fn sorted(attrs: &Attributes) -> Vec<(String, u64)> {
+    let mut attrs = attrs
+        .into_iter()
+        .map(|(k, v)| (k.to_string(), *v))
+        .collect::<Vec<_>>();
+    attrs.sort();
+    attrs
+}
+
+/// Format attributes of the form `@static(1)`. Attributes with the value 1
+/// are printed without a value.
+fn at_attributes(attrs: &Attributes) -> String {
+    sorted(attrs)
+        .into_iter()
+        .map(|(k, v)| {
+            if v == 1 {
+                format!("@{k} ")
+            } else {
+                format!("@{k}({v}) ")
+            }
+        })
+        .collect()
+}
+
+/// Format attributes of the form `<"static"=1>`.
+fn attributes(attrs: &Attributes) -> String {
+    let attrs = sorted(attrs);
+    if attrs.is_empty() {
+        return String::new();
+    }
+    let attrs = attrs.into_iter().map(|(k, v)| format!("\"{k}\"={v}"));
+    format!("<{}>", attrs.collect::<Vec<_>>().join(", "))
+}
+
+fn with(cond: &Option<ir::Id>) -> String {
+    cond.map(|c| format!(" with {c}")).unwrap_or_default()
+}
+
+fn port(port: &ast::Port) -> String {
+    match port {
+        ast::Port::Comp { component, port } => format!("{component}.{port}"),
+        ast::Port::This { port } => port.to_string(),
+        ast::Port::Hole { group, name } => format!("{group}[{name}]"),
+    }
+}
+
+/// Numbers are printed as they were written.
+fn atom(expr: &ast::Atom) -> String {
+    match expr {
+        ast::Atom::Port(p) => port(p),
+        ast::Atom::Num(num) => num.span.get_text().to_string(),
+        ast::Atom::BinOp {
+            op, left, right, ..
+        } => {
+            let prec = op.precedence();
+            // Operators are left associative.
+            format!(
+                "{} {} {}",
+                arith_operand(left, prec),
+                op.symbol(),
+                arith_operand(right, prec + 1)
+            )
+        }
+        ast::Atom::Slice {
+            atom: inner,
+            hi,
+            lo,
+            ..
+        } => match &**inner {
+            ast::Atom::Port(_) | ast::Atom::Num(_) => {
+                format!("{}[{hi}:{lo}]", atom(inner))
+            }
+            _ => format!("({})[{hi}:{lo}]", atom(inner)),
+        },
+    }
+}
+
+/// Format the operand of an inline operator with parentheses if it binds
+/// weaker than `prec`.
+fn arith_operand(operand: &ast::Atom, prec: u8) -> String {
+    match operand {
+        ast::Atom::BinOp { op, .. } if op.precedence() < prec => {
+            format!("({})", atom(operand))
+        }
+        _ => atom(operand),
+    }
+}
+
+/// Returns true if `operand` uses an operator that has a different meaning
+/// in guards.
+fn uses_guard_op(operand: &ast::Atom) -> bool {
+    match operand {
+        ast::Atom::BinOp {
+            op, left, right, ..
+        } => {
+            !matches!(
+                op,
+                ast::BinOp::Add
+                    | ast::BinOp::Sub
+                    | ast::BinOp::Lsh
+                    | ast::BinOp::Rsh
+                    | ast::BinOp::Xor
+            ) || uses_guard_op(left)
+                || uses_guard_op(right)
+        }
+        ast::Atom::Port(_) | ast::Atom::Num(_) | ast::Atom::Slice { .. } => {
+            false
+        }
+    }
+}
+
+/// Format an operand of a guard comparison.
+fn guard_operand(operand: &ast::Atom) -> String {
+    if uses_guard_op(operand) {
+        format!("({})", atom(operand))
+    } else {
+        atom(operand)
+    }
+}
+
+/// Binding strength of the operator at the root of `guard`.
+fn precedence(guard: &ast::GuardExpr) -> u8 {
+    match guard {
+        ast::GuardExpr::Or(..) => 1,
+        ast::GuardExpr::And(..) => 2,
+        _ => 3,
+    }
+}
+
+/// Format `guard` with parentheses if it binds weaker than `prec`.
+fn operand(guard: &ast::GuardExpr, prec: u8) -> String {
+    if precedence(guard) < prec {
+        format!("({})", guard_expr(guard))
+    } else {
+        guard_expr(guard)
+    }
+}
+
+fn guard_expr(guard: &ast::GuardExpr) -> String {
+    match guard {
+        ast::GuardExpr::Or(l, r) | ast::GuardExpr::And(l, r) => {
+            let prec = precedence(guard);
+            let op = if prec == 1 { "|" } else { "&" };
+            // Operators are left associative.
+            format!("{} {op} {}", operand(l, prec), operand(r, prec + 1))
+        }
+        ast::GuardExpr::Not(g) => match &**g {
+            ast::GuardExpr::Atom(a) => format!("!{}", atom(a)),
+            g => format!("!({})", guard_expr(g)),
+        },
+        ast::GuardExpr::CompOp(op, l, r) => {
+            let op = match op {
+                ast::GuardComp::Eq => "==",
+                ast::GuardComp::Neq => "!=",
+                ast::GuardComp::Gt => ">",
+                ast::GuardComp::Lt => "<",
+                ast::GuardComp::Geq => ">=",
+                ast::GuardComp::Leq => "<=",
+            };
+            format!("{} {op} {}", guard_operand(l), guard_operand(r))
+        }
+        ast::GuardExpr::Atom(a) => atom(a),
+    }
+}
+
+/// The operands of the chain of operators at the root of `guard`.
+fn flatten(guard: &ast::GuardExpr, operands: &mut Vec<String>) {
+    let prec = precedence(guard);
+    match guard {
+        ast::GuardExpr::Or(l, r) | ast::GuardExpr::And(l, r) => {
+            if precedence(l) == prec {
+                flatten(l, operands);
+            } else {
+                operands.push(operand(l, prec));
+            }
+            operands.push(operand(r, prec + 1));
+        }
+        _ => operands.push(guard_expr(guard)),
+    }
+}
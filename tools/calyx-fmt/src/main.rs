@@ -0,0 +1,116 @@
+//! A formatter for Calyx programs. Programs are parsed and printed in a
+//! canonical style:
+//!  - Blocks are indented by two spaces and statements are placed on their
+//!    own lines.
+//!  - Attributes are sorted by name.
+//!  - Assignments with long guards are split at the top-level operators of
+//!    the guard and long signatures and invocations place each port on its
+//!    own line.
+//!
+//! Comments and single blank lines between definitions are preserved.
+mod format;
+mod source;
+
+#[cfg(test)]
+mod tests;
+
+use argh::FromArgs;
+use calyx::errors::{CalyxResult, Error};
+use std::{
+    fs,
+    io::{self, Read},
+    path::{Path, PathBuf},
+};
+
+#[derive(FromArgs)]
+/// Format Calyx programs. Prints the formatted programs unless `--check` or
+/// `--in-place` is passed. Reads from stdin if no files are given.
+struct Opts {
+    /// files to format
+    #[argh(positional)]
+    files: Vec<PathBuf>,
+
+    /// exit with an error if any file is not formatted instead of printing
+    /// the formatted program
+    #[argh(switch)]
+    check: bool,
+
+    /// overwrite the files with the formatted programs
+    #[argh(switch, short = 'i')]
+    in_place: bool,
+
+    /// maximum width of lines
+    #[argh(option, short = 'w', default = "100")]
+    width: usize,
+}
+
+/// The first line of `formatted` that differs from `source`.
+fn first_diff(source: &str, formatted: &str) -> (usize, String, String) {
+    let mut src = source.lines();
+    let mut fmt = formatted.lines();
+    for linum in 1.. {
+        match (src.next(), fmt.next()) {
+            (Some(s), Some(f)) if s == f => continue,
+            (None, None) => break,
+            (s, f) => {
+                return (
+                    linum,
+                    s.unwrap_or_default().to_string(),
+                    f.unwrap_or_default().to_string(),
+                )
+            }
+        }
+    }
+    (1, String::new(), String::new())
+}
+
+fn main() -> CalyxResult<()> {
+    let opts: Opts = argh::from_env();
+    let mut inputs = vec![];
+    if opts.files.is_empty() {
+        let mut buf = String::new();
+        io::stdin().read_to_string(&mut buf).map_err(|err| {
+            Error::invalid_file(format!("Failed to read stdin: {err}"))
+        })?;
+        inputs.push((PathBuf::from("<stdin>"), buf));
+    }
+    for file in &opts.files {
+        let source = fs::read_to_string(file).map_err(|err| {
+            Error::invalid_file(format!(
+                "Failed to read {}: {err}",
+                file.to_string_lossy()
+            ))
+        })?;
+        inputs.push((file.clone(), source));
+    }
+
+    let mut unformatted = false;
+    for (path, source) in inputs {
+        let formatted = format::format(&path, source.clone(), opts.width)?;
+        if opts.check {
+            if formatted != source {
+                let (linum, old, new) = first_diff(&source, &formatted);
+                eprintln!(
+                    "{}:{linum}: not formatted\n-{old}\n+{new}",
+                    path.to_string_lossy()
+                );
+                unformatted = true;
+            }
+        } else if opts.in_place && path != Path::new("<stdin>") {
+            if formatted != source {
+                fs::write(&path, formatted).map_err(|err| {
+                    Error::write_error(format!(
+                        "Failed to write {}: {err}",
+                        path.to_string_lossy()
+                    ))
+                })?;
+            }
+        } else {
+            print!("{formatted}");
+        }
+    }
+    if unformatted {
+        std::process::exit(1);
+    }
+    Ok(())
+}
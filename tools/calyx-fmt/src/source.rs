@@ -0,0 +1,164 @@
+//! A lightweight lexer for Calyx programs. The AST does not record comments
+//! or the positions of punctuation, so the formatter uses the tokens found
+//! here to locate braces and keywords and to place comments.
+
+/// A comment in the source program.
+pub struct Comment {
+    /// Byte offset of the start of the comment.
+    pub start: usize,
+    /// Byte offset of the end of the comment. Excludes the newline ending
+    /// line comments.
+    pub end: usize,
+    /// True if the comment follows code on the same line.
+    pub trailing: bool,
+}
+
+/// The tokens and comments of a program.
+pub struct Source<'a> {
+    pub text: &'a str,
+    /// Byte offsets of the start and end of each token.
+    tokens: Vec<(usize, usize)>,
+    pub comments: Vec<Comment>,
+}
+
+impl<'a> Source<'a> {
+    pub fn new(text: &'a str) -> Self {
+        let bytes = text.as_bytes();
+        let mut tokens: Vec<(usize, usize)> = vec![];
+        let mut comments = vec![];
+        // Set after the `primitive` keyword. The next `{` before a `;` starts
+        // the body of an inline primitive, which is not Calyx code.
+        let mut in_prim = false;
+        let mut idx = 0;
+        while idx < bytes.len() {
+            let start = idx;
+            let c = bytes[idx];
+            let rest = &text[idx..];
+            if c.is_ascii_whitespace() {
+                idx += 1;
+                continue;
+            } else if rest.starts_with("//") {
+                idx += rest.find('\n').unwrap_or(rest.len());
+            } else if rest.starts_with("/*") {
+                idx += rest.find("*/").map_or(rest.len(), |end| end + 2);
+            } else if c == b'"' {
+                idx += rest[1..].find('"').map_or(rest.len(), |end| end + 2);
+            } else if c == b'{' && in_prim {
+                idx += rest.find('}').map_or(rest.len(), |end| end + 1);
+                in_prim = false;
+            } else if c == b'#' && rest.starts_with("#{") {
+                idx += rest.find("}#").map_or(rest.len(), |end| end + 2);
+            } else if is_word(c) {
+                while idx < bytes.len() && is_word(bytes[idx]) {
+                    idx += 1;
+                }
+            } else {
+                idx += 1;
+            }
+            if rest.starts_with("//") || rest.starts_with("/*") {
+                let line_start = text[..start].rfind('\n').map_or(0, |i| i + 1);
+                comments.push(Comment {
+                    start,
+                    end: idx,
+                    trailing: !text[line_start..start].trim().is_empty(),
+                });
+                continue;
+            }
+            match &text[start..idx] {
+                // Cells, groups, and ports may also be named `primitive`.
+                "primitive" => {
+                    in_prim = matches!(
+                        tokens.last().map(|(s, e)| &text[*s..*e]),
+                        None | Some(";" | "{" | "}" | "comb")
+                    )
+                }
+                ";" => in_prim = false,
+                _ => (),
+            }
+            tokens.push((start, idx));
+        }
+        Self {
+            text,
+            tokens,
+            comments,
+        }
+    }
+
+    /// The text of the token `idx`.
+    fn token(&self, idx: usize) -> &'a str {
+        let (start, end) = self.tokens[idx];
+        &self.text[start..end]
+    }
+
+    /// The offset and text of the first token at or after `pos`.
+    pub fn next(&self, pos: usize) -> Option<(usize, &'a str)> {
+        let idx = self.tokens.partition_point(|(start, _)| *start < pos);
+        (idx < self.tokens.len()).then(|| (self.tokens[idx].0, self.token(idx)))
+    }
+
+    /// The offset of the first token at or after `pos` with the text `tok`.
+    pub fn find(&self, pos: usize, tok: &str) -> usize {
+        let idx = self.tokens.partition_point(|(start, _)| *start < pos);
+        (idx..self.tokens.len())
+            .find(|idx| self.token(*idx) == tok)
+            .map_or(self.text.len(), |idx| self.tokens[idx].0)
+    }
+
+    /// The offset of the bracket matching the `{` or `(` at `open`.
+    pub fn closing(&self, open: usize) -> usize {
+        let idx = self.tokens.partition_point(|(start, _)| *start < open);
+        let (left, right) = match self.next(open) {
+            Some((_, "(")) => ("(", ")"),
+            _ => ("{", "}"),
+        };
+        let mut depth = 0;
+        for idx in idx..self.tokens.len() {
+            match self.token(idx) {
+                t if t == left => depth += 1,
+                t if t == right => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return self.tokens[idx].0;
+                    }
+                }
+                _ => (),
+            }
+        }
+        self.text.len()
+    }
+
+    /// The offset of the token `name` followed by `next` between `start` and
+    /// `end`. Used to find ports and arguments.
+    pub fn find_name(
+        &self,
+        start: usize,
+        end: usize,
+        name: &str,
+        next: &str,
+    ) -> Option<usize> {
+        let idx = self.tokens.partition_point(|(s, _)| *s < start);
+        (idx..self.tokens.len().saturating_sub(1))
+            .take_while(|idx| self.tokens[*idx].0 < end)
+            .find(|idx| self.token(*idx) == name && self.token(idx + 1) == next)
+            .map(|idx| self.tokens[idx].0)
+    }
+
+    /// The offsets of the top-level tokens with the text `tok`.
+    pub fn top_level(&self, tok: &str) -> Vec<usize> {
+        let mut depth = 0;
+        let mut out = vec![];
+        for idx in 0..self.tokens.len() {
+            match self.token(idx) {
+                "{" => depth += 1,
+                "}" => depth -= 1,
+                t if t == tok && depth == 0 => out.push(self.tokens[idx].0),
+                _ => (),
+            }
+        }
+        out
+    }
+}
+
+fn is_word(c: u8) -> bool {
+    c.is_ascii_alphanumeric() || c == b'_' || c == b'\''
+}
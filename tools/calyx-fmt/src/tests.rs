@@ -0,0 +1,86 @@
+//! Tests that format the programs in the repository.
+use crate::format::format;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+/// The position and symbol tables used by the parser are global and not
+/// thread-safe so tests that parse programs cannot run concurrently.
+static LOCK: Mutex<()> = Mutex::new(());
+
+/// The Calyx programs in `dir` relative to the root of the repository.
+fn programs(dir: &str) -> Vec<PathBuf> {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("../..")
+        .join(dir);
+    let mut files = fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "futil"))
+        .collect::<Vec<_>>();
+    files.sort();
+    files
+}
+
+fn format_file(path: &Path) -> String {
+    let source = fs::read_to_string(path).unwrap();
+    format(path, source, 100).unwrap_or_else(|err| {
+        panic!("Failed to format {}: {err:?}", path.display())
+    })
+}
+
+#[test]
+fn formatting_is_idempotent() {
+    let _guard = LOCK.lock().unwrap_or_else(|err| err.into_inner());
+    for dir in ["primitives", "examples/futil", "tests/fmt", "tests/passes"] {
+        for path in programs(dir) {
+            let once = format_file(&path);
+            let twice = format(&path, once.clone(), 100).unwrap();
+            assert_eq!(once, twice, "{}", path.display());
+        }
+    }
+}
+
+#[test]
+fn comments_are_preserved() {
+    let _guard = LOCK.lock().unwrap_or_else(|err| err.into_inner());
+    for dir in ["primitives", "examples/futil", "tests/fmt"] {
+        for path in programs(dir) {
+            let source = fs::read_to_string(&path).unwrap();
+            let formatted = format_file(&path);
+            let count = |text: &str| text.matches("//").count();
+            assert_eq!(count(&source), count(&formatted), "{}", path.display());
+        }
+    }
+}
+
+#[test]
+fn long_guards_are_wrapped() {
+    let _guard = LOCK.lock().unwrap_or_else(|err| err.into_inner());
+    let source = r#"
+component main() -> () {
+  cells {}
+  wires {
+    a.in = b.out & c.out | d.out & (e.out | f.out) | !g.out ? 1'd1;
+  }
+  control {}
+}
+"#;
+    let formatted =
+        format(Path::new("<test>"), source.to_string(), 30).unwrap();
+    assert!(
+        formatted.contains(
+            "    a.in = b.out & c.out\n      | d.out & (e.out | f.out)\n      | !g.out ? 1'd1;\n"
+        ),
+        "{formatted}"
+    );
+}
+
+#[test]
+fn syntax_errors_are_reported() {
+    let _guard = LOCK.lock().unwrap_or_else(|err| err.into_inner());
+    let source = "component main() -> () { cells { r = std_reg(32) } }";
+    assert!(format(Path::new("<test>"), source.to_string(), 100).is_err());
+}
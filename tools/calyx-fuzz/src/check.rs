@@ -0,0 +1,282 @@
+//! Compiles programs with pass pipelines and compares the interpretation of
+//! the result against that of the original program.
+
+use crate::program::Program;
+use calyx::{frontend, ir, pass_manager::PassManager};
+use interp::{
+    configuration::Config,
+    differential,
+    environment::InterpreterState,
+    errors::{InterpreterError, InterpreterResult},
+    interpreter::{ComponentInterpreter, Interpreter},
+    interpreter_ir as iir,
+};
+use std::{
+    fmt,
+    panic::{self, AssertUnwindSafe},
+    path::{Path, PathBuf},
+    rc::Rc,
+};
+
+/// Cycles the original program may take. Generated programs run for far
+/// fewer.
+const ORIGINAL_LIMIT: u64 = 100_000;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Kind {
+    /// The passes returned an error or panicked
+    Compile,
+    /// The interpreter returned an error or panicked
+    Interpret,
+    /// The program did not finish in time
+    Timeout,
+    /// The final values of the registers or memory differ
+    Mismatch,
+}
+
+impl fmt::Display for Kind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let desc = match self {
+            Kind::Compile => "compilation failed",
+            Kind::Interpret => "interpretation failed",
+            Kind::Timeout => "did not finish",
+            Kind::Mismatch => "final values differ",
+        };
+        write!(f, "{}", desc)
+    }
+}
+
+pub struct Failure {
+    /// The pipeline that was run, or `None` when the original program failed
+    pub pipeline: Option<String>,
+    pub kind: Kind,
+    pub message: String,
+}
+
+impl Failure {
+    /// Whether both failures are likely to be caused by the same bug
+    fn same_as(&self, other: &Failure) -> bool {
+        self.pipeline == other.pipeline && self.kind == other.kind
+    }
+}
+
+impl fmt::Display for Failure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.pipeline {
+            Some(pipeline) => write!(f, "pipeline `{}`", pipeline)?,
+            None => write!(f, "original program")?,
+        }
+        write!(f, ": {}: {}", self.kind, self.message)
+    }
+}
+
+/// Runs `f`, turning a panic into its message
+fn catch<T>(f: impl FnOnce() -> T) -> Result<T, String> {
+    panic::catch_unwind(AssertUnwindSafe(f)).map_err(|payload| {
+        payload
+            .downcast_ref::<&str>()
+            .map(|s| s.to_string())
+            .or_else(|| payload.downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "unknown panic".to_string())
+    })
+}
+
+/// Interprets the main component of the context for at most `limit` cycles.
+/// Returns `None` if it hasn't finished by then.
+fn interpret(
+    ctx: ir::Context,
+    limit: u64,
+    config: &Config,
+) -> InterpreterResult<Option<(InterpreterState, u64)>> {
+    let entry_point = ctx.entrypoint;
+    let components: iir::ComponentCtx = Rc::new(
+        ctx.components
+            .into_iter()
+            .map(|x| Rc::new(x.into()))
+            .collect(),
+    );
+    let main_component = components
+        .iter()
+        .find(|&cm| cm.name == entry_point)
+        .ok_or(InterpreterError::MissingMainComponent)?;
+    let env = InterpreterState::init_top_level(
+        &components,
+        main_component,
+        &mut None,
+        config,
+    )?;
+    let mut main_comp =
+        ComponentInterpreter::make_main_component(env, main_component);
+    let mut cycles = 0;
+    main_comp.set_go_high();
+    main_comp.converge()?;
+    while !main_comp.is_done() {
+        if cycles == limit {
+            return Ok(None);
+        }
+        main_comp.step()?;
+        main_comp.converge()?;
+        cycles += 1;
+    }
+    main_comp.set_go_low();
+    Ok(Some((main_comp.deconstruct()?, cycles)))
+}
+
+pub struct Checker {
+    pm: PassManager,
+    lib_path: Vec<PathBuf>,
+    /// Comma-separated passes and aliases
+    pipelines: Vec<String>,
+    /// Passes left out of every pipeline
+    disabled: Vec<String>,
+    config: Config,
+}
+
+impl Checker {
+    pub fn new(
+        lib_path: Vec<PathBuf>,
+        pipelines: Vec<String>,
+        disabled: Vec<String>,
+        config: Config,
+    ) -> calyx::errors::CalyxResult<Self> {
+        Ok(Checker {
+            pm: PassManager::default_passes()?,
+            lib_path,
+            pipelines,
+            disabled,
+            config,
+        })
+    }
+
+    fn construct(&self, src: &str) -> calyx::errors::CalyxResult<ir::Context> {
+        let ws = frontend::Workspace::construct_from_source(
+            Path::new("fuzz.futil"),
+            src.to_string(),
+            &self.lib_path,
+        )?;
+        ir::from_ast::ast_to_ir(ws)
+    }
+
+    /// Compiles the source with the passes and interprets the result
+    fn run(
+        &self,
+        src: &str,
+        plan: &[String],
+        limit: u64,
+    ) -> Result<(InterpreterState, u64), (Kind, String)> {
+        let compiled = catch(|| {
+            let mut ctx = self.construct(src)?;
+            self.pm.execute_plan(&mut ctx, plan, &self.disabled)?;
+            Ok(ctx)
+        });
+        let ctx = match compiled {
+            Ok(Ok(ctx)) => ctx,
+            Ok(Err(e)) => {
+                let e: calyx::errors::Error = e;
+                return Err((Kind::Compile, format!("{:?}", e)));
+            }
+            Err(panic) => return Err((Kind::Compile, panic)),
+        };
+        match catch(|| interpret(ctx, limit, &self.config)) {
+            Ok(Ok(Some(res))) => Ok(res),
+            Ok(Ok(None)) => Err((
+                Kind::Timeout,
+                format!("still running after {} cycles", limit),
+            )),
+            Ok(Err(e)) => Err((Kind::Interpret, e.to_string())),
+            Err(panic) => Err((Kind::Interpret, panic)),
+        }
+    }
+
+    /// Returns the first way in which the program misbehaves
+    pub fn check(&self, program: &Program) -> Option<Failure> {
+        let src = program.to_string();
+        let (expected, cycles) =
+            match self.run(&src, &["validate".to_string()], ORIGINAL_LIMIT) {
+                Ok(res) => res,
+                Err((kind, message)) => {
+                    return Some(Failure {
+                        pipeline: None,
+                        kind,
+                        message,
+                    })
+                }
+            };
+
+        for pipeline in &self.pipelines {
+            let plan: Vec<String> =
+                pipeline.split(',').map(|p| p.trim().to_string()).collect();
+            // Compilation can change the timing but shouldn't change it
+            // by much
+            let failure = match self.run(&src, &plan, 10 * cycles + 100) {
+                Ok((env, _)) => {
+                    let names = env.differing_memories(&expected);
+                    if names.is_empty() {
+                        continue;
+                    }
+                    let names: Vec<_> =
+                        names.iter().map(|x| x.to_string()).collect();
+                    (Kind::Mismatch, names.join(", "))
+                }
+                Err(failure) => failure,
+            };
+            return Some(Failure {
+                pipeline: Some(pipeline.clone()),
+                kind: failure.0,
+                message: failure.1,
+            });
+        }
+        None
+    }
+
+    /// Shrinks a failing program to a smaller one that still fails in the
+    /// same way, by repeatedly taking the first smaller variant that does
+    pub fn shrink(
+        &self,
+        mut program: Program,
+        mut failure: Failure,
+    ) -> (Program, Failure) {
+        'shrink: loop {
+            for candidate in program.shrink() {
+                if let Some(f) = self.check(&candidate) {
+                    if f.same_as(&failure) {
+                        program = candidate;
+                        failure = f;
+                        continue 'shrink;
+                    }
+                }
+            }
+            return (program, failure);
+        }
+    }
+
+    /// Names the first pass of the failing pipeline after which the program
+    /// computes different values, if the failure is a mismatch
+    pub fn blame(
+        &self,
+        program: &Program,
+        failure: &Failure,
+    ) -> Option<String> {
+        let pipeline = match (&failure.pipeline, failure.kind) {
+            (Some(pipeline), Kind::Mismatch) => pipeline,
+            _ => return None,
+        };
+        let src = program.to_string();
+        let plan: Vec<String> =
+            pipeline.split(',').map(|p| p.trim().to_string()).collect();
+        let divergence = catch(|| {
+            differential::find_divergence(
+                || self.construct(&src),
+                &self.pm,
+                &plan,
+                &self.disabled,
+                &None,
+                &self.config,
+            )
+        });
+        match divergence {
+            Ok(Ok(Some(divergence))) => Some(divergence.to_string()),
+            _ => None,
+        }
+    }
+}
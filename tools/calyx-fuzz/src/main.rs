@@ -0,0 +1,110 @@
+//! A differential fuzzer for the Calyx compiler. It generates random
+//! well-formed programs, compiles each of them with several pass pipelines
+//! and checks that interpreting the compiled program produces the same final
+//! registers and memory as interpreting the original one. Failing programs
+//! are shrunk to small counterexamples.
+mod check;
+mod program;
+
+#[cfg(test)]
+mod tests;
+
+use argh::FromArgs;
+use calyx::errors::{CalyxResult, Error};
+use check::Checker;
+use program::Program;
+use rand::{rngs::StdRng, SeedableRng};
+use std::{fs, panic, path::PathBuf};
+
+#[derive(FromArgs)]
+/// Fuzz the Calyx compiler by comparing the interpretation of random programs
+/// before and after compilation
+struct Opts {
+    /// number of programs to generate
+    #[argh(option, short = 'n', default = "100")]
+    iterations: u64,
+
+    /// seed of the first program. Program `i` is generated from `seed + i`
+    /// (default: random)
+    #[argh(option)]
+    seed: Option<u64>,
+
+    /// maximum number of statements in a program
+    #[argh(option, default = "12")]
+    size: usize,
+
+    /// pipeline to compile programs with, as comma-separated passes and
+    /// aliases. Can be repeated (default: all, no-opt)
+    #[argh(option, short = 'p')]
+    pipeline: Vec<String>,
+
+    /// pass or alias to leave out of every pipeline
+    #[argh(option, long = "disable-pass")]
+    disable_pass: Vec<String>,
+
+    /// paths searched for imported files, in order (default: .)
+    #[argh(option, short = 'l')]
+    lib_path: Vec<PathBuf>,
+
+    /// directory counterexamples are written to (default: .)
+    #[argh(option, short = 'o')]
+    out: Option<PathBuf>,
+
+    /// keep generating programs after finding a failure
+    #[argh(switch)]
+    keep_going: bool,
+}
+
+fn main() -> CalyxResult<()> {
+    let mut opts: Opts = argh::from_env();
+    if opts.lib_path.is_empty() {
+        opts.lib_path = vec![PathBuf::from(".")];
+    }
+    if opts.pipeline.is_empty() {
+        opts.pipeline = vec!["all".to_string(), "no-opt".to_string()];
+    }
+    let out = opts.out.unwrap_or_else(|| PathBuf::from("."));
+    let seed = opts.seed.unwrap_or_else(rand::random);
+
+    interp::logging::initialze_logger(true);
+    // Panics in passes are reported as failures
+    panic::set_hook(Box::new(|_| {}));
+    let config = interp::configuration::ConfigBuilder::new()
+        .quiet(true)
+        .build();
+    let checker =
+        Checker::new(opts.lib_path, opts.pipeline, opts.disable_pass, config)?;
+
+    let mut failures = 0;
+    for seed in (0..opts.iterations).map(|i| seed.wrapping_add(i)) {
+        let program =
+            Program::generate(&mut StdRng::seed_from_u64(seed), opts.size);
+        let failure = match checker.check(&program) {
+            Some(failure) => failure,
+            None => continue,
+        };
+        failures += 1;
+        eprintln!("seed {}: {}", seed, failure);
+        let (program, failure) = checker.shrink(program, failure);
+        eprintln!("  shrunk to {} statements: {}", program.size(), failure);
+        if let Some(blame) = checker.blame(&program, &failure) {
+            eprintln!("  {}", blame);
+        }
+        let path = out.join(format!("fuzz-{}.futil", seed));
+        fs::write(&path, program.to_string()).map_err(|err| {
+            Error::write_error(format!(
+                "Failed to write {}: {err}",
+                path.to_string_lossy()
+            ))
+        })?;
+        eprintln!("  written to {}", path.to_string_lossy());
+        if !opts.keep_going {
+            break;
+        }
+    }
+    if failures > 0 {
+        std::process::exit(1);
+    }
+    eprintln!("{} programs passed", opts.iterations);
+    Ok(())
+}
@@ -0,0 +1,781 @@
+//! Random Calyx programs. Programs are generated and shrunk as a small AST,
+//! which is printed as Calyx source that only uses the primitives in
+//! `core.futil`.
+//!
+//! Every register and the memory are `@external` so that their final values
+//! can be compared. Generated programs are race-free: the arms of a `par`
+//! use disjoint sets of registers and at most one of them uses the memory.
+
+use rand::{seq::SliceRandom, Rng};
+use std::{collections::BTreeSet, fmt::Write};
+
+/// Number of entries in the memory
+const MEM_SIZE: u64 = 4;
+/// Maximum nesting of control operators
+const MAX_DEPTH: usize = 3;
+/// Maximum number of iterations of a `while` loop
+const MAX_BOUND: u64 = 3;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BinOp {
+    Add,
+    Sub,
+    And,
+    Or,
+    Xor,
+    Lsh,
+    Rsh,
+}
+
+impl BinOp {
+    const ALL: [BinOp; 7] = [
+        BinOp::Add,
+        BinOp::Sub,
+        BinOp::And,
+        BinOp::Or,
+        BinOp::Xor,
+        BinOp::Lsh,
+        BinOp::Rsh,
+    ];
+
+    fn primitive(&self) -> &'static str {
+        match self {
+            BinOp::Add => "std_add",
+            BinOp::Sub => "std_sub",
+            BinOp::And => "std_and",
+            BinOp::Or => "std_or",
+            BinOp::Xor => "std_xor",
+            BinOp::Lsh => "std_lsh",
+            BinOp::Rsh => "std_rsh",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CmpOp {
+    Lt,
+    Gt,
+    Le,
+    Ge,
+    Eq,
+    Neq,
+}
+
+impl CmpOp {
+    const ALL: [CmpOp; 6] = [
+        CmpOp::Lt,
+        CmpOp::Gt,
+        CmpOp::Le,
+        CmpOp::Ge,
+        CmpOp::Eq,
+        CmpOp::Neq,
+    ];
+
+    fn primitive(&self) -> &'static str {
+        match self {
+            CmpOp::Lt => "std_lt",
+            CmpOp::Gt => "std_gt",
+            CmpOp::Le => "std_le",
+            CmpOp::Ge => "std_ge",
+            CmpOp::Eq => "std_eq",
+            CmpOp::Neq => "std_neq",
+        }
+    }
+}
+
+/// The right-hand side of an operation
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Operand {
+    Reg(usize),
+    Const(u64),
+}
+
+/// The assignments of a group, which each write a single register or memory
+/// entry
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Action {
+    /// `dst := value`
+    Set { dst: usize, value: u64 },
+    /// `dst := left op right`
+    Compute {
+        dst: usize,
+        op: BinOp,
+        left: usize,
+        right: Operand,
+    },
+    /// `dst := mem[addr]`
+    Load { dst: usize, addr: u64 },
+    /// `mem[addr] := src`
+    Store { src: usize, addr: u64 },
+}
+
+/// The condition of an `if`, computed by a combinational group
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Cond {
+    pub op: CmpOp,
+    pub left: usize,
+    pub right: Operand,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Control {
+    Empty,
+    Enable(Action),
+    Seq(Vec<Control>),
+    Par(Vec<Control>),
+    If {
+        cond: Cond,
+        tbranch: Box<Control>,
+        fbranch: Box<Control>,
+    },
+    /// Runs the body `bound` times using a private counter
+    While {
+        bound: u64,
+        body: Box<Control>,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Program {
+    /// Width of the registers and memory entries
+    pub width: u64,
+    /// Number of registers
+    pub regs: usize,
+    /// Whether the program has a memory
+    pub mem: bool,
+    pub control: Control,
+}
+
+/// Limits on the control generated for a subtree
+struct Scope {
+    /// Registers the subtree can read and write
+    regs: Vec<usize>,
+    /// Whether the subtree can use the memory
+    mem: bool,
+}
+
+/// Generates the control of programs
+struct Generator<'a, R: Rng> {
+    rng: &'a mut R,
+    width: u64,
+    /// Number of statements left to generate
+    budget: usize,
+}
+
+impl<'a, R: Rng> Generator<'a, R> {
+    fn constant(&mut self) -> u64 {
+        let max = if self.width >= 64 {
+            u64::MAX
+        } else {
+            (1 << self.width) - 1
+        };
+        // Favor small values, which are more likely to hit edge cases
+        if self.rng.gen_bool(0.5) {
+            self.rng.gen_range(0..=max.min(4))
+        } else {
+            self.rng.gen_range(0..=max)
+        }
+    }
+
+    fn operand(&mut self, scope: &Scope) -> Operand {
+        if self.rng.gen_bool(0.5) {
+            Operand::Reg(*scope.regs.choose(self.rng).unwrap())
+        } else {
+            Operand::Const(self.constant())
+        }
+    }
+
+    fn action(&mut self, scope: &Scope) -> Action {
+        let dst = *scope.regs.choose(self.rng).unwrap();
+        let choice = self.rng.gen_range(0..if scope.mem { 5 } else { 3 });
+        match choice {
+            0 => Action::Set {
+                dst,
+                value: self.constant(),
+            },
+            1 | 2 => Action::Compute {
+                dst,
+                op: *BinOp::ALL.choose(self.rng).unwrap(),
+                left: *scope.regs.choose(self.rng).unwrap(),
+                right: self.operand(scope),
+            },
+            3 => Action::Load {
+                dst,
+                addr: self.rng.gen_range(0..MEM_SIZE),
+            },
+            _ => Action::Store {
+                src: dst,
+                addr: self.rng.gen_range(0..MEM_SIZE),
+            },
+        }
+    }
+
+    /// Splits the registers and memory of a scope between the arms of a
+    /// `par`
+    fn partition(&mut self, scope: &Scope) -> Vec<Scope> {
+        let arms = self.rng.gen_range(2..=scope.regs.len().min(3));
+        let mut regs = scope.regs.clone();
+        regs.shuffle(self.rng);
+        let mut scopes: Vec<Scope> = (0..arms)
+            .map(|_| Scope {
+                regs: vec![],
+                mem: false,
+            })
+            .collect();
+        for (idx, reg) in regs.into_iter().enumerate() {
+            // Give every arm one register before distributing the rest
+            let arm = if idx < arms {
+                idx
+            } else {
+                self.rng.gen_range(0..arms)
+            };
+            scopes[arm].regs.push(reg);
+        }
+        if scope.mem {
+            let arm = self.rng.gen_range(0..arms);
+            scopes[arm].mem = true;
+        }
+        scopes
+    }
+
+    fn control(&mut self, scope: &Scope, depth: usize) -> Control {
+        if self.budget == 0 || depth >= MAX_DEPTH {
+            self.budget = self.budget.saturating_sub(1);
+            return Control::Enable(self.action(scope));
+        }
+        self.budget -= 1;
+        match self.rng.gen_range(0..10) {
+            0..=2 => Control::Enable(self.action(scope)),
+            3..=4 => {
+                let len = self.rng.gen_range(2..=4);
+                Control::Seq(
+                    (0..len).map(|_| self.control(scope, depth + 1)).collect(),
+                )
+            }
+            5..=6 if scope.regs.len() >= 2 => Control::Par(
+                self.partition(scope)
+                    .iter()
+                    .map(|arm| self.control(arm, depth + 1))
+                    .collect(),
+            ),
+            5..=7 => {
+                let cond = Cond {
+                    op: *CmpOp::ALL.choose(self.rng).unwrap(),
+                    left: *scope.regs.choose(self.rng).unwrap(),
+                    right: self.operand(scope),
+                };
+                let tbranch = Box::new(self.control(scope, depth + 1));
+                let fbranch = if self.rng.gen_bool(0.2) {
+                    Box::new(Control::Empty)
+                } else {
+                    Box::new(self.control(scope, depth + 1))
+                };
+                Control::If {
+                    cond,
+                    tbranch,
+                    fbranch,
+                }
+            }
+            _ => Control::While {
+                bound: self.rng.gen_range(0..=MAX_BOUND),
+                body: Box::new(self.control(scope, depth + 1)),
+            },
+        }
+    }
+}
+
+impl Program {
+    /// Generates a random program with about `size` statements
+    pub fn generate<R: Rng>(rng: &mut R, size: usize) -> Self {
+        let width = *[1, 4, 8, 16, 32].choose(rng).unwrap();
+        let regs = rng.gen_range(1..=4);
+        let mem = rng.gen_bool(0.5);
+        let scope = Scope {
+            regs: (0..regs).collect(),
+            mem,
+        };
+        let mut gen = Generator {
+            rng,
+            width,
+            budget: size,
+        };
+        let control = gen.control(&scope, 0);
+        Program {
+            width,
+            regs,
+            mem,
+            control,
+        }
+    }
+
+    /// Number of statements in the program, which shrinking reduces
+    pub fn size(&self) -> usize {
+        self.control.size()
+    }
+
+    /// Smaller variants of the program, most aggressive first
+    pub fn shrink(&self) -> Vec<Program> {
+        self.control
+            .shrink()
+            .into_iter()
+            .map(|control| {
+                Program {
+                    control,
+                    ..self.clone()
+                }
+                .compact()
+            })
+            .collect()
+    }
+
+    /// Removes the registers and memory the control doesn't use
+    fn compact(mut self) -> Self {
+        let mut used = BTreeSet::new();
+        let mut mem = false;
+        self.control.uses(&mut used, &mut mem);
+        let renaming: Vec<usize> = used.into_iter().collect();
+        self.control
+            .rename(&|reg| renaming.iter().position(|&r| r == reg).unwrap());
+        // Keep a register so that the main component has some state
+        self.regs = renaming.len().max(1);
+        self.mem = mem;
+        self
+    }
+}
+
+impl Action {
+    fn uses(&self, regs: &mut BTreeSet<usize>, mem: &mut bool) {
+        match self {
+            Action::Set { dst, .. } => {
+                regs.insert(*dst);
+            }
+            Action::Compute {
+                dst, left, right, ..
+            } => {
+                regs.extend([*dst, *left]);
+                if let Operand::Reg(r) = right {
+                    regs.insert(*r);
+                }
+            }
+            Action::Load { dst: reg, .. } | Action::Store { src: reg, .. } => {
+                regs.insert(*reg);
+                *mem = true;
+            }
+        }
+    }
+
+    fn rename(&mut self, f: &dyn Fn(usize) -> usize) {
+        match self {
+            Action::Set { dst, .. } => *dst = f(*dst),
+            Action::Compute {
+                dst, left, right, ..
+            } => {
+                *dst = f(*dst);
+                *left = f(*left);
+                right.rename(f);
+            }
+            Action::Load { dst: reg, .. } | Action::Store { src: reg, .. } => {
+                *reg = f(*reg)
+            }
+        }
+    }
+
+    fn shrink(&self) -> Vec<Action> {
+        match self {
+            Action::Set { dst, value } if *value > 0 => vec![Action::Set {
+                dst: *dst,
+                value: 0,
+            }],
+            Action::Set { .. } => vec![],
+            Action::Compute {
+                dst, left, right, ..
+            } => {
+                let mut smaller = vec![Action::Set {
+                    dst: *dst,
+                    value: 0,
+                }];
+                if let Operand::Const(c) = right {
+                    if *c > 1 {
+                        let mut action = self.clone();
+                        if let Action::Compute { right, .. } = &mut action {
+                            *right = Operand::Const(1);
+                        }
+                        smaller.push(action);
+                    }
+                }
+                if left != dst {
+                    let mut action = self.clone();
+                    if let Action::Compute { left, .. } = &mut action {
+                        *left = *dst;
+                    }
+                    smaller.push(action);
+                }
+                smaller
+            }
+            Action::Load { dst, addr } => vec![
+                Action::Set {
+                    dst: *dst,
+                    value: 0,
+                },
+                Action::Load {
+                    dst: *dst,
+                    addr: addr / 2,
+                },
+            ],
+            Action::Store { src, addr } => {
+                if *addr > 0 {
+                    vec![Action::Store {
+                        src: *src,
+                        addr: addr / 2,
+                    }]
+                } else {
+                    vec![]
+                }
+            }
+        }
+    }
+}
+
+impl Operand {
+    fn rename(&mut self, f: &dyn Fn(usize) -> usize) {
+        if let Operand::Reg(r) = self {
+            *r = f(*r)
+        }
+    }
+}
+
+impl Control {
+    fn size(&self) -> usize {
+        match self {
+            Control::Empty => 0,
+            Control::Enable(_) => 1,
+            Control::Seq(stmts) | Control::Par(stmts) => {
+                1 + stmts.iter().map(Control::size).sum::<usize>()
+            }
+            Control::If {
+                tbranch, fbranch, ..
+            } => 1 + tbranch.size() + fbranch.size(),
+            Control::While { body, .. } => 1 + body.size(),
+        }
+    }
+
+    fn uses(&self, regs: &mut BTreeSet<usize>, mem: &mut bool) {
+        match self {
+            Control::Empty => (),
+            Control::Enable(action) => action.uses(regs, mem),
+            Control::Seq(stmts) | Control::Par(stmts) => {
+                stmts.iter().for_each(|s| s.uses(regs, mem))
+            }
+            Control::If {
+                cond,
+                tbranch,
+                fbranch,
+            } => {
+                regs.insert(cond.left);
+                if let Operand::Reg(r) = cond.right {
+                    regs.insert(r);
+                }
+                tbranch.uses(regs, mem);
+                fbranch.uses(regs, mem);
+            }
+            Control::While { body, .. } => body.uses(regs, mem),
+        }
+    }
+
+    fn rename(&mut self, f: &dyn Fn(usize) -> usize) {
+        match self {
+            Control::Empty => (),
+            Control::Enable(action) => action.rename(f),
+            Control::Seq(stmts) | Control::Par(stmts) => {
+                stmts.iter_mut().for_each(|s| s.rename(f))
+            }
+            Control::If {
+                cond,
+                tbranch,
+                fbranch,
+            } => {
+                cond.left = f(cond.left);
+                cond.right.rename(f);
+                tbranch.rename(f);
+                fbranch.rename(f);
+            }
+            Control::While { body, .. } => body.rename(f),
+        }
+    }
+
+    /// Smaller variants of this control: first replacing it with one of its
+    /// children, then shrinking it in place, then shrinking its children.
+    fn shrink(&self) -> Vec<Control> {
+        let mut smaller = vec![];
+        match self {
+            Control::Empty => (),
+            Control::Enable(action) => {
+                smaller.extend(action.shrink().into_iter().map(Control::Enable))
+            }
+            Control::Seq(stmts) | Control::Par(stmts) => {
+                let rebuild = |stmts: Vec<Control>| match self {
+                    Control::Seq(_) => Control::Seq(stmts),
+                    _ => Control::Par(stmts),
+                };
+                smaller.extend(stmts.iter().cloned());
+                if stmts.len() > 2 {
+                    for idx in 0..stmts.len() {
+                        let mut fewer = stmts.clone();
+                        fewer.remove(idx);
+                        smaller.push(rebuild(fewer));
+                    }
+                }
+                for (idx, stmt) in stmts.iter().enumerate() {
+                    for s in stmt.shrink() {
+                        let mut stmts = stmts.clone();
+                        stmts[idx] = s;
+                        smaller.push(rebuild(stmts));
+                    }
+                }
+            }
+            Control::If {
+                cond,
+                tbranch,
+                fbranch,
+            } => {
+                smaller.push((**tbranch).clone());
+                smaller.push((**fbranch).clone());
+                for t in tbranch.shrink() {
+                    smaller.push(Control::If {
+                        cond: cond.clone(),
+                        tbranch: Box::new(t),
+                        fbranch: fbranch.clone(),
+                    });
+                }
+                for f in fbranch.shrink() {
+                    smaller.push(Control::If {
+                        cond: cond.clone(),
+                        tbranch: tbranch.clone(),
+                        fbranch: Box::new(f),
+                    });
+                }
+            }
+            Control::While { bound, body } => {
+                smaller.push((**body).clone());
+                if *bound > 0 {
+                    smaller.push(Control::While {
+                        bound: bound - 1,
+                        body: body.clone(),
+                    });
+                }
+                for b in body.shrink() {
+                    smaller.push(Control::While {
+                        bound: *bound,
+                        body: Box::new(b),
+                    });
+                }
+            }
+        }
+        smaller
+    }
+}
+
+/// Prints a program as Calyx source. The cells, groups and control are
+/// collected separately and numbered in the order the control is visited.
+#[derive(Default)]
+struct Printer {
+    cells: String,
+    wires: String,
+    /// Counter used to name the groups and cells of each statement
+    next: usize,
+}
+
+impl Printer {
+    fn fresh(&mut self) -> usize {
+        self.next += 1;
+        self.next - 1
+    }
+
+    fn operand(width: u64, op: &Operand) -> String {
+        match op {
+            Operand::Reg(r) => format!("r{}.out", r),
+            Operand::Const(c) => format!("{}'d{}", width, c),
+        }
+    }
+
+    fn action(&mut self, prog: &Program, action: &Action) -> String {
+        let (w, idx) = (prog.width, self.fresh());
+        let group = format!("g{}", idx);
+        let mut body = String::new();
+        let done = match action {
+            Action::Set { dst, value } => {
+                writeln!(body, "      r{}.in = {}'d{};", dst, w, value)
+                    .unwrap();
+                writeln!(body, "      r{}.write_en = 1'd1;", dst).unwrap();
+                format!("r{}.done", dst)
+            }
+            Action::Compute {
+                dst,
+                op,
+                left,
+                right,
+            } => {
+                let cell = format!("op{}", idx);
+                writeln!(
+                    self.cells,
+                    "    {} = {}({});",
+                    cell,
+                    op.primitive(),
+                    w
+                )
+                .unwrap();
+                writeln!(body, "      {}.left = r{}.out;", cell, left).unwrap();
+                writeln!(
+                    body,
+                    "      {}.right = {};",
+                    cell,
+                    Self::operand(w, right)
+                )
+                .unwrap();
+                writeln!(body, "      r{}.in = {}.out;", dst, cell).unwrap();
+                writeln!(body, "      r{}.write_en = 1'd1;", dst).unwrap();
+                format!("r{}.done", dst)
+            }
+            Action::Load { dst, addr } => {
+                writeln!(body, "      mem.addr0 = 2'd{};", addr).unwrap();
+                writeln!(body, "      r{}.in = mem.read_data;", dst).unwrap();
+                writeln!(body, "      r{}.write_en = 1'd1;", dst).unwrap();
+                format!("r{}.done", dst)
+            }
+            Action::Store { src, addr } => {
+                writeln!(body, "      mem.addr0 = 2'd{};", addr).unwrap();
+                writeln!(body, "      mem.write_data = r{}.out;", src).unwrap();
+                writeln!(body, "      mem.write_en = 1'd1;").unwrap();
+                "mem.done".to_string()
+            }
+        };
+        writeln!(
+            self.wires,
+            "    group {} {{\n{}      {}[done] = {};\n    }}",
+            group, body, group, done
+        )
+        .unwrap();
+        group
+    }
+
+    fn control(
+        &mut self,
+        prog: &Program,
+        control: &Control,
+        indent: usize,
+    ) -> String {
+        let pad = " ".repeat(indent);
+        let w = prog.width;
+        match control {
+            Control::Empty => String::new(),
+            Control::Enable(action) => {
+                format!("{}{};\n", pad, self.action(prog, action))
+            }
+            Control::Seq(stmts) | Control::Par(stmts) => {
+                let kw = if matches!(control, Control::Seq(_)) {
+                    "seq"
+                } else {
+                    "par"
+                };
+                let body: String = stmts
+                    .iter()
+                    .map(|s| self.control(prog, s, indent + 2))
+                    .collect();
+                format!("{}{} {{\n{}{}}}\n", pad, kw, body, pad)
+            }
+            Control::If {
+                cond,
+                tbranch,
+                fbranch,
+            } => {
+                let idx = self.fresh();
+                writeln!(
+                    self.cells,
+                    "    c{} = {}({});",
+                    idx,
+                    cond.op.primitive(),
+                    w
+                )
+                .unwrap();
+                writeln!(
+                    self.wires,
+                    "    comb group cond{} {{\n      c{}.left = r{}.out;\n      c{}.right = {};\n    }}",
+                    idx,
+                    idx,
+                    cond.left,
+                    idx,
+                    Self::operand(w, &cond.right)
+                )
+                .unwrap();
+                let tbranch = self.control(prog, tbranch, indent + 2);
+                let fbranch = self.control(prog, fbranch, indent + 2);
+                format!(
+                    "{}if c{}.out with cond{} {{\n{}{}}} else {{\n{}{}}}\n",
+                    pad, idx, idx, tbranch, pad, fbranch, pad
+                )
+            }
+            Control::While { bound, body } => {
+                // The counter is wide enough for any bound
+                let idx = self.fresh();
+                writeln!(self.cells, "    i{} = std_reg(2);", idx).unwrap();
+                writeln!(self.cells, "    lt{} = std_lt(2);", idx).unwrap();
+                writeln!(self.cells, "    incr{} = std_add(2);", idx).unwrap();
+                writeln!(
+                    self.wires,
+                    "    group init{i} {{\n      i{i}.in = 2'd0;\n      i{i}.write_en = 1'd1;\n      init{i}[done] = i{i}.done;\n    }}",
+                    i = idx
+                )
+                .unwrap();
+                writeln!(
+                    self.wires,
+                    "    group step{i} {{\n      incr{i}.left = i{i}.out;\n      incr{i}.right = 2'd1;\n      i{i}.in = incr{i}.out;\n      i{i}.write_en = 1'd1;\n      step{i}[done] = i{i}.done;\n    }}",
+                    i = idx
+                )
+                .unwrap();
+                writeln!(
+                    self.wires,
+                    "    comb group loop{i} {{\n      lt{i}.left = i{i}.out;\n      lt{i}.right = 2'd{b};\n    }}",
+                    i = idx,
+                    b = bound
+                )
+                .unwrap();
+                let body = self.control(prog, body, indent + 6);
+                format!(
+                    "{p}seq {{\n{p}  init{i};\n{p}  while lt{i}.out with loop{i} {{\n{p}    seq {{\n{body}{p}      step{i};\n{p}    }}\n{p}  }}\n{p}}}\n",
+                    p = pad,
+                    i = idx,
+                    body = body
+                )
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for Program {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut printer = Printer::default();
+        let control = printer.control(self, &self.control, 4);
+        writeln!(f, "import \"primitives/core.futil\";")?;
+        writeln!(f, "component main() -> () {{")?;
+        writeln!(f, "  cells {{")?;
+        for r in 0..self.regs {
+            writeln!(f, "    @external r{} = std_reg({});", r, self.width)?;
+        }
+        if self.mem {
+            writeln!(
+                f,
+                "    @external mem = std_mem_d1({}, {}, 2);",
+                self.width, MEM_SIZE
+            )?;
+        }
+        write!(f, "{}", printer.cells)?;
+        writeln!(f, "  }}")?;
+        writeln!(f, "  wires {{")?;
+        write!(f, "{}", printer.wires)?;
+        writeln!(f, "  }}")?;
+        writeln!(f, "  control {{")?;
+        write!(f, "{}", control)?;
+        writeln!(f, "  }}")?;
+        writeln!(f, "}}")
+    }
+}
@@ -0,0 +1,61 @@
+use crate::{check::Checker, program::Program};
+use rand::{rngs::StdRng, SeedableRng};
+use std::path::PathBuf;
+
+fn checker(pipelines: &[&str]) -> Checker {
+    interp::logging::initialze_logger(true);
+    Checker::new(
+        vec![PathBuf::from("../..")],
+        pipelines.iter().map(|p| p.to_string()).collect(),
+        vec![],
+        interp::configuration::ConfigBuilder::new()
+            .quiet(true)
+            .build(),
+    )
+    .unwrap()
+}
+
+#[test]
+fn generated_programs_are_valid() {
+    // An empty pipeline only checks that the original program is valid and
+    // runs to completion
+    let checker = checker(&[]);
+    for seed in 0..20 {
+        let program = Program::generate(&mut StdRng::seed_from_u64(seed), 12);
+        if let Some(failure) = checker.check(&program) {
+            panic!("seed {}: {}\n{}", seed, failure, program);
+        }
+    }
+}
+
+#[test]
+fn generation_is_deterministic() {
+    let gen = |seed| Program::generate(&mut StdRng::seed_from_u64(seed), 12);
+    assert_eq!(gen(7), gen(7));
+}
+
+#[test]
+fn shrinking_makes_programs_smaller() {
+    for seed in 0..20 {
+        let program = Program::generate(&mut StdRng::seed_from_u64(seed), 12);
+        for smaller in program.shrink() {
+            assert!(smaller.size() <= program.size());
+            assert_ne!(smaller, program);
+        }
+    }
+}
+
+#[test]
+fn shrinks_to_a_minimal_counterexample() {
+    // Sharing the external registers makes them hold the same value
+    let checker = checker(&["validate,cell-share,compile,lower"]);
+    let failing = (0..50)
+        .map(|seed| Program::generate(&mut StdRng::seed_from_u64(seed), 12))
+        .find_map(|program| {
+            let failure = checker.check(&program)?;
+            Some((program, failure))
+        })
+        .expect("no program exposes the register sharing");
+    let (program, _) = checker.shrink(failing.0, failing.1);
+    assert!(program.size() <= 3, "not shrunk enough:\n{}", program);
+}
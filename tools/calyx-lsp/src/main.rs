@@ -0,0 +1,408 @@
+//! A language server for Calyx. The server communicates with editors using
+//! the [Language Server Protocol][lsp] over stdio and provides:
+//!  - Diagnostics for errors found by the frontend and the `well-formed` and
+//!    `papercut` passes.
+//!  - Go-to-definition for cells, groups, and components.
+//!  - Hover information with the widths of ports.
+//!  - Completion for names and the ports of cells.
+//!
+//! [lsp]: https://microsoft.github.io/language-server-protocol/
+mod program;
+mod text;
+
+#[cfg(test)]
+mod tests;
+
+use argh::FromArgs;
+use calyx::utils::GlobalPositionTable;
+use lsp_server::{
+    Connection, ErrorCode, ExtractError, Message, Notification, Request,
+    Response,
+};
+use lsp_types::{
+    notification::{
+        DidChangeTextDocument, DidCloseTextDocument, DidOpenTextDocument,
+        Notification as _, PublishDiagnostics,
+    },
+    request::{Completion, GotoDefinition, HoverRequest, Request as _},
+    CompletionOptions, CompletionResponse, Diagnostic,
+    DiagnosticRelatedInformation, DiagnosticSeverity, GotoDefinitionResponse,
+    Hover, HoverContents, HoverProviderCapability, Location, MarkupContent,
+    MarkupKind, NumberOrString, OneOf, PublishDiagnosticsParams,
+    ServerCapabilities, TextDocumentPositionParams, TextDocumentSyncCapability,
+    TextDocumentSyncKind, Url,
+};
+use program::{Program, Span};
+use std::{
+    collections::HashMap,
+    error::Error,
+    fs,
+    path::{Path, PathBuf},
+};
+use text::{LineIndex, Word};
+
+#[derive(FromArgs)]
+/// Language server for Calyx. Communicates with the editor over stdio.
+struct Opts {
+    /// paths searched for imported files, in order (default: .)
+    #[argh(option, short = 'l')]
+    lib_path: Vec<PathBuf>,
+}
+
+/// A file opened in the editor.
+struct Document {
+    path: PathBuf,
+    text: String,
+    /// The definitions from the last time the document could be parsed.
+    program: Option<Program>,
+    /// Files for which diagnostics were published while checking this
+    /// document.
+    published: Vec<Url>,
+}
+
+pub struct Server {
+    lib_paths: Vec<PathBuf>,
+    documents: HashMap<Url, Document>,
+}
+
+impl Server {
+    pub fn new(lib_paths: Vec<PathBuf>) -> Self {
+        Self {
+            lib_paths,
+            documents: HashMap::new(),
+        }
+    }
+
+    pub fn capabilities() -> ServerCapabilities {
+        ServerCapabilities {
+            text_document_sync: Some(TextDocumentSyncCapability::Kind(
+                TextDocumentSyncKind::FULL,
+            )),
+            definition_provider: Some(OneOf::Left(true)),
+            hover_provider: Some(HoverProviderCapability::Simple(true)),
+            completion_provider: Some(CompletionOptions {
+                trigger_characters: Some(vec![".".to_string()]),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+
+    /// Handle a notification and return the notifications that should be
+    /// sent back to the client.
+    pub fn handle_notification(
+        &mut self,
+        not: Notification,
+    ) -> Vec<Notification> {
+        match not.method.as_str() {
+            DidOpenTextDocument::METHOD => {
+                match cast_notification::<DidOpenTextDocument>(not) {
+                    Some(params) => self.update(
+                        params.text_document.uri,
+                        params.text_document.text,
+                    ),
+                    None => vec![],
+                }
+            }
+            DidChangeTextDocument::METHOD => {
+                // With full synchronization, the last change contains the
+                // entire document.
+                match cast_notification::<DidChangeTextDocument>(not) {
+                    Some(mut params) => match params.content_changes.pop() {
+                        Some(change) => {
+                            self.update(params.text_document.uri, change.text)
+                        }
+                        None => vec![],
+                    },
+                    None => vec![],
+                }
+            }
+            DidCloseTextDocument::METHOD => cast_notification::<
+                DidCloseTextDocument,
+            >(not)
+            .and_then(|params| self.documents.remove(&params.text_document.uri))
+            .map(|doc| clear_diagnostics(doc.published))
+            .unwrap_or_default(),
+            _ => vec![],
+        }
+    }
+
+    /// Handle a request and return the response to it.
+    pub fn handle_request(&self, req: Request) -> Response {
+        let id = req.id.clone();
+        let result = match req.method.as_str() {
+            GotoDefinition::METHOD => {
+                cast_request::<GotoDefinition>(req).map(|params| {
+                    to_value(
+                        self.definition(&params.text_document_position_params),
+                    )
+                })
+            }
+            HoverRequest::METHOD => {
+                cast_request::<HoverRequest>(req).map(|params| {
+                    to_value(self.hover(&params.text_document_position_params))
+                })
+            }
+            Completion::METHOD => {
+                cast_request::<Completion>(req).map(|params| {
+                    to_value(self.completion(&params.text_document_position))
+                })
+            }
+            _ => {
+                return Response::new_err(
+                    id,
+                    ErrorCode::MethodNotFound as i32,
+                    format!("Unsupported request: {}", req.method),
+                )
+            }
+        };
+        match result {
+            Ok(value) => Response {
+                id,
+                result: Some(value),
+                error: None,
+            },
+            Err(err) => Response::new_err(
+                id,
+                ErrorCode::InvalidParams as i32,
+                err.to_string(),
+            ),
+        }
+    }
+
+    /// Update the contents of a document and check it again. Returns the
+    /// diagnostics for the document.
+    fn update(&mut self, uri: Url, text: String) -> Vec<Notification> {
+        let Ok(path) = uri.to_file_path() else {
+            return vec![];
+        };
+        // The program and its imports are added to the global position table
+        // while they are analyzed. The server only keeps copies of the spans
+        // it needs, so they are removed again afterwards instead of adding
+        // every version of the document to the table.
+        let checkpoint = GlobalPositionTable::as_ref().checkpoint();
+        let (program, error) =
+            Program::analyze(&path, text.clone(), &self.lib_paths);
+
+        // Group the diagnostics by the file they occur in. The document
+        // always gets diagnostics so that stale ones are cleared.
+        let mut diagnostics: HashMap<Url, Vec<Diagnostic>> = HashMap::new();
+        diagnostics.insert(uri.clone(), vec![]);
+        for err in error.iter().flat_map(|err| err.errors()) {
+            let (file, range) = match err.location() {
+                Some((file, start, end)) if Path::new(file) != path => {
+                    let source = self.source(Path::new(file));
+                    let index = LineIndex::new(&source);
+                    (Url::from_file_path(file).ok(), index.range(start, end))
+                }
+                Some((_, start, end)) => {
+                    (Some(uri.clone()), LineIndex::new(&text).range(start, end))
+                }
+                None => (Some(uri.clone()), Default::default()),
+            };
+            let related = err
+                .secondary()
+                .filter_map(|((file, start, end), label)| {
+                    let location = if Path::new(file) == path {
+                        Location::new(
+                            uri.clone(),
+                            LineIndex::new(&text).range(start, end),
+                        )
+                    } else {
+                        self.location(Span {
+                            file: file.into(),
+                            start,
+                            end,
+                        })?
+                    };
+                    Some(DiagnosticRelatedInformation {
+                        location,
+                        message: label.to_string(),
+                    })
+                })
+                .collect::<Vec<_>>();
+            let message = std::iter::once(err.message())
+                .chain(err.notes().iter().map(|note| format!("note: {note}")))
+                .collect::<Vec<_>>()
+                .join("\n");
+            let diag = Diagnostic {
+                range,
+                severity: Some(DiagnosticSeverity::ERROR),
+                code: Some(NumberOrString::String(err.code().to_string())),
+                source: Some("calyx".to_string()),
+                message,
+                related_information: (!related.is_empty()).then_some(related),
+                ..Default::default()
+            };
+            diagnostics
+                .entry(file.unwrap_or_else(|| uri.clone()))
+                .or_default()
+                .push(diag);
+        }
+        drop(error);
+        GlobalPositionTable::as_mut().truncate(checkpoint);
+
+        let old = self.documents.remove(&uri);
+        let (old_program, old_published) = old
+            .map(|doc| (doc.program, doc.published))
+            .unwrap_or_default();
+        let mut notifications = clear_diagnostics(
+            old_published
+                .into_iter()
+                .filter(|file| !diagnostics.contains_key(file))
+                .collect(),
+        );
+        let published = diagnostics.keys().cloned().collect();
+        notifications.extend(
+            diagnostics
+                .into_iter()
+                .map(|(uri, diags)| publish_diagnostics(uri, diags)),
+        );
+        self.documents.insert(
+            uri,
+            Document {
+                path,
+                text,
+                // Keep the old definitions around while the document cannot
+                // be parsed so that navigation keeps working while editing.
+                program: program.or(old_program),
+                published,
+            },
+        );
+        notifications
+    }
+
+    /// The source of `file`, which is read from disk unless it is open in
+    /// the editor.
+    fn source(&self, file: &Path) -> String {
+        self.documents
+            .values()
+            .find(|doc| doc.path == file)
+            .map(|doc| doc.text.clone())
+            .unwrap_or_else(|| fs::read_to_string(file).unwrap_or_default())
+    }
+
+    /// The document, program, and byte offset at a position.
+    fn lookup(
+        &self,
+        pos: &TextDocumentPositionParams,
+    ) -> Option<(&Document, &Program, usize)> {
+        let doc = self.documents.get(&pos.text_document.uri)?;
+        let program = doc.program.as_ref()?;
+        let offset = LineIndex::new(&doc.text).offset(pos.position);
+        Some((doc, program, offset))
+    }
+
+    fn location(&self, span: Span) -> Option<Location> {
+        let source = self.source(&span.file);
+        let range = LineIndex::new(&source).range(span.start, span.end);
+        Some(Location::new(Url::from_file_path(&span.file).ok()?, range))
+    }
+
+    fn definition(
+        &self,
+        pos: &TextDocumentPositionParams,
+    ) -> Option<GotoDefinitionResponse> {
+        let (doc, program, offset) = self.lookup(pos)?;
+        let word = Word::at(&doc.text, offset)?;
+        let sym = program.resolve(&doc.path, offset, &word)?;
+        let loc = self.location(program.definition(&sym)?)?;
+        Some(GotoDefinitionResponse::Scalar(loc))
+    }
+
+    fn hover(&self, pos: &TextDocumentPositionParams) -> Option<Hover> {
+        let (doc, program, offset) = self.lookup(pos)?;
+        let word = Word::at(&doc.text, offset)?;
+        let sym = program.resolve(&doc.path, offset, &word)?;
+        Some(Hover {
+            contents: HoverContents::Markup(MarkupContent {
+                kind: MarkupKind::Markdown,
+                value: program.hover(&sym)?,
+            }),
+            range: None,
+        })
+    }
+
+    fn completion(
+        &self,
+        pos: &TextDocumentPositionParams,
+    ) -> Option<CompletionResponse> {
+        let (doc, program, offset) = self.lookup(pos)?;
+        let word = Word::before(&doc.text, offset);
+        Some(CompletionResponse::Array(
+            program.completions(&doc.path, offset, &word),
+        ))
+    }
+}
+
+fn cast_notification<N: lsp_types::notification::Notification>(
+    not: Notification,
+) -> Option<N::Params> {
+    not.extract(N::METHOD).ok()
+}
+
+fn cast_request<R: lsp_types::request::Request>(
+    req: Request,
+) -> Result<R::Params, ExtractError<Request>> {
+    req.extract(R::METHOD).map(|(_, params)| params)
+}
+
+fn to_value<T: serde::Serialize>(value: T) -> serde_json::Value {
+    serde_json::to_value(value).expect("Failed to serialize response")
+}
+
+fn publish_diagnostics(uri: Url, diagnostics: Vec<Diagnostic>) -> Notification {
+    Notification::new(
+        PublishDiagnostics::METHOD.to_string(),
+        PublishDiagnosticsParams {
+            uri,
+            diagnostics,
+            version: None,
+        },
+    )
+}
+
+fn clear_diagnostics(files: Vec<Url>) -> Vec<Notification> {
+    files
+        .into_iter()
+        .map(|uri| publish_diagnostics(uri, vec![]))
+        .collect()
+}
+
+fn run(
+    connection: Connection,
+    lib_paths: Vec<PathBuf>,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    connection.initialize(serde_json::to_value(Server::capabilities())?)?;
+    let mut server = Server::new(lib_paths);
+    for msg in &connection.receiver {
+        match msg {
+            Message::Request(req) => {
+                if connection.handle_shutdown(&req)? {
+                    return Ok(());
+                }
+                let resp = server.handle_request(req);
+                connection.sender.send(Message::Response(resp))?;
+            }
+            Message::Notification(not) => {
+                for not in server.handle_notification(not) {
+                    connection.sender.send(Message::Notification(not))?;
+                }
+            }
+            Message::Response(_) => (),
+        }
+    }
+    Ok(())
+}
+
+fn main() -> Result<(), Box<dyn Error + Send + Sync>> {
+    let mut opts: Opts = argh::from_env();
+    if opts.lib_path.is_empty() {
+        opts.lib_path = vec![Path::new(".").into()];
+    }
+    let (connection, io_threads) = Connection::stdio();
+    // The connection must be dropped before joining the IO threads.
+    run(connection, opts.lib_path)?;
+    io_threads.join()?;
+    Ok(())
+}
@@ -0,0 +1,522 @@
+//! Analysis of a Calyx file and the names defined by it.
+use crate::text::Word;
+use calyx::{
+    errors::{CalyxResult, Error},
+    frontend::{self, ast},
+    ir::{self, traversal::Visitor},
+    passes,
+    utils::{GPosIdx, WithPos},
+};
+use lsp_types::{CompletionItem, CompletionItemKind};
+use std::{
+    collections::HashMap,
+    panic::{self, AssertUnwindSafe},
+    path::{Path, PathBuf},
+};
+
+/// A range of bytes in a file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Span {
+    pub file: PathBuf,
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    fn new(pos: GPosIdx) -> Option<Self> {
+        pos.into_option().map(|pos| {
+            let (file, start, end) = pos.get_location();
+            Self {
+                file: file.into(),
+                start,
+                end,
+            }
+        })
+    }
+
+    /// The span of the first occurrence of the identifier `name` in the
+    /// definition at `pos`.
+    fn name(pos: GPosIdx, name: ir::Id) -> Option<Self> {
+        let span = Self::new(pos)?;
+        let text = pos.get_text();
+        let name = name.as_ref();
+        let is_ident = |c: char| c.is_ascii_alphanumeric() || c == '_';
+        let offset = text
+            .match_indices(name)
+            .find(|&(idx, _)| {
+                !text[..idx].ends_with(is_ident)
+                    && !text[idx + name.len()..].starts_with(is_ident)
+            })
+            .map(|(idx, _)| idx)?;
+        Some(Self {
+            start: span.start + offset,
+            end: span.start + offset + name.len(),
+            file: span.file,
+        })
+    }
+
+    fn contains(&self, file: &Path, offset: usize) -> bool {
+        self.file == file && self.start <= offset && offset <= self.end
+    }
+}
+
+/// A cell defined by a component.
+struct CellDef {
+    name: ir::Id,
+    /// Name of the primitive or component the cell is an instance of.
+    proto_name: ir::Id,
+    /// The prototype of the cell as written in the program.
+    prototype: String,
+    span: Option<Span>,
+}
+
+/// Names defined by a component.
+pub struct ComponentDefs {
+    name: ir::Id,
+    /// Span of the whole definition.
+    span: Option<Span>,
+    /// Span of the name of the component.
+    name_span: Option<Span>,
+    signature: Vec<ir::PortDef<u64>>,
+    cells: Vec<CellDef>,
+    groups: Vec<(ir::Id, Option<Span>)>,
+}
+
+impl From<&ast::ComponentDef> for ComponentDefs {
+    fn from(comp: &ast::ComponentDef) -> Self {
+        let cells = comp
+            .cells
+            .iter()
+            .map(|cell| {
+                let ast::Proto { name, params } = &cell.prototype;
+                CellDef {
+                    name: cell.name,
+                    proto_name: *name,
+                    prototype: format!(
+                        "{}{}({})",
+                        if cell.reference { "ref " } else { "" },
+                        name,
+                        params
+                            .iter()
+                            .map(|p| p.to_string())
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    ),
+                    span: Span::name(cell.attributes.copy_span(), cell.name),
+                }
+            })
+            .collect();
+        let groups = comp
+            .groups
+            .iter()
+            .map(|group| {
+                (
+                    group.name,
+                    Span::name(group.attributes.copy_span(), group.name),
+                )
+            })
+            .collect();
+        let span = comp.attributes.copy_span();
+        Self {
+            name: comp.name,
+            span: Span::new(span),
+            // Components from namespaced imports are defined with their
+            // unqualified names.
+            name_span: Span::name(span, comp.name.unqualified().into()),
+            signature: comp.signature.clone(),
+            cells,
+            groups,
+        }
+    }
+}
+
+impl ComponentDefs {
+    fn find_cell(&self, name: &str) -> Option<&CellDef> {
+        self.cells.iter().find(|cell| cell.name == name)
+    }
+
+    fn find_group(&self, name: &str) -> Option<&(ir::Id, Option<Span>)> {
+        self.groups.iter().find(|(group, _)| group == name)
+    }
+
+    fn signature(&self) -> String {
+        let ports = |dir: ir::Direction| {
+            self.signature
+                .iter()
+                .filter(|pd| pd.direction == dir)
+                .map(|pd| format!("{}: {}", pd.name, pd.width))
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        format!(
+            "component {}({}) -> ({})",
+            self.name,
+            ports(ir::Direction::Input),
+            ports(ir::Direction::Output)
+        )
+    }
+}
+
+/// A name used in the program.
+pub enum Symbol<'a> {
+    Cell(&'a ComponentDefs, ir::Id),
+    Group(&'a ComponentDefs, ir::Id),
+    /// A port of a cell or, if the cell is `None`, of the signature of the
+    /// component.
+    Port(&'a ComponentDefs, Option<ir::Id>, ir::Id),
+    Component(&'a ComponentDefs),
+    Primitive(ir::Id),
+}
+
+/// A port with its width and direction.
+type PortInfo = (ir::Id, u64, ir::Direction);
+
+/// What the server needs from the IR of a program. Unlike the IR, it does
+/// not refer to positions in the global position table.
+#[derive(Default)]
+struct IrDefs {
+    /// The primitives of the libraries, with their definitions as printed by
+    /// the compiler.
+    primitives: Vec<(ir::Id, String)>,
+    /// The ports of the cells of each component, with the ports of the
+    /// signature under `None`.
+    ports: HashMap<(ir::Id, Option<ir::Id>), Vec<PortInfo>>,
+}
+
+impl From<&ir::Context> for IrDefs {
+    fn from(ctx: &ir::Context) -> Self {
+        let primitives = ctx
+            .lib
+            .signatures()
+            .map(|prim| {
+                let mut buf = Vec::new();
+                ir::Printer::write_primitive(prim, 0, &mut buf)
+                    .expect("Failed to print primitive");
+                let text = String::from_utf8_lossy(&buf).trim_end().to_string();
+                (prim.name, text)
+            })
+            .collect();
+        let ports_of = |cell: &ir::Cell, signature: bool| {
+            cell.ports()
+                .iter()
+                .map(|port| {
+                    let port = port.borrow();
+                    // The directions of signature ports are reversed inside
+                    // the component.
+                    let direction = if signature {
+                        port.direction.reverse()
+                    } else {
+                        port.direction.clone()
+                    };
+                    (port.name, port.width, direction)
+                })
+                .collect()
+        };
+        let mut ports = HashMap::new();
+        for comp in &ctx.components {
+            ports.insert(
+                (comp.name, None),
+                ports_of(&comp.signature.borrow(), true),
+            );
+            for cell in comp.cells.iter() {
+                let cell = cell.borrow();
+                ports.insert(
+                    (comp.name, Some(cell.name())),
+                    ports_of(&cell, false),
+                );
+            }
+        }
+        Self { primitives, ports }
+    }
+}
+
+/// The names defined by a file and the files it imports. Spans are copied out
+/// of the global position table so that the server can remove the positions
+/// of a program once it has been analyzed.
+pub struct Program {
+    components: Vec<ComponentDefs>,
+    /// Definitions from the IR. Only available if the program could be
+    /// transformed into the IR.
+    ir: Option<IrDefs>,
+}
+
+impl Program {
+    /// Analyze the file `file` with the contents `source`. Returns the
+    /// definitions in the program, if it could be parsed, and the first error
+    /// in the program.
+    pub fn analyze(
+        file: &Path,
+        source: String,
+        lib_paths: &[PathBuf],
+    ) -> (Option<Self>, Option<Error>) {
+        let mut ws = match frontend::Workspace::construct_from_source(
+            file, source, lib_paths,
+        ) {
+            Ok(ws) => ws,
+            Err(err) => return (None, Some(err)),
+        };
+        let mut program = Program {
+            components: ws
+                .components
+                .iter()
+                .chain(ws.declarations.iter())
+                .map(ComponentDefs::from)
+                .collect(),
+            ir: None,
+        };
+        if ws.components.is_empty() {
+            return (Some(program), None);
+        }
+        // Files that only define library components do not have an
+        // entrypoint so we use the first component instead.
+        if !ws
+            .components
+            .iter()
+            .any(|comp| comp.name == "main" || comp.attributes.has("toplevel"))
+        {
+            ws.components[0].attributes.insert("toplevel", 1);
+        }
+        // Some malformed programs make the compiler panic. Report them
+        // instead of bringing down the server.
+        match panic::catch_unwind(AssertUnwindSafe(|| Self::build(ws))) {
+            Ok((ir, err)) => {
+                program.ir = ir;
+                (Some(program), err)
+            }
+            Err(_) => (
+                Some(program),
+                Some(Error::misc(
+                    "The compiler panicked while checking this file"
+                        .to_string(),
+                )),
+            ),
+        }
+    }
+
+    /// Build the IR for the workspace and check that it is well-formed.
+    fn build(ws: frontend::Workspace) -> (Option<IrDefs>, Option<Error>) {
+        let mut ctx = match ir::from_ast::ast_to_ir(ws) {
+            Ok(ctx) => ctx,
+            Err(err) => return (None, Some(err)),
+        };
+        let res = Self::validate(&mut ctx);
+        (Some(IrDefs::from(&ctx)), res.err())
+    }
+
+    fn find_primitive(&self, name: &str) -> Option<&(ir::Id, String)> {
+        self.ir
+            .as_ref()?
+            .primitives
+            .iter()
+            .find(|(prim, _)| prim == name)
+    }
+
+    fn validate(ctx: &mut ir::Context) -> CalyxResult<()> {
+        passes::WellFormed::do_pass_default(ctx)?;
+        passes::Papercut::do_pass_default(ctx)?;
+        Ok(())
+    }
+
+    fn find_component(&self, name: &str) -> Option<&ComponentDefs> {
+        self.components.iter().find(|comp| comp.name == name)
+    }
+
+    /// The component whose definition contains `offset`.
+    fn enclosing(&self, file: &Path, offset: usize) -> Option<&ComponentDefs> {
+        self.components.iter().find(|comp| {
+            comp.span
+                .as_ref()
+                .is_some_and(|span| span.contains(file, offset))
+        })
+    }
+
+    /// Resolve the identifier `word` found at `offset` in `file`.
+    pub fn resolve(
+        &self,
+        file: &Path,
+        offset: usize,
+        word: &Word,
+    ) -> Option<Symbol<'_>> {
+        let name = word.text;
+        let comp = self.enclosing(file, offset);
+        if let (Some(comp), Some(recv)) = (comp, word.receiver) {
+            return comp
+                .find_cell(recv)
+                .map(|cell| Symbol::Port(comp, Some(cell.name), name.into()));
+        }
+        if let Some(comp) = comp {
+            if let Some(cell) = comp.find_cell(name) {
+                return Some(Symbol::Cell(comp, cell.name));
+            }
+            if let Some((group, _)) = comp.find_group(name) {
+                return Some(Symbol::Group(comp, *group));
+            }
+            if let Some(pd) = comp.signature.iter().find(|pd| pd.name == name) {
+                return Some(Symbol::Port(comp, None, pd.name));
+            }
+        }
+        if let Some(comp) = self.find_component(name) {
+            return Some(Symbol::Component(comp));
+        }
+        self.find_primitive(name)
+            .map(|(prim, _)| Symbol::Primitive(*prim))
+    }
+
+    /// The location where `sym` is defined.
+    pub fn definition(&self, sym: &Symbol) -> Option<Span> {
+        match sym {
+            Symbol::Cell(comp, name) => {
+                comp.find_cell(name.as_ref())?.span.clone()
+            }
+            Symbol::Group(comp, name) => {
+                comp.find_group(name.as_ref())?.1.clone()
+            }
+            Symbol::Component(comp) => comp.name_span.clone(),
+            Symbol::Port(comp, Some(cell), _) => {
+                // Ports of component cells are defined by the component.
+                let proto = comp.find_cell(cell.as_ref())?.proto_name;
+                self.find_component(proto.as_ref())?.name_span.clone()
+            }
+            Symbol::Port(_, None, _) | Symbol::Primitive(_) => None,
+        }
+    }
+
+    /// The ports of the cell `cell` in `comp` with their widths as computed by
+    /// the IR. Signature ports are returned if `cell` is `None`.
+    fn ports(&self, comp: ir::Id, cell: Option<ir::Id>) -> Vec<PortInfo> {
+        self.ir
+            .as_ref()
+            .and_then(|ir| ir.ports.get(&(comp, cell)))
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Markdown describing `sym`.
+    pub fn hover(&self, sym: &Symbol) -> Option<String> {
+        let code = |text: String| format!("```calyx\n{}\n```", text);
+        let port_list = |ports: Vec<PortInfo>| {
+            ports
+                .into_iter()
+                .map(|(name, width, dir)| {
+                    format!("- {} `{}`: {}", direction(&dir), name, width)
+                })
+                .collect::<Vec<_>>()
+                .join("\n")
+        };
+        match sym {
+            Symbol::Cell(comp, name) => {
+                let cell = comp.find_cell(name.as_ref())?;
+                let ports = port_list(self.ports(comp.name, Some(*name)));
+                Some(format!(
+                    "{}\n\n{}",
+                    code(format!("{} = {}", name, cell.prototype)),
+                    ports
+                ))
+            }
+            Symbol::Group(_, name) => Some(code(format!("group {}", name))),
+            Symbol::Port(comp, cell, port) => {
+                let (_, width, dir) = self
+                    .ports(comp.name, *cell)
+                    .into_iter()
+                    .find(|(name, _, _)| name == port)?;
+                let name = match cell {
+                    Some(cell) => format!("{}.{}", cell, port),
+                    None => port.to_string(),
+                };
+                Some(format!(
+                    "{}\n\n{} port",
+                    code(format!("{}: {}", name, width)),
+                    direction(&dir)
+                ))
+            }
+            Symbol::Component(comp) => Some(code(comp.signature())),
+            Symbol::Primitive(name) => {
+                let (_, text) = self.find_primitive(name.as_ref())?;
+                Some(code(text.clone()))
+            }
+        }
+    }
+
+    /// Completions for the identifier `word` being typed at `offset` in
+    /// `file`.
+    pub fn completions(
+        &self,
+        file: &Path,
+        offset: usize,
+        word: &Word,
+    ) -> Vec<CompletionItem> {
+        let item = |label: String, kind, detail: String| CompletionItem {
+            label,
+            kind: Some(kind),
+            detail: Some(detail),
+            ..Default::default()
+        };
+        let comp = self.enclosing(file, offset);
+        if let Some(recv) = word.receiver {
+            let Some(cell) = comp.and_then(|comp| comp.find_cell(recv)) else {
+                return vec![];
+            };
+            return self
+                .ports(comp.unwrap().name, Some(cell.name))
+                .into_iter()
+                .map(|(name, width, dir)| {
+                    item(
+                        name.to_string(),
+                        CompletionItemKind::FIELD,
+                        format!("{} {}", direction(&dir), width),
+                    )
+                })
+                .collect();
+        }
+
+        let mut items = vec![];
+        if let Some(comp) = comp {
+            items.extend(comp.cells.iter().map(|cell| {
+                item(
+                    cell.name.to_string(),
+                    CompletionItemKind::VARIABLE,
+                    cell.prototype.clone(),
+                )
+            }));
+            items.extend(comp.groups.iter().map(|(group, _)| {
+                item(
+                    group.to_string(),
+                    CompletionItemKind::FUNCTION,
+                    "group".to_string(),
+                )
+            }));
+            items.extend(comp.signature.iter().map(|pd| {
+                item(
+                    pd.name.to_string(),
+                    CompletionItemKind::FIELD,
+                    format!("{} {}", direction(&pd.direction), pd.width),
+                )
+            }));
+        }
+        items.extend(self.components.iter().map(|comp| {
+            item(
+                comp.name.to_string(),
+                CompletionItemKind::MODULE,
+                comp.signature(),
+            )
+        }));
+        if let Some(ir) = &self.ir {
+            items.extend(ir.primitives.iter().map(|(prim, _)| {
+                item(
+                    prim.to_string(),
+                    CompletionItemKind::CLASS,
+                    "primitive".to_string(),
+                )
+            }));
+        }
+        items
+    }
+}
+
+fn direction(dir: &ir::Direction) -> &'static str {
+    match dir {
+        ir::Direction::Input => "input",
+        ir::Direction::Output => "output",
+        ir::Direction::Inout => "inout",
+    }
+}
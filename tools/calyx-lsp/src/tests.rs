@@ -0,0 +1,322 @@
+//! Tests that drive the server using the files in `tests/`.
+use crate::Server;
+use calyx::utils::GlobalPositionTable;
+use lsp_server::{Notification, Request, RequestId};
+use lsp_types::{
+    notification::{
+        DidChangeTextDocument, DidOpenTextDocument,
+        Notification as LspNotification, PublishDiagnostics,
+    },
+    request::{
+        Completion, GotoDefinition, HoverRequest, Request as LspRequest,
+    },
+    CompletionParams, CompletionResponse, DidChangeTextDocumentParams,
+    DidOpenTextDocumentParams, GotoDefinitionParams, GotoDefinitionResponse,
+    HoverContents, HoverParams, Position, PublishDiagnosticsParams,
+    TextDocumentContentChangeEvent, TextDocumentIdentifier, TextDocumentItem,
+    TextDocumentPositionParams, Url, VersionedTextDocumentIdentifier,
+};
+use std::{
+    path::{Path, PathBuf},
+    sync::{Mutex, MutexGuard},
+};
+
+/// The position and symbol tables used by the compiler are global and not
+/// thread-safe so tests that parse programs cannot run concurrently.
+static LOCK: Mutex<()> = Mutex::new(());
+
+fn test_file(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join(name)
+        .canonicalize()
+        .unwrap()
+}
+
+/// A server with `tests/main.futil` open.
+fn open_main() -> (MutexGuard<'static, ()>, Server, Url, String) {
+    let guard = LOCK.lock().unwrap_or_else(|err| err.into_inner());
+    // The standard library is found relative to the root of the repository.
+    let mut server =
+        Server::new(vec![Path::new(env!("CARGO_MANIFEST_DIR")).join("../..")]);
+    let path = test_file("main.futil");
+    let text = std::fs::read_to_string(&path).unwrap();
+    let uri = Url::from_file_path(&path).unwrap();
+    let diags = diagnostics(server.handle_notification(Notification::new(
+        DidOpenTextDocument::METHOD.to_string(),
+        DidOpenTextDocumentParams {
+            text_document: TextDocumentItem::new(
+                uri.clone(),
+                "calyx".to_string(),
+                0,
+                text.clone(),
+            ),
+        },
+    )));
+    assert_eq!(diags.len(), 1);
+    assert_eq!(diags[0].uri, uri);
+    assert!(
+        diags[0].diagnostics.is_empty(),
+        "{:?}",
+        diags[0].diagnostics
+    );
+    (guard, server, uri, text)
+}
+
+fn change(
+    server: &mut Server,
+    uri: &Url,
+    text: String,
+) -> Vec<PublishDiagnosticsParams> {
+    diagnostics(server.handle_notification(Notification::new(
+        DidChangeTextDocument::METHOD.to_string(),
+        DidChangeTextDocumentParams {
+            text_document: VersionedTextDocumentIdentifier::new(uri.clone(), 1),
+            content_changes: vec![TextDocumentContentChangeEvent {
+                range: None,
+                range_length: None,
+                text,
+            }],
+        },
+    )))
+}
+
+fn diagnostics(nots: Vec<Notification>) -> Vec<PublishDiagnosticsParams> {
+    nots.into_iter()
+        .map(|not| {
+            assert_eq!(not.method, PublishDiagnostics::METHOD);
+            serde_json::from_value(not.params).unwrap()
+        })
+        .collect()
+}
+
+fn request<R: LspRequest>(server: &Server, params: R::Params) -> R::Result {
+    let resp = server.handle_request(Request::new(
+        RequestId::from(0),
+        R::METHOD.to_string(),
+        params,
+    ));
+    assert!(resp.error.is_none(), "{:?}", resp.error);
+    serde_json::from_value(resp.result.unwrap()).unwrap()
+}
+
+/// The position `offset` bytes after the `nth` occurrence of `needle`.
+fn position(text: &str, needle: &str, nth: usize, offset: usize) -> Position {
+    let (idx, _) = text.match_indices(needle).nth(nth).unwrap();
+    let before = &text[..idx + offset];
+    let line = before.matches('\n').count();
+    let col = before.len() - before.rfind('\n').map_or(0, |i| i + 1);
+    Position::new(line as u32, col as u32)
+}
+
+fn at(uri: &Url, position: Position) -> TextDocumentPositionParams {
+    TextDocumentPositionParams::new(
+        TextDocumentIdentifier::new(uri.clone()),
+        position,
+    )
+}
+
+fn definition(server: &Server, uri: &Url, pos: Position) -> (Url, Position) {
+    match request::<GotoDefinition>(
+        server,
+        GotoDefinitionParams {
+            text_document_position_params: at(uri, pos),
+            work_done_progress_params: Default::default(),
+            partial_result_params: Default::default(),
+        },
+    ) {
+        Some(GotoDefinitionResponse::Scalar(loc)) => (loc.uri, loc.range.start),
+        resp => panic!("Unexpected definition: {:?}", resp),
+    }
+}
+
+fn hover(server: &Server, uri: &Url, pos: Position) -> String {
+    let resp = request::<HoverRequest>(
+        server,
+        HoverParams {
+            text_document_position_params: at(uri, pos),
+            work_done_progress_params: Default::default(),
+        },
+    );
+    match resp.map(|hover| hover.contents) {
+        Some(HoverContents::Markup(content)) => content.value,
+        resp => panic!("Unexpected hover: {:?}", resp),
+    }
+}
+
+#[test]
+fn diagnostics_are_published_and_cleared() {
+    let (_guard, mut server, uri, text) = open_main();
+
+    let broken = text.replace("r.in = add.sum", "q.in = add.sum");
+    let diags = change(&mut server, &uri, broken.clone());
+    assert_eq!(diags.len(), 1);
+    let diag = &diags[0].diagnostics;
+    assert_eq!(diag.len(), 1);
+    assert!(diag[0].message.contains("q"), "{}", diag[0].message);
+    assert_eq!(
+        diag[0].range.start.line,
+        position(&broken, "q.in", 0, 0).line
+    );
+
+    // Parse errors point at the location of the error.
+    let broken = text.replace("r.write_en = 1'd1;", "r.write_en = ;");
+    let diags = change(&mut server, &uri, broken.clone());
+    let diag = &diags[0].diagnostics;
+    assert!(diag[0].message.starts_with("Calyx Parser"));
+    assert_eq!(diag[0].range.start, position(&broken, " ;", 0, 1));
+
+    // Every syntax error is reported.
+    let broken = broken.replace("r.in = add.sum;", "r.in = add.sum add.left;");
+    let diags = change(&mut server, &uri, broken.clone());
+    let diag = &diags[0].diagnostics;
+    assert_eq!(diag.len(), 2, "{:?}", diag);
+    assert_eq!(diag[0].range.start, position(&broken, "sum add.left", 0, 4));
+
+    // Errors found by the well-formed pass are reported.
+    let broken = text.replace("    incr;\n", "");
+    let diags = change(&mut server, &uri, broken);
+    assert!(diags[0].diagnostics[0].message.contains("incr"));
+
+    // Panics in the compiler are reported instead of crashing the server.
+    let broken = text.replace("std_reg(32)", "std_reg()");
+    let diags = change(&mut server, &uri, broken);
+    assert!(diags[0].diagnostics[0].message.contains("panicked"));
+
+    let diags = change(&mut server, &uri, text);
+    assert!(diags[0].diagnostics.is_empty());
+}
+
+#[test]
+fn changes_do_not_grow_position_table() {
+    let (_guard, mut server, uri, text) = open_main();
+    let checkpoint = GlobalPositionTable::as_ref().checkpoint();
+    for n in 0..3 {
+        let edited = format!("{}// edit {}\n", text, n);
+        assert!(change(&mut server, &uri, edited)[0].diagnostics.is_empty());
+        let broken = text.replace("r.write_en = 1'd1;", "r.write_en = ;");
+        assert!(!change(&mut server, &uri, broken)[0].diagnostics.is_empty());
+        assert_eq!(GlobalPositionTable::as_ref().checkpoint(), checkpoint);
+    }
+    // Navigation still works with the spans copied out of the table.
+    let (def, pos) = definition(&server, &uri, position(&text, "r.in", 0, 0));
+    assert_eq!(def, uri);
+    assert_eq!(pos, position(&text, "r = std_reg", 0, 0));
+}
+
+#[test]
+fn related_locations_are_reported() {
+    let (_guard, mut server, uri, text) = open_main();
+
+    let broken =
+        text.replace("add = adder();", "add = adder();\n    r = std_reg(32);");
+    let diags = change(&mut server, &uri, broken.clone());
+    let diag = &diags[0].diagnostics[0];
+    assert_eq!(diag.range.start, position(&broken, "r = std_reg", 1, 0));
+    let related = diag.related_information.as_ref().unwrap();
+    assert_eq!(related.len(), 1);
+    assert_eq!(related[0].location.uri, uri);
+    assert_eq!(
+        related[0].location.range.start,
+        position(&broken, "r = std_reg", 0, 0)
+    );
+    assert_eq!(related[0].message, "previous definition");
+}
+
+#[test]
+fn go_to_definition() {
+    let (_guard, mut server, uri, text) = open_main();
+
+    // Cells
+    let (file, pos) = definition(&server, &uri, position(&text, "r.out", 0, 0));
+    assert_eq!(file, uri);
+    assert_eq!(pos, position(&text, "r = std_reg", 0, 0));
+
+    // Groups
+    let (file, pos) = definition(&server, &uri, position(&text, "incr;", 0, 1));
+    assert_eq!(file, uri);
+    assert_eq!(pos, position(&text, "incr {", 0, 0));
+
+    // Components defined in imported files
+    let adder = Url::from_file_path(test_file("adder.futil")).unwrap();
+    let adder_text = std::fs::read_to_string(test_file("adder.futil")).unwrap();
+    let (file, pos) =
+        definition(&server, &uri, position(&text, "adder()", 0, 2));
+    assert_eq!(file, adder);
+    assert_eq!(pos, position(&adder_text, "adder(", 0, 0));
+
+    // Definitions are still available while the document does not parse.
+    let broken = format!("{}\ncomponent", text);
+    change(&mut server, &uri, broken);
+    let (_, pos) = definition(&server, &uri, position(&text, "r.done", 0, 0));
+    assert_eq!(pos, position(&text, "r = std_reg", 0, 0));
+}
+
+#[test]
+fn hover_shows_port_widths() {
+    let (_guard, server, uri, text) = open_main();
+
+    let info = hover(&server, &uri, position(&text, "r.out", 0, 3));
+    assert!(info.contains("r.out: 32"), "{}", info);
+    let info = hover(&server, &uri, position(&text, "add.sum", 0, 5));
+    assert!(info.contains("add.sum: 32"), "{}", info);
+    let info = hover(&server, &uri, position(&text, "r.write_en", 0, 3));
+    assert!(info.contains("r.write_en: 1"), "{}", info);
+
+    let info = hover(&server, &uri, position(&text, "r = std_reg", 0, 0));
+    assert!(info.contains("r = std_reg(32)"), "{}", info);
+    assert!(info.contains("input `in`: 32"), "{}", info);
+
+    let info = hover(&server, &uri, position(&text, "std_reg", 0, 0));
+    assert!(info.contains("primitive std_reg"), "{}", info);
+    let info = hover(&server, &uri, position(&text, "adder()", 0, 0));
+    assert!(
+        info.contains("component adder(left: 32, right: 32) -> (sum: 32)"),
+        "{}",
+        info
+    );
+}
+
+#[test]
+fn complete_port_names() {
+    let (_guard, mut server, uri, text) = open_main();
+    let text = text.replace("out = r.out;", "out = add.");
+    change(&mut server, &uri, text.clone());
+    let items = match request::<Completion>(
+        &server,
+        CompletionParams {
+            text_document_position: at(&uri, position(&text, "add.\n", 0, 4)),
+            work_done_progress_params: Default::default(),
+            partial_result_params: Default::default(),
+            context: None,
+        },
+    ) {
+        Some(CompletionResponse::Array(items)) => items,
+        resp => panic!("Unexpected completion: {:?}", resp),
+    };
+    let mut labels =
+        items.into_iter().map(|item| item.label).collect::<Vec<_>>();
+    labels.sort();
+    assert_eq!(labels, vec!["left", "right", "sum"]);
+}
+
+#[test]
+fn qualified_names() {
+    let (_guard, mut server, uri, text) = open_main();
+    let text = text
+        .replace("import \"adder.futil\";", "import \"adder.futil\" as lib;")
+        .replace("adder()", "lib::adder()");
+    let diags = change(&mut server, &uri, text.clone());
+    assert!(
+        diags[0].diagnostics.is_empty(),
+        "{:?}",
+        diags[0].diagnostics
+    );
+
+    let adder = Url::from_file_path(test_file("adder.futil")).unwrap();
+    let (file, _) =
+        definition(&server, &uri, position(&text, "lib::adder", 0, 7));
+    assert_eq!(file, adder);
+    let info = hover(&server, &uri, position(&text, "lib::adder", 0, 1));
+    assert!(info.contains("component lib::adder(left: 32"), "{}", info);
+}
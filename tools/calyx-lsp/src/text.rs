@@ -0,0 +1,117 @@
+//! Helpers to work with the text of source files.
+use lsp_types::{Position, Range};
+
+/// Converts between byte offsets in a file and LSP positions, which count
+/// lines and UTF-16 code units.
+pub struct LineIndex<'a> {
+    text: &'a str,
+    /// Byte offset of the start of every line.
+    starts: Vec<usize>,
+}
+
+impl<'a> LineIndex<'a> {
+    pub fn new(text: &'a str) -> Self {
+        let starts = std::iter::once(0)
+            .chain(text.match_indices('\n').map(|(idx, _)| idx + 1))
+            .collect();
+        Self { text, starts }
+    }
+
+    pub fn position(&self, offset: usize) -> Position {
+        let offset = offset.min(self.text.len());
+        let line = self.starts.partition_point(|&start| start <= offset) - 1;
+        let character =
+            self.text[self.starts[line]..offset].encode_utf16().count();
+        Position::new(line as u32, character as u32)
+    }
+
+    pub fn range(&self, start: usize, end: usize) -> Range {
+        Range::new(self.position(start), self.position(end))
+    }
+
+    pub fn offset(&self, pos: Position) -> usize {
+        let Some(&start) = self.starts.get(pos.line as usize) else {
+            return self.text.len();
+        };
+        let mut units = 0;
+        for (idx, c) in self.text[start..].char_indices() {
+            if units >= pos.character || c == '\n' {
+                return start + idx;
+            }
+            units += c.len_utf16() as u32;
+        }
+        self.text.len()
+    }
+}
+
+fn is_ident(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_'
+}
+
+/// An identifier in a source file.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Word<'a> {
+    pub text: &'a str,
+    /// The identifier this one is accessed through. For example, `a` is the
+    /// receiver of `out` in `a.out`.
+    pub receiver: Option<&'a str>,
+}
+
+impl<'a> Word<'a> {
+    fn new(text: &'a str, start: usize, end: usize) -> Self {
+        let receiver = text[..start].strip_suffix('.').map(|before| {
+            let recv_start = before
+                .rfind(|c| !is_ident(c))
+                .map(|idx| idx + 1)
+                .unwrap_or(0);
+            &before[recv_start..]
+        });
+        Self {
+            text: &text[start..end],
+            receiver,
+        }
+    }
+
+    /// The identifier that contains `offset`. Qualified names like
+    /// `lib::pe` are a single identifier.
+    pub fn at(text: &'a str, offset: usize) -> Option<Self> {
+        let mut start = offset;
+        loop {
+            start = text[..start]
+                .rfind(|c| !is_ident(c))
+                .map(|idx| idx + 1)
+                .unwrap_or(0);
+            match text[..start].strip_suffix("::") {
+                Some(before) if before.ends_with(is_ident) => {
+                    start = before.len()
+                }
+                _ => break,
+            }
+        }
+        let mut end = offset;
+        loop {
+            end = text[end..]
+                .find(|c| !is_ident(c))
+                .map(|idx| end + idx)
+                .unwrap_or(text.len());
+            match text[end..].strip_prefix("::") {
+                Some(after) if after.starts_with(is_ident) => end += 2,
+                _ => break,
+            }
+        }
+        if start == end {
+            None
+        } else {
+            Some(Self::new(text, start, end))
+        }
+    }
+
+    /// The (possibly empty) identifier that ends at `offset`.
+    pub fn before(text: &'a str, offset: usize) -> Self {
+        let start = text[..offset]
+            .rfind(|c| !is_ident(c))
+            .map(|idx| idx + 1)
+            .unwrap_or(0);
+        Self::new(text, start, offset)
+    }
+}
@@ -9,6 +9,8 @@ use std::path::{Path, PathBuf};
 // run: cargo run -p data_gen -- <calyx file> to generate data w/ all 0s and
 // type int
 // add -f true if you want random values of type fix<32,16>
+// memories annotated with `@signed`, `@unsigned` or `@frac_width(n)` use the
+// annotated format instead. Fixed-point memories get random values.
 
 lazy_static::lazy_static! {
     static ref SIZEMAP: HashMap<&'static str, Vec<&'static str>> = {
@@ -36,10 +38,12 @@ lazy_static::lazy_static! {
 /// Holds data for std_mem cells, including name of cell, width, and sizes
 /// Name is the name of cell itself, not its type. Sizes is a vector
 /// that holds the dimensions of the cell (ex: for a 2 x 3 std_mem_d2 cell it would be [2,3])
+/// Format is the numeric format the cell is annotated with, if any.
 struct CellData {
     name: String,
     width: u64,
     sizes: Vec<usize>,
+    format: Option<ir::NumericFormat>,
 }
 
 #[derive(Debug, FromArgs)]
@@ -49,9 +53,9 @@ struct FilePaths {
     #[argh(positional, from_str_fn(read_path))]
     file_path: Option<PathBuf>,
 
-    /// library path
-    #[argh(option, short = 'l', default = "Path::new(\".\").into()")]
-    pub lib_path: PathBuf,
+    /// paths searched for imported files, in order (default: .)
+    #[argh(option, short = 'l')]
+    pub lib_path: Vec<PathBuf>,
 
     /// whether data is fixpoint or int
     #[argh(option, short = 'f', default = "false")]
@@ -63,7 +67,10 @@ fn read_path(path: &str) -> Result<PathBuf, String> {
 }
 
 fn main() -> CalyxResult<()> {
-    let p: FilePaths = argh::from_env();
+    let mut p: FilePaths = argh::from_env();
+    if p.lib_path.is_empty() {
+        p.lib_path = vec![Path::new(".").into()];
+    }
     let fp_data = p.fp_data;
 
     let ws = frontend::Workspace::construct(&p.file_path, &p.lib_path)?;
@@ -80,11 +87,28 @@ fn main() -> CalyxResult<()> {
 
     let mut map = Map::new();
 
-    for CellData { name, width, sizes } in data_vec {
-        let json_comp = if !(fp_data) {
-            gen_comp(&sizes[..], width)
-        } else {
-            gen_comp_float(&sizes[..], width)
+    for CellData {
+        name,
+        width,
+        sizes,
+        format,
+    } in data_vec
+    {
+        let format =
+            format.or(fp_data.then_some(ir::NumericFormat::SFixed(16)));
+        let json_comp = match format {
+            Some(ir::NumericFormat::UFixed(frac)) => {
+                gen_comp_float(&sizes[..], width, false, frac)
+            }
+            Some(ir::NumericFormat::SFixed(frac)) => {
+                gen_comp_float(&sizes[..], width, true, frac)
+            }
+            Some(ir::NumericFormat::Signed) => {
+                gen_comp(&sizes[..], width, true)
+            }
+            Some(ir::NumericFormat::Unsigned) | None => {
+                gen_comp(&sizes[..], width, false)
+            }
         };
         map.insert(name, json_comp);
     }
@@ -128,8 +152,12 @@ fn gen_random_4d(
         .collect()
 }
 
-//generates a json value associated with sizes_vec and width
-fn gen_comp(sizes_vec: &[usize], width: u64) -> serde_json::Value {
+//generates a json value associated with sizes_vec, width and signedness
+fn gen_comp(
+    sizes_vec: &[usize],
+    width: u64,
+    is_signed: bool,
+) -> serde_json::Value {
     let data = match *sizes_vec {
         [d0] => serde_json::to_value(vec![0_u64; d0]),
         [d0, d1] => serde_json::to_value(vec![vec![0_u64; d1]; d0]),
@@ -146,14 +174,20 @@ fn gen_comp(sizes_vec: &[usize], width: u64) -> serde_json::Value {
         "data": data,
         "format": {
             "numeric_type": "bitnum",
-            "is_signed": false,
+            "is_signed": is_signed,
             "width": width,
         }
     })
 }
 
-// generates a fix<32,16> json value associated with sizes_vec and width
-fn gen_comp_float(sizes_vec: &[usize], width: u64) -> serde_json::Value {
+// generates a random fixed-point json value associated with sizes_vec, width,
+// signedness and the number of fractional bits
+fn gen_comp_float(
+    sizes_vec: &[usize],
+    width: u64,
+    is_signed: bool,
+    frac_width: u64,
+) -> serde_json::Value {
     let data = match *sizes_vec {
         [d0] => serde_json::to_value(gen_random_vec(d0)),
         [d0, d1] => serde_json::to_value(gen_random_2d(d0, d1)),
@@ -165,8 +199,8 @@ fn gen_comp_float(sizes_vec: &[usize], width: u64) -> serde_json::Value {
     json!({
         "data": data,
         "format": {
-            "frac_width": 16,
-            "is_signed": true,
+            "frac_width": frac_width,
+            "is_signed": is_signed,
             "numeric_type": "fixed_point",
             "width": width
         }
@@ -194,6 +228,7 @@ fn get_data(cell: &ir::RRC<ir::Cell>) -> Option<CellData> {
                         }) as usize
                     })
                     .collect(),
+                format: ir::NumericFormat::of_cell(&final_cell),
             })
         }
         _ => None,
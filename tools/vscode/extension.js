@@ -0,0 +1,27 @@
+// Starts the Calyx interpreter as a debug adapter for `calyx` launch
+// configurations.
+const vscode = require('vscode');
+
+function activate(context) {
+    const factory = {
+        createDebugAdapterDescriptor(session) {
+            const config = session.configuration;
+            const args = [config.program];
+            for (const lib of config.libPaths || []) {
+                args.push('-l', lib);
+            }
+            if (config.data) {
+                args.push('-d', config.data);
+            }
+            args.push('debug', '--dap');
+            return new vscode.DebugAdapterExecutable(
+                config.interpreter || 'interp', args, { cwd: config.cwd });
+        }
+    };
+    context.subscriptions.push(
+        vscode.debug.registerDebugAdapterDescriptorFactory('calyx', factory));
+}
+
+function deactivate() { }
+
+module.exports = { activate, deactivate };